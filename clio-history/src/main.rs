@@ -43,5 +43,5 @@ fn main() -> anyhow::Result<()> {
         .db_path
         .unwrap_or_else(|| clio::config::resolve_db_path(&config));
 
-    ui::run_history_window(&config, db_path)
+    ui::run_history_window(&config, args.config, db_path)
 }
@@ -18,18 +18,27 @@ mod imp {
         preview_text: RefCell<String>,
         #[property(get, set)]
         content_type: RefCell<String>,
+        /// Milliseconds since the Unix epoch.
         #[property(get, set)]
-        created_at: RefCell<String>,
+        created_at: RefCell<i64>,
         #[property(get, set)]
         thumbnail: RefCell<Option<gdk::Texture>>,
         #[property(get, set)]
         source_app: RefCell<String>,
         #[property(get, set)]
         source_title: RefCell<String>,
+        /// Milliseconds since the Unix epoch; 0 if the entry never expires.
         #[property(get, set)]
-        expires_at: RefCell<String>,
+        expires_at: RefCell<i64>,
         #[property(get, set)]
         mask_text: RefCell<String>,
+        /// User-given title; empty if none.
+        #[property(get, set)]
+        title: RefCell<String>,
+        #[property(get, set)]
+        note: RefCell<String>,
+        #[property(get, set)]
+        pinned: RefCell<bool>,
     }
 
     #[glib::object_subclass]
@@ -52,12 +61,15 @@ impl EntryObject {
         id: i64,
         preview_text: &str,
         content_type: &str,
-        created_at: &str,
+        created_at: i64,
         thumbnail: Option<gdk::Texture>,
         source_app: &str,
         source_title: &str,
-        expires_at: &str,
+        expires_at: i64,
         mask_text: &str,
+        title: &str,
+        note: &str,
+        pinned: bool,
     ) -> Self {
         glib::Object::builder()
             .property("id", id)
@@ -69,6 +81,9 @@ impl EntryObject {
             .property("source-title", source_title)
             .property("expires-at", expires_at)
             .property("mask-text", mask_text)
+            .property("title", title)
+            .property("note", note)
+            .property("pinned", pinned)
             .build()
     }
 }
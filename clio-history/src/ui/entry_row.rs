@@ -1,17 +1,35 @@
-use chrono::Utc;
+use std::rc::Rc;
+
 use gtk4::glib;
 use gtk4::prelude::*;
 use gtk4::{Align, Label, ListItem, Orientation, SignalListItemFactory};
 
+use clio::config::Config;
 use clio::models::entry::Timestamp;
-use clio::time_fmt::format_created_at;
+use clio::time_fmt::{format_created_at, format_local};
 
 use super::entry_object::EntryObject;
 
 const MASK_ICON: &str = "\u{1F512} ";
+/// Shown before the meta line of pinned entries.
+const PIN_ICON: &str = "\u{1F4CC} ";
+/// Themed icon shown next to file-copy entries.
+const FILES_ICON_NAME: &str = "text-x-generic";
+/// Pixel size for the file-copy icon.
+const FILES_ICON_PX: i32 = 32;
+
+/// Local-time formats from the config (`date_format`, `datetime_format`).
+struct TimeFormats {
+    date: String,
+    datetime: String,
+}
 
-pub fn create_factory() -> SignalListItemFactory {
+pub fn create_factory(config: &Config) -> SignalListItemFactory {
     let factory = SignalListItemFactory::new();
+    let formats = Rc::new(TimeFormats {
+        date: config.date_format.clone(),
+        datetime: config.datetime_format.clone(),
+    });
 
     factory.connect_setup(|_, list_item| {
         let Some(list_item) = list_item.downcast_ref::<ListItem>() else {
@@ -50,7 +68,7 @@ pub fn create_factory() -> SignalListItemFactory {
         list_item.set_child(Some(&root_vbox));
     });
 
-    factory.connect_bind(|_, list_item| {
+    factory.connect_bind(move |_, list_item| {
         let Some(list_item) = list_item.downcast_ref::<ListItem>() else {
             return;
         };
@@ -67,8 +85,14 @@ pub fn create_factory() -> SignalListItemFactory {
         };
         if let Some(tex) = entry_obj.thumbnail() {
             thumbnail.set_paintable(Some(&tex));
+            thumbnail.set_pixel_size(-1);
             thumbnail.set_size_request(tex.width(), tex.height());
             thumbnail.set_visible(true);
+        } else if entry_obj.content_type() == "files" {
+            thumbnail.set_icon_name(Some(FILES_ICON_NAME));
+            thumbnail.set_pixel_size(FILES_ICON_PX);
+            thumbnail.set_size_request(-1, -1);
+            thumbnail.set_visible(true);
         } else {
             thumbnail.set_paintable(gtk4::gdk::Paintable::NONE);
             thumbnail.set_size_request(-1, -1);
@@ -87,23 +111,30 @@ pub fn create_factory() -> SignalListItemFactory {
         };
 
         let ct = entry_obj.content_type();
-        let meta_text = build_meta_text(&entry_obj);
+        let meta_text = build_meta_text(&entry_obj, &formats);
 
+        let title = entry_obj.title();
         let mask = entry_obj.mask_text();
         if !mask.is_empty() {
-            // Mask overrides both text and image display
+            // Mask overrides both text and image display; a title replaces the mask text
             thumbnail.set_paintable(gtk4::gdk::Paintable::NONE);
             thumbnail.set_size_request(-1, -1);
             thumbnail.set_visible(false);
-            preview_label.set_text(&format!("{MASK_ICON}{mask}"));
+            let shown = if title.is_empty() { mask } else { title };
+            preview_label.set_text(&format!("{MASK_ICON}{shown}"));
             preview_label.add_css_class("masked");
             preview_label.set_visible(true);
+        } else if !title.is_empty() {
+            // A title replaces the text preview; images keep their thumbnail
+            preview_label.set_text(&title);
+            preview_label.remove_css_class("masked");
+            preview_label.set_visible(true);
         } else if ct == "image" {
             preview_label.set_text("");
             preview_label.remove_css_class("masked");
             preview_label.set_visible(false);
         } else {
-            let display_text = if ct == "text" {
+            let display_text = if ct == "text" || ct == "files" {
                 entry_obj.preview_text().to_string()
             } else {
                 "[Unknown content]".to_string()
@@ -113,13 +144,15 @@ pub fn create_factory() -> SignalListItemFactory {
             preview_label.set_visible(true);
         }
         meta_label.set_text(&meta_text);
+        meta_label.set_tooltip_text(Some(&build_meta_tooltip(&entry_obj, &formats)));
         meta_label.set_visible(true);
 
         // Live update: refresh "expires in" when timer emits notify::expires-at
         let label_clone = meta_label.clone();
+        let formats = formats.clone();
         let handler_id = entry_obj.connect_notify_local(Some("expires-at"), move |obj, _| {
             let eo: &EntryObject = obj.downcast_ref().expect("notify handler called with wrong type");
-            label_clone.set_text(&build_meta_text(eo));
+            label_clone.set_text(&build_meta_text(eo, &formats));
         });
         // SAFETY: standard gtk4-rs pattern for storing handler ID on a ListItem
         unsafe {
@@ -144,13 +177,10 @@ pub fn create_factory() -> SignalListItemFactory {
     factory
 }
 
-fn build_meta_text(entry_obj: &EntryObject) -> String {
-    let created_at_str = entry_obj.created_at();
-    let mut meta_text = if let Ok(ts) = Timestamp::parse(&created_at_str) {
-        format_created_at(&ts)
-    } else {
-        created_at_str.to_string()
-    };
+fn build_meta_text(entry_obj: &EntryObject, formats: &TimeFormats) -> String {
+    let created = Timestamp::from_millis(entry_obj.created_at());
+    let mut meta_text = if entry_obj.pinned() { PIN_ICON.to_owned() } else { String::new() };
+    meta_text.push_str(&format_created_at(&created, &formats.date));
 
     let source_app = entry_obj.source_app();
     if !source_app.is_empty() {
@@ -163,24 +193,42 @@ fn build_meta_text(entry_obj: &EntryObject) -> String {
         }
     }
 
+    let note = entry_obj.note();
+    if let Some(first_line) = note.lines().next() {
+        meta_text.push_str(" | ");
+        meta_text.push_str(first_line);
+    }
+
     let expires_at = entry_obj.expires_at();
-    if !expires_at.is_empty() {
-        if let Ok(ts) = Timestamp::parse(&expires_at) {
-            let now = Utc::now().naive_utc();
-            let expires = ts.to_naive();
-            if expires > now {
-                let remaining = expires - now;
-                let std_dur =
-                    std::time::Duration::from_secs(remaining.num_seconds().unsigned_abs());
-                let formatted = humantime::format_duration(std_dur).to_string();
-                meta_text.push_str(" | expires in ");
-                meta_text.push_str(&formatted);
-            } else {
-                meta_text.push_str(" | expired");
-            }
+    if expires_at != 0 {
+        let remaining = Timestamp::from_millis(expires_at).remaining();
+        if remaining.is_zero() {
+            meta_text.push_str(" | expired");
+        } else {
+            // Whole seconds: humantime would otherwise print milliseconds.
+            let remaining = std::time::Duration::from_secs(remaining.as_secs());
+            meta_text.push_str(" | expires in ");
+            meta_text.push_str(&humantime::format_duration(remaining).to_string());
         }
     }
 
     meta_text
 }
 
+/// Absolute local times of the entry, shown on hover.
+fn build_meta_tooltip(entry_obj: &EntryObject, formats: &TimeFormats) -> String {
+    let created = Timestamp::from_millis(entry_obj.created_at());
+    let mut tooltip = format!("Copied {}", format_local(&created, &formats.datetime));
+    let expires_at = entry_obj.expires_at();
+    if expires_at != 0 {
+        let expires = Timestamp::from_millis(expires_at);
+        tooltip.push_str(&format!("\nExpires {}", format_local(&expires, &formats.datetime)));
+    }
+    let note = entry_obj.note();
+    if !note.is_empty() {
+        tooltip.push_str("\n\n");
+        tooltip.push_str(&note);
+    }
+    tooltip
+}
+
@@ -14,7 +14,7 @@ use anyhow::Result;
 
 const APPLICATION_ID: &str = "com.clio.history";
 
-pub fn run_history_window(config: &Config, db_path: PathBuf) -> Result<()> {
+pub fn run_history_window(config: &Config, config_path: Option<PathBuf>, db_path: PathBuf) -> Result<()> {
     let app = gtk4::Application::builder()
         .application_id(APPLICATION_ID)
         .build();
@@ -24,7 +24,7 @@ pub fn run_history_window(config: &Config, db_path: PathBuf) -> Result<()> {
     let config = config.clone();
     let sel = selected.clone();
     app.connect_activate(move |app| {
-        window::build_window(app, &config, db_path.clone(), sel.clone());
+        window::build_window(app, &config, config_path.clone(), db_path.clone(), sel.clone());
     });
 
     app.run_with_args::<&str>(&[]);
@@ -47,6 +47,7 @@ fn write_selected_to_clipboard(content: window::SelectedContent) -> Result<()> {
             width,
             height,
         } => clipboard::write_clipboard_image_sync(width, height, rgba)?,
+        window::SelectedContent::Files(files) => clipboard::write_clipboard_files_sync(&files)?,
     }
     Ok(())
 }
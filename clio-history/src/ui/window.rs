@@ -6,19 +6,21 @@ use std::rc::Rc;
 use gtk4::gio;
 use gtk4::glib;
 use gtk4::prelude::*;
-use gtk4::{EventControllerKey, ListView, ScrolledWindow, SearchEntry, SingleSelection};
+use gtk4::{DropDown, EventControllerKey, ListView, ScrolledWindow, SearchEntry, SingleSelection};
 
 use clio::db::Connection;
 
-use chrono::Utc;
-
-use log::{debug, error};
+use log::{debug, error, warn};
 
 use clio::clipboard;
-use clio::config::Config;
+use clio::config::{Config, ConfigOverride, HistorySort, DEFAULT_PROFILE};
 use clio::db;
-use clio::db::repository;
-use clio::models::entry::{ClipboardEntry, ContentHash, EntryContent, Timestamp};
+use clio::db::repository::{self, Annotation, Cursor, EntryFilter, Page};
+use clio::db::{copy_events, snippets, tags};
+use clio::models::entry::{
+    ClipboardEntry, CopyOrigin, EntryContent, FileList, FileOperation, Selection, Timestamp,
+};
+use clio::models::snippet::Snippet;
 
 use super::entry_object::EntryObject;
 use super::entry_row;
@@ -26,6 +28,18 @@ use super::entry_row;
 /// Scroll position threshold (fraction of total height) at which to load more entries.
 const SCROLL_LOAD_THRESHOLD: f64 = 0.8;
 
+/// Selection filter choices shown in the dropdown, in display order.
+const SELECTION_FILTERS: &[(&str, Option<Selection>)] = &[
+    ("All", None),
+    ("Clipboard", Some(Selection::Clipboard)),
+    ("Primary", Some(Selection::Primary)),
+    ("Both", Some(Selection::Both)),
+    ("CLI", Some(Selection::Cli)),
+];
+
+/// First entry of the tag dropdown, showing entries with or without tags.
+const ALL_TAGS: &str = "All tags";
+
 /// Content selected by the user for clipboard restore (passed out of GTK loop).
 pub enum SelectedContent {
     Text(String),
@@ -34,52 +48,73 @@ pub enum SelectedContent {
         width: u32,
         height: u32,
     },
+    Files(FileList),
 }
 
 /// Shared state for history window callbacks.
 struct WindowState {
-    conn: Rc<Connection>,
+    /// Replaced when the user switches profiles.
+    conn: RefCell<Rc<Connection>>,
     store: gio::ListStore,
-    offset: RefCell<usize>,
-    all_loaded: RefCell<bool>,
+    /// Where the next page starts; `None` once every entry is loaded.
+    cursor: RefCell<Option<Cursor>>,
     search_query: RefCell<String>,
+    /// Selections and tags picked in the dropdowns.
+    filter: RefCell<EntryFilter>,
+    /// Tags in the tag dropdown, after `ALL_TAGS`.
+    tag_names: RefCell<Vec<String>>,
+    /// Snippets in the snippet section, one per row.
+    shown_snippets: RefCell<Vec<Snippet>>,
     page_size: usize,
     preview_chars: usize,
+    sort: HistorySort,
     image_max_px: i32,
-    /// Cache of decoded thumbnail textures keyed by content hash.
-    thumbnail_cache: RefCell<HashMap<ContentHash, gtk4::gdk::Texture>>,
+    /// Cache of decoded thumbnail textures keyed by entry ID (preview
+    /// entries carry no content hash).
+    thumbnail_cache: RefCell<HashMap<i64, gtk4::gdk::Texture>>,
 }
 
 impl WindowState {
-    /// Fetch entries from DB with arbitrary limit (respects current search query).
+    fn conn(&self) -> Rc<Connection> {
+        self.conn.borrow().clone()
+    }
+
+    /// Fetch entries from DB with arbitrary limit (respects current search query
+    /// and filters), continuing after `after`.
     /// Text content is truncated to `preview_chars` in SQL to reduce I/O.
-    fn fetch_page_n(&self, offset: usize, limit: usize) -> Vec<ClipboardEntry> {
+    fn fetch_page_n(&self, after: Option<&Cursor>, limit: usize) -> Page {
         let query = self.search_query.borrow().clone();
+        let filter = self.filter.borrow();
+        let conn = self.conn();
         let result = if query.is_empty() {
             repository::list_entries_preview(
-                &self.conn,
+                &conn,
                 limit,
-                offset,
+                after,
                 self.preview_chars,
+                &filter,
+                self.sort,
             )
         } else {
             repository::search_entries_preview(
-                &self.conn,
+                &conn,
                 &query,
                 limit,
-                offset,
+                after,
                 self.preview_chars,
+                &filter,
+                self.sort,
             )
         };
         result.unwrap_or_else(|e| {
             error!("failed to fetch entries: {e}");
-            Vec::new()
+            Page::default()
         })
     }
 
     /// Fetch a page of entries using the configured page size.
-    fn fetch_page(&self, offset: usize) -> Vec<ClipboardEntry> {
-        self.fetch_page_n(offset, self.page_size)
+    fn fetch_page(&self, after: Option<&Cursor>) -> Page {
+        self.fetch_page_n(after, self.page_size)
     }
 
     /// Append entries to the backing ListStore.
@@ -88,11 +123,11 @@ impl WindowState {
         for entry in entries {
             let id = entry.id().unwrap_or(0);
             let ct = entry.content().content_type_str();
-            let created = entry.created_at().map(|t| t.as_str()).unwrap_or("");
+            let created = entry.created_at().map_or(0, |t| t.as_millis());
 
             let (preview, thumbnail) = match entry.content() {
                 EntryContent::Image(blob) => {
-                    let thumb = if let Some(cached) = cache.get(entry.content_hash()) {
+                    let thumb = if let Some(cached) = cache.get(&id) {
                         Some(cached.clone())
                     } else {
                         let decoded = create_thumbnail_texture(blob, self.image_max_px);
@@ -103,7 +138,7 @@ impl WindowState {
                                     cache.remove(&key);
                                 }
                             }
-                            cache.insert(*entry.content_hash(), texture.clone());
+                            cache.insert(id, texture.clone());
                         }
                         decoded
                     };
@@ -119,15 +154,19 @@ impl WindowState {
                     }
                     (preview, None)
                 }
+                EntryContent::Files(files) => (files_preview(files), None),
             };
 
             let source_app = entry.source_app().unwrap_or("");
             let source_title = entry.source_title().unwrap_or("");
-            let expires_at = entry.expires_at().map(|t| t.as_str()).unwrap_or("");
+            let expires_at = entry.expires_at().map_or(0, |t| t.as_millis());
             let mask_text = entry.mask_text().unwrap_or("");
+            let title = entry.title().unwrap_or("");
+            let note = entry.note().unwrap_or("");
 
             self.store.append(&EntryObject::new(
-                id, &preview, ct, created, thumbnail, source_app, source_title, expires_at, mask_text,
+                id, &preview, ct, created, thumbnail, source_app, source_title, expires_at, mask_text, title,
+                note, entry.pinned(),
             ));
         }
     }
@@ -136,52 +175,109 @@ impl WindowState {
     fn reload(&self) {
         self.store.remove_all();
         self.thumbnail_cache.borrow_mut().clear();
-        *self.offset.borrow_mut() = 0;
-        *self.all_loaded.borrow_mut() = false;
 
-        let entries = self.fetch_page(0);
-        let has_more = entries.len() >= self.page_size;
-        *self.offset.borrow_mut() = entries.len();
-        *self.all_loaded.borrow_mut() = !has_more;
-        self.append_entries(&entries);
+        let page = self.fetch_page(None);
+        *self.cursor.borrow_mut() = page.next;
+        self.append_entries(&page.entries);
     }
 
-    /// Load one replacement entry after a deletion.
-    /// Adjusts offset (the deleted row shifts all subsequent rows) and fetches
-    /// a single entry to fill the gap, unless all entries are already loaded.
+    /// Load one replacement entry after a deletion, unless all entries are
+    /// already loaded. The cursor is unaffected by the deleted row.
     fn load_one_more(&self) {
-        let current_offset = {
-            let mut off = self.offset.borrow_mut();
-            *off = off.saturating_sub(1);
-            *off
-        };
-
-        if *self.all_loaded.borrow() {
+        let Some(after) = *self.cursor.borrow() else {
             return;
-        }
-
-        let entries = self.fetch_page_n(current_offset, 1);
-        if entries.is_empty() {
-            *self.all_loaded.borrow_mut() = true;
-        } else {
-            *self.offset.borrow_mut() = current_offset + entries.len();
-            self.append_entries(&entries);
-        }
+        };
+        let page = self.fetch_page_n(Some(&after), 1);
+        *self.cursor.borrow_mut() = page.next;
+        self.append_entries(&page.entries);
     }
 
     /// Load the next page (for infinite scroll).
     fn load_next_page(&self) {
-        if *self.all_loaded.borrow() {
+        let Some(after) = *self.cursor.borrow() else {
             return;
+        };
+        let page = self.fetch_page(Some(&after));
+        *self.cursor.borrow_mut() = page.next;
+        self.append_entries(&page.entries);
+    }
+}
+
+/// The "Snippets" section above the history: snippets whose name or text
+/// matches the search query. Hidden while none do.
+#[derive(Clone)]
+struct SnippetSection {
+    section: gtk4::Box,
+    list: gtk4::ListBox,
+}
+
+impl SnippetSection {
+    fn new() -> Self {
+        let section = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+        let header = gtk4::Label::new(Some("Snippets"));
+        header.set_xalign(0.0);
+        header.add_css_class("heading");
+        header.set_margin_top(4);
+        header.set_margin_bottom(4);
+        header.set_margin_start(8);
+        section.append(&header);
+        let list = gtk4::ListBox::new();
+        section.append(&list);
+        section.append(&gtk4::Separator::new(gtk4::Orientation::Horizontal));
+        Self { section, list }
+    }
+
+    /// Show the snippets of the current database matching the search query.
+    fn refresh(&self, state: &WindowState) {
+        let query = state.search_query.borrow().to_lowercase();
+        let all = snippets::all(&state.conn()).unwrap_or_else(|e| {
+            error!("loading snippets: {e}");
+            Vec::new()
+        });
+        let matching: Vec<Snippet> = all
+            .into_iter()
+            .filter(|snippet| {
+                query.is_empty()
+                    || snippet.name.to_lowercase().contains(&query)
+                    || snippet.body.to_lowercase().contains(&query)
+            })
+            .collect();
+        self.list.remove_all();
+        for snippet in &matching {
+            self.list.append(&snippet_row(snippet, state.preview_chars));
         }
-        let current_offset = *self.offset.borrow();
-        let entries = self.fetch_page(current_offset);
-        let fetched = entries.len();
-        if fetched < self.page_size {
-            *self.all_loaded.borrow_mut() = true;
-        }
-        *self.offset.borrow_mut() = current_offset + fetched;
-        self.append_entries(&entries);
+        self.section.set_visible(!matching.is_empty());
+        *state.shown_snippets.borrow_mut() = matching;
+    }
+}
+
+/// Snippet name followed by the start of its first line.
+fn snippet_row(snippet: &Snippet, preview_chars: usize) -> gtk4::Box {
+    let row = gtk4::Box::new(gtk4::Orientation::Horizontal, 12);
+    row.set_margin_top(4);
+    row.set_margin_bottom(4);
+    row.set_margin_start(8);
+    row.set_margin_end(8);
+    let name = gtk4::Label::new(Some(&snippet.name));
+    name.add_css_class("heading");
+    row.append(&name);
+    let first_line: String =
+        snippet.body.lines().next().unwrap_or_default().chars().take(preview_chars).collect();
+    let preview = gtk4::Label::new(Some(&first_line));
+    preview.set_xalign(0.0);
+    preview.set_hexpand(true);
+    preview.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+    preview.add_css_class("dim-label");
+    row.append(&preview);
+    row
+}
+
+/// One file name per line; cut operations are marked so they stand out.
+fn files_preview(files: &FileList) -> String {
+    let names = files.file_names().join("\n");
+    match files.operation {
+        FileOperation::Copy => names,
+        FileOperation::Cut => format!("\u{2702} {names}"),
     }
 }
 
@@ -199,6 +295,7 @@ pub fn truncate_preview(text: &str, max_chars: usize) -> (String, bool) {
 pub fn build_window(
     app: &gtk4::Application,
     config: &Config,
+    config_path: Option<PathBuf>,
     db_path: PathBuf,
     selected: Rc<RefCell<Option<SelectedContent>>>,
 ) {
@@ -214,13 +311,16 @@ pub fn build_window(
     let store = gio::ListStore::new::<EntryObject>();
 
     let state = Rc::new(WindowState {
-        conn,
+        conn: RefCell::new(conn),
         store: store.clone(),
-        offset: RefCell::new(0),
-        all_loaded: RefCell::new(false),
+        cursor: RefCell::new(None),
         search_query: RefCell::new(String::new()),
+        filter: RefCell::new(EntryFilter::default()),
+        tag_names: RefCell::new(Vec::new()),
+        shown_snippets: RefCell::new(Vec::new()),
         page_size: config.history_page_size,
         preview_chars: config.preview_text_chars,
+        sort: config.sort,
         image_max_px: config.image_preview_max_px,
         thumbnail_cache: RefCell::new(HashMap::new()),
     });
@@ -239,19 +339,48 @@ pub fn build_window(
 
     let main_box = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
 
+    let search_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
     let search_entry = SearchEntry::new();
     search_entry.set_placeholder_text(Some("Type to filter..."));
-    main_box.append(&search_entry);
+    search_entry.set_hexpand(true);
+    search_box.append(&search_entry);
+
+    let filter_names: Vec<&str> = SELECTION_FILTERS.iter().map(|(name, _)| *name).collect();
+    let selection_dropdown = DropDown::from_strings(&filter_names);
+    selection_dropdown.set_tooltip_text(Some("Filter by selection"));
+    search_box.append(&selection_dropdown);
+    let tag_dropdown = DropDown::from_strings(&[ALL_TAGS]);
+    tag_dropdown.set_tooltip_text(Some("Filter by tag"));
+    fill_tag_dropdown(&tag_dropdown, &state);
+    search_box.append(&tag_dropdown);
+    let snippet_section = SnippetSection::new();
+    snippet_section.refresh(&state);
+    if !config.profiles.is_empty() {
+        let profile_dropdown = build_profile_dropdown(config);
+        setup_profile_switch(
+            &profile_dropdown,
+            &tag_dropdown,
+            &snippet_section,
+            &state,
+            config,
+            config_path,
+        );
+        search_box.append(&profile_dropdown);
+    }
+    main_box.append(&search_box);
+    main_box.append(&snippet_section.section);
 
     let selection = SingleSelection::new(Some(store.clone()));
     selection.set_autoselect(true);
 
-    let factory = entry_row::create_factory();
+    let factory = entry_row::create_factory(config);
     let list_view = ListView::new(Some(selection.clone()), Some(factory));
     list_view.set_vexpand(true);
     list_view.set_show_separators(true);
 
-    setup_search(&search_entry, &state);
+    setup_search(&search_entry, &snippet_section, &state);
+    setup_selection_filter(&selection_dropdown, &state);
+    setup_tag_filter(&tag_dropdown, &state);
     search_entry.set_key_capture_widget(Some(&list_view));
 
     let scrolled = ScrolledWindow::new();
@@ -260,8 +389,11 @@ pub fn build_window(
     main_box.append(&scrolled);
 
     setup_scroll(&scrolled, &state);
+    setup_snippet_activate(&snippet_section, &state, &window, selected.clone());
     setup_activate(&list_view, &state, &selection, &window, selected);
     setup_delete(&list_view, &state, &selection);
+    setup_annotate(&list_view, &state, &selection, &window);
+    setup_pin(&list_view, &state, &selection);
     setup_escape(&window);
     setup_expiry_timer(&window, &state);
 
@@ -284,14 +416,131 @@ pub fn build_window(
     }
 }
 
-fn setup_search(search_entry: &SearchEntry, state: &Rc<WindowState>) {
+fn setup_search(search_entry: &SearchEntry, snippet_section: &SnippetSection, state: &Rc<WindowState>) {
     let state = state.clone();
+    let snippet_section = snippet_section.clone();
     search_entry.connect_search_changed(move |entry| {
         *state.search_query.borrow_mut() = entry.text().to_string();
+        snippet_section.refresh(&state);
+        state.reload();
+    });
+}
+
+fn setup_selection_filter(dropdown: &DropDown, state: &Rc<WindowState>) {
+    let state = state.clone();
+    dropdown.connect_selected_notify(move |dropdown| {
+        let filter = SELECTION_FILTERS
+            .get(dropdown.selected() as usize)
+            .and_then(|(_, selection)| *selection);
+        state.filter.borrow_mut().selections = filter.into_iter().collect();
+        state.reload();
+    });
+}
+
+fn setup_tag_filter(dropdown: &DropDown, state: &Rc<WindowState>) {
+    let state = state.clone();
+    dropdown.connect_selected_notify(move |dropdown| {
+        let tag = (dropdown.selected() as usize)
+            .checked_sub(1)
+            .and_then(|index| state.tag_names.borrow().get(index).cloned());
+        state.filter.borrow_mut().tags = tag.into_iter().collect();
         state.reload();
     });
 }
 
+/// Fill the tag dropdown with the tags of the current database, each with
+/// its number of entries. The dropdown is hidden while there are no tags.
+fn fill_tag_dropdown(dropdown: &DropDown, state: &WindowState) {
+    let counts = tags::all(&state.conn()).unwrap_or_else(|e| {
+        error!("loading tags: {e}");
+        Vec::new()
+    });
+    let labels: Vec<String> = std::iter::once(ALL_TAGS.to_owned())
+        .chain(counts.iter().map(|(name, count)| format!("{name} ({count})")))
+        .collect();
+    let model = gtk4::StringList::new(&labels.iter().map(String::as_str).collect::<Vec<_>>());
+    *state.tag_names.borrow_mut() = counts.into_iter().map(|(name, _)| name).collect();
+    dropdown.set_visible(labels.len() > 1);
+    dropdown.set_model(Some(&model));
+}
+
+/// Profile names in dropdown order: `default` first, then the configured ones.
+fn profile_names(config: &Config) -> Vec<String> {
+    std::iter::once(DEFAULT_PROFILE.to_owned())
+        .chain(config.profiles.keys().cloned())
+        .collect()
+}
+
+fn build_profile_dropdown(config: &Config) -> DropDown {
+    let names = profile_names(config);
+    let labels: Vec<&str> = names.iter().map(String::as_str).collect();
+    let dropdown = DropDown::from_strings(&labels);
+    let active = config.profile.as_deref().unwrap_or(DEFAULT_PROFILE);
+    if let Some(index) = names.iter().position(|name| name == active) {
+        dropdown.set_selected(index as u32);
+    }
+    dropdown.set_tooltip_text(Some("Profile"));
+    dropdown
+}
+
+/// Switching makes the profile active everywhere (like `clio profile switch`)
+/// and reopens the window on that profile's database.
+fn setup_profile_switch(
+    dropdown: &DropDown,
+    tag_dropdown: &DropDown,
+    snippet_section: &SnippetSection,
+    state: &Rc<WindowState>,
+    config: &Config,
+    config_path: Option<PathBuf>,
+) {
+    let state = state.clone();
+    let tag_dropdown = tag_dropdown.clone();
+    let snippet_section = snippet_section.clone();
+    let config = config.clone();
+    let names = profile_names(&config);
+    // Database of the active profile, whose lock the watcher holds.
+    let active_db = RefCell::new(clio::config::resolve_db_path(&config));
+    dropdown.connect_selected_notify(move |dropdown| {
+        let Some(name) = names.get(dropdown.selected() as usize) else {
+            return;
+        };
+        if let Err(e) = clio::config::activate_profile(&config, name) {
+            error!("switching profile: {e}");
+            return;
+        }
+        match clio::ipc::request_reload(&active_db.borrow()) {
+            Ok(Some(clio::ipc::Watcher { pid, pinned_profile: Some(pinned) })) if pinned != *name => {
+                warn!("clio watch (PID {pid}) was started with profile {pinned} and keeps it");
+            }
+            Ok(_) => {}
+            Err(e) => error!("notifying clio watch: {e}"),
+        }
+        // Select the profile explicitly: a CLIO_PROFILE inherited from
+        // `clio --profile history` would otherwise win over the switch.
+        let select = ConfigOverride { key: "profile".to_owned(), value: name.clone() };
+        let db_path = match clio::config::load_config_with_overrides(config_path.as_deref(), &[select]) {
+            Ok(profile_config) => clio::config::resolve_db_path(&profile_config),
+            Err(e) => {
+                error!("loading profile {name}: {e}");
+                return;
+            }
+        };
+        active_db.replace(db_path.clone());
+        match db::init_db_ui(&db_path) {
+            Ok(conn) => {
+                debug!("history window: switched to profile {name} ({})", db_path.display());
+                *state.conn.borrow_mut() = Rc::new(conn);
+                // Tags belong to the database: start over without a tag filter.
+                state.filter.borrow_mut().tags.clear();
+                fill_tag_dropdown(&tag_dropdown, &state);
+                snippet_section.refresh(&state);
+                state.reload();
+            }
+            Err(e) => error!("opening database {}: {e}", db_path.display()),
+        }
+    });
+}
+
 fn setup_scroll(scrolled: &ScrolledWindow, state: &Rc<WindowState>) {
     let state = state.clone();
     let vadj = scrolled.vadjustment();
@@ -323,13 +572,24 @@ fn setup_activate(
         let item = sel.item(position);
         if let Some(entry_obj) = item.and_then(|o| o.downcast::<EntryObject>().ok()) {
             let entry_id = entry_obj.id();
-            if let Ok(Some(entry)) = repository::get_entry_content(&state.conn, entry_id) {
+            let entry = match repository::get_entry_content(&state.conn(), entry_id) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    // E.g. its blob store file is gone: paste nothing rather than a part.
+                    error!("cannot read entry {entry_id}: {e}");
+                    return;
+                }
+            };
+            if let Some(entry) = entry {
                 match entry.content() {
                     EntryContent::Text(text) => {
                         *selected.borrow_mut() = Some(SelectedContent::Text(text.clone()));
                     }
+                    EntryContent::Files(files) => {
+                        *selected.borrow_mut() = Some(SelectedContent::Files(files.clone()));
+                    }
                     EntryContent::Image(blob) => {
-                        if let Ok(img) = image::load_from_memory_with_format(blob, image::ImageFormat::Png) {
+                        if let Ok(img) = image::load_from_memory(blob) {
                             let rgba = img.to_rgba8();
                             let (w, h) = rgba.dimensions();
                             *selected.borrow_mut() = Some(SelectedContent::Image {
@@ -340,15 +600,275 @@ fn setup_activate(
                         }
                     }
                 }
-                if let Err(e) = repository::update_timestamp_and_expiry(&state.conn, entry_id, None) {
+                if let Err(e) = repository::update_timestamp_and_expiry(&state.conn(), entry_id, None) {
                     log::error!("failed to update timestamp for entry {entry_id}: {e}");
                 }
+                if let Err(e) = copy_events::record_use(&state.conn(), entry_id, CopyOrigin::History) {
+                    log::error!("failed to record copy of entry {entry_id}: {e}");
+                }
             }
             win.close();
         }
     });
 }
 
+/// Expand the activated snippet and put it on the clipboard, asking for its
+/// `{prompt:NAME}` values first.
+fn setup_snippet_activate(
+    snippet_section: &SnippetSection,
+    state: &Rc<WindowState>,
+    window: &gtk4::ApplicationWindow,
+    selected: Rc<RefCell<Option<SelectedContent>>>,
+) {
+    let state = state.clone();
+    let win = window.clone();
+    snippet_section.list.connect_row_activated(move |_list, row| {
+        let Some(snippet) = usize::try_from(row.index())
+            .ok()
+            .and_then(|index| state.shown_snippets.borrow().get(index).cloned())
+        else {
+            return;
+        };
+        let prompts: Vec<String> = snippet.prompts().into_iter().map(str::to_owned).collect();
+        let title = snippet.name.clone();
+        let finish = {
+            let win = win.clone();
+            let selected = selected.clone();
+            move |answers: HashMap<String, String>| {
+                match snippet.expand_now(&answers) {
+                    Ok(text) => {
+                        *selected.borrow_mut() = Some(SelectedContent::Text(text));
+                        win.close();
+                    }
+                    Err(e) => error!("expanding snippet {}: {e}", snippet.name),
+                }
+            }
+        };
+        if prompts.is_empty() {
+            finish(HashMap::new());
+        } else {
+            ask_prompts(&win, &title, prompts, finish);
+        }
+    });
+}
+
+/// Ask for the values of `names` in a small dialog and pass them to `done`.
+/// Enter moves to the next field and accepts on the last one; Escape cancels.
+fn ask_prompts(
+    parent: &gtk4::ApplicationWindow,
+    title: &str,
+    names: Vec<String>,
+    done: impl Fn(HashMap<String, String>) + 'static,
+) {
+    let dialog = gtk4::Window::builder()
+        .transient_for(parent)
+        .modal(true)
+        .title(title)
+        .default_width(360)
+        .build();
+
+    let grid = gtk4::Grid::new();
+    grid.set_row_spacing(6);
+    grid.set_column_spacing(12);
+    grid.set_margin_top(12);
+    grid.set_margin_bottom(12);
+    grid.set_margin_start(12);
+    grid.set_margin_end(12);
+    let fields: Vec<(String, gtk4::Entry)> = names
+        .into_iter()
+        .enumerate()
+        .map(|(row, name)| {
+            let label = gtk4::Label::new(Some(&name));
+            label.set_xalign(0.0);
+            let entry = gtk4::Entry::new();
+            entry.set_hexpand(true);
+            grid.attach(&label, 0, row as i32, 1, 1);
+            grid.attach(&entry, 1, row as i32, 1, 1);
+            (name, entry)
+        })
+        .collect();
+
+    let fields = Rc::new(fields);
+    let done = Rc::new(done);
+    for (index, (_, entry)) in fields.iter().enumerate() {
+        let fields = fields.clone();
+        let done = done.clone();
+        let dialog = dialog.clone();
+        entry.connect_activate(move |_| {
+            if let Some((_, next)) = fields.get(index + 1) {
+                next.grab_focus();
+                return;
+            }
+            let answers = fields
+                .iter()
+                .map(|(name, entry)| (name.clone(), entry.text().to_string()))
+                .collect();
+            dialog.close();
+            done(answers);
+        });
+    }
+
+    close_on_escape(&dialog);
+    dialog.set_child(Some(&grid));
+    dialog.present();
+    if let Some((_, first)) = fields.first() {
+        first.grab_focus();
+    }
+}
+
+fn close_on_escape(dialog: &gtk4::Window) {
+    let d = dialog.clone();
+    let controller = EventControllerKey::new();
+    controller.connect_key_pressed(move |_, key, _, _| {
+        if key == gtk4::gdk::Key::Escape {
+            d.close();
+            return glib::Propagation::Stop;
+        }
+        glib::Propagation::Proceed
+    });
+    dialog.add_controller(controller);
+}
+
+/// F2 edits the title and note of the selected entry.
+fn setup_annotate(
+    list_view: &ListView,
+    state: &Rc<WindowState>,
+    selection: &SingleSelection,
+    window: &gtk4::ApplicationWindow,
+) {
+    let state = state.clone();
+    let sel = selection.clone();
+    let win = window.clone();
+    let controller = EventControllerKey::new();
+    controller.connect_key_pressed(move |_, key, _, _| {
+        if key != gtk4::gdk::Key::F2 {
+            return glib::Propagation::Proceed;
+        }
+        if let Some(entry_obj) = sel.selected_item().and_downcast::<EntryObject>() {
+            edit_annotation(&win, &state, &sel, entry_obj);
+        }
+        glib::Propagation::Stop
+    });
+    list_view.add_controller(controller);
+}
+
+/// Dialog editing the title and note of `entry_obj`. Enter in the title
+/// field or Save stores them and refreshes the row; Escape cancels.
+fn edit_annotation(
+    parent: &gtk4::ApplicationWindow,
+    state: &Rc<WindowState>,
+    selection: &SingleSelection,
+    entry_obj: EntryObject,
+) {
+    let dialog = gtk4::Window::builder()
+        .transient_for(parent)
+        .modal(true)
+        .title("Edit entry")
+        .default_width(420)
+        .build();
+
+    let vbox = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+    vbox.set_margin_top(12);
+    vbox.set_margin_bottom(12);
+    vbox.set_margin_start(12);
+    vbox.set_margin_end(12);
+
+    let title = gtk4::Entry::new();
+    title.set_placeholder_text(Some("Title, shown instead of the content"));
+    title.set_text(&entry_obj.title());
+    vbox.append(&title);
+
+    let note = gtk4::TextView::new();
+    note.set_wrap_mode(gtk4::WrapMode::WordChar);
+    note.buffer().set_text(&entry_obj.note());
+    let note_scroll = ScrolledWindow::new();
+    note_scroll.set_child(Some(&note));
+    note_scroll.set_min_content_height(100);
+    note_scroll.set_vexpand(true);
+    vbox.append(&gtk4::Label::new(Some("Note")));
+    vbox.append(&note_scroll);
+
+    let save_button = gtk4::Button::with_label("Save");
+    save_button.set_halign(gtk4::Align::End);
+    save_button.add_css_class("suggested-action");
+    vbox.append(&save_button);
+
+    let save = Rc::new({
+        let state = state.clone();
+        let selection = selection.clone();
+        let dialog = dialog.clone();
+        let title = title.clone();
+        move || {
+            let buffer = note.buffer();
+            let annotation = Annotation {
+                title: Some(title.text().to_string()),
+                note: Some(buffer.text(&buffer.start_iter(), &buffer.end_iter(), false).to_string()),
+            };
+            let conn = state.conn();
+            let saved = repository::set_annotation(&conn, entry_obj.id(), &annotation)
+                .and_then(|_| repository::get_annotation(&conn, entry_obj.id()));
+            match saved {
+                Ok(saved) => {
+                    let saved = saved.unwrap_or_default();
+                    entry_obj.set_title(saved.title.unwrap_or_default());
+                    entry_obj.set_note(saved.note.unwrap_or_default());
+                    // Replace the item with itself so its row is bound again.
+                    let position = (0..state.store.n_items()).find(|&i| {
+                        state.store.item(i).and_downcast::<EntryObject>().as_ref() == Some(&entry_obj)
+                    });
+                    if let Some(position) = position {
+                        state.store.splice(position, 1, &[entry_obj.clone()]);
+                        selection.set_selected(position);
+                    }
+                }
+                Err(e) => error!("annotating entry {}: {e}", entry_obj.id()),
+            }
+            dialog.close();
+        }
+    });
+    title.connect_activate({
+        let save = save.clone();
+        move |_| save()
+    });
+    save_button.connect_clicked(move |_| save());
+
+    close_on_escape(&dialog);
+    dialog.set_child(Some(&vbox));
+    dialog.present();
+    title.grab_focus();
+}
+
+/// Ctrl+P pins or unpins the selected entry.
+fn setup_pin(list_view: &ListView, state: &Rc<WindowState>, selection: &SingleSelection) {
+    let state = state.clone();
+    let sel = selection.clone();
+    let controller = EventControllerKey::new();
+    controller.connect_key_pressed(move |_, key, _, modifiers| {
+        if key != gtk4::gdk::Key::p || !modifiers.contains(gtk4::gdk::ModifierType::CONTROL_MASK) {
+            return glib::Propagation::Proceed;
+        }
+        let Some(entry_obj) = sel.selected_item().and_downcast::<EntryObject>() else {
+            return glib::Propagation::Stop;
+        };
+        let pinned = !entry_obj.pinned();
+        match repository::set_pinned(&state.conn(), entry_obj.id(), pinned) {
+            Ok(_) => {
+                entry_obj.set_pinned(pinned);
+                if pinned {
+                    entry_obj.set_expires_at(0);
+                }
+                // Replace the item with itself so its row is bound again.
+                let position = sel.selected();
+                state.store.splice(position, 1, &[entry_obj]);
+                sel.set_selected(position);
+            }
+            Err(e) => error!("pinning entry {}: {e}", entry_obj.id()),
+        }
+        glib::Propagation::Stop
+    });
+    list_view.add_controller(controller);
+}
+
 /// Delete entry from DB and clear clipboard if the deleted entry is currently there.
 /// Mirrors the watch daemon's expiry-restore logic for consistency.
 fn delete_and_clear_clipboard(conn: &Connection, entry_id: i64) {
@@ -386,7 +906,7 @@ fn setup_delete(
             let selected = sel.selected();
             if let Some(obj) = sel.selected_item() {
                 if let Ok(entry_obj) = obj.downcast::<EntryObject>() {
-                    delete_and_clear_clipboard(&state.conn, entry_obj.id());
+                    delete_and_clear_clipboard(&state.conn(), entry_obj.id());
                     state.store.remove(selected);
                     state.load_one_more();
                     let new_n = sel.n_items();
@@ -425,7 +945,7 @@ fn setup_expiry_timer(window: &gtk4::ApplicationWindow, state: &Rc<WindowState>)
             return glib::ControlFlow::Break;
         };
 
-        let now = Utc::now().naive_utc();
+        let now = Timestamp::now();
         let n = state.store.n_items();
 
         // Remove expired entries (reverse to avoid index shift)
@@ -437,14 +957,11 @@ fn setup_expiry_timer(window: &gtk4::ApplicationWindow, state: &Rc<WindowState>)
                 continue;
             };
             let expires_at = entry_obj.expires_at();
-            if expires_at.is_empty() {
+            if expires_at == 0 {
                 continue;
             }
-            let Ok(ts) = Timestamp::parse(&expires_at) else {
-                continue;
-            };
-            if ts.to_naive() <= now {
-                delete_and_clear_clipboard(&state.conn, entry_obj.id());
+            if Timestamp::from_millis(expires_at) <= now {
+                delete_and_clear_clipboard(&state.conn(), entry_obj.id());
                 state.store.remove(i);
                 state.load_one_more();
             }
@@ -459,7 +976,7 @@ fn setup_expiry_timer(window: &gtk4::ApplicationWindow, state: &Rc<WindowState>)
             let Ok(entry_obj) = obj.downcast::<EntryObject>() else {
                 continue;
             };
-            if !entry_obj.expires_at().is_empty() {
+            if entry_obj.expires_at() != 0 {
                 entry_obj.notify("expires-at");
             }
         }
@@ -23,6 +23,10 @@ pub struct ActionResult {
     pub ttl: Option<Duration>,
     /// Mask text to display instead of real content in history UI.
     pub mask_with: Option<String>,
+    /// Names of the rules that matched, in definition order.
+    pub matched_rules: Vec<String>,
+    /// Tags of all matching rules, without duplicates.
+    pub tags: Vec<String>,
 }
 
 /// Evaluate all rules against an entry and apply matching actions.
@@ -36,6 +40,8 @@ pub fn apply_rules(rules: &[CompiledRule], entry: &ClipboardEntry) -> ActionResu
 
     let mut ttl: Option<Duration> = None;
     let mut mask_with: Option<String> = None;
+    let mut matched_rules = Vec::new();
+    let mut tags: Vec<String> = Vec::new();
     let mut current_text: Option<Cow<'_, str>> = text.map(Cow::Borrowed);
 
     for rule in rules {
@@ -44,6 +50,7 @@ pub fn apply_rules(rules: &[CompiledRule], entry: &ClipboardEntry) -> ActionResu
         }
 
         debug!("rule '{}' matched", rule.name);
+        matched_rules.push(rule.name.clone());
 
         // Apply TTL action (last match wins)
         if let Some(rule_ttl) = rule.ttl {
@@ -55,6 +62,13 @@ pub fn apply_rules(rules: &[CompiledRule], entry: &ClipboardEntry) -> ActionResu
             mask_with = Some(m.clone());
         }
 
+        // Collect tags (all matches contribute)
+        for tag in &rule.tags {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+
         // Apply command action (only for text entries)
         if let Some(ref cmd) = rule.command {
             if let Some(ref input) = current_text {
@@ -86,6 +100,8 @@ pub fn apply_rules(rules: &[CompiledRule], entry: &ClipboardEntry) -> ActionResu
         expires_at,
         ttl,
         mask_with,
+        matched_rules,
+        tags,
     }
 }
 
@@ -217,7 +233,6 @@ fn wait_with_timeout(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::Utc;
     use crate::config::{ActionRule, RuleActions, RuleConditions};
 
     fn compile_rule(rule: &ActionRule) -> CompiledRule {
@@ -235,6 +250,7 @@ mod tests {
         assert!(result.transformed_text.is_none());
         assert!(result.expires_at.is_none());
         assert!(result.ttl.is_none());
+        assert!(result.matched_rules.is_empty());
     }
 
     #[test]
@@ -251,6 +267,7 @@ mod tests {
                 command: None,
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         });
 
@@ -259,6 +276,7 @@ mod tests {
         assert!(result.expires_at.is_some());
         assert_eq!(result.ttl, Some(Duration::from_secs(30)));
         assert!(result.transformed_text.is_none());
+        assert_eq!(result.matched_rules, ["test"]);
     }
 
     #[test]
@@ -275,6 +293,7 @@ mod tests {
                 command: None,
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         });
 
@@ -297,6 +316,7 @@ mod tests {
                 command: None,
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         });
 
@@ -319,6 +339,7 @@ mod tests {
                 command: None,
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         });
 
@@ -341,6 +362,7 @@ mod tests {
                 command: None,
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         });
 
@@ -363,6 +385,7 @@ mod tests {
                 command: None,
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         });
 
@@ -386,6 +409,7 @@ mod tests {
                 command: Some(vec!["tr".into(), "a-z".into(), "A-Z".into()]),
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         });
 
@@ -408,6 +432,7 @@ mod tests {
                 command: Some(vec!["false".into()]),
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         });
 
@@ -430,6 +455,7 @@ mod tests {
                 command: Some(vec!["nonexistent_binary_xyz".into()]),
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         });
 
@@ -453,6 +479,7 @@ mod tests {
                     command: None,
                     command_timeout: None,
                     mask_with: None,
+                    ..Default::default()
                 },
             }),
             compile_rule(&ActionRule {
@@ -467,21 +494,20 @@ mod tests {
                     command: None,
                     command_timeout: None,
                     mask_with: None,
+                    ..Default::default()
                 },
             }),
         ];
 
         let entry = text_entry("hello", None);
-        let before = Utc::now();
+        let before = Timestamp::now();
         let result = apply_rules(&rules, &entry);
         // Both matched, last TTL (60s) wins
         let expires = result.expires_at.unwrap();
-        let parsed = expires.to_naive();
-        let diff = parsed - before.naive_utc();
+        let diff_secs = (expires.as_millis() - before.as_millis()) / 1000;
         assert!(
-            diff.num_seconds() >= 55 && diff.num_seconds() <= 65,
-            "expected ~60s TTL, got {}s",
-            diff.num_seconds()
+            (55..=65).contains(&diff_secs),
+            "expected ~60s TTL, got {diff_secs}s"
         );
     }
 
@@ -500,6 +526,7 @@ mod tests {
                     command: Some(vec!["tr".into(), "a-z".into(), "A-Z".into()]),
                     command_timeout: None,
                     mask_with: None,
+                    ..Default::default()
                 },
             }),
             compile_rule(&ActionRule {
@@ -514,6 +541,7 @@ mod tests {
                     command: Some(vec!["rev".into()]),
                     command_timeout: None,
                     mask_with: None,
+                    ..Default::default()
                 },
             }),
         ];
@@ -523,6 +551,33 @@ mod tests {
         assert_eq!(result.transformed_text.as_deref(), Some("OLLEH"));
     }
 
+    #[test]
+    fn test_tags_are_merged() {
+        let rule = |name: &str, regex: &str, tags: &[&str]| {
+            compile_rule(&ActionRule {
+                name: name.into(),
+                conditions: RuleConditions {
+                    source_app: None,
+                    content_regex: Some(regex.into()),
+                    source_title_regex: None,
+                },
+                actions: RuleActions {
+                    tags: tags.iter().map(|t| t.to_string()).collect(),
+                    ..Default::default()
+                },
+            })
+        };
+        let rules = vec![
+            rule("kubectl", "^kubectl ", &["k8s", "shell"]),
+            rule("logs", "logs", &["shell", "logs"]),
+            rule("sql", "^SELECT ", &["sql"]),
+        ];
+
+        let result = apply_rules(&rules, &text_entry("kubectl logs pod", None));
+        assert_eq!(result.tags, ["k8s", "shell", "logs"]);
+        assert!(apply_rules(&rules, &text_entry("echo", None)).tags.is_empty());
+    }
+
     #[test]
     fn test_source_app_none_skips_source_app_rules() {
         let rule = compile_rule(&ActionRule {
@@ -537,6 +592,7 @@ mod tests {
                 command: None,
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         });
 
@@ -559,6 +615,7 @@ mod tests {
                 command: None,
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         });
 
@@ -582,6 +639,7 @@ mod tests {
                 command: None,
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         });
 
@@ -607,6 +665,7 @@ mod tests {
                 command: None,
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         };
         assert!(rule.compile().is_err());
@@ -626,6 +685,7 @@ mod tests {
                 command: None,
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         };
         assert!(rule.compile().is_err());
@@ -645,6 +705,7 @@ mod tests {
                 command: Some(vec![]),
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         };
         assert!(rule.compile().is_err());
@@ -664,6 +725,7 @@ mod tests {
                 command: None,
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         });
 
@@ -688,6 +750,7 @@ mod tests {
                 command: None,
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         });
 
@@ -711,6 +774,7 @@ mod tests {
                 command: None,
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         });
 
@@ -734,6 +798,7 @@ mod tests {
                 command: None,
                 command_timeout: None,
                 mask_with: Some("***".into()),
+                ..Default::default()
             },
         });
 
@@ -758,6 +823,7 @@ mod tests {
                     command: None,
                     command_timeout: None,
                     mask_with: Some("***".into()),
+                    ..Default::default()
                 },
             }),
             compile_rule(&ActionRule {
@@ -772,6 +838,7 @@ mod tests {
                     command: None,
                     command_timeout: None,
                     mask_with: Some("••••••".into()),
+                    ..Default::default()
                 },
             }),
         ];
@@ -795,6 +862,7 @@ mod tests {
                 command: None,
                 command_timeout: None,
                 mask_with: Some("***".into()),
+                ..Default::default()
             },
         });
 
@@ -817,6 +885,7 @@ mod tests {
                 command: None,
                 command_timeout: None,
                 mask_with: None,
+                ..Default::default()
             },
         });
 
@@ -0,0 +1,64 @@
+use anyhow::{bail, Context};
+use rusqlite::Connection;
+
+use crate::db::repository::{self, Annotation};
+
+/// Set the title and note of entry `id`, keeping the one not given, then
+/// print both.
+pub fn run(conn: &Connection, id: i64, title: Option<String>, note: Option<String>) -> anyhow::Result<()> {
+    let annotation = annotate(conn, id, title, note)?;
+    if let Some(title) = annotation.title {
+        println!("Title: {title}");
+    }
+    if let Some(note) = annotation.note {
+        println!("Note: {note}");
+    }
+    Ok(())
+}
+
+/// Apply `title` and `note` ("" removes one) and return the resulting annotation.
+fn annotate(conn: &Connection, id: i64, title: Option<String>, note: Option<String>) -> anyhow::Result<Annotation> {
+    let Some(current) = repository::get_annotation(conn, id).context("failed to read entry")? else {
+        bail!("entry {id} not found");
+    };
+    if title.is_none() && note.is_none() {
+        return Ok(current);
+    }
+    let annotation = Annotation { title: title.or(current.title), note: note.or(current.note) };
+    repository::set_annotation(conn, id, &annotation).context("failed to annotate entry")?;
+    Ok(repository::get_annotation(conn, id)?.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db_in_memory;
+    use crate::models::entry::ClipboardEntry;
+
+    fn annotated_entry(conn: &Connection) -> i64 {
+        let id = repository::insert_entry(conn, &ClipboardEntry::from_text("SELECT 1".to_owned(), None)).unwrap();
+        annotate(conn, id, Some("Query".to_owned()), Some("for the report".to_owned())).unwrap();
+        id
+    }
+
+    #[test]
+    fn empty_value_clears_one_field_and_keeps_the_other() {
+        let conn = init_db_in_memory().unwrap();
+        let id = annotated_entry(&conn);
+
+        let annotation = annotate(&conn, id, Some(String::new()), None).unwrap();
+        assert_eq!(annotation.title, None);
+        assert_eq!(annotation.note.as_deref(), Some("for the report"));
+
+        let annotation = annotate(&conn, id, None, Some(String::new())).unwrap();
+        assert_eq!(annotation, Annotation::default());
+    }
+
+    #[test]
+    fn missing_entry_is_an_error() {
+        let conn = init_db_in_memory().unwrap();
+        let err = annotate(&conn, 42, Some("Query".to_owned()), None).unwrap_err().to_string();
+        assert_eq!(err, "entry 42 not found");
+        assert!(annotate(&conn, 42, None, None).is_err());
+    }
+}
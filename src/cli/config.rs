@@ -3,27 +3,70 @@ use std::path::Path;
 use anyhow::Context;
 
 use super::ConfigCommands;
+use crate::config::{Config, ConfigOverride};
 
-pub fn run(config_path: &Path, command: &ConfigCommands) -> anyhow::Result<()> {
+pub fn run(config_path: &Path, sets: &[ConfigOverride], command: &ConfigCommands) -> anyhow::Result<()> {
     match command {
-        ConfigCommands::Show => cmd_show(config_path),
+        ConfigCommands::Show { sources: false } => cmd_show(config_path, sets),
+        ConfigCommands::Show { sources: true } => cmd_show_sources(config_path, sets),
         ConfigCommands::Init { force, output } => {
             let target = output.as_deref().unwrap_or(config_path);
             cmd_init(target, *force)
         }
-        ConfigCommands::Validate => cmd_validate(config_path),
+        ConfigCommands::Validate => cmd_validate(config_path, sets),
         ConfigCommands::Path => cmd_path(config_path),
+        ConfigCommands::Schema => cmd_schema(),
     }
 }
 
-fn cmd_show(config_path: &Path) -> anyhow::Result<()> {
-    let config = crate::config::load_config(Some(config_path))
+fn cmd_show(config_path: &Path, sets: &[ConfigOverride]) -> anyhow::Result<()> {
+    let config = crate::config::load_config_with_overrides(Some(config_path), sets)
         .context("failed to load config")?;
     let yaml = serde_yaml::to_string(&config).context("failed to serialize config")?;
     print!("{yaml}");
     Ok(())
 }
 
+/// Print each effective setting annotated with the file that set it.
+fn cmd_show_sources(config_path: &Path, sets: &[ConfigOverride]) -> anyhow::Result<()> {
+    let (config, sources) = crate::config::load_config_with_sources(Some(config_path), sets)
+        .context("failed to load config")?;
+
+    if sources.files.is_empty() {
+        println!("# No config files loaded; all values are defaults.");
+    } else {
+        println!("# Loaded files, in merge order:");
+        for file in &sources.files {
+            println!("#   {}", file.display());
+        }
+    }
+
+    let value = serde_yaml::to_value(&config).context("failed to serialize config")?;
+    let serde_yaml::Value::Mapping(map) = value else {
+        anyhow::bail!("config did not serialize to a mapping");
+    };
+    for (key, value) in &map {
+        let Some(key) = key.as_str() else { continue };
+        if key == "actions" {
+            if config.actions.is_empty() {
+                println!("actions: []  # default");
+                continue;
+            }
+            println!("actions:");
+            for (rule, source) in config.actions.iter().zip(&sources.actions) {
+                println!("  - name: {}  # {}", rule.name, source);
+            }
+            continue;
+        }
+        let source = sources
+            .values
+            .get(key)
+            .map_or_else(|| "default".to_owned(), ToString::to_string);
+        println!("{key}: {}  # {source}", crate::config::render_value(value));
+    }
+    Ok(())
+}
+
 fn cmd_init(config_path: &Path, force: bool) -> anyhow::Result<()> {
     if config_path.exists() && !force {
         anyhow::bail!(
@@ -35,17 +78,17 @@ fn cmd_init(config_path: &Path, force: bool) -> anyhow::Result<()> {
         std::fs::create_dir_all(parent)
             .with_context(|| format!("failed to create directory {}", parent.display()))?;
     }
-    std::fs::write(config_path, crate::config::Config::default_yaml())
+    std::fs::write(config_path, Config::default_yaml())
         .with_context(|| format!("failed to write config to {}", config_path.display()))?;
     println!("Config written to {}", config_path.display());
     Ok(())
 }
 
-fn cmd_validate(config_path: &Path) -> anyhow::Result<()> {
+fn cmd_validate(config_path: &Path, sets: &[ConfigOverride]) -> anyhow::Result<()> {
     // load_config already calls validate(), so a successful load implies valid config.
-    if config_path.exists() {
-        crate::config::load_config(Some(config_path)).context("failed to load config")?;
-    } else {
+    // It runs even without a main file, since conf.d drop-ins may still apply.
+    crate::config::load_config_with_overrides(Some(config_path), sets).context("failed to load config")?;
+    if !config_path.exists() {
         println!(
             "No config file found at {}. Using defaults.",
             config_path.display()
@@ -56,6 +99,13 @@ fn cmd_validate(config_path: &Path) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn cmd_schema() -> anyhow::Result<()> {
+    let schema = serde_json::to_string_pretty(&Config::json_schema())
+        .context("failed to serialize schema")?;
+    println!("{schema}");
+    Ok(())
+}
+
 fn cmd_path(config_path: &Path) -> anyhow::Result<()> {
     println!("{}", config_path.display());
     Ok(())
@@ -7,9 +7,10 @@ use rusqlite::Connection;
 
 use crate::clipboard;
 use crate::config::Config;
-use crate::db::repository;
+use crate::db::repository::{self, StorageOptions};
+use crate::db::retention::RetentionPolicy;
 use crate::models::ClipboardEntry;
-use crate::models::entry::Timestamp;
+use crate::models::entry::{CopyOrigin, Selection, Timestamp};
 
 pub fn run(
     conn: &Connection,
@@ -36,12 +37,19 @@ pub fn run(
     #[cfg(target_os = "linux")]
     clipboard::write_selection_text(arboard::LinuxClipboardKind::Primary, &input);
     let mut entry = ClipboardEntry::from_text(input, None);
+    entry.set_selection(Some(Selection::Cli));
 
     entry.set_mask_text(mask_with);
 
     entry.set_expires_at(ttl.map(Timestamp::after));
 
-    repository::save_or_update(conn, &entry, config.max_history)?;
+    repository::save_entry(
+        conn,
+        &entry,
+        &RetentionPolicy::from_config(config),
+        &StorageOptions::from_config(config),
+        CopyOrigin::Copy,
+    )?;
     debug!("entry saved to database");
 
     Ok(())
@@ -0,0 +1,147 @@
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use rusqlite::Connection;
+
+use crate::cli::DbCommands;
+use crate::config::Config;
+use crate::db::{blob_store, maintenance, thumbnails};
+use crate::ipc::{self, Watcher};
+
+pub fn run(conn: &mut Connection, config: &Config, command: &DbCommands) -> anyhow::Result<()> {
+    match command {
+        DbCommands::Check { fix } => cmd_check(conn, *fix),
+        DbCommands::Vacuum => cmd_vacuum(conn),
+        DbCommands::Backup { path } => cmd_backup(conn, path),
+        DbCommands::Restore { path } => cmd_restore(conn, path),
+        DbCommands::Stats => cmd_stats(conn),
+        DbCommands::Thumbnails { rebuild } => cmd_thumbnails(conn, config, *rebuild),
+    }
+}
+
+fn cmd_check(conn: &Connection, fix: bool) -> anyhow::Result<()> {
+    let integrity = maintenance::integrity_check(conn)?;
+    if integrity.is_empty() {
+        println!("Integrity: ok");
+    } else {
+        println!("Integrity: {} problems", integrity.len());
+        for message in &integrity {
+            println!("  {message}");
+        }
+    }
+
+    let blobs = blob_store::check(conn)?;
+    println!(
+        "Blob store: {} files, {} missing, {} orphaned",
+        blobs.files,
+        blobs.missing.len(),
+        blobs.orphaned.len()
+    );
+    for id in &blobs.missing {
+        println!("  missing blob for entry {id}");
+    }
+    for path in &blobs.orphaned {
+        println!("  orphaned {}", path.display());
+    }
+
+    let mut hashes = maintenance::verify_hashes(conn)?;
+    // Entries without their blob file are already reported above.
+    hashes.mismatched.retain(|id| !blobs.missing.contains(id));
+    hashes.unreadable.retain(|(id, _)| !blobs.missing.contains(id));
+    println!(
+        "Content hashes: {} checked, {} mismatched, {} unreadable",
+        hashes.checked,
+        hashes.mismatched.len(),
+        hashes.unreadable.len()
+    );
+    for id in &hashes.mismatched {
+        println!("  content of entry {id} does not match its hash");
+    }
+    for (id, reason) in &hashes.unreadable {
+        println!("  cannot read entry {id}: {reason}");
+    }
+
+    let unfixable = !integrity.is_empty() || !hashes.mismatched.is_empty() || !hashes.unreadable.is_empty();
+    if blobs.is_ok() && !unfixable {
+        return Ok(());
+    }
+    if !fix {
+        bail!("database check found problems; run `clio db check --fix` to repair");
+    }
+    blob_store::repair(conn, &blobs)?;
+    println!(
+        "Removed {} orphaned files and {} entries without content",
+        blobs.orphaned.len(),
+        blobs.missing.len()
+    );
+    if unfixable {
+        bail!("database check found problems `--fix` cannot repair; restore a backup with `clio db restore`");
+    }
+    Ok(())
+}
+
+fn cmd_vacuum(conn: &Connection) -> anyhow::Result<()> {
+    let result = maintenance::vacuum(conn).context("failed to vacuum database")?;
+    println!(
+        "Database: {} KB -> {} KB",
+        result.before / 1024,
+        result.after / 1024
+    );
+    Ok(())
+}
+
+fn cmd_backup(conn: &Connection, path: &Path) -> anyhow::Result<()> {
+    maintenance::backup(conn, path).with_context(|| format!("failed to back up to {}", path.display()))?;
+    println!("Backed up to {}", path.display());
+    Ok(())
+}
+
+fn cmd_restore(conn: &mut Connection, path: &Path) -> anyhow::Result<()> {
+    let running = conn.path().and_then(|db_path| ipc::running_watcher(Path::new(db_path)));
+    if let Some(Watcher { pid, .. }) = running {
+        bail!("clio watch is running (PID {pid}); stop it before restoring");
+    }
+    if !path.is_file() {
+        bail!("backup {} does not exist", path.display());
+    }
+    maintenance::restore(conn, path).with_context(|| format!("failed to restore {}", path.display()))?;
+    println!("Restored from {}", path.display());
+    Ok(())
+}
+
+fn cmd_stats(conn: &Connection) -> anyhow::Result<()> {
+    let stats = maintenance::stats(conn)?;
+    println!("Schema version: {}", stats.schema_version);
+    println!(
+        "Database: {} KB ({} KB free)",
+        stats.db_bytes / 1024,
+        stats.free_bytes / 1024
+    );
+    for t in &stats.types {
+        println!(
+            "  {:<8} {:>7} entries {:>9} KB  ({} compressed, {} in blob store)",
+            t.content_type,
+            t.entries,
+            t.bytes / 1024,
+            t.compressed,
+            t.in_blob_store
+        );
+    }
+    println!("Blob store: {} files, {} KB", stats.blob_files, stats.blob_bytes / 1024);
+    Ok(())
+}
+
+fn cmd_thumbnails(conn: &Connection, config: &Config, rebuild: bool) -> anyhow::Result<()> {
+    let max_px = u32::try_from(config.image_preview_max_px).context("invalid image_preview_max_px")?;
+    let report = thumbnails::backfill(conn, max_px, rebuild)?;
+    println!(
+        "Thumbnails: {} created, {} images checked, {} failed",
+        report.created,
+        report.checked,
+        report.failed.len()
+    );
+    for id in &report.failed {
+        println!("  cannot read image of entry {id}");
+    }
+    Ok(())
+}
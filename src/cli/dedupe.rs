@@ -0,0 +1,50 @@
+use rusqlite::Connection;
+
+use crate::config::Config;
+use crate::db::blob_store;
+use crate::db::near_duplicates::{self, NearDuplicate, DEFAULT_DISTANCE};
+
+/// Delete images that look like a newer one, or with `dry_run` only list them.
+pub fn run(conn: &Connection, config: &Config, distance: Option<u32>, dry_run: bool) -> anyhow::Result<()> {
+    let distance = distance.or(config.image_dedupe_distance).unwrap_or(DEFAULT_DISTANCE);
+    // A dry run changes nothing, not even the hashes of old images.
+    let (missing, failed) = near_duplicates::missing_hashes(conn)?;
+    let duplicates = near_duplicates::find_all(conn, distance, &missing)?;
+    if !dry_run {
+        let tx = conn.unchecked_transaction()?;
+        near_duplicates::store_hashes(&tx, &missing)?;
+        for duplicate in &duplicates {
+            near_duplicates::merge_into(&tx, duplicate.id, duplicate.kept)?;
+        }
+        tx.commit()?;
+        blob_store::remove_released(conn)?;
+    }
+    print!("{}", format_report(&duplicates, distance, dry_run));
+    for id in &failed {
+        println!("Cannot read image of entry {id}; skipped");
+    }
+    Ok(())
+}
+
+fn format_report(duplicates: &[NearDuplicate], distance: u32, dry_run: bool) -> String {
+    let verb = if dry_run { "Would delete" } else { "Deleted" };
+    let mut out = format!("{verb} {} near-duplicate images (distance <= {distance})\n", duplicates.len());
+    for d in duplicates {
+        out.push_str(&format!("  {:>6}  like {} (distance {})\n", d.id, d.kept, d.distance));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_lists_duplicates() {
+        let duplicates = [NearDuplicate { id: 3, kept: 7, distance: 2 }];
+        let out = format_report(&duplicates, 5, true);
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines[0], "Would delete 1 near-duplicate images (distance <= 5)");
+        assert_eq!(lines[1], "       3  like 7 (distance 2)");
+    }
+}
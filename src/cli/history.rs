@@ -3,15 +3,21 @@ use std::process::Command;
 
 use anyhow::Context;
 
+use crate::config::ConfigOverride;
+
 const HISTORY_BINARY: &str = "clio-history";
 
-pub fn run(config_path: Option<&Path>, db_path: PathBuf) -> anyhow::Result<()> {
+pub fn run(config_path: Option<&Path>, sets: &[ConfigOverride], db_path: PathBuf) -> anyhow::Result<()> {
     let binary = find_clio_history();
     let mut cmd = Command::new(&binary);
     cmd.arg("--db-path").arg(&db_path);
     if let Some(path) = config_path {
         cmd.arg("--config").arg(path);
     }
+    // clio-history has no --set; pass overrides as the equivalent CLIO_<KEY> variables.
+    for set in sets {
+        cmd.env(set.env_var(), &set.value);
+    }
     let status = cmd.status().with_context(|| {
         format!(
             "failed to launch {HISTORY_BINARY}; ensure it is installed alongside clio or in PATH"
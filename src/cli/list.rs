@@ -1,12 +1,23 @@
 use anyhow::Context;
 use rusqlite::Connection;
 
-use crate::db::repository;
-use crate::models::entry::EntryContent;
+use crate::config::HistorySort;
+use crate::db::repository::{self, EntryFilter};
+use crate::models::entry::{ClipboardEntry, EntryContent, FileList, FileOperation};
 use crate::time_fmt::format_created_at;
 
 use super::ListFormat;
 
+/// Icon shown before copied files.
+const FILES_ICON: &str = "\u{1F4C4}";
+/// Icon shown before cut files.
+const CUT_FILES_ICON: &str = "\u{2702}";
+/// Shown before pinned entries.
+const PIN_ICON: &str = "\u{1F4CC} ";
+
+/// Entries fetched per query; `--limit` is served in pages of this size.
+const PAGE_SIZE: usize = 200;
+
 /// 300 spaces to push the ID far beyond the visible area in dmenu/rofi/wofi.
 const SPACER: &str = concat!(
     "                                                  ",
@@ -22,14 +33,34 @@ pub fn run(
     _format: &ListFormat,
     preview_length: usize,
     limit: usize,
+    filter: &EntryFilter,
+    sort: HistorySort,
+    date_format: &str,
 ) -> anyhow::Result<()> {
-    let entries = repository::list_entries_preview(conn, limit, 0, preview_length)
-        .context("failed to list entries")?;
+    let mut remaining = limit;
+    let mut after = None;
+    while remaining > 0 {
+        let page_size = remaining.min(PAGE_SIZE);
+        let page = repository::list_entries_preview(conn, page_size, after.as_ref(), preview_length, filter, sort)
+            .context("failed to list entries")?;
+        remaining -= page.entries.len();
+        print_entries(&page.entries, date_format);
+        match page.next {
+            Some(next) => after = Some(next),
+            None => break,
+        }
+    }
 
-    for entry in &entries {
+    Ok(())
+}
+
+fn print_entries(entries: &[ClipboardEntry], date_format: &str) {
+    for entry in entries {
         let id = entry.id.unwrap_or(0);
 
-        let preview = if let Some(mask) = &entry.mask_text {
+        let preview = if let Some(title) = &entry.title {
+            title.split_whitespace().collect::<Vec<_>>().join(" ")
+        } else if let Some(mask) = &entry.mask_text {
             mask.clone()
         } else {
             match &entry.content {
@@ -37,18 +68,28 @@ pub fn run(
                     raw.split_whitespace().collect::<Vec<_>>().join(" ")
                 }
                 EntryContent::Image(_) => "[image]".to_string(),
+                EntryContent::Files(files) => files_preview(files),
             }
         };
 
-        let time_ago = entry.created_at.as_ref().map(format_created_at).unwrap_or_default();
-        println!("{time_ago} {preview}{SPACER}{id}");
+        let time_ago = entry.created_at.as_ref().map(|ts| format_created_at(ts, date_format)).unwrap_or_default();
+        let pin = if entry.pinned { PIN_ICON } else { "" };
+        println!("{time_ago} {pin}{preview}{SPACER}{id}");
     }
+}
 
-    Ok(())
+fn files_preview(files: &FileList) -> String {
+    let icon = match files.operation {
+        FileOperation::Copy => FILES_ICON,
+        FileOperation::Cut => CUT_FILES_ICON,
+    };
+    format!("{icon} {}", files.file_names().join(", "))
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     fn collapse_whitespace(s: &str) -> String {
         s.split_whitespace().collect::<Vec<_>>().join(" ")
     }
@@ -62,4 +103,13 @@ mod tests {
     fn test_whitespace_collapse_only_spaces() {
         assert_eq!(collapse_whitespace("  a  b  "), "a b");
     }
+
+    #[test]
+    fn test_files_preview() {
+        let files = FileList::parse("copy\nfile:///tmp/a.txt\nfile:///home/u/My%20Doc.odt").unwrap();
+        assert_eq!(files_preview(&files), format!("{FILES_ICON} a.txt, My Doc.odt"));
+
+        let cut = FileList::parse("cut\nfile:///tmp/a.txt").unwrap();
+        assert_eq!(files_preview(&cut), format!("{CUT_FILES_ICON} a.txt"));
+    }
 }
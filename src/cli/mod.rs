@@ -1,9 +1,20 @@
+pub mod annotate;
 pub mod config;
 pub mod copy;
+pub mod db;
+pub mod dedupe;
 pub mod history;
 pub mod list;
+pub mod pin;
+pub mod profile;
+pub mod prune;
 pub mod select;
+pub mod service;
 pub mod show;
+pub mod snippet;
+pub mod stats;
+pub mod tag;
+pub mod timeline;
 pub mod watch;
 
 use std::path::PathBuf;
@@ -11,6 +22,9 @@ use std::time::Duration;
 
 use clap::{Parser, Subcommand};
 
+use crate::config::{ConfigOverride, HistorySort};
+use crate::models::entry::{Selection, Timestamp};
+
 /// Command name for the internal clipboard server subprocess.
 /// Must match the `#[command(name = "_serve-clipboard")]` attribute on `Commands::ServeClipboard`.
 pub const SERVE_CLIPBOARD_CMD: &str = "_serve-clipboard";
@@ -22,10 +36,30 @@ pub struct Cli {
     #[arg(short, long, value_name = "PATH")]
     pub config: Option<PathBuf>,
 
+    /// Override a config setting for this run (repeatable), e.g. `--set max_history=50`.
+    /// Applied after the config files and `CLIO_<KEY>` environment variables.
+    #[arg(long = "set", value_name = "KEY=VALUE", global = true)]
+    pub set: Vec<ConfigOverride>,
+
+    /// Use this config profile for this run (`default` for none)
+    #[arg(long, value_name = "NAME", global = true)]
+    pub profile: Option<String>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
+impl Cli {
+    /// `--profile` and `--set` flags as config overrides, in precedence order.
+    pub fn config_overrides(&self) -> Vec<ConfigOverride> {
+        let profile = self.profile.iter().map(|name| ConfigOverride {
+            key: "profile".to_owned(),
+            value: name.clone(),
+        });
+        profile.chain(self.set.iter().cloned()).collect()
+    }
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
     /// Show current clipboard content
@@ -59,12 +93,106 @@ pub enum Commands {
         /// Max entries to show
         #[arg(long, default_value_t = 100)]
         limit: usize,
+        /// Only show entries from these selections (comma-separated);
+        /// `both` entries match `clipboard` and `primary`
+        #[arg(long, value_delimiter = ',')]
+        selection: Vec<SelectionArg>,
+        /// Only show entries with any of these tags (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        tag: Vec<String>,
+        /// Entry order (default: `sort` from the config)
+        #[arg(long)]
+        sort: Option<HistorySort>,
     },
     /// Select entry by ID and copy to clipboard
     Select {
         #[command(subcommand)]
         source: SelectSource,
     },
+    /// Show, add or remove the tags of an entry
+    Tag {
+        /// Entry ID
+        id: i64,
+        #[command(subcommand)]
+        action: Option<TagAction>,
+    },
+    /// Show or set the title and note of an entry
+    Annotate {
+        /// Entry ID
+        id: i64,
+        /// Title shown instead of the content; "" removes it
+        #[arg(long)]
+        title: Option<String>,
+        /// Free-form note; "" removes it
+        #[arg(long)]
+        note: Option<String>,
+    },
+    /// Pin an entry so that retention never prunes it
+    Pin {
+        /// Entry ID
+        id: i64,
+    },
+    /// Unpin an entry
+    Unpin {
+        /// Entry ID
+        id: i64,
+    },
+    /// List when an entry was copied, or show which entry was on the clipboard at a time
+    Timeline {
+        /// Entry ID
+        #[arg(required_unless_present = "at", conflicts_with = "at")]
+        id: Option<i64>,
+        /// Local time ("14:05", "2024-05-01 14:05") or how long ago ("90m")
+        #[arg(long, value_parser = parse_time)]
+        at: Option<Timestamp>,
+    },
+    /// Manage named snippets, kept apart from the history
+    Snippet {
+        #[command(subcommand)]
+        command: SnippetCommands,
+    },
+    /// Delete entries over the retention limits (max_history, max_age, max_images, ...)
+    Prune {
+        /// Only report what would be deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Delete near-duplicate images, keeping the newest of each group
+    Dedupe {
+        /// Compare images by perceptual hash (currently the only mode)
+        #[arg(long, required = true)]
+        images: bool,
+        /// Maximum differing bits of 64 (default: `image_dedupe_distance`, else 5)
+        #[arg(long, value_parser = clap::value_parser!(u32).range(0..=32))]
+        distance: Option<u32>,
+        /// Only list what would be deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Report clipboard usage: entries by type and app, sizes, rule matches, busiest hours
+    Stats {
+        /// Only count the last period (e.g. "7d", "12h")
+        #[arg(long, value_parser = parse_duration)]
+        since: Option<Duration>,
+        /// Output format
+        #[arg(long, default_value = "text")]
+        format: StatsFormat,
+    },
+    /// Database maintenance
+    Db {
+        #[command(subcommand)]
+        command: DbCommands,
+    },
+    /// List config profiles or switch the active one
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommands,
+    },
+    /// Run `clio watch` at login (systemd user unit or XDG autostart)
+    Service {
+        #[command(subcommand)]
+        command: ServiceCommands,
+    },
     /// Internal: serve clipboard content from stdin (used by spawn_clipboard_server)
     #[command(hide = true, name = "_serve-clipboard")]
     ServeClipboard,
@@ -75,6 +203,78 @@ pub enum ListFormat {
     Dmenu,
 }
 
+/// `--selection` values, mapped onto the stored `Selection`.
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum SelectionArg {
+    /// CLIPBOARD (Ctrl+C / Ctrl+V)
+    Clipboard,
+    /// PRIMARY (mouse selection / middle-click paste)
+    Primary,
+    /// Seen in both CLIPBOARD and PRIMARY
+    Both,
+    /// Stored via `clio copy`
+    Cli,
+}
+
+impl From<SelectionArg> for Selection {
+    fn from(arg: SelectionArg) -> Self {
+        match arg {
+            SelectionArg::Clipboard => Self::Clipboard,
+            SelectionArg::Primary => Self::Primary,
+            SelectionArg::Both => Self::Both,
+            SelectionArg::Cli => Self::Cli,
+        }
+    }
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum StatsFormat {
+    Text,
+    Json,
+}
+
+/// Tag subcommands; without one, `clio tag` prints the entry's tags.
+#[derive(Subcommand)]
+pub enum TagAction {
+    /// Tag the entry
+    Add {
+        /// Tags, space- or comma-separated
+        #[arg(required = true, value_delimiter = ',')]
+        tags: Vec<String>,
+    },
+    /// Remove tags from the entry
+    Rm {
+        /// Tags, space- or comma-separated
+        #[arg(required = true, value_delimiter = ',')]
+        tags: Vec<String>,
+    },
+}
+
+/// Snippet subcommands.
+#[derive(Subcommand)]
+pub enum SnippetCommands {
+    /// Save a snippet read from stdin, replacing one with the same name
+    Add {
+        name: String,
+    },
+    /// List snippets with the first line of each
+    List,
+    /// Delete a snippet
+    Rm {
+        name: String,
+    },
+    /// Expand a snippet's placeholders and copy it to the clipboard
+    Use {
+        name: String,
+        /// Value of a `{prompt:NAME}` placeholder, instead of asking (repeatable)
+        #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_var)]
+        vars: Vec<(String, String)>,
+        /// Print the expanded snippet instead of copying it
+        #[arg(long)]
+        print: bool,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum SelectSource {
     /// Read entry ID from stdin (for piping from dmenu)
@@ -86,11 +286,80 @@ pub enum SelectSource {
     },
 }
 
+/// Database maintenance subcommands.
+#[derive(Subcommand)]
+pub enum DbCommands {
+    /// Check database integrity, content hashes and the blob store
+    Check {
+        /// Delete orphaned files and entries whose file is missing
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Rebuild the database file to reclaim space left by deleted entries
+    Vacuum,
+    /// Copy the database and its blob store to PATH (safe while `clio watch` runs)
+    Backup {
+        path: PathBuf,
+    },
+    /// Replace the database with a backup made by `clio db backup`
+    Restore {
+        path: PathBuf,
+    },
+    /// Show schema version, entry counts and sizes per content type
+    Stats,
+    /// Create missing thumbnails of images larger than `image_preview_max_px`
+    Thumbnails {
+        /// Recreate all thumbnails, e.g. after changing `image_preview_max_px`
+        #[arg(long)]
+        rebuild: bool,
+    },
+}
+
+/// Profile subcommands.
+#[derive(Subcommand)]
+pub enum ProfileCommands {
+    /// List profiles, marking the active one
+    List,
+    /// Make a profile active for all clio commands and the running `clio watch`
+    Switch {
+        /// Profile name from `profiles:`, or `default` for the top-level settings
+        name: String,
+    },
+}
+
+/// Service management subcommands.
+#[derive(Subcommand)]
+pub enum ServiceCommands {
+    /// Install a systemd user unit or XDG autostart entry for `clio watch`
+    Install {
+        /// What to generate
+        #[arg(long, default_value = "systemd")]
+        target: ServiceTarget,
+        /// Overwrite an existing file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Disable and remove installed service files
+    Uninstall,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum ServiceTarget {
+    /// `~/.config/systemd/user/clio-watch.service` (Type=notify)
+    Systemd,
+    /// `~/.config/autostart/clio-watch.desktop`
+    Autostart,
+}
+
 /// Configuration management subcommands.
 #[derive(Subcommand)]
 pub enum ConfigCommands {
     /// Show current effective configuration
-    Show,
+    Show {
+        /// Annotate each value with the file it came from (or `default`)
+        #[arg(long)]
+        sources: bool,
+    },
     /// Create default configuration file
     Init {
         /// Overwrite existing file
@@ -104,8 +373,35 @@ pub enum ConfigCommands {
     Validate,
     /// Print configuration file path
     Path,
+    /// Print a JSON Schema of the config file (for editor completion)
+    Schema,
 }
 
 fn parse_duration(s: &str) -> Result<Duration, humantime::DurationError> {
     humantime::parse_duration(s)
 }
+
+/// A local `HH:MM` today, a local `YYYY-MM-DD HH:MM`, or a duration before now.
+fn parse_time(s: &str) -> Result<Timestamp, String> {
+    use chrono::{Local, NaiveDateTime, NaiveTime, TimeZone};
+
+    let local = if let Ok(time) = NaiveTime::parse_from_str(s, "%H:%M") {
+        Local::now().date_naive().and_time(time)
+    } else if let Ok(datetime) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M") {
+        datetime
+    } else {
+        return humantime::parse_duration(s)
+            .map(Timestamp::before)
+            .map_err(|_| format!("expected HH:MM, YYYY-MM-DD HH:MM or a duration, got '{s}'"));
+    };
+    Local
+        .from_local_datetime(&local)
+        .earliest()
+        .map(|time| Timestamp::from_millis(time.timestamp_millis()))
+        .ok_or_else(|| format!("'{s}' does not exist in the local timezone"))
+}
+
+fn parse_var(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s.split_once('=').ok_or_else(|| format!("expected NAME=VALUE, got '{s}'"))?;
+    Ok((name.to_owned(), value.to_owned()))
+}
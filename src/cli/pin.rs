@@ -0,0 +1,12 @@
+use anyhow::{bail, Context};
+use rusqlite::Connection;
+
+use crate::db::repository;
+
+/// Pin or unpin entry `id`.
+pub fn run(conn: &Connection, id: i64, pinned: bool) -> anyhow::Result<()> {
+    if !repository::set_pinned(conn, id, pinned).context("failed to update entry")? {
+        bail!("entry {id} not found");
+    }
+    Ok(())
+}
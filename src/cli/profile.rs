@@ -0,0 +1,37 @@
+use anyhow::Context;
+
+use crate::config::{self, Config, DEFAULT_PROFILE};
+use crate::ipc::{self, Watcher};
+
+use super::ProfileCommands;
+
+pub fn run(command: &ProfileCommands, config: &Config) -> anyhow::Result<()> {
+    match command {
+        ProfileCommands::List => cmd_list(config),
+        ProfileCommands::Switch { name } => cmd_switch(config, name),
+    }
+}
+
+fn cmd_list(config: &Config) -> anyhow::Result<()> {
+    let active = config.profile.as_deref().unwrap_or(DEFAULT_PROFILE);
+    let names = std::iter::once(DEFAULT_PROFILE).chain(config.profiles.keys().map(String::as_str));
+    for name in names {
+        let marker = if name == active { '*' } else { ' ' };
+        println!("{marker} {name}");
+    }
+    Ok(())
+}
+
+fn cmd_switch(config: &Config, name: &str) -> anyhow::Result<()> {
+    config::activate_profile(config, name)?;
+    // The watcher still holds the lock of the database it records into now.
+    let watcher = ipc::request_reload(&config::resolve_db_path(config)).context("failed to notify clio watch")?;
+    match watcher {
+        Some(Watcher { pid, pinned_profile: Some(pinned) }) if pinned != name => println!(
+            "Switched to profile {name}; clio watch (PID {pid}) was started with profile {pinned} and keeps it"
+        ),
+        Some(Watcher { pid, .. }) => println!("Switched to profile {name} (clio watch, PID {pid}, notified)"),
+        None => println!("Switched to profile {name}"),
+    }
+    Ok(())
+}
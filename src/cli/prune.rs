@@ -0,0 +1,64 @@
+use rusqlite::Connection;
+
+use crate::config::Config;
+use crate::db::retention::{self, PruneReport, RetentionPolicy};
+
+/// Apply the retention settings now, or with `dry_run` only report what
+/// would be deleted.
+pub fn run(conn: &Connection, config: &Config, dry_run: bool) -> anyhow::Result<()> {
+    let policy = RetentionPolicy::from_config(config);
+    let report = if dry_run {
+        retention::plan(conn, &policy)?
+    } else {
+        retention::enforce(conn, &policy)?
+    };
+    print!("{}", format_report(&report, &policy, dry_run));
+    Ok(())
+}
+
+fn format_report(report: &PruneReport, policy: &RetentionPolicy, dry_run: bool) -> String {
+    let verb = if dry_run { "Would delete" } else { "Deleted" };
+    let mut out = format!(
+        "{verb} {} entries ({} KB)\n",
+        report.total_entries(),
+        report.total_bytes().div_ceil(1024)
+    );
+    for count in &report.counts {
+        out.push_str(&format!(
+            "  {:<34} {:>6} entries {:>8} KB\n",
+            count.reason.as_str(),
+            count.entries,
+            count.bytes.div_ceil(1024)
+        ));
+    }
+    let used_kb = report.db_size_bytes.div_ceil(1024);
+    match policy.max_db_size_bytes {
+        Some(limit) => out.push_str(&format!("Database: {used_kb} KB used, limit {} KB\n", limit / 1024)),
+        None => out.push_str(&format!("Database: {used_kb} KB used\n")),
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::retention::{PruneCount, PruneReason};
+
+    #[test]
+    fn report_lists_each_rule() {
+        let report = PruneReport {
+            counts: vec![
+                PruneCount { reason: PruneReason::Expired, entries: 2, bytes: 100 },
+                PruneCount { reason: PruneReason::MaxImages, entries: 1, bytes: 4096 },
+            ],
+            db_size_bytes: 8192,
+        };
+        let policy = RetentionPolicy { max_db_size_bytes: Some(1024 * 1024), ..RetentionPolicy::max_history(10) };
+        let out = format_report(&report, &policy, true);
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines[0], "Would delete 3 entries (5 KB)");
+        assert!(lines[1].trim_start().starts_with("expired") && lines[1].contains(" 2 entries"), "{out}");
+        assert!(lines[2].trim_start().starts_with("max_images") && lines[2].contains("4 KB"), "{out}");
+        assert_eq!(lines[3], "Database: 8 KB used, limit 1024 KB");
+    }
+}
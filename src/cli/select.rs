@@ -5,8 +5,8 @@ use log::debug;
 use rusqlite::Connection;
 
 use crate::clipboard;
-use crate::db::repository;
-use crate::models::entry::EntryContent;
+use crate::db::{copy_events, repository};
+use crate::models::entry::{CopyOrigin, EntryContent};
 
 use super::SelectSource;
 
@@ -23,6 +23,7 @@ pub fn run(conn: &Connection, source: &SelectSource) -> anyhow::Result<()> {
         .ok_or_else(|| anyhow::anyhow!("entry {id} not found"))?;
 
     clipboard::write_entry_to_clipboard(&entry.content)?;
+    copy_events::record_use(conn, id, CopyOrigin::Select).context("failed to record copy")?;
 
     // Also set PRIMARY selection for text so middle-click paste works.
     #[cfg(target_os = "linux")]
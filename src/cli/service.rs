@@ -0,0 +1,244 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use anyhow::Context;
+use directories::BaseDirs;
+use log::warn;
+
+use crate::config::Config;
+
+use super::{ServiceCommands, ServiceTarget};
+
+/// Name of the generated systemd user unit.
+const UNIT_NAME: &str = "clio-watch.service";
+/// Name of the generated XDG autostart entry.
+const DESKTOP_NAME: &str = "clio-watch.desktop";
+/// Lower bound for the unit's `WatchdogSec`.
+const MIN_WATCHDOG: Duration = Duration::from_secs(30);
+/// `WatchdogSec` is at least this many poll intervals, so slow polling never trips it.
+/// It is fixed when the unit is written; a new `watch_interval` needs a reinstall.
+const WATCHDOG_INTERVALS: u32 = 4;
+
+pub fn run(
+    command: &ServiceCommands,
+    config_path: Option<&Path>,
+    config: &Config,
+) -> anyhow::Result<()> {
+    let config_home = xdg_config_home()?;
+    match command {
+        ServiceCommands::Install { target, force } => {
+            let exec = exec_line(config_path, *target)?;
+            cmd_install(&config_home, *target, &exec, config, *force)
+        }
+        ServiceCommands::Uninstall => cmd_uninstall(&config_home),
+    }
+}
+
+fn xdg_config_home() -> anyhow::Result<PathBuf> {
+    BaseDirs::new()
+        .map(|dirs| dirs.config_dir().to_path_buf())
+        .context("cannot determine the user config directory")
+}
+
+fn unit_path(config_home: &Path) -> PathBuf {
+    config_home.join("systemd").join("user").join(UNIT_NAME)
+}
+
+fn desktop_path(config_home: &Path) -> PathBuf {
+    config_home.join("autostart").join(DESKTOP_NAME)
+}
+
+/// Command line that starts the watcher: this binary, the `--config` override if any, and `watch`.
+fn exec_line(config_path: Option<&Path>, target: ServiceTarget) -> anyhow::Result<String> {
+    let exe = std::env::current_exe().context("cannot determine the clio executable path")?;
+    let mut args = vec![quote_arg(&exe.to_string_lossy(), target)];
+    if let Some(path) = config_path {
+        let path = std::path::absolute(path)
+            .with_context(|| format!("failed to resolve {}", path.display()))?;
+        args.push("--config".to_owned());
+        args.push(quote_arg(&path.to_string_lossy(), target));
+    }
+    args.push("watch".to_owned());
+    Ok(args.join(" "))
+}
+
+/// Quote an argument for `ExecStart=` or `Exec=`; both accept double-quoted words.
+/// systemd expands `$VAR` and `%` specifiers, so those become `$$` and `%%`.
+/// A desktop entry backslash-escapes `$` and `` ` `` in quotes, then unescapes
+/// the whole value once more as a string, so its backslashes are doubled.
+fn quote_arg(arg: &str, target: ServiceTarget) -> String {
+    let special = |c: char| c.is_whitespace() || matches!(c, '"' | '\\' | '\'' | '$' | '%' | '`');
+    if !arg.is_empty() && !arg.contains(special) {
+        return arg.to_owned();
+    }
+    let quoted = arg.replace('\\', "\\\\").replace('"', "\\\"");
+    let escaped = match target {
+        ServiceTarget::Systemd => quoted.replace('$', "$$"),
+        ServiceTarget::Autostart => quoted.replace('$', "\\$").replace('`', "\\`").replace('\\', "\\\\"),
+    };
+    format!("\"{}\"", escaped.replace('%', "%%"))
+}
+
+fn render_unit(exec: &str, config: &Config) -> String {
+    let watchdog = (config.watch_interval * WATCHDOG_INTERVALS).max(MIN_WATCHDOG);
+    format!(
+        "[Unit]
+Description=Clio clipboard history watcher
+PartOf=graphical-session.target
+After=graphical-session.target
+
+[Service]
+Type=notify
+ExecStart={exec}
+ExecReload=/bin/kill -HUP $MAINPID
+Restart=on-failure
+RestartSec=2
+WatchdogSec={}
+
+[Install]
+WantedBy=graphical-session.target
+",
+        watchdog.as_secs().max(1)
+    )
+}
+
+fn render_desktop(exec: &str) -> String {
+    format!(
+        "[Desktop Entry]
+Type=Application
+Name=Clio clipboard watcher
+Comment=Record clipboard history
+Exec={exec}
+Terminal=false
+NoDisplay=true
+X-GNOME-Autostart-enabled=true
+"
+    )
+}
+
+fn cmd_install(
+    config_home: &Path,
+    target: ServiceTarget,
+    exec: &str,
+    config: &Config,
+    force: bool,
+) -> anyhow::Result<()> {
+    let (path, contents) = match target {
+        ServiceTarget::Systemd => (unit_path(config_home), render_unit(exec, config)),
+        ServiceTarget::Autostart => (desktop_path(config_home), render_desktop(exec)),
+    };
+    if path.exists() && !force {
+        anyhow::bail!("{} already exists. Use --force to overwrite.", path.display());
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create directory {}", parent.display()))?;
+    }
+    std::fs::write(&path, contents)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    println!("Installed {}", path.display());
+
+    match target {
+        ServiceTarget::Systemd => {
+            println!("Enable it with:");
+            println!("  systemctl --user daemon-reload");
+            println!("  systemctl --user enable --now {UNIT_NAME}");
+        }
+        ServiceTarget::Autostart => println!("clio watch will start with your next desktop session."),
+    }
+    Ok(())
+}
+
+fn cmd_uninstall(config_home: &Path) -> anyhow::Result<()> {
+    let unit = unit_path(config_home);
+    let desktop = desktop_path(config_home);
+    if !unit.exists() && !desktop.exists() {
+        println!("No clio service files installed.");
+        return Ok(());
+    }
+
+    if unit.exists() {
+        // Disable first: once the unit file is gone systemctl can no longer resolve it.
+        systemctl(&["disable", "--now", UNIT_NAME]);
+        remove(&unit)?;
+        systemctl(&["daemon-reload"]);
+    }
+    if desktop.exists() {
+        remove(&desktop)?;
+    }
+    Ok(())
+}
+
+fn remove(path: &Path) -> anyhow::Result<()> {
+    std::fs::remove_file(path).with_context(|| format!("failed to remove {}", path.display()))?;
+    println!("Removed {}", path.display());
+    Ok(())
+}
+
+/// Best-effort `systemctl --user`: missing systemd is not an error.
+fn systemctl(args: &[&str]) {
+    match Command::new("systemctl").arg("--user").args(args).status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => warn!("systemctl --user {} exited with {status}", args.join(" ")),
+        Err(e) => warn!("running systemctl: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_arg_only_when_needed() {
+        let quote = |arg| quote_arg(arg, ServiceTarget::Systemd);
+        assert_eq!(quote("/usr/bin/clio"), "/usr/bin/clio");
+        assert_eq!(quote("/home/me/my bin/clio"), "\"/home/me/my bin/clio\"");
+        assert_eq!(quote("a\"b"), "\"a\\\"b\"");
+        assert_eq!(quote("100%"), "\"100%%\"");
+        assert_eq!(quote("$HOME/clio"), "\"$$HOME/clio\"");
+    }
+
+    #[test]
+    fn quote_arg_for_desktop_entry() {
+        let quote = |arg| quote_arg(arg, ServiceTarget::Autostart);
+        assert_eq!(quote("/usr/bin/clio"), "/usr/bin/clio");
+        assert_eq!(quote("$HOME/clio"), "\"\\\\$HOME/clio\"");
+        assert_eq!(quote("a\"b"), "\"a\\\\\"b\"");
+        assert_eq!(quote("100%"), "\"100%%\"");
+    }
+
+    #[test]
+    fn unit_is_notify_type_with_watchdog() {
+        let unit = render_unit("/usr/bin/clio watch", &Config::default());
+        assert!(unit.contains("Type=notify"));
+        assert!(unit.contains("ExecStart=/usr/bin/clio watch"));
+        assert!(unit.contains("WatchdogSec=30"));
+
+        let slow = Config { watch_interval: Duration::from_secs(20), ..Config::default() };
+        assert!(render_unit("clio watch", &slow).contains("WatchdogSec=80"));
+    }
+
+    #[test]
+    fn install_and_uninstall_autostart() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::default();
+
+        cmd_install(dir.path(), ServiceTarget::Autostart, "clio watch", &config, false).unwrap();
+        let path = desktop_path(dir.path());
+        assert!(std::fs::read_to_string(&path).unwrap().contains("Exec=clio watch"));
+
+        assert!(cmd_install(dir.path(), ServiceTarget::Autostart, "clio watch", &config, false).is_err());
+        cmd_install(dir.path(), ServiceTarget::Autostart, "clio watch", &config, true).unwrap();
+
+        cmd_uninstall(dir.path()).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn install_systemd_unit_path() {
+        let dir = tempfile::tempdir().unwrap();
+        cmd_install(dir.path(), ServiceTarget::Systemd, "clio watch", &Config::default(), false).unwrap();
+        assert!(dir.path().join("systemd/user/clio-watch.service").exists());
+    }
+}
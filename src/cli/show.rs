@@ -1,6 +1,7 @@
 use anyhow::bail;
 
 use crate::clipboard::{self, ClipboardContent};
+use crate::models::images;
 
 pub fn run() -> anyhow::Result<()> {
     match clipboard::read_clipboard()? {
@@ -12,9 +13,22 @@ pub fn run() -> anyhow::Result<()> {
             width,
             height,
             rgba_bytes,
+            encoded,
         } => {
-            let size_kb = rgba_bytes.len() / 1024;
-            println!("Image: {width}x{height} ({size_kb} KB RGBA)");
+            match encoded {
+                Some(bytes) => println!(
+                    "Image: {width}x{height} {} ({} KB)",
+                    images::format_name(&bytes),
+                    bytes.len() / 1024
+                ),
+                None => println!("Image: {width}x{height} ({} KB RGBA)", rgba_bytes.len() / 1024),
+            }
+            Ok(())
+        }
+        ClipboardContent::Files(files) => {
+            for uri in &files.uris {
+                println!("{uri}");
+            }
             Ok(())
         }
         ClipboardContent::Empty => {
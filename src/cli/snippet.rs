@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::io::{BufRead, IsTerminal, Read, Write};
+
+use anyhow::{anyhow, bail, Context};
+use rusqlite::Connection;
+
+use crate::clipboard;
+use crate::config::Config;
+use crate::db::snippets;
+
+use super::SnippetCommands;
+
+/// Characters of the first line shown by `clio snippet list`.
+const LIST_PREVIEW_CHARS: usize = 60;
+
+pub fn run(conn: &Connection, config: &Config, command: &SnippetCommands) -> anyhow::Result<()> {
+    match command {
+        SnippetCommands::Add { name } => {
+            snippets::check_name(name).map_err(anyhow::Error::msg)?;
+            let max_bytes = config.max_entry_size_bytes();
+            let mut body = String::new();
+            std::io::stdin().take(max_bytes + 1).read_to_string(&mut body)?;
+            if body.len() as u64 > max_bytes {
+                bail!("stdin exceeds max_entry_size_kb ({} KB)", config.max_entry_size_kb);
+            }
+            if body.is_empty() {
+                bail!("stdin is empty");
+            }
+            snippets::save(conn, name, &body).context("failed to save snippet")?;
+        }
+        SnippetCommands::List => {
+            for snippet in snippets::all(conn)? {
+                println!("{}\t{}", snippet.name, first_line(&snippet.body));
+            }
+        }
+        SnippetCommands::Rm { name } => {
+            if !snippets::remove(conn, name)? {
+                bail!("snippet {name} not found");
+            }
+        }
+        SnippetCommands::Use { name, vars, print } => {
+            let snippet = snippets::get(conn, name)?.ok_or_else(|| anyhow!("snippet {name} not found"))?;
+            let mut answers: HashMap<String, String> = vars.iter().cloned().collect();
+            for prompt in snippet.prompts() {
+                if !answers.contains_key(prompt) {
+                    answers.insert(prompt.to_owned(), ask(prompt)?);
+                }
+            }
+            let text = snippet.expand_now(&answers)?;
+            if *print {
+                print!("{text}");
+            } else {
+                clipboard::write_clipboard_text_sync(&text)?;
+                #[cfg(target_os = "linux")]
+                clipboard::write_selection_text(arboard::LinuxClipboardKind::Primary, &text);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Ask for the value of `{prompt:NAME}` on the terminal.
+fn ask(name: &str) -> anyhow::Result<String> {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        bail!("snippet asks for {name}: run it in a terminal or pass --var {name}=VALUE");
+    }
+    eprint!("{name}: ");
+    std::io::stderr().flush()?;
+    let mut line = String::new();
+    stdin.lock().read_line(&mut line).context("failed to read stdin")?;
+    Ok(line.trim_end_matches(['\n', '\r']).to_owned())
+}
+
+fn first_line(body: &str) -> String {
+    let line = body.lines().next().unwrap_or_default();
+    let mut preview: String = line.chars().take(LIST_PREVIEW_CHARS).collect();
+    if preview.len() < line.len() || body.trim_end().contains('\n') {
+        preview.push('…');
+    }
+    preview
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_shows_first_line() {
+        assert_eq!(first_line("one line"), "one line");
+        assert_eq!(first_line("Regards,\nJohn\n"), "Regards,…");
+        assert_eq!(first_line(&"x".repeat(80)), format!("{}…", "x".repeat(LIST_PREVIEW_CHARS)));
+    }
+}
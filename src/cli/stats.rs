@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use rusqlite::Connection;
+
+use crate::db::usage::{self, Count, Usage};
+use crate::models::entry::Timestamp;
+
+use super::StatsFormat;
+
+/// Busiest hours listed in the text report.
+const BUSIEST_HOURS: usize = 3;
+
+pub fn run(conn: &Connection, since: Option<Duration>, format: StatsFormat) -> anyhow::Result<()> {
+    let usage = usage::usage(conn, since.map(Timestamp::before)).context("failed to compute usage")?;
+    match format {
+        StatsFormat::Text => print!("{}", format_report(&usage, since)),
+        StatsFormat::Json => println!("{}", serde_json::to_string_pretty(&usage)?),
+    }
+    Ok(())
+}
+
+fn format_report(usage: &Usage, since: Option<Duration>) -> String {
+    let period = since.map(|d| format!(" (last {})", humantime::format_duration(d))).unwrap_or_default();
+    let mut out = format!("Entries: {}{period}\n", usage.entries);
+    push_counts(&mut out, &usage.by_type);
+    out.push_str("Source apps:\n");
+    push_counts(&mut out, &usage.by_source_app);
+    out.push_str(&format!("Size: {} KB", usage.total_bytes / 1024));
+    if let Some(largest) = &usage.largest {
+        out.push_str(&format!(", largest entry {} ({}, {} KB)", largest.id, largest.content_type, largest.bytes / 1024));
+    }
+    out.push('\n');
+    out.push_str(&format!("Expiring: {}, masked: {}\n", usage.expiring, usage.masked));
+    out.push_str(&format!("Copies: {} ({} from history)\n", usage.copies, usage.uses));
+    if !usage.rule_matches.is_empty() {
+        out.push_str("Rule matches:\n");
+        push_counts(&mut out, &usage.rule_matches);
+    }
+    let hours: Vec<_> = usage
+        .busiest_hours(BUSIEST_HOURS)
+        .into_iter()
+        .map(|(hour, copies)| format!("{hour:02}:00 ({copies})"))
+        .collect();
+    if !hours.is_empty() {
+        out.push_str(&format!("Busiest hours: {}\n", hours.join(", ")));
+    }
+    out
+}
+
+fn push_counts(out: &mut String, counts: &[Count]) {
+    for c in counts {
+        out.push_str(&format!("  {:<20} {:>7}\n", c.name, c.count));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::usage::LargestEntry;
+
+    #[test]
+    fn text_report() {
+        let mut copies_by_hour = [0; 24];
+        copies_by_hour[9] = 4;
+        copies_by_hour[14] = 7;
+        let usage = Usage {
+            entries: 3,
+            by_type: vec![Count { name: "text".to_owned(), count: 3 }],
+            by_source_app: vec![Count { name: "kitty".to_owned(), count: 3 }],
+            total_bytes: 4096,
+            largest: Some(LargestEntry { id: 2, content_type: "text".to_owned(), bytes: 2048 }),
+            expiring: 1,
+            masked: 0,
+            copies: 11,
+            uses: 2,
+            rule_matches: vec![Count { name: "passwords".to_owned(), count: 1 }],
+            copies_by_hour,
+        };
+        let out = format_report(&usage, Some(Duration::from_secs(7 * 86400)));
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines[0], "Entries: 3 (last 7days)");
+        assert!(lines.contains(&"Size: 4 KB, largest entry 2 (text, 2 KB)"), "{out}");
+        assert!(lines.contains(&"Copies: 11 (2 from history)"), "{out}");
+        assert!(lines.contains(&"  passwords                  1"), "{out}");
+        assert_eq!(lines.last(), Some(&"Busiest hours: 14:00 (7), 09:00 (4)"));
+    }
+}
@@ -0,0 +1,38 @@
+use anyhow::{bail, Context};
+use rusqlite::Connection;
+
+use crate::db::{repository, tags};
+
+use super::TagAction;
+
+/// Add or remove tags of entry `id`, then print its tags.
+pub fn run(conn: &Connection, id: i64, action: Option<&TagAction>) -> anyhow::Result<()> {
+    if !repository::entry_exists(conn, id)? {
+        bail!("entry {id} not found");
+    }
+    match action {
+        Some(TagAction::Add { tags: names }) => {
+            for name in names {
+                tags::check_name(name).map_err(anyhow::Error::msg)?;
+            }
+            let tx = conn.unchecked_transaction()?;
+            for name in names {
+                tags::add(&tx, id, name).with_context(|| format!("failed to add tag {name}"))?;
+            }
+            tx.commit()?;
+        }
+        Some(TagAction::Rm { tags: names }) => {
+            for name in names {
+                if !tags::remove(conn, id, name).with_context(|| format!("failed to remove tag {name}"))? {
+                    eprintln!("entry {id} has no tag {name}");
+                }
+            }
+        }
+        None => {}
+    }
+    let names = tags::for_entry(conn, id)?;
+    if !names.is_empty() {
+        println!("{}", names.join(" "));
+    }
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use anyhow::{bail, Context};
+use rusqlite::Connection;
+
+use crate::db::{copy_events, repository};
+use crate::models::entry::Timestamp;
+use crate::time_fmt::format_local;
+
+/// Print the copies of entry `id`, or the copy that was on the clipboard at `at`.
+pub fn run(conn: &Connection, id: Option<i64>, at: Option<Timestamp>, datetime_format: &str) -> anyhow::Result<()> {
+    if let Some(at) = at {
+        match copy_events::on_clipboard_at(conn, at).context("failed to read copy events")? {
+            Some(event) => println!("{}", event_line(&event, datetime_format)),
+            None => bail!("nothing recorded on the clipboard at {}", format_local(&at, datetime_format)),
+        }
+        return Ok(());
+    }
+    let Some(id) = id else { bail!("give an entry ID or --at") };
+    if !repository::entry_exists(conn, id)? {
+        bail!("entry {id} not found");
+    }
+    for event in copy_events::for_entry(conn, id).context("failed to read copy events")? {
+        println!("{}", event_line(&event, datetime_format));
+    }
+    Ok(())
+}
+
+/// Time, entry, origin, selection and source app of `event`, tab-separated.
+fn event_line(event: &copy_events::CopyEvent, datetime_format: &str) -> String {
+    format!(
+        "{}\t{}\t{}\t{}\t{}",
+        format_local(&event.created_at, datetime_format),
+        event.entry_id,
+        event.origin.map_or("-", |origin| origin.as_str()),
+        event.selection.map_or("-", |selection| selection.as_str()),
+        event.source_app.as_deref().unwrap_or("-"),
+    )
+}
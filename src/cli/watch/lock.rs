@@ -0,0 +1,127 @@
+//! Single-instance lock for `clio watch`.
+//!
+//! Two watchers on the same session fight over the selections (sync ping-pong)
+//! and record every change twice into the same database. The lock is an
+//! `flock` on a file in the runtime directory named after the database and
+//! holding the owner's PID (see `ipc`, which reads it); the kernel releases it
+//! when the process exits, so a crash never leaves a stale lock behind.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::ipc::{self, Watcher};
+
+/// Held for the lifetime of the watcher; dropping it releases the lock.
+pub(super) struct InstanceLock {
+    _file: File,
+    path: PathBuf,
+}
+
+impl InstanceLock {
+    /// Take the lock at `path`, or fail with the PID of the running watcher.
+    /// `pinned_profile` is recorded for `clio profile switch` to report.
+    pub(super) fn acquire(path: &Path, pinned_profile: Option<&str>) -> anyhow::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create directory {}", parent.display()))?;
+        }
+        let mut file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("failed to open lock file {}", path.display()))?;
+
+        if !try_lock(&file).with_context(|| format!("failed to lock {}", path.display()))? {
+            let mut contents = String::new();
+            let _ = file.read_to_string(&mut contents);
+            match Watcher::parse(&contents) {
+                Some(Watcher { pid, .. }) => anyhow::bail!(
+                    "clio watch is already running (PID {pid}). \
+                     Stop it first, or check that it is not started both by autostart and systemd."
+                ),
+                None => anyhow::bail!(
+                    "clio watch is already running (lock held on {}).",
+                    path.display()
+                ),
+            }
+        }
+
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        let owner = Watcher { pid: std::process::id(), pinned_profile: pinned_profile.map(str::to_owned) };
+        file.write_all(owner.lock_file_contents().as_bytes())?;
+        file.flush()?;
+        Ok(Self { _file: file, path: path.to_path_buf() })
+    }
+
+    pub(super) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Non-blocking exclusive lock. `Ok(false)` means another process holds it.
+#[cfg(unix)]
+fn try_lock(file: &File) -> std::io::Result<bool> {
+    ipc::try_flock(file, libc::LOCK_EX)
+}
+
+#[cfg(not(unix))]
+fn try_lock(_file: &File) -> std::io::Result<bool> {
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_lock_fails_with_pid() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run").join("clio.watch.lock");
+
+        let lock = InstanceLock::acquire(&path, None).unwrap();
+        let pid = std::fs::read_to_string(lock.path()).unwrap();
+        assert_eq!(pid.trim(), std::process::id().to_string());
+
+        let err = InstanceLock::acquire(&path, None).err().unwrap().to_string();
+        assert!(err.contains("already running"), "{err}");
+        assert!(err.contains(&std::process::id().to_string()), "{err}");
+    }
+
+    #[test]
+    fn lock_is_released_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clio.watch.lock");
+
+        drop(InstanceLock::acquire(&path, None).unwrap());
+        assert!(InstanceLock::acquire(&path, None).is_ok());
+    }
+
+    #[test]
+    fn watcher_found_only_while_locked() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clio.watch.lock");
+        assert_eq!(ipc::watcher_at(&path), None);
+
+        let lock = InstanceLock::acquire(&path, Some("work")).unwrap();
+        let watcher = ipc::watcher_at(&path).unwrap();
+        assert_eq!(watcher.pid, std::process::id());
+        assert_eq!(watcher.pinned_profile.as_deref(), Some("work"));
+        drop(lock);
+        assert_eq!(ipc::watcher_at(&path), None);
+    }
+
+    #[test]
+    fn stale_pid_file_does_not_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clio.watch.lock");
+        std::fs::write(&path, "999999\n").unwrap();
+
+        assert!(InstanceLock::acquire(&path, None).is_ok());
+    }
+}
@@ -1,9 +1,12 @@
+mod lock;
+mod notify;
+mod primary;
+mod reload;
 mod state;
 
-use std::cell::Cell;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
 
 use rusqlite::Connection;
 
@@ -11,16 +14,20 @@ use rusqlite::Connection;
 use arboard::LinuxClipboardKind;
 
 use anyhow::Context;
-use log::{info, warn};
+use log::{debug, info, warn};
 
 use arboard::Clipboard;
 
 use crate::clipboard;
-use crate::config::{Config, SyncMode};
-use crate::models::entry::{compute_hash, ContentHash};
+use crate::db;
+use crate::ipc;
+use crate::config::{self, Config, ConfigOverride, SyncMode};
+use crate::models::entry::{compute_hash, ContentHash, Selection};
 use crate::platform;
 
-use state::WatchState;
+use notify::Notifier;
+use reload::ConfigReloader;
+use state::{HintDecision, WatchState};
 
 /// Post-iteration OS-level cleanup: release heap pages and reap zombie children.
 fn post_iteration_cleanup() {
@@ -28,10 +35,20 @@ fn post_iteration_cleanup() {
     platform::reap_zombies();
 }
 
-pub fn run(conn: &Connection, config: &Config) -> anyhow::Result<()> {
+pub fn run(
+    conn: Connection,
+    config: &Config,
+    config_path: &Path,
+    sets: &[ConfigOverride],
+) -> anyhow::Result<()> {
     #[cfg(target_os = "linux")]
     platform::limit_malloc_arenas();
 
+    let db_path = config::resolve_db_path(config);
+    let pinned_profile = pinned_profile(sets);
+    let lock = lock::InstanceLock::acquire(&ipc::lock_path(&db_path), pinned_profile.as_deref())?;
+    debug!("instance lock acquired: {}", lock.path().display());
+
     let running = Arc::new(AtomicBool::new(true));
     let r = running.clone();
     ctrlc::set_handler(move || {
@@ -39,60 +56,152 @@ pub fn run(conn: &Connection, config: &Config) -> anyhow::Result<()> {
     })
     .context("failed to set Ctrl+C handler")?;
 
-    let rules = config.compile_rules();
-    let has_ttl_rules = rules.iter().any(|r| r.ttl.is_some());
-
-    if !rules.is_empty() {
-        info!("loaded {} action rule(s)", rules.len());
+    if !config.actions.is_empty() {
+        info!("loaded {} action rule(s)", config.actions.len());
     }
 
     info!(
-        "watching clipboard (interval: {}ms, sync: {})",
-        config.watch_interval.as_millis(), config.sync_mode
+        "watching clipboard (interval: {}ms, sync: {}, sensitive hints: {})",
+        config.watch_interval.as_millis(), config.sync_mode, config.sensitive_hint_policy
     );
 
-    let prune_interval = config.prune_interval;
-    let state = WatchState {
-        conn,
-        max_history: config.max_history,
-        max_age: config.max_age,
-        max_entry_size_bytes: config.max_entry_size_bytes(),
-        prune_interval,
-        last_prune: Cell::new(Instant::now()),
-        rules,
-        has_ttl_rules,
-        expiry: state::ExpiryTracker::new(),
-    };
-    let interval = config.watch_interval;
-    let sync_mode = config.sync_mode;
-
     let mut cb = clipboard::open_clipboard()
         .context("failed to open clipboard")?;
 
-    if sync_mode == SyncMode::Disabled {
-        return run_disabled(&state, &running, interval, &mut cb);
+    let notifier = Notifier::from_env();
+    let mut control = LoopControl {
+        running: &running,
+        notifier: &notifier,
+        reloader: ConfigReloader::new(config_path, sets, config.clone()),
+        db_path,
+        lock,
+        pinned_profile,
+        db_changed: false,
+    };
+    notifier.ready(&status_line(control.reloader.current()));
+
+    let mut conn = conn;
+    // Switching profiles may change the database: the watch state is rebuilt
+    // around a freshly opened connection.
+    let result = loop {
+        let mut state = WatchState::new(&conn, control.reloader.current());
+        // A reload that switches to or from `disabled` returns from the loop
+        // so the matching one can take over.
+        let result = loop {
+            let result = if state.sync_mode == SyncMode::Disabled {
+                run_disabled(&mut state, &mut control, &mut cb)
+            } else {
+                run_sync(&mut state, &mut control, &mut cb)
+            };
+            if result.is_err() || !running.load(Ordering::SeqCst) || control.db_changed {
+                break result;
+            }
+        };
+        drop(state);
+        if result.is_err() || !running.load(Ordering::SeqCst) || !std::mem::take(&mut control.db_changed) {
+            break result;
+        }
+        match db::init_db(&control.db_path) {
+            Ok(new_conn) => conn = new_conn,
+            Err(e) => {
+                break Err(anyhow::Error::new(e)
+                    .context(format!("failed to open database {}", control.db_path.display())))
+            }
+        }
+    };
+    notifier.stopping();
+    result
+}
+
+/// Profile selected by `--profile` (in `sets`) or `CLIO_PROFILE`. Reloads
+/// re-apply it, so `clio profile switch` cannot move this watcher.
+fn pinned_profile(sets: &[ConfigOverride]) -> Option<String> {
+    let flag = sets.iter().rev().find(|set| set.key == "profile");
+    flag.map(|set| set.value.clone()).or_else(|| std::env::var("CLIO_PROFILE").ok())
+}
+
+fn status_line(config: &Config) -> String {
+    match &config.profile {
+        Some(profile) => format!("watching clipboard (sync: {}, profile: {profile})", config.sync_mode),
+        None => format!("watching clipboard (sync: {})", config.sync_mode),
     }
+}
+
+/// Per-iteration housekeeping shared by the watch loops.
+struct LoopControl<'a> {
+    running: &'a AtomicBool,
+    notifier: &'a Notifier,
+    reloader: ConfigReloader,
+    /// Database the watch state is writing to.
+    db_path: PathBuf,
+    /// Single-instance lock of `db_path`.
+    lock: lock::InstanceLock,
+    /// Recorded in every lock this watcher takes.
+    pinned_profile: Option<String>,
+    /// Set when a reload moved `db_path`; the loops exit so it can be reopened.
+    db_changed: bool,
+}
+
+impl LoopControl<'_> {
+    /// Sleep one poll interval, then ping the watchdog, apply a pending config
+    /// reload and prune. Returns false when the loop must exit: on shutdown,
+    /// or when a reload switched between disabled and sync modes or changed
+    /// the database.
+    fn tick(&mut self, state: &mut WatchState<'_>) -> bool {
+        std::thread::sleep(state.watch_interval);
+        if !self.running.load(Ordering::SeqCst) {
+            return false;
+        }
+        self.notifier.ping_watchdog();
+
+        if let Some(new_config) = self.reloader.poll() {
+            self.notifier.status(&status_line(&new_config));
+            let db_path = config::resolve_db_path(&new_config);
+            if db_path != self.db_path {
+                // Another watcher may already record into the new database.
+                match lock::InstanceLock::acquire(&ipc::lock_path(&db_path), self.pinned_profile.as_deref()) {
+                    Ok(lock) => {
+                        info!("switching database to {}", db_path.display());
+                        self.lock = lock;
+                        self.db_path = db_path;
+                        self.db_changed = true;
+                        return false;
+                    }
+                    Err(e) => warn!("staying on {}: {e:#}", self.db_path.display()),
+                }
+            }
+            let was_disabled = state.sync_mode == SyncMode::Disabled;
+            state.apply_config(&new_config);
+            if was_disabled != (state.sync_mode == SyncMode::Disabled) {
+                info!("sync mode changed to {}, restarting watch loop", state.sync_mode);
+                return false;
+            }
+        }
 
-    run_sync(&state, &running, interval, sync_mode, &mut cb)
+        state.maybe_prune();
+        true
+    }
 }
 
 /// Disabled mode: only monitor CLIPBOARD, no PRIMARY interaction.
 fn run_disabled(
-    state: &WatchState<'_>,
-    running: &Arc<AtomicBool>,
-    interval: Duration,
+    state: &mut WatchState<'_>,
+    control: &mut LoopControl<'_>,
     cb: &mut Clipboard,
 ) -> anyhow::Result<()> {
     let mut last_hash: Option<ContentHash> = None;
 
-    while running.load(Ordering::SeqCst) {
-        std::thread::sleep(interval);
-        state.maybe_prune();
-
+    while control.tick(state) {
         if let Some(result) = state.check_expiry_and_restore(cb) {
             last_hash = Some(result.clipboard_hash);
         }
 
+        // Checked before reading: a secret the policy skips is never read.
+        let hint = state.check_sensitive_hint("CLIPBOARD");
+        if hint == HintDecision::Skip {
+            continue;
+        }
+
         let content = match clipboard::read_clipboard_with(cb) {
             Ok(c) => c,
             Err(e) => {
@@ -113,8 +222,12 @@ fn run_disabled(
             continue;
         }
 
-        state.process_change(content);
         last_hash = Some(hash);
+        if hint == HintDecision::RecordMasked {
+            state.record_masked(hash, Selection::Clipboard);
+        } else {
+            state.process_change(content);
+        }
 
         post_iteration_cleanup();
     }
@@ -140,10 +253,8 @@ fn sync_to_selection(
 /// Sync-enabled modes: monitor both CLIPBOARD and PRIMARY, sync per mode.
 #[cfg(target_os = "linux")]
 fn run_sync(
-    state: &WatchState<'_>,
-    running: &Arc<AtomicBool>,
-    interval: Duration,
-    sync_mode: SyncMode,
+    state: &mut WatchState<'_>,
+    control: &mut LoopControl<'_>,
     cb: &mut Clipboard,
 ) -> anyhow::Result<()> {
     let mut last_clipboard_hash: Option<ContentHash> = None;
@@ -151,12 +262,10 @@ fn run_sync(
     let mut primary_handle: Option<std::thread::JoinHandle<()>> = None;
     let mut clipboard_handle: Option<std::thread::JoinHandle<()>> = None;
 
-    let sync_to_primary = matches!(sync_mode, SyncMode::Both | SyncMode::ToPrimary);
-    let sync_to_clipboard = matches!(sync_mode, SyncMode::Both | SyncMode::ToClipboard);
-
-    while running.load(Ordering::SeqCst) {
-        std::thread::sleep(interval);
-        state.maybe_prune();
+    while control.tick(state) {
+        // Re-read every iteration: a config reload may change the mode.
+        let sync_to_primary = matches!(state.sync_mode, SyncMode::Both | SyncMode::ToPrimary);
+        let sync_to_clipboard = matches!(state.sync_mode, SyncMode::Both | SyncMode::ToClipboard);
 
         if let Some(result) = state.check_expiry_and_restore(cb) {
             last_clipboard_hash = Some(result.clipboard_hash);
@@ -197,14 +306,10 @@ fn run_sync(
 
 #[cfg(not(target_os = "linux"))]
 fn run_sync(
-    state: &WatchState<'_>,
-    running: &Arc<AtomicBool>,
-    interval: Duration,
-    _sync_mode: SyncMode,
+    state: &mut WatchState<'_>,
+    control: &mut LoopControl<'_>,
     cb: &mut Clipboard,
 ) -> anyhow::Result<()> {
-    if !matches!(_sync_mode, SyncMode::Disabled) {
-        warn!("clipboard sync is not supported on this platform, running without sync");
-    }
-    run_disabled(state, running, interval, cb)
+    warn!("clipboard sync is not supported on this platform, running without sync");
+    run_disabled(state, control, cb)
 }
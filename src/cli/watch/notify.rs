@@ -0,0 +1,150 @@
+//! Minimal `sd_notify` client for running `clio watch` as a `Type=notify` unit.
+//!
+//! Sends READY/STATUS/WATCHDOG/STOPPING datagrams to `$NOTIFY_SOCKET`. Every
+//! method is a no-op when the watcher was not started by systemd.
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use log::{debug, warn};
+
+#[cfg(unix)]
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+pub(super) struct Notifier {
+    #[cfg(unix)]
+    target: Option<(UnixDatagram, SocketAddr)>,
+    /// How often to send `WATCHDOG=1`: half the unit's `WatchdogSec`.
+    watchdog_interval: Option<Duration>,
+    last_watchdog: Cell<Instant>,
+}
+
+impl Notifier {
+    /// Connect to `$NOTIFY_SOCKET` if set; otherwise return a no-op notifier.
+    pub(super) fn from_env() -> Self {
+        let watchdog_interval = watchdog_interval(
+            std::env::var("WATCHDOG_USEC").ok().as_deref(),
+            std::env::var("WATCHDOG_PID").ok().as_deref(),
+        );
+        Self {
+            #[cfg(unix)]
+            target: std::env::var("NOTIFY_SOCKET").ok().and_then(|s| connect(&s)),
+            watchdog_interval,
+            last_watchdog: Cell::new(Instant::now()),
+        }
+    }
+
+    /// Tell systemd the watcher finished starting up.
+    pub(super) fn ready(&self, status: &str) {
+        self.send(&format!("READY=1\nSTATUS={status}"));
+    }
+
+    pub(super) fn status(&self, status: &str) {
+        self.send(&format!("STATUS={status}"));
+    }
+
+    pub(super) fn stopping(&self) {
+        self.send("STOPPING=1");
+    }
+
+    /// Send `WATCHDOG=1` if the watchdog is enabled and half its timeout passed.
+    /// Called from the poll loop, so a hung loop stops the pings.
+    pub(super) fn ping_watchdog(&self) {
+        let Some(interval) = self.watchdog_interval else {
+            return;
+        };
+        if self.last_watchdog.get().elapsed() < interval {
+            return;
+        }
+        self.send("WATCHDOG=1");
+        self.last_watchdog.set(Instant::now());
+    }
+
+    #[cfg(unix)]
+    fn send(&self, message: &str) {
+        let Some((socket, addr)) = &self.target else {
+            return;
+        };
+        if let Err(e) = socket.send_to_addr(message.as_bytes(), addr) {
+            warn!("sd_notify failed: {e}");
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn send(&self, _message: &str) {}
+}
+
+/// Parse `$NOTIFY_SOCKET`: a filesystem path, or `@name` for the abstract namespace.
+#[cfg(unix)]
+fn connect(notify_socket: &str) -> Option<(UnixDatagram, SocketAddr)> {
+    let addr = match notify_socket.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            SocketAddr::from_abstract_name(name.as_bytes())
+        }
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => return None,
+        None => SocketAddr::from_pathname(notify_socket),
+    };
+    let addr = addr.inspect_err(|e| warn!("invalid NOTIFY_SOCKET {notify_socket:?}: {e}")).ok()?;
+    let socket = UnixDatagram::unbound()
+        .inspect_err(|e| warn!("creating sd_notify socket: {e}"))
+        .ok()?;
+    debug!("sd_notify enabled ({notify_socket})");
+    Some((socket, addr))
+}
+
+/// Watchdog ping interval from `WATCHDOG_USEC`, ignored when `WATCHDOG_PID`
+/// names another process.
+fn watchdog_interval(usec: Option<&str>, pid: Option<&str>) -> Option<Duration> {
+    if let Some(pid) = pid {
+        if pid.parse::<u32>().ok() != Some(std::process::id()) {
+            return None;
+        }
+    }
+    let usec: u64 = usec?.parse().ok().filter(|&u| u > 0)?;
+    Some(Duration::from_micros(usec) / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watchdog_interval_is_half_the_timeout() {
+        assert_eq!(watchdog_interval(Some("30000000"), None), Some(Duration::from_secs(15)));
+        assert_eq!(watchdog_interval(None, None), None);
+        assert_eq!(watchdog_interval(Some("0"), None), None);
+        assert_eq!(watchdog_interval(Some("junk"), None), None);
+    }
+
+    #[test]
+    fn watchdog_for_other_pid_is_ignored() {
+        let own = std::process::id().to_string();
+        assert!(watchdog_interval(Some("1000000"), Some(&own)).is_some());
+        assert!(watchdog_interval(Some("1000000"), Some("1")).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sends_ready_to_notify_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notify.sock");
+        let server = UnixDatagram::bind(&path).unwrap();
+
+        let notifier = Notifier {
+            target: connect(path.to_str().unwrap()),
+            watchdog_interval: Some(Duration::ZERO),
+            last_watchdog: Cell::new(Instant::now()),
+        };
+        notifier.ready("watching");
+        notifier.ping_watchdog();
+
+        let mut buf = [0u8; 128];
+        let n = server.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"READY=1\nSTATUS=watching");
+        let n = server.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"WATCHDOG=1");
+    }
+}
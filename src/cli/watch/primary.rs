@@ -0,0 +1,162 @@
+use std::cell::{Cell, RefCell};
+use std::time::{Duration, Instant};
+
+use crate::db::repository::SaveOutcome;
+use crate::models::entry::ContentHash;
+
+/// The last PRIMARY entry committed to the DB.
+struct LastCommit {
+    id: i64,
+    text: String,
+    /// False when the commit only refreshed an existing entry (dedup): such
+    /// entries predate the drag and must never be dropped.
+    inserted: bool,
+    at: Instant,
+}
+
+/// Debounces PRIMARY selections made by dragging the mouse.
+///
+/// A selection is committed only after it stayed unchanged for `debounce`.
+/// A commit that merely grows or shrinks the previous PRIMARY commit within
+/// `merge_window` replaces it instead of adding another entry.
+pub(super) struct PrimaryTracker {
+    debounce: Duration,
+    merge_window: Duration,
+    pending: Cell<Option<(ContentHash, Instant)>>,
+    last_commit: RefCell<Option<LastCommit>>,
+}
+
+impl PrimaryTracker {
+    pub(super) fn new(debounce: Duration, merge_window: Duration) -> Self {
+        Self {
+            debounce,
+            merge_window,
+            pending: Cell::new(None),
+            last_commit: RefCell::new(None),
+        }
+    }
+
+    /// Apply new timings from a config reload; pending state is kept.
+    pub(super) fn reconfigure(&mut self, debounce: Duration, merge_window: Duration) {
+        self.debounce = debounce;
+        self.merge_window = merge_window;
+    }
+
+    /// Returns true once `hash` has been seen unchanged for at least `debounce`.
+    /// A different hash restarts the stability timer.
+    pub(super) fn is_stable(&self, hash: &ContentHash) -> bool {
+        match self.pending.get() {
+            Some((pending, since)) if pending == *hash => since.elapsed() >= self.debounce,
+            _ => {
+                self.pending.set(Some((*hash, Instant::now())));
+                self.debounce.is_zero()
+            }
+        }
+    }
+
+    /// ID of the previous PRIMARY entry that `text` supersedes, if any.
+    pub(super) fn superseded_by(&self, text: &str) -> Option<i64> {
+        let last = self.last_commit.borrow();
+        let last = last.as_ref()?;
+        if !last.inserted || last.at.elapsed() > self.merge_window {
+            return None;
+        }
+        is_partial_selection(&last.text, text).then_some(last.id)
+    }
+
+    /// Remember a committed PRIMARY entry; `None` clears the history
+    /// (e.g. for non-text content or a skipped save).
+    pub(super) fn record(&self, commit: Option<(SaveOutcome, &str)>) {
+        *self.last_commit.borrow_mut() = commit.map(|(outcome, text)| LastCommit {
+            id: outcome.id,
+            text: text.to_owned(),
+            inserted: outcome.inserted,
+            at: Instant::now(),
+        });
+    }
+}
+
+/// Shortest selection (in characters) that another one may count as extending:
+/// "a" followed by "banana" is two selections, not one dragged further.
+const MIN_PARTIAL_CHARS: usize = 3;
+
+/// True if one selection strictly extends the other at either end
+/// (dragging forwards or backwards over the same text) and the shorter one
+/// has at least `MIN_PARTIAL_CHARS` characters.
+pub(super) fn is_partial_selection(previous: &str, current: &str) -> bool {
+    let shorter = if previous.len() < current.len() { previous } else { current };
+    previous != current
+        && shorter.chars().count() >= MIN_PARTIAL_CHARS
+        && (current.starts_with(previous)
+            || previous.starts_with(current)
+            || current.ends_with(previous)
+            || previous.ends_with(current))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(id: i64, inserted: bool) -> SaveOutcome {
+        SaveOutcome { id, inserted }
+    }
+
+    #[test]
+    fn partial_selection_detects_extensions_and_prefixes() {
+        assert!(is_partial_selection("hello", "hello world"));
+        assert!(is_partial_selection("hello world", "hello"));
+        assert!(is_partial_selection("world", "hello world"));
+        assert!(!is_partial_selection("hello", "hello"));
+        assert!(!is_partial_selection("hello", "goodbye"));
+    }
+
+    #[test]
+    fn short_overlap_is_not_a_partial_selection() {
+        assert!(!is_partial_selection("a", "banana"));
+        assert!(!is_partial_selection("banana", "na"));
+        assert!(is_partial_selection("ban", "banana"));
+    }
+
+    #[test]
+    fn stable_after_debounce() {
+        let tracker = PrimaryTracker::new(Duration::from_millis(20), Duration::from_secs(5));
+        let a = [1u8; 32];
+        let b = [2u8; 32];
+
+        assert!(!tracker.is_stable(&a));
+        std::thread::sleep(Duration::from_millis(25));
+        assert!(tracker.is_stable(&a));
+
+        // A different selection restarts the timer.
+        assert!(!tracker.is_stable(&b));
+    }
+
+    #[test]
+    fn zero_debounce_commits_immediately() {
+        let tracker = PrimaryTracker::new(Duration::ZERO, Duration::from_secs(5));
+        assert!(tracker.is_stable(&[1u8; 32]));
+    }
+
+    #[test]
+    fn superseded_by_extension_within_window() {
+        let tracker = PrimaryTracker::new(Duration::ZERO, Duration::from_secs(5));
+        tracker.record(Some((outcome(7, true), "The quick")));
+        assert_eq!(tracker.superseded_by("The quick brown fox"), Some(7));
+        assert_eq!(tracker.superseded_by("unrelated"), None);
+    }
+
+    #[test]
+    fn deduped_entry_is_never_superseded() {
+        let tracker = PrimaryTracker::new(Duration::ZERO, Duration::from_secs(5));
+        tracker.record(Some((outcome(7, false), "The quick")));
+        assert_eq!(tracker.superseded_by("The quick brown fox"), None);
+    }
+
+    #[test]
+    fn not_superseded_after_window() {
+        let tracker = PrimaryTracker::new(Duration::ZERO, Duration::ZERO);
+        tracker.record(Some((outcome(7, true), "The quick")));
+        std::thread::sleep(Duration::from_millis(2));
+        assert_eq!(tracker.superseded_by("The quick brown fox"), None);
+    }
+}
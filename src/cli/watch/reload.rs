@@ -0,0 +1,291 @@
+//! Config hot-reload for the watch daemon, triggered by changes to the config
+//! file or its `conf.d` drop-ins (inotify) or SIGHUP. Files pulled in via
+//! `include:` elsewhere are only re-read on SIGHUP. Both are polled from the watch loop, never acted
+//! on in a signal handler.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::{debug, error, info, warn};
+
+use crate::config::{self, Config, ConfigOverride};
+
+/// Set by the SIGHUP handler, cleared by `ConfigReloader::reload_requested`.
+static SIGHUP_RECEIVED: AtomicBool = AtomicBool::new(false);
+
+/// Reloads the config file when triggered, keeping the last valid config.
+pub(super) struct ConfigReloader {
+    path: PathBuf,
+    /// `--set` overrides, re-applied on every reload.
+    sets: Vec<ConfigOverride>,
+    current: Config,
+    /// Set when SIGHUP arrived: `SIGHUP_RECEIVED` outside tests.
+    sighup: &'static AtomicBool,
+    #[cfg(target_os = "linux")]
+    inotify: Option<inotify::DirWatch>,
+}
+
+impl ConfigReloader {
+    /// Install the SIGHUP handler and start watching `path`; `current` is the
+    /// config the daemon was started with.
+    pub(super) fn new(path: &Path, sets: &[ConfigOverride], current: Config) -> Self {
+        install_sighup_handler();
+        Self::with_sighup_flag(path, sets, current, &SIGHUP_RECEIVED)
+    }
+
+    /// Like `new`, with reload requests read from `sighup` instead of the
+    /// process-wide signal handler.
+    fn with_sighup_flag(path: &Path, sets: &[ConfigOverride], current: Config, sighup: &'static AtomicBool) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            sets: sets.to_vec(),
+            current,
+            sighup,
+            #[cfg(target_os = "linux")]
+            inotify: inotify::DirWatch::new(path),
+        }
+    }
+
+    /// If a reload was triggered, load and validate the config file and
+    /// return it when it differs from the current one. Each changed setting
+    /// is logged; an invalid file is reported and the current config kept.
+    pub(super) fn poll(&mut self) -> Option<Config> {
+        if !self.reload_requested() {
+            return None;
+        }
+        let new = match config::load_config_with_overrides(Some(&self.path), &self.sets) {
+            Ok(c) => c,
+            Err(e) => {
+                error!("config reload failed, keeping previous configuration: {e}");
+                return None;
+            }
+        };
+        let changes = self.current.diff(&new);
+        if changes.is_empty() {
+            info!("config reloaded from {}: no changes", self.path.display());
+            return None;
+        }
+        for change in &changes {
+            info!("config reloaded: {change}");
+        }
+        self.current = new.clone();
+        Some(new)
+    }
+
+    /// The last successfully loaded config.
+    pub(super) fn current(&self) -> &Config {
+        &self.current
+    }
+
+    /// True if SIGHUP arrived or the config file or a drop-in was written since the last call.
+    fn reload_requested(&self) -> bool {
+        let sighup = self.sighup.swap(false, Ordering::SeqCst);
+        if sighup {
+            debug!("SIGHUP received");
+        }
+        sighup | self.file_changed()
+    }
+
+    #[cfg(target_os = "linux")]
+    fn file_changed(&self) -> bool {
+        self.inotify.as_ref().is_some_and(inotify::DirWatch::file_changed)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn file_changed(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_sighup(_signal: libc::c_int) {
+    SIGHUP_RECEIVED.store(true, Ordering::SeqCst);
+}
+
+#[cfg(unix)]
+fn install_sighup_handler() {
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe.
+    let previous = unsafe { libc::signal(libc::SIGHUP, on_sighup as *const () as libc::sighandler_t) };
+    if previous == libc::SIG_ERR {
+        warn!("failed to install SIGHUP handler: {}", std::io::Error::last_os_error());
+    }
+}
+
+#[cfg(not(unix))]
+fn install_sighup_handler() {}
+
+/// Non-blocking inotify watch on the config file's directory.
+///
+/// The directory is watched rather than the file because editors usually save
+/// by writing a temporary file and renaming it over the original.
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::ffi::{CString, OsStr, OsString};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+    use std::path::Path;
+
+    use log::{debug, warn};
+
+    use crate::config;
+
+    /// Size of `struct inotify_event` without the trailing name.
+    const EVENT_HEADER_LEN: usize = std::mem::size_of::<libc::inotify_event>();
+
+    pub(super) struct DirWatch {
+        fd: OwnedFd,
+        file_wd: i32,
+        file_name: OsString,
+        /// Watch on the `conf.d` drop-in directory, if it existed at startup.
+        conf_d_wd: Option<i32>,
+    }
+
+    impl DirWatch {
+        pub(super) fn new(path: &Path) -> Option<Self> {
+            let file_name = path.file_name()?.to_os_string();
+            let dir = match path.parent() {
+                Some(p) if !p.as_os_str().is_empty() => p,
+                _ => Path::new("."),
+            };
+
+            // SAFETY: plain syscall; the returned fd is owned by `OwnedFd` below.
+            let raw = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+            if raw < 0 {
+                warn!("inotify_init1 failed: {}", std::io::Error::last_os_error());
+                return None;
+            }
+            // SAFETY: `raw` is a freshly created, valid descriptor.
+            let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+
+            let file_wd = add_watch(&fd, dir)?;
+            let conf_d = config::conf_d_dir(path);
+            let conf_d_wd = if conf_d.is_dir() { add_watch(&fd, &conf_d) } else { None };
+            Some(Self { fd, file_wd, file_name, conf_d_wd })
+        }
+
+        /// Drain pending events; true if any of them concerns the config file
+        /// or a YAML file in `conf.d`.
+        pub(super) fn file_changed(&self) -> bool {
+            let mut buf = [0u8; 4096];
+            let mut changed = false;
+            loop {
+                // SAFETY: valid fd and buffer of the given length.
+                let n = unsafe { libc::read(self.fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+                if n <= 0 {
+                    // EAGAIN: queue drained.
+                    return changed;
+                }
+                changed |= events(&buf[..n as usize]).any(|(wd, name)| {
+                    (wd == self.file_wd && name == self.file_name)
+                        || (Some(wd) == self.conf_d_wd && config::is_yaml(Path::new(name)))
+                });
+            }
+        }
+    }
+
+    fn add_watch(fd: &OwnedFd, dir: &Path) -> Option<i32> {
+        let dir_c = CString::new(dir.as_os_str().as_bytes()).ok()?;
+        // SAFETY: valid fd and NUL-terminated path.
+        let wd = unsafe {
+            libc::inotify_add_watch(fd.as_raw_fd(), dir_c.as_ptr(), libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO)
+        };
+        if wd < 0 {
+            warn!(
+                "cannot watch {} for config changes: {}",
+                dir.display(),
+                std::io::Error::last_os_error()
+            );
+            return None;
+        }
+        debug!("watching {} for config changes", dir.display());
+        Some(wd)
+    }
+
+    /// Watch descriptor and name of each event in `buf` (NUL padding stripped).
+    pub(super) fn events(buf: &[u8]) -> impl Iterator<Item = (i32, &OsStr)> {
+        let mut offset = 0;
+        std::iter::from_fn(move || {
+            while offset + EVENT_HEADER_LEN <= buf.len() {
+                let wd = i32::from_ne_bytes(buf[offset..offset + 4].try_into().ok()?);
+                let len_start = offset + EVENT_HEADER_LEN - 4;
+                let name_len = u32::from_ne_bytes(buf[len_start..len_start + 4].try_into().ok()?) as usize;
+                let name_start = offset + EVENT_HEADER_LEN;
+                let name_end = (name_start + name_len).min(buf.len());
+                offset = name_end;
+                let raw = &buf[name_start..name_end];
+                let name = raw.split(|&b| b == 0).next().unwrap_or_default();
+                if !name.is_empty() {
+                    return Some((wd, OsStr::from_bytes(name)));
+                }
+            }
+            None
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// For tests that only exercise file watching.
+    #[cfg(target_os = "linux")]
+    static NO_SIGHUP: AtomicBool = AtomicBool::new(false);
+
+    #[test]
+    fn sighup_reloads_and_keeps_previous_on_invalid_config() {
+        static SIGHUP: AtomicBool = AtomicBool::new(false);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        let mut reloader = ConfigReloader::with_sighup_flag(&path, &[], Config::default(), &SIGHUP);
+        assert!(reloader.poll().is_none());
+
+        std::fs::write(&path, "max_history: 0\n").unwrap();
+        let _ = reloader.file_changed();
+        SIGHUP.store(true, Ordering::SeqCst);
+        assert!(reloader.poll().is_none());
+        assert_eq!(reloader.current.max_history, Config::default().max_history);
+
+        std::fs::write(&path, "max_history: 42\n").unwrap();
+        SIGHUP.store(true, Ordering::SeqCst);
+        assert_eq!(reloader.poll().map(|c| c.max_history), Some(42));
+        assert!(reloader.poll().is_none());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn config_write_requests_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        let watcher = ConfigReloader::with_sighup_flag(&path, &[], Config::default(), &NO_SIGHUP);
+
+        std::fs::write(dir.path().join("other.yaml"), "x").unwrap();
+        assert!(!watcher.file_changed());
+
+        std::fs::write(&path, "max_history: 10\n").unwrap();
+        assert!(watcher.file_changed());
+        assert!(!watcher.file_changed());
+
+        // Editors that save via rename.
+        let tmp = dir.path().join(".config.yaml.swp");
+        std::fs::write(&tmp, "max_history: 20\n").unwrap();
+        let _ = watcher.file_changed();
+        std::fs::rename(&tmp, &path).unwrap();
+        assert!(watcher.file_changed());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn conf_d_write_requests_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let conf_d = dir.path().join("conf.d");
+        std::fs::create_dir(&conf_d).unwrap();
+        let path = dir.path().join("config.yaml");
+        let watcher = ConfigReloader::with_sighup_flag(&path, &[], Config::default(), &NO_SIGHUP);
+
+        std::fs::write(conf_d.join("README"), "x").unwrap();
+        assert!(!watcher.file_changed());
+
+        std::fs::write(conf_d.join("10-local.yaml"), "max_history: 10\n").unwrap();
+        assert!(watcher.file_changed());
+    }
+}
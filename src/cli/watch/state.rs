@@ -2,7 +2,6 @@ use std::cell::{Cell, RefCell};
 use std::time::{Duration, Instant};
 
 use arboard::Clipboard;
-use chrono::Utc;
 use log::{debug, error, info, warn};
 use rusqlite::Connection;
 
@@ -12,9 +11,28 @@ use arboard::LinuxClipboardKind;
 use crate::actions;
 use crate::clipboard::source_app;
 use crate::clipboard::{self, ClipboardContent};
-use crate::config::CompiledRule;
-use crate::db::repository;
-use crate::models::entry::{compute_hash, ClipboardEntry, ContentHash, EntryContent};
+use crate::config::{CompiledRule, Config, SensitiveHintPolicy, SyncMode};
+use crate::db::repository::{self, SaveOutcome, StorageOptions};
+use crate::db::retention::{self, RetentionPolicy};
+use crate::db::{tags, usage};
+use crate::models::entry::{compute_hash, ClipboardEntry, ContentHash, CopyOrigin, EntryContent, Selection, Timestamp};
+use crate::models::images::{self, ImageStorage};
+
+use super::primary::PrimaryTracker;
+
+/// Mask shown in history for copies the source app marked as sensitive.
+const SENSITIVE_MASK: &str = "••••••";
+
+/// How a clipboard change should be handled, based on password-manager hints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum HintDecision {
+    /// No hint (or hints ignored): process normally.
+    Record,
+    /// Hint present: store a placeholder with a short TTL, never sync.
+    RecordMasked,
+    /// Hint present: do not read or store the content.
+    Skip,
+}
 
 /// Tracks per-entry TTL expiry for clipboard clearing.
 pub(super) struct ExpiryTracker {
@@ -58,34 +76,123 @@ impl ExpiryTracker {
 /// Shared state for the watch loop.
 pub(super) struct WatchState<'a> {
     pub(super) conn: &'a Connection,
-    pub(super) max_history: usize,
-    pub(super) max_age: Option<Duration>,
+    pub(super) retention: RetentionPolicy,
+    pub(super) storage: StorageOptions,
+    pub(super) image_storage: ImageStorage,
     pub(super) max_entry_size_bytes: u64,
     pub(super) prune_interval: Duration,
     pub(super) last_prune: Cell<Instant>,
     pub(super) rules: Vec<CompiledRule>,
     pub(super) has_ttl_rules: bool,
     pub(super) expiry: ExpiryTracker,
+    pub(super) sensitive_hint_policy: SensitiveHintPolicy,
+    pub(super) sensitive_hint_ttl: Duration,
+    pub(super) primary: PrimaryTracker,
+    pub(super) watch_interval: Duration,
+    pub(super) sync_mode: SyncMode,
+}
+
+impl<'a> WatchState<'a> {
+    pub(super) fn new(conn: &'a Connection, config: &Config) -> Self {
+        let rules = config.compile_rules();
+        let has_ttl_rules = rules.iter().any(|r| r.ttl.is_some());
+        Self {
+            conn,
+            retention: RetentionPolicy::from_config(config),
+            storage: StorageOptions::from_config(config),
+            image_storage: ImageStorage::from_config(config),
+            max_entry_size_bytes: config.max_entry_size_bytes(),
+            prune_interval: config.prune_interval,
+            last_prune: Cell::new(Instant::now()),
+            rules,
+            has_ttl_rules,
+            expiry: ExpiryTracker::new(),
+            sensitive_hint_policy: config.sensitive_hint_policy,
+            sensitive_hint_ttl: config.sensitive_hint_ttl,
+            primary: PrimaryTracker::new(config.primary_debounce, config.primary_merge_window),
+            watch_interval: config.watch_interval,
+            sync_mode: config.sync_mode,
+        }
+    }
+
+    /// Swap in settings from a reloaded config. Runtime state (expiry
+    /// tracking, pending PRIMARY selection, prune timer) is preserved.
+    pub(super) fn apply_config(&mut self, config: &Config) {
+        self.rules = config.compile_rules();
+        self.has_ttl_rules = self.rules.iter().any(|r| r.ttl.is_some());
+        self.retention = RetentionPolicy::from_config(config);
+        self.storage = StorageOptions::from_config(config);
+        self.image_storage = ImageStorage::from_config(config);
+        self.max_entry_size_bytes = config.max_entry_size_bytes();
+        self.prune_interval = config.prune_interval;
+        self.sensitive_hint_policy = config.sensitive_hint_policy;
+        self.sensitive_hint_ttl = config.sensitive_hint_ttl;
+        self.primary.reconfigure(config.primary_debounce, config.primary_merge_window);
+        self.watch_interval = config.watch_interval;
+        self.sync_mode = config.sync_mode;
+    }
 }
 
 impl WatchState<'_> {
-    /// Run prune_expired periodically, independent of clipboard changes.
+    /// Check the selection's TARGETS for password-manager hints before reading content.
+    pub(super) fn check_sensitive_hint(&self, selection: &str) -> HintDecision {
+        if self.sensitive_hint_policy == SensitiveHintPolicy::Ignore
+            || !clipboard::has_sensitive_hint(selection)
+        {
+            return HintDecision::Record;
+        }
+        debug!("{selection}: source app marked content as sensitive");
+        match self.sensitive_hint_policy {
+            SensitiveHintPolicy::Skip => HintDecision::Skip,
+            SensitiveHintPolicy::Mask => HintDecision::RecordMasked,
+            SensitiveHintPolicy::Ignore => HintDecision::Record,
+        }
+    }
+
+    /// Force a mask and cap the TTL at `sensitive_hint_ttl`.
+    fn mask_sensitive(&self, entry: &mut ClipboardEntry, ttl: Option<Duration>) -> Duration {
+        let ttl = ttl.map_or(self.sensitive_hint_ttl, |t| t.min(self.sensitive_hint_ttl));
+        entry.set_expires_at(Some(Timestamp::after(ttl)));
+        entry.set_mask_text(Some(SENSITIVE_MASK.to_owned()));
+        ttl
+    }
+
+    /// Record a copy marked sensitive as a placeholder: the secret itself is
+    /// never stored. Its clipboard `hash` is tracked, so the secret is still
+    /// cleared from the clipboard when the placeholder expires.
+    pub(super) fn record_masked(&self, hash: ContentHash, selection: Selection) {
+        let info = source_app::detect_source_app();
+        let mut entry = ClipboardEntry::from_text(SENSITIVE_MASK.to_owned(), info.class);
+        entry.set_source_title(info.title);
+        entry.set_selection(Some(selection));
+        let ttl = self.mask_sensitive(&mut entry, None);
+        self.save_if_fits(&entry);
+        self.expiry.update(Some(ttl), &hash);
+    }
+
+    /// Enforce retention periodically, so entries expire independent of clipboard changes.
     pub(super) fn maybe_prune(&self) {
-        if self.max_age.is_none() && !self.has_ttl_rules {
+        if !self.retention.has_age_limits() && !self.has_ttl_rules {
             return;
         }
         if self.last_prune.get().elapsed() < self.prune_interval {
             return;
         }
-        if let Err(e) = repository::prune_expired(self.conn, self.max_age) {
+        if let Err(e) = retention::enforce(self.conn, &self.retention) {
             error!("pruning expired entries: {e}");
         }
         self.last_prune.set(Instant::now());
     }
 
     /// Build a ClipboardEntry from content, or None if empty.
-    /// Rejects oversized images early (before PNG encoding) based on RGBA size.
-    fn build_entry(&self, content: ClipboardContent) -> Option<ClipboardEntry> {
+    /// Rejects oversized images early (before encoding) based on RGBA size.
+    fn build_entry(&self, content: ClipboardContent, selection: Selection) -> Option<ClipboardEntry> {
+        let mut entry = self.build_entry_content(content)?;
+        entry.set_selection(Some(selection));
+        Some(entry)
+    }
+
+    fn build_entry_content(&self, content: ClipboardContent) -> Option<ClipboardEntry> {
         let info = source_app::detect_source_app();
         debug!(
             "source app: class={:?}, title={:?}",
@@ -102,6 +209,7 @@ impl WatchState<'_> {
                 width,
                 height,
                 rgba_bytes,
+                encoded,
             } => {
                 if rgba_bytes.len() as u64 > self.max_entry_size_bytes {
                     warn!(
@@ -111,9 +219,26 @@ impl WatchState<'_> {
                     );
                     return None;
                 }
-                let mut entry = ClipboardEntry::from_image(width, height, rgba_bytes, info.class)
-                    .inspect_err(|e| warn!("PNG encoding failed: {e}"))
+                let pixels = images::pixel_hash(width, height, &rgba_bytes);
+                let mut entry =
+                    ClipboardEntry::from_image_with(width, height, rgba_bytes, encoded, &self.image_storage, info.class)
+                        .inspect_err(|e| warn!("image encoding failed: {e}"))
                     .ok()?;
+                // Restoring a lossy entry puts its decoded pixels on the clipboard;
+                // re-encoding them would hash differently, so match the entry itself.
+                if *entry.content_hash() != pixels
+                    && repository::hash_exists(self.conn, &pixels)
+                        .inspect_err(|e| error!("looking up image: {e}"))
+                        .unwrap_or(false)
+                {
+                    entry.content_hash = pixels;
+                }
+                entry.set_source_title(info.title);
+                Some(entry)
+            }
+            ClipboardContent::Files(files) => {
+                debug!("clipboard files, {} item(s)", files.uris.len());
+                let mut entry = ClipboardEntry::from_files(files, info.class);
                 entry.set_source_title(info.title);
                 Some(entry)
             }
@@ -122,13 +247,18 @@ impl WatchState<'_> {
     }
 
     /// Apply action rules to an entry, mutating it in place.
-    /// Returns the TTL duration if a TTL rule matched.
-    fn apply_actions(&self, entry: &mut ClipboardEntry) -> Option<Duration> {
+    /// Returns the TTL duration if a TTL rule matched, and the tags to add.
+    fn apply_actions(&self, entry: &mut ClipboardEntry) -> (Option<Duration>, Vec<String>) {
         if self.rules.is_empty() {
-            return None;
+            return (None, Vec::new());
         }
 
         let result = actions::apply_rules(&self.rules, entry);
+        if !result.matched_rules.is_empty() {
+            if let Err(e) = usage::record_rule_matches(self.conn, &result.matched_rules) {
+                error!("recording rule matches: {e}");
+            }
+        }
 
         if let Some(transformed) = result.transformed_text {
             entry.set_content(EntryContent::Text(transformed));
@@ -136,11 +266,11 @@ impl WatchState<'_> {
 
         entry.set_expires_at(result.expires_at);
         entry.set_mask_text(result.mask_with);
-        result.ttl
+        (result.ttl, result.tags)
     }
 
     /// Save entry to DB if within size limit.
-    fn save_if_fits(&self, entry: &ClipboardEntry) {
+    fn save_if_fits(&self, entry: &ClipboardEntry) -> Option<SaveOutcome> {
         debug!("saving entry, size={} bytes", entry.content_size_bytes());
         if entry.content_size_bytes() as u64 > self.max_entry_size_bytes {
             warn!(
@@ -148,11 +278,11 @@ impl WatchState<'_> {
                 entry.content_size_bytes() / 1024,
                 self.max_entry_size_bytes / 1024
             );
-            return;
-        }
-        if let Err(e) = repository::save_or_update(self.conn, entry, self.max_history) {
-            error!("saving entry: {e}");
+            return None;
         }
+        repository::save_entry(self.conn, entry, &self.retention, &self.storage, CopyOrigin::Watch)
+            .inspect_err(|e| error!("saving entry: {e}"))
+            .ok()
     }
 
     /// If no TTL came from action rules, check if the DB entry has an `expires_at`
@@ -161,43 +291,60 @@ impl WatchState<'_> {
         let expires_at = repository::find_expires_at(self.conn, content_hash)
             .inspect_err(|e| error!("looking up expiry: {e}"))
             .ok()??;
-        let expires_ts = expires_at.as_ref()?;
-        let expires = expires_ts.to_naive();
-        let now = Utc::now().naive_utc();
-        if expires > now {
-            let remaining = (expires - now).to_std().ok()?;
-            Some(remaining)
-        } else {
-            Some(Duration::ZERO)
-        }
+        Some(expires_at?.remaining())
     }
 
     /// Apply actions, save to DB, pick up DB expiry if needed, and update tracking.
-    fn apply_save_and_track(&self, entry: &mut ClipboardEntry) {
-        let mut ttl = self.apply_actions(entry);
-        self.save_if_fits(entry);
+    fn apply_save_and_track(&self, entry: &mut ClipboardEntry) -> Option<SaveOutcome> {
+        let (mut ttl, entry_tags) = self.apply_actions(entry);
+        let outcome = self.save_if_fits(entry);
+        if let Some(ref outcome) = outcome {
+            for tag in &entry_tags {
+                if let Err(e) = tags::add(self.conn, outcome.id, tag) {
+                    error!("tagging entry {} with '{tag}': {e}", outcome.id);
+                }
+            }
+        }
         if ttl.is_none() {
             ttl = self.pick_up_db_expiry(entry.content_hash());
         }
         self.expiry.update(ttl, entry.content_hash());
+        outcome
     }
 
-    /// Process a clipboard content change: build entry, apply actions, and save.
+    /// Drop the previous PRIMARY entry if this commit only grew or shrank it
+    /// (an intermediate drag selection), then remember this commit.
+    fn merge_partial_primary(&self, entry: &ClipboardEntry, outcome: Option<SaveOutcome>) {
+        let (Some(outcome), Some(text)) = (outcome, entry.content().text()) else {
+            self.primary.record(None);
+            return;
+        };
+        if let Some(previous_id) = self.primary.superseded_by(text) {
+            if previous_id != outcome.id {
+                debug!("replacing partial PRIMARY selection (entry {previous_id})");
+                if let Err(e) = repository::delete_entry(self.conn, previous_id) {
+                    error!("deleting partial PRIMARY entry: {e}");
+                }
+            }
+        }
+        self.primary.record(Some((outcome, text)));
+    }
+
+    /// Process a CLIPBOARD content change: build entry, apply actions, and save.
     pub(super) fn process_change(&self, content: ClipboardContent) {
-        if let Some(mut entry) = self.build_entry(content) {
+        if let Some(mut entry) = self.build_entry(content, Selection::Clipboard) {
             self.apply_save_and_track(&mut entry);
         }
     }
 
     /// Process a change and optionally extract text for syncing (avoids an extra clone).
     /// When `need_sync` is true and the entry is text, returns the text by move.
-    fn process_change_with_sync(
-        &self,
-        content: ClipboardContent,
-        need_sync: bool,
-    ) -> Option<String> {
-        if let Some(mut entry) = self.build_entry(content) {
-            self.apply_save_and_track(&mut entry);
+    fn process_change_with_sync(&self, content: ClipboardContent, need_sync: bool, selection: Selection) -> Option<String> {
+        if let Some(mut entry) = self.build_entry(content, selection) {
+            let outcome = self.apply_save_and_track(&mut entry);
+            if selection == Selection::Primary {
+                self.merge_partial_primary(&entry, outcome);
+            }
             if need_sync {
                 if let EntryContent::Text(t) = entry.into_content() {
                     return Some(t);
@@ -213,7 +360,7 @@ impl WatchState<'_> {
         let expired_hash = self.expiry.check_expired()?;
 
         // Prune expired entries from DB
-        if let Err(e) = repository::prune_expired(self.conn, self.max_age) {
+        if let Err(e) = retention::enforce(self.conn, &self.retention) {
             error!("pruning expired entries: {e}");
         }
         self.last_prune.set(Instant::now());
@@ -287,6 +434,12 @@ impl WatchState<'_> {
         last_hash: &Option<ContentHash>,
         should_sync: bool,
     ) -> SelectionResult {
+        // Checked before reading: a secret the policy skips is never read.
+        let hint = self.check_sensitive_hint(clipboard::selection_name(kind));
+        if hint == HintDecision::Skip {
+            return SelectionResult { hash: *last_hash, sync_text: None };
+        }
+
         let content = match clipboard::read_selection_with(cb, kind) {
             Ok(c) => Some(c),
             Err(e) => {
@@ -304,11 +457,24 @@ impl WatchState<'_> {
             return SelectionResult { hash: *last_hash, sync_text: None };
         }
 
-        let sync_text = if let Some(content) = content {
-            self.process_change_with_sync(content, should_sync)
-        } else {
-            None
+        // Wait until a drag selection settles before committing it.
+        let selection = match kind {
+            LinuxClipboardKind::Primary => Selection::Primary,
+            _ => Selection::Clipboard,
         };
+        if selection == Selection::Primary && !hash.as_ref().is_some_and(|h| self.primary.is_stable(h)) {
+            return SelectionResult { hash: *last_hash, sync_text: None };
+        }
+
+        // Sensitive copies are never synced to the other selection.
+        if hint == HintDecision::RecordMasked {
+            if let Some(hash) = hash {
+                self.record_masked(hash, selection);
+            }
+            return SelectionResult { hash, sync_text: None };
+        }
+
+        let sync_text = content.and_then(|content| self.process_change_with_sync(content, should_sync, selection));
 
         SelectionResult { hash, sync_text }
     }
@@ -322,14 +488,20 @@ mod tests {
     fn test_state(conn: &Connection) -> WatchState<'_> {
         WatchState {
             conn,
-            max_history: 100,
-            max_age: None,
+            retention: RetentionPolicy::max_history(100),
+            storage: StorageOptions::default(),
+            image_storage: ImageStorage::default(),
             max_entry_size_bytes: 1024 * 1024, // 1 MB
             prune_interval: Duration::from_secs(60),
             last_prune: Cell::new(Instant::now()),
             rules: vec![],
             has_ttl_rules: false,
             expiry: ExpiryTracker::new(),
+            sensitive_hint_policy: SensitiveHintPolicy::Skip,
+            sensitive_hint_ttl: Duration::from_secs(30),
+            primary: PrimaryTracker::new(Duration::ZERO, Duration::from_secs(5)),
+            watch_interval: Duration::from_millis(500),
+            sync_mode: SyncMode::Both,
         }
     }
 
@@ -346,9 +518,43 @@ mod tests {
             width: 10,
             height: 10,
             rgba_bytes: rgba,
+            encoded: None,
         };
 
-        assert!(state.build_entry(content).is_none());
+        assert!(state.build_entry(content, Selection::Clipboard).is_none());
+    }
+
+    #[test]
+    fn restored_jpeg_entry_is_not_duplicated() {
+        let conn = init_db_in_memory().unwrap();
+        let mut state = test_state(&conn);
+        state.image_storage = ImageStorage { encoding: crate::config::ImageEncoding::Jpeg, quality: 50 };
+        let (width, height) = (16, 8);
+        let rgba = (0..width * height)
+            .flat_map(|i| [(i * 7) as u8, (i * 13) as u8, (i * 29) as u8, 255])
+            .collect();
+
+        let content = ClipboardContent::Image { width, height, rgba_bytes: rgba, encoded: None };
+        let mut entry = state.build_entry(content, Selection::Clipboard).unwrap();
+        let saved = state.apply_save_and_track(&mut entry).unwrap();
+
+        // Restoring writes the decoded JPEG pixels, as `write_entry_to_clipboard` does.
+        let stored = repository::get_entry_content(&conn, saved.id).unwrap().unwrap();
+        let EntryContent::Image(bytes) = stored.content() else { panic!("not an image") };
+        let decoded = images::decode(bytes).unwrap();
+        let restored = ClipboardContent::Image {
+            width: decoded.width(),
+            height: decoded.height(),
+            rgba_bytes: decoded.into_raw(),
+            encoded: None,
+        };
+        assert_eq!(restored.content_hash().as_ref(), Some(stored.content_hash()));
+
+        let mut entry = state.build_entry(restored, Selection::Clipboard).unwrap();
+        let again = state.apply_save_and_track(&mut entry).unwrap();
+        assert_eq!((again.id, again.inserted), (saved.id, false));
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM clipboard_entries", [], |r| r.get(0)).unwrap();
+        assert_eq!(count, 1);
     }
 
     #[test]
@@ -357,7 +563,7 @@ mod tests {
         let state = test_state(&conn);
 
         let content = ClipboardContent::Text("hello".into());
-        let entry = state.build_entry(content);
+        let entry = state.build_entry(content, Selection::Clipboard);
 
         assert!(entry.is_some());
         let entry = entry.unwrap();
@@ -369,7 +575,7 @@ mod tests {
         let conn = init_db_in_memory().unwrap();
         let state = test_state(&conn);
 
-        assert!(state.build_entry(ClipboardContent::Empty).is_none());
+        assert!(state.build_entry(ClipboardContent::Empty, Selection::Clipboard).is_none());
     }
 
     #[test]
@@ -396,9 +602,175 @@ mod tests {
         assert!(state.rules.is_empty());
 
         let mut entry = ClipboardEntry::from_text("test".into(), None);
-        let ttl = state.apply_actions(&mut entry);
+        let (ttl, tags) = state.apply_actions(&mut entry);
 
         assert!(ttl.is_none());
+        assert!(tags.is_empty());
+    }
+
+    #[test]
+    fn tag_rules_tag_saved_entry() {
+        use crate::config::{ActionRule, RuleActions, RuleConditions};
+
+        let conn = init_db_in_memory().unwrap();
+        let mut state = test_state(&conn);
+        let rule = ActionRule {
+            name: "kubectl".into(),
+            conditions: RuleConditions {
+                source_app: None,
+                content_regex: Some("^kubectl ".into()),
+                source_title_regex: None,
+            },
+            actions: RuleActions {
+                tags: vec!["k8s".into()],
+                ..Default::default()
+            },
+        };
+        state.rules = vec![rule.compile().unwrap()];
+
+        let mut entry = ClipboardEntry::from_text("kubectl get pods".into(), None);
+        let outcome = state.apply_save_and_track(&mut entry).unwrap();
+
+        assert_eq!(tags::for_entry(&conn, outcome.id).unwrap(), ["k8s"]);
+    }
+
+    #[test]
+    fn sensitive_copy_is_stored_as_placeholder_with_short_ttl() {
+        let conn = init_db_in_memory().unwrap();
+        let state = test_state(&conn);
+
+        let secret = compute_hash(b"hunter2");
+        state.record_masked(secret, Selection::Clipboard);
+
+        assert_eq!(entry_texts(&conn), vec![SENSITIVE_MASK.to_string()]);
+        let saved = repository::find_by_hash(&conn, &compute_hash(SENSITIVE_MASK.as_bytes())).unwrap().unwrap();
+        assert_eq!(saved.mask_text(), Some(SENSITIVE_MASK));
+        assert!(saved.expires_at().is_some());
+        assert!(state.expiry.current_expiry.get().is_some());
+        // The secret is still cleared from the clipboard when the placeholder expires.
+        assert_eq!(*state.expiry.current_expiry_hash.borrow(), Some(secret));
+    }
+
+    #[test]
+    fn sensitive_ttl_caps_longer_rule_ttl() {
+        let conn = init_db_in_memory().unwrap();
+        let state = test_state(&conn);
+
+        let mut entry = ClipboardEntry::from_text("hunter2".into(), None);
+        let ttl = state.mask_sensitive(&mut entry, Some(Duration::from_secs(3600)));
+        assert_eq!(ttl, Duration::from_secs(30));
+
+        let ttl = state.mask_sensitive(&mut entry, Some(Duration::from_secs(5)));
+        assert_eq!(ttl, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn ignore_policy_always_records() {
+        let conn = init_db_in_memory().unwrap();
+        let mut state = test_state(&conn);
+        state.sensitive_hint_policy = SensitiveHintPolicy::Ignore;
+        assert_eq!(state.check_sensitive_hint("CLIPBOARD"), HintDecision::Record);
+    }
+
+    #[test]
+    fn process_change_without_hint_is_not_masked() {
+        let conn = init_db_in_memory().unwrap();
+        let state = test_state(&conn);
+
+        let sync = state.process_change_with_sync(ClipboardContent::Text("plain".into()), true, Selection::Clipboard);
+        assert_eq!(sync.as_deref(), Some("plain"));
+        assert!(repository::find_by_hash(&conn, &compute_hash(b"plain")).unwrap().unwrap().mask_text().is_none());
+    }
+
+    fn entry_texts(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare("SELECT text_content FROM clipboard_entries ORDER BY id")
+            .unwrap();
+        stmt.query_map([], |r| r.get(0)).unwrap().map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn partial_primary_selections_are_replaced() {
+        let conn = init_db_in_memory().unwrap();
+        let state = test_state(&conn);
+
+        for text in ["The", "The quick", "The quick brown fox"] {
+            state.process_change_with_sync(ClipboardContent::Text(text.into()), false, Selection::Primary);
+        }
+        assert_eq!(entry_texts(&conn), vec!["The quick brown fox".to_string()]);
+
+        state.process_change_with_sync(ClipboardContent::Text("unrelated".into()), false, Selection::Primary);
+        assert_eq!(entry_texts(&conn).len(), 2);
+    }
+
+    #[test]
+    fn partial_primary_keeps_preexisting_entry() {
+        let conn = init_db_in_memory().unwrap();
+        let state = test_state(&conn);
+
+        // "The quick" was copied deliberately before the drag started.
+        repository::save_or_update(&conn, &ClipboardEntry::from_text("The quick".into(), None), 100).unwrap();
+
+        state.process_change_with_sync(ClipboardContent::Text("The quick".into()), false, Selection::Primary);
+        state.process_change_with_sync(ClipboardContent::Text("The quick brown".into()), false, Selection::Primary);
+        assert_eq!(entry_texts(&conn).len(), 2);
+    }
+
+    #[test]
+    fn apply_config_swaps_rules_and_keeps_expiry() {
+        let conn = init_db_in_memory().unwrap();
+        let mut state = WatchState::new(&conn, &Config::default());
+        state.expiry.update(Some(Duration::from_secs(60)), &[7u8; 32]);
+
+        let yaml = r#"
+max_history: 7
+sync_mode: disabled
+actions:
+  - name: short
+    conditions:
+      content_regex: "^tmp"
+    actions:
+      ttl: 5s
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        state.apply_config(&config);
+
+        assert_eq!(state.retention.max_history, 7);
+        assert_eq!(state.sync_mode, SyncMode::Disabled);
+        assert_eq!(state.rules.len(), 1);
+        assert!(state.has_ttl_rules);
+        assert!(state.expiry.current_expiry.get().is_some());
+    }
+
+    #[test]
+    fn entries_record_their_selection() {
+        let conn = init_db_in_memory().unwrap();
+        let state = test_state(&conn);
+
+        state.process_change_with_sync(ClipboardContent::Text("mouse".into()), false, Selection::Primary);
+        state.process_change(ClipboardContent::Text("ctrl-c".into()));
+
+        let selection_of = |text: &str| {
+            repository::find_by_hash(&conn, &compute_hash(text.as_bytes()))
+                .unwrap()
+                .unwrap()
+                .selection()
+        };
+        assert_eq!(selection_of("mouse"), Some(Selection::Primary));
+        assert_eq!(selection_of("ctrl-c"), Some(Selection::Clipboard));
+
+        state.process_change(ClipboardContent::Text("mouse".into()));
+        assert_eq!(selection_of("mouse"), Some(Selection::Both));
+    }
+
+    #[test]
+    fn clipboard_changes_are_never_merged() {
+        let conn = init_db_in_memory().unwrap();
+        let state = test_state(&conn);
+
+        state.process_change_with_sync(ClipboardContent::Text("The".into()), false, Selection::Clipboard);
+        state.process_change_with_sync(ClipboardContent::Text("The quick".into()), false, Selection::Clipboard);
+        assert_eq!(entry_texts(&conn).len(), 2);
     }
 
     #[test]
@@ -1,16 +1,45 @@
 pub(crate) mod protocol;
 pub mod serve;
 pub mod source_app;
+pub(crate) mod targets;
 
 use arboard::Clipboard;
-use image::ImageFormat;
 use log::error;
 
 #[cfg(target_os = "linux")]
 use arboard::{GetExtLinux, LinuxClipboardKind, SetExtLinux};
 
 use crate::errors::{AppError, Result};
-use crate::models::entry::{ContentHash, EntryContent};
+use crate::models::entry::{ContentHash, EntryContent, FileList, FileOperation};
+use crate::models::images;
+
+/// GNOME/Thunar/Nemo target carrying the copy/cut operation plus URIs.
+const GNOME_COPIED_FILES_TARGET: &str = "x-special/gnome-copied-files";
+/// KDE target set to "1" when files were cut rather than copied.
+const KDE_CUT_SELECTION_TARGET: &str = "application/x-kde-cutselection";
+
+/// Targets password managers (KeePassXC, KDE apps, 1Password) add to copies
+/// that clipboard managers should not record.
+const SENSITIVE_HINT_TARGETS: &[&str] = &[
+    "x-kde-passwordManagerHint",
+    "application/x-nspasteboard-concealed-type",
+];
+
+/// Whether the current owner of `selection` ("CLIPBOARD" or "PRIMARY") marked
+/// its content as sensitive. Inspects only the TARGETS list, never the content.
+pub fn has_sensitive_hint(selection: &str) -> bool {
+    targets::offers_any(selection, SENSITIVE_HINT_TARGETS)
+}
+
+/// X11 selection name for a clipboard kind.
+#[cfg(target_os = "linux")]
+pub fn selection_name(kind: LinuxClipboardKind) -> &'static str {
+    match kind {
+        LinuxClipboardKind::Clipboard => "CLIPBOARD",
+        LinuxClipboardKind::Primary => "PRIMARY",
+        LinuxClipboardKind::Secondary => "SECONDARY",
+    }
+}
 
 #[derive(Debug)]
 pub enum ClipboardContent {
@@ -19,23 +48,24 @@ pub enum ClipboardContent {
         width: u32,
         height: u32,
         rgba_bytes: Vec<u8>,
+        /// The image as the source offered it (PNG, WebP or JPEG), if read
+        /// from one of `IMAGE_TARGETS`.
+        encoded: Option<Vec<u8>>,
     },
+    Files(FileList),
     Empty,
 }
 
 impl ClipboardContent {
-    /// Compute content hash for **change detection** in the watch loop.
-    ///
-    /// INVARIANT: For images, this hashes raw RGBA bytes (from arboard),
-    /// while `ClipboardEntry::from_image()` hashes the PNG-encoded bytes.
-    /// These hashes are NOT comparable across the two types. Change detection
-    /// (comparing successive `ClipboardContent` hashes) and DB dedup
-    /// (comparing `ClipboardEntry` hashes) operate in separate hash spaces.
+    /// Compute content hash for change detection in the watch loop. Equal to
+    /// the `content_hash` of the entry built from this content; images hash
+    /// their pixels, whatever encoding they are stored in.
     pub fn content_hash(&self) -> Option<ContentHash> {
         use crate::models::entry::compute_hash;
         match self {
             Self::Text(t) => Some(compute_hash(t.as_bytes())),
-            Self::Image { rgba_bytes, .. } => Some(compute_hash(rgba_bytes)),
+            Self::Image { width, height, rgba_bytes, .. } => Some(images::pixel_hash(*width, *height, rgba_bytes)),
+            Self::Files(files) => Some(compute_hash(files.to_gnome_format().as_bytes())),
             Self::Empty => None,
         }
     }
@@ -50,22 +80,35 @@ pub fn open_clipboard() -> Result<Clipboard> {
 // ---------------------------------------------------------------------------
 
 /// Read a selection using an existing Clipboard instance (avoids reconnecting).
+/// File lists are checked first: file managers also offer the paths as plain text.
 #[cfg(target_os = "linux")]
 pub fn read_selection_with(
     cb: &mut Clipboard,
     kind: LinuxClipboardKind,
 ) -> Result<ClipboardContent> {
+    if matches!(kind, LinuxClipboardKind::Clipboard) {
+        if let Ok(paths) = cb.get().clipboard(kind).file_list() {
+            if !paths.is_empty() {
+                return Ok(ClipboardContent::Files(read_file_list(&paths)));
+            }
+        }
+    }
+
     match cb.get().clipboard(kind).text() {
         Ok(text) if !text.is_empty() => return Ok(ClipboardContent::Text(text)),
         _ => {}
     }
 
     if matches!(kind, LinuxClipboardKind::Clipboard) {
+        if let Some(image) = read_encoded_image() {
+            return Ok(image);
+        }
         if let Ok(img) = cb.get().clipboard(kind).image() {
             return Ok(ClipboardContent::Image {
                 width: img.width as u32,
                 height: img.height as u32,
                 rgba_bytes: img.bytes.into_owned(),
+                encoded: None,
             });
         }
     }
@@ -73,6 +116,91 @@ pub fn read_selection_with(
     Ok(ClipboardContent::Empty)
 }
 
+/// The last image `read_encoded_image` decoded and the owner stamp it was read under.
+#[cfg(target_os = "linux")]
+struct DecodedImage {
+    stamp: (u32, u32),
+    width: u32,
+    height: u32,
+    rgba_bytes: Vec<u8>,
+    encoded: Vec<u8>,
+}
+
+#[cfg(target_os = "linux")]
+thread_local! {
+    static LAST_IMAGE: std::cell::RefCell<Option<DecodedImage>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Read the CLIPBOARD image in the first encoding of `images::IMAGE_TARGETS` its owner
+/// offers, keeping the encoded bytes. `None` if none is offered or readable
+/// this way (e.g. on Wayland); arboard's image reader is the fallback then.
+///
+/// The watcher polls this every tick, so an image is only transferred and
+/// decoded again once the owner stamp says the clipboard changed.
+#[cfg(target_os = "linux")]
+fn read_encoded_image() -> Option<ClipboardContent> {
+    let stamp = targets::owner_stamp("CLIPBOARD");
+    let cached = LAST_IMAGE.with(|last| {
+        let last = last.borrow();
+        let image = last.as_ref().filter(|image| Some(image.stamp) == stamp)?;
+        Some(ClipboardContent::Image {
+            width: image.width,
+            height: image.height,
+            rgba_bytes: image.rgba_bytes.clone(),
+            encoded: Some(image.encoded.clone()),
+        })
+    });
+    if cached.is_some() {
+        return cached;
+    }
+    let content = decode_encoded_image()?;
+    if let (Some(stamp), ClipboardContent::Image { width, height, rgba_bytes, encoded: Some(encoded) }) =
+        (stamp, &content)
+    {
+        let image = DecodedImage {
+            stamp,
+            width: *width,
+            height: *height,
+            rgba_bytes: rgba_bytes.clone(),
+            encoded: encoded.clone(),
+        };
+        LAST_IMAGE.with(|last| *last.borrow_mut() = Some(image));
+    }
+    Some(content)
+}
+
+#[cfg(target_os = "linux")]
+fn decode_encoded_image() -> Option<ClipboardContent> {
+    images::IMAGE_TARGETS.iter().find_map(|(target, format)| {
+        let encoded = targets::read_target("CLIPBOARD", target)?;
+        let img = image::load_from_memory_with_format(&encoded, *format)
+            .inspect_err(|e| log::debug!("cannot decode {target}: {e}"))
+            .ok()?
+            .to_rgba8();
+        Some(ClipboardContent::Image {
+            width: img.width(),
+            height: img.height(),
+            rgba_bytes: img.into_raw(),
+            encoded: Some(encoded),
+        })
+    })
+}
+
+/// Build a `FileList` for the CLIPBOARD selection, preferring the file manager's
+/// own `x-special/gnome-copied-files` data (which carries the cut/copy operation).
+fn read_file_list(paths: &[std::path::PathBuf]) -> FileList {
+    if let Some(files) = targets::read_target("CLIPBOARD", GNOME_COPIED_FILES_TARGET)
+        .and_then(|raw| FileList::parse(&String::from_utf8_lossy(&raw)))
+    {
+        return files;
+    }
+    let operation = match targets::read_target("CLIPBOARD", KDE_CUT_SELECTION_TARGET) {
+        Some(raw) if raw.first() == Some(&b'1') => FileOperation::Cut,
+        _ => FileOperation::Copy,
+    };
+    FileList::from_paths(paths, operation)
+}
+
 /// Read CLIPBOARD using an existing Clipboard instance.
 pub fn read_clipboard_with(cb: &mut Clipboard) -> Result<ClipboardContent> {
     #[cfg(target_os = "linux")]
@@ -81,6 +209,11 @@ pub fn read_clipboard_with(cb: &mut Clipboard) -> Result<ClipboardContent> {
     }
     #[cfg(not(target_os = "linux"))]
     {
+        if let Ok(paths) = cb.get().file_list() {
+            if !paths.is_empty() {
+                return Ok(ClipboardContent::Files(read_file_list(&paths)));
+            }
+        }
         match cb.get_text() {
             Ok(text) if !text.is_empty() => return Ok(ClipboardContent::Text(text)),
             _ => {}
@@ -90,6 +223,7 @@ pub fn read_clipboard_with(cb: &mut Clipboard) -> Result<ClipboardContent> {
                 width: img.width as u32,
                 height: img.height as u32,
                 rgba_bytes: img.bytes.into_owned(),
+                encoded: None,
             });
         }
         Ok(ClipboardContent::Empty)
@@ -183,13 +317,31 @@ pub fn write_clipboard_text_sync(text: &str) -> Result<()> {
     }
 }
 
-/// Write an entry's content to the clipboard. Decodes PNG for images.
+/// Put a file list on the clipboard as `x-special/gnome-copied-files` and
+/// `text/uri-list`, so pasting into a file manager works. Always offered as a copy: re-pasting a cut would try to
+/// move files that were already moved.
+pub fn write_clipboard_files_sync(files: &FileList) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        spawn_clipboard_server(&ClipboardContent::Files(files.clone()))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let mut cb = open_clipboard()?;
+        cb.set()
+            .file_list(&files.paths())
+            ?;
+        Ok(())
+    }
+}
+
+/// Write an entry's content to the clipboard. Decodes images to RGBA.
 pub fn write_entry_to_clipboard(content: &EntryContent) -> Result<()> {
     match content {
         EntryContent::Text(text) => write_clipboard_text_sync(text),
-        EntryContent::Image(png_bytes) => {
-            let img = image::load_from_memory_with_format(png_bytes, ImageFormat::Png)?
-                .to_rgba8();
+        EntryContent::Files(files) => write_clipboard_files_sync(files),
+        EntryContent::Image(bytes) => {
+            let img = images::decode(bytes)?;
             let (w, h) = img.dimensions();
             write_clipboard_image_sync(w, h, img.into_raw())
         }
@@ -213,6 +365,7 @@ pub fn write_clipboard_image_sync(width: u32, height: u32, rgba_bytes: Vec<u8>)
             width,
             height,
             rgba_bytes,
+            encoded: None,
         })
     }
     #[cfg(not(target_os = "linux"))]
@@ -1,19 +1,22 @@
 //! Binary protocol for clipboard content transfer via stdin/stdout.
 //!
 //! Wire format:
-//! - `[1 byte]`  type: 0x01 = text, 0x02 = image
+//! - `[1 byte]`  type: 0x01 = text, 0x02 = image, 0x03 = files
 //! - `[4 bytes]` payload_len: u32 big-endian
 //! - For text:  `[payload_len bytes]` UTF-8 string
 //! - For image: `[4 bytes]` width u32 BE, `[4 bytes]` height u32 BE, `[payload_len bytes]` RGBA
+//! - For files: `[payload_len bytes]` UTF-8 in `x-special/gnome-copied-files` format
 
 use std::io::{Read, Write};
 
 use crate::errors::{AppError, Result};
+use crate::models::entry::FileList;
 
 use super::ClipboardContent;
 
 const TYPE_TEXT: u8 = 0x01;
 const TYPE_IMAGE: u8 = 0x02;
+const TYPE_FILES: u8 = 0x03;
 const MAX_PAYLOAD_SIZE: usize = 256 * 1024 * 1024;
 
 fn read_exact(r: &mut impl Read, buf: &mut [u8]) -> Result<()> {
@@ -27,21 +30,30 @@ fn read_u32(r: &mut impl Read) -> Result<u32> {
     Ok(u32::from_be_bytes(buf))
 }
 
+fn read_utf8(r: &mut impl Read, len: usize) -> Result<String> {
+    let mut buf = vec![0u8; len];
+    read_exact(r, &mut buf)?;
+    String::from_utf8(buf).map_err(|e| AppError::Clipboard(format!("invalid utf-8: {e}")))
+}
+
+fn write_payload(w: &mut impl Write, content_type: u8, bytes: &[u8]) -> Result<()> {
+    let len: u32 = bytes.len().try_into().map_err(|_| {
+        AppError::Clipboard(format!("payload too large: {} bytes", bytes.len()))
+    })?;
+    w.write_all(&[content_type])?;
+    w.write_all(&len.to_be_bytes())?;
+    w.write_all(bytes)?;
+    Ok(())
+}
+
 pub fn encode(content: &ClipboardContent, w: &mut impl Write) -> Result<()> {
     match content {
-        ClipboardContent::Text(text) => {
-            let bytes = text.as_bytes();
-            let len: u32 = bytes.len().try_into().map_err(|_| {
-                AppError::Clipboard(format!("text too large: {} bytes", bytes.len()))
-            })?;
-            w.write_all(&[TYPE_TEXT])?;
-            w.write_all(&len.to_be_bytes())?;
-            w.write_all(bytes)?;
-        }
+        ClipboardContent::Text(text) => write_payload(w, TYPE_TEXT, text.as_bytes())?,
         ClipboardContent::Image {
             width,
             height,
             rgba_bytes,
+            ..
         } => {
             let len: u32 = rgba_bytes.len().try_into().map_err(|_| {
                 AppError::Clipboard(format!("image too large: {} bytes", rgba_bytes.len()))
@@ -52,6 +64,9 @@ pub fn encode(content: &ClipboardContent, w: &mut impl Write) -> Result<()> {
             w.write_all(&height.to_be_bytes())?;
             w.write_all(rgba_bytes)?;
         }
+        ClipboardContent::Files(files) => {
+            write_payload(w, TYPE_FILES, files.to_gnome_format().as_bytes())?;
+        }
         ClipboardContent::Empty => {}
     }
     Ok(())
@@ -69,12 +84,12 @@ pub fn decode(r: &mut impl Read) -> Result<ClipboardContent> {
     }
 
     match type_buf[0] {
-        TYPE_TEXT => {
-            let mut text_buf = vec![0u8; payload_len];
-            read_exact(r, &mut text_buf)?;
-            let text = String::from_utf8(text_buf)
-                .map_err(|e| AppError::Clipboard(format!("invalid utf-8: {e}")))?;
-            Ok(ClipboardContent::Text(text))
+        TYPE_TEXT => Ok(ClipboardContent::Text(read_utf8(r, payload_len)?)),
+        TYPE_FILES => {
+            let raw = read_utf8(r, payload_len)?;
+            let files = FileList::parse(&raw)
+                .ok_or_else(|| AppError::Clipboard(format!("invalid file list: {raw:?}")))?;
+            Ok(ClipboardContent::Files(files))
         }
         TYPE_IMAGE => {
             let width = read_u32(r)?;
@@ -93,6 +108,7 @@ pub fn decode(r: &mut impl Read) -> Result<ClipboardContent> {
                 width,
                 height,
                 rgba_bytes: rgba_buf,
+                encoded: None,
             })
         }
         other => Err(AppError::Clipboard(format!(
@@ -123,6 +139,7 @@ mod tests {
             width: 2,
             height: 3,
             rgba_bytes: vec![0xAA; 2 * 3 * 4],
+            encoded: None,
         };
         let mut buf = Vec::new();
         encode(&content, &mut buf).unwrap();
@@ -132,6 +149,7 @@ mod tests {
                 width,
                 height,
                 rgba_bytes,
+                ..
             } => {
                 assert_eq!(width, 2);
                 assert_eq!(height, 3);
@@ -141,6 +159,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn roundtrip_files() {
+        let files = FileList::parse("cut\nfile:///tmp/a.txt\nfile:///tmp/b.txt").unwrap();
+        let content = ClipboardContent::Files(files.clone());
+        let mut buf = Vec::new();
+        encode(&content, &mut buf).unwrap();
+        let decoded = decode(&mut &buf[..]).unwrap();
+        match decoded {
+            ClipboardContent::Files(f) => assert_eq!(f, files),
+            other => panic!("expected Files, got {other:?}"),
+        }
+    }
+
     #[test]
     fn decode_image_dimension_mismatch() {
         // Encode a 2x3 image but tamper with dimensions to 2x2 (expects 16 bytes, got 24)
@@ -148,6 +179,7 @@ mod tests {
             width: 2,
             height: 3,
             rgba_bytes: vec![0xAA; 2 * 3 * 4],
+            encoded: None,
         };
         let mut buf = Vec::new();
         encode(&content, &mut buf).unwrap();
@@ -198,7 +230,7 @@ mod tests {
     #[test]
     fn decode_truncated_input() {
         // Only type byte, no payload length
-        let buf = vec![TYPE_TEXT];
+        let buf = [TYPE_TEXT];
         let result = decode(&mut &buf[..]);
         assert!(result.is_err());
     }
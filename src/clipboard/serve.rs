@@ -6,8 +6,9 @@ use arboard::Clipboard;
 use arboard::{LinuxClipboardKind, SetExtLinux};
 
 use crate::errors::Result;
+use crate::models::entry::{FileList, FileOperation};
 
-use super::ClipboardContent;
+use super::{ClipboardContent, GNOME_COPIED_FILES_TARGET};
 
 pub fn run() -> Result<()> {
     let mut stdin = std::io::stdin().lock();
@@ -33,6 +34,7 @@ pub fn run() -> Result<()> {
             width,
             height,
             rgba_bytes,
+            ..
         } => {
             let img = arboard::ImageData {
                 width: width as usize,
@@ -52,6 +54,32 @@ pub fn run() -> Result<()> {
                 .image(img)
                 ?;
         }
+        ClipboardContent::Files(files) => {
+            // File managers read the operation from `x-special/gnome-copied-files`;
+            // arboard alone only offers `text/uri-list`.
+            let copy = FileList { operation: FileOperation::Copy, ..files };
+            let uri_list: String = copy.uris.iter().map(|uri| format!("{uri}\r\n")).collect();
+            let values = [
+                (GNOME_COPIED_FILES_TARGET, copy.to_gnome_format().into_bytes()),
+                ("text/uri-list", uri_list.into_bytes()),
+            ];
+            if super::targets::serve("CLIPBOARD", &values) {
+                return Ok(());
+            }
+            let paths = copy.paths();
+
+            #[cfg(target_os = "linux")]
+            cb.set()
+                .wait()
+                .clipboard(LinuxClipboardKind::Clipboard)
+                .file_list(&paths)
+                ?;
+
+            #[cfg(not(target_os = "linux"))]
+            cb.set()
+                .file_list(&paths)
+                ?;
+        }
         ClipboardContent::Empty => {}
     }
 
@@ -0,0 +1,413 @@
+//! Raw access to selection targets (MIME types) that arboard does not expose.
+//!
+//! Best-effort: implemented for X11 only. Returns `None` on Wayland, when the
+//! selection owner does not offer the target, or if the owner does not answer
+//! within `READ_TIMEOUT`. Large values sent incrementally (`INCR`) are read
+//! up to `MAX_VALUE_BYTES`. `serve` offers targets of our own, for values small
+//! enough to be sent in one request.
+
+#[cfg(all(target_os = "linux", feature = "x11-source-app"))]
+pub fn read_target(selection: &str, target: &str) -> Option<Vec<u8>> {
+    x11_reader::read(selection.as_bytes(), target.as_bytes())
+}
+
+#[cfg(not(all(target_os = "linux", feature = "x11-source-app")))]
+pub fn read_target(_selection: &str, _target: &str) -> Option<Vec<u8>> {
+    None
+}
+
+/// Whether the selection owner advertises any of `targets` in its `TARGETS` list.
+/// Only the target list is requested — the content itself is never read.
+#[cfg(all(target_os = "linux", feature = "x11-source-app"))]
+pub fn offers_any(selection: &str, targets: &[&str]) -> bool {
+    x11_reader::offers_any(selection.as_bytes(), targets)
+}
+
+#[cfg(not(all(target_os = "linux", feature = "x11-source-app")))]
+pub fn offers_any(_selection: &str, _targets: &[&str]) -> bool {
+    false
+}
+
+/// Owner window of `selection` and the time it acquired it, which changes
+/// whenever new content is set. `None` if there is no owner or it does not
+/// report the time.
+#[cfg(all(target_os = "linux", feature = "x11-source-app"))]
+pub fn owner_stamp(selection: &str) -> Option<(u32, u32)> {
+    x11_reader::owner_stamp(selection.as_bytes())
+}
+
+#[cfg(not(all(target_os = "linux", feature = "x11-source-app")))]
+pub fn owner_stamp(_selection: &str) -> Option<(u32, u32)> {
+    None
+}
+
+/// Own `selection` and offer `values` (target name and bytes), plus `TARGETS`
+/// and `TIMESTAMP`, until another client takes the selection. Returns false
+/// if the selection could not be taken (e.g. on Wayland) and nothing was served.
+#[cfg(all(target_os = "linux", feature = "x11-source-app"))]
+pub fn serve(selection: &str, values: &[(&str, Vec<u8>)]) -> bool {
+    x11_owner::serve(selection.as_bytes(), values)
+}
+
+#[cfg(not(all(target_os = "linux", feature = "x11-source-app")))]
+pub fn serve(_selection: &str, _values: &[(&str, Vec<u8>)]) -> bool {
+    false
+}
+
+/// Cached X11 connection and hidden requestor window for selection conversions.
+#[cfg(all(target_os = "linux", feature = "x11-source-app"))]
+mod x11_reader {
+    use std::cell::RefCell;
+    use std::time::{Duration, Instant};
+
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt, CreateWindowAux, EventMask, Property, WindowClass};
+    use x11rb::protocol::Event;
+    use x11rb::rust_connection::RustConnection;
+
+    /// How long to wait for the selection owner to answer a conversion request,
+    /// and for each chunk of an `INCR` transfer.
+    const READ_TIMEOUT: Duration = Duration::from_millis(200);
+    /// Poll step while waiting for `SelectionNotify`.
+    const POLL_STEP: Duration = Duration::from_millis(2);
+    /// Part of a property read back per request (in 32-bit units): 1 MB.
+    const PROPERTY_CHUNK_LEN: u32 = 256 * 1024;
+    /// Largest value we read, however it is transferred: 64 MB.
+    const MAX_VALUE_BYTES: usize = 64 * 1024 * 1024;
+
+    struct Reader {
+        conn: RustConnection,
+        window: u32,
+        property_atom: u32,
+        incr_atom: u32,
+    }
+
+    thread_local! {
+        static READER: RefCell<Option<Reader>> = const { RefCell::new(None) };
+    }
+
+    pub fn read(selection: &[u8], target: &[u8]) -> Option<Vec<u8>> {
+        with_reader(|reader| Ok(convert(reader, selection, target)?.map(|(value, _)| value)))
+    }
+
+    pub fn offers_any(selection: &[u8], targets: &[&str]) -> bool {
+        with_reader(|reader| {
+            let Some((value, format)) = convert(reader, selection, b"TARGETS")? else {
+                return Ok(None);
+            };
+            if format != 32 {
+                return Ok(None);
+            }
+            let offered: Vec<u32> = value
+                .chunks_exact(4)
+                .map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+                .collect();
+            for name in targets {
+                // only_if_exists: an atom nobody has interned cannot be offered.
+                let atom = reader
+                    .conn
+                    .intern_atom(true, name.as_bytes())
+                    .map_err(drop)?
+                    .reply()
+                    .map_err(drop)?
+                    .atom;
+                if atom != x11rb::NONE && offered.contains(&atom) {
+                    return Ok(Some(()));
+                }
+            }
+            Ok(None)
+        })
+        .is_some()
+    }
+
+    pub fn owner_stamp(selection: &[u8]) -> Option<(u32, u32)> {
+        with_reader(|reader| {
+            let atom = reader.conn.intern_atom(false, selection).map_err(drop)?.reply().map_err(drop)?.atom;
+            let owner = reader.conn.get_selection_owner(atom).map_err(drop)?.reply().map_err(drop)?.owner;
+            if owner == x11rb::NONE {
+                return Ok(None);
+            }
+            let Some((value, 32)) = convert(reader, selection, b"TIMESTAMP")? else {
+                return Ok(None);
+            };
+            // CurrentTime (0) is not a real acquisition time.
+            let time = value.get(..4).map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]));
+            Ok(time.filter(|&time| time != 0).map(|time| (owner, time)))
+        })
+    }
+
+    fn with_reader<T>(f: impl FnOnce(&Reader) -> Result<Option<T>, ()>) -> Option<T> {
+        READER.with(|cell| {
+            let mut borrow = cell.borrow_mut();
+            if borrow.is_none() {
+                *borrow = connect();
+            }
+            let result = f(borrow.as_ref()?);
+            if result.is_err() {
+                // Connection may be stale — drop it so the next call reconnects.
+                *borrow = None;
+            }
+            result.ok().flatten()
+        })
+    }
+
+    fn connect() -> Option<Reader> {
+        let (conn, screen_num) = x11rb::connect(None).ok()?;
+        let screen = &conn.setup().roots[screen_num];
+        let window = conn.generate_id().ok()?;
+        conn.create_window(
+            x11rb::COPY_DEPTH_FROM_PARENT,
+            window,
+            screen.root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_ONLY,
+            x11rb::COPY_FROM_PARENT,
+            // PropertyNotify announces each chunk of an INCR transfer.
+            &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )
+        .ok()?;
+        let property_atom = conn.intern_atom(false, b"CLIO_SELECTION").ok()?.reply().ok()?.atom;
+        let incr_atom = conn.intern_atom(false, b"INCR").ok()?.reply().ok()?.atom;
+        conn.flush().ok()?;
+        Some(Reader { conn, window, property_atom, incr_atom })
+    }
+
+    /// Returns the property value and its format (8, 16 or 32 bits per item).
+    /// `Err` means the connection is broken; `Ok(None)` means the target is unavailable.
+    fn convert(reader: &Reader, selection: &[u8], target: &[u8]) -> Result<Option<(Vec<u8>, u8)>, ()> {
+        let conn = &reader.conn;
+        let selection_atom = conn.intern_atom(false, selection).map_err(drop)?.reply().map_err(drop)?.atom;
+        let target_atom = conn.intern_atom(false, target).map_err(drop)?.reply().map_err(drop)?.atom;
+
+        conn.convert_selection(
+            reader.window,
+            selection_atom,
+            target_atom,
+            reader.property_atom,
+            x11rb::CURRENT_TIME,
+        )
+        .map_err(drop)?;
+        conn.flush().map_err(drop)?;
+
+        let deadline = Instant::now() + READ_TIMEOUT;
+        loop {
+            match conn.poll_for_event().map_err(drop)? {
+                Some(Event::SelectionNotify(ev))
+                    if ev.requestor == reader.window && ev.selection == selection_atom =>
+                {
+                    if ev.property == x11rb::NONE {
+                        return Ok(None);
+                    }
+                    // Deleting the property (done by `read_property`) starts an INCR transfer.
+                    return match read_property(reader, ev.property)? {
+                        Some((_, _, kind)) if kind == reader.incr_atom => read_incremental(reader, ev.property),
+                        Some((value, format, _)) => Ok(Some((value, format))),
+                        None => Ok(None),
+                    };
+                }
+                Some(_) => continue,
+                None if Instant::now() >= deadline => return Ok(None),
+                None => std::thread::sleep(POLL_STEP),
+            }
+        }
+    }
+
+    /// Read the chunks of an `INCR` transfer into `property` until the owner
+    /// sends an empty one. `Ok(None)` if it stalls or exceeds `MAX_VALUE_BYTES`.
+    fn read_incremental(reader: &Reader, property: u32) -> Result<Option<(Vec<u8>, u8)>, ()> {
+        let conn = &reader.conn;
+        let mut value = Vec::new();
+        let mut format = 8;
+        let mut deadline = Instant::now() + READ_TIMEOUT;
+        loop {
+            match conn.poll_for_event().map_err(drop)? {
+                Some(Event::PropertyNotify(ev))
+                    if ev.window == reader.window && ev.atom == property && ev.state == Property::NEW_VALUE =>
+                {
+                    let Some((chunk, chunk_format, _)) = read_property(reader, property)? else {
+                        return Ok(None);
+                    };
+                    if chunk.is_empty() {
+                        return Ok(Some((value, format)));
+                    }
+                    if value.len() + chunk.len() > MAX_VALUE_BYTES {
+                        log::debug!("selection value exceeds {MAX_VALUE_BYTES} bytes, not read");
+                        return Ok(None);
+                    }
+                    value.extend_from_slice(&chunk);
+                    format = chunk_format;
+                    deadline = Instant::now() + READ_TIMEOUT;
+                }
+                Some(_) => continue,
+                None if Instant::now() >= deadline => return Ok(None),
+                None => std::thread::sleep(POLL_STEP),
+            }
+        }
+    }
+
+    /// Read and delete `property` of the requestor window, in chunks of
+    /// `PROPERTY_CHUNK_LEN`. Returns the value, its format and its type.
+    fn read_property(reader: &Reader, property: u32) -> Result<Option<(Vec<u8>, u8, u32)>, ()> {
+        let mut value = Vec::new();
+        loop {
+            let offset = (value.len() / 4) as u32;
+            // The property is only deleted once its last part has been read.
+            let reply = reader
+                .conn
+                .get_property(true, reader.window, property, AtomEnum::ANY, offset, PROPERTY_CHUNK_LEN)
+                .map_err(drop)?
+                .reply()
+                .map_err(drop)?;
+            if reply.type_ == x11rb::NONE {
+                return Ok(None);
+            }
+            value.extend_from_slice(&reply.value);
+            if reply.bytes_after == 0 {
+                return Ok(Some((value, reply.format, reply.type_)));
+            }
+            if value.len() + reply.bytes_after as usize > MAX_VALUE_BYTES {
+                log::debug!("selection value exceeds {MAX_VALUE_BYTES} bytes, not read");
+                reader.conn.delete_property(reader.window, property).map_err(drop)?;
+                return Ok(None);
+            }
+        }
+    }
+}
+
+/// Selection owner on a connection of its own, used by the clipboard server process.
+#[cfg(all(target_os = "linux", feature = "x11-source-app"))]
+mod x11_owner {
+    use x11rb::connection::{Connection, RequestConnection};
+    use x11rb::errors::ConnectionError;
+    use x11rb::protocol::xproto::{
+        AtomEnum, ConnectionExt, CreateWindowAux, EventMask, PropMode, SelectionNotifyEvent, SelectionRequestEvent,
+        WindowClass, SELECTION_NOTIFY_EVENT,
+    };
+    use x11rb::protocol::Event;
+    use x11rb::rust_connection::RustConnection;
+    use x11rb::wrapper::ConnectionExt as _;
+
+    /// Room for the `ChangeProperty` request header in front of a value.
+    const REQUEST_HEADER_BYTES: usize = 24;
+
+    struct Owner {
+        window: u32,
+        selection: u32,
+        /// Server time the selection was taken at, answered to `TIMESTAMP`.
+        time: u32,
+        targets_atom: u32,
+        timestamp_atom: u32,
+        values: Vec<(u32, Vec<u8>)>,
+    }
+
+    pub fn serve(selection: &[u8], values: &[(&str, Vec<u8>)]) -> bool {
+        let Ok((conn, screen_num)) = x11rb::connect(None) else {
+            return false;
+        };
+        let Some(owner) = take(&conn, screen_num, selection, values) else {
+            return false;
+        };
+        if let Err(e) = owner.run(&conn) {
+            log::debug!("serving selection: {e}");
+        }
+        true
+    }
+
+    fn take(conn: &RustConnection, screen_num: usize, selection: &[u8], values: &[(&str, Vec<u8>)]) -> Option<Owner> {
+        let intern = |name: &[u8]| Some(conn.intern_atom(false, name).ok()?.reply().ok()?.atom);
+        let screen = &conn.setup().roots[screen_num];
+        let window = conn.generate_id().ok()?;
+        conn.create_window(
+            x11rb::COPY_DEPTH_FROM_PARENT,
+            window,
+            screen.root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_ONLY,
+            x11rb::COPY_FROM_PARENT,
+            &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )
+        .ok()?;
+        let mut owner = Owner {
+            window,
+            selection: intern(selection)?,
+            time: 0,
+            targets_atom: intern(b"TARGETS")?,
+            timestamp_atom: intern(b"TIMESTAMP")?,
+            values: Vec::with_capacity(values.len()),
+        };
+        for (target, value) in values {
+            owner.values.push((intern(target.as_bytes())?, value.clone()));
+        }
+
+        // Owning a selection at CurrentTime is not allowed: an empty append to
+        // a property of our window returns the server time in PropertyNotify.
+        conn.change_property8(PropMode::APPEND, window, AtomEnum::WM_NAME, AtomEnum::STRING, &[]).ok()?;
+        conn.flush().ok()?;
+        owner.time = loop {
+            if let Event::PropertyNotify(ev) = conn.wait_for_event().ok()? {
+                if ev.window == window {
+                    break ev.time;
+                }
+            }
+        };
+        conn.set_selection_owner(window, owner.selection, owner.time).ok()?;
+        let current = conn.get_selection_owner(owner.selection).ok()?.reply().ok()?.owner;
+        (current == window).then_some(owner)
+    }
+
+    impl Owner {
+        /// Answer requests until another client takes the selection.
+        fn run(&self, conn: &RustConnection) -> Result<(), ConnectionError> {
+            loop {
+                match conn.wait_for_event()? {
+                    Event::SelectionClear(ev) if ev.selection == self.selection => return Ok(()),
+                    Event::SelectionRequest(ev) => self.answer(conn, &ev)?,
+                    _ => {}
+                }
+            }
+        }
+
+        fn answer(&self, conn: &RustConnection, ev: &SelectionRequestEvent) -> Result<(), ConnectionError> {
+            // Obsolete requestors leave `property` unset and expect the target as its name.
+            let property = if ev.property == x11rb::NONE { ev.target } else { ev.property };
+            let stored = if ev.selection != self.selection || ev.owner != self.window {
+                false
+            } else if ev.target == self.targets_atom {
+                let mut targets = vec![self.targets_atom, self.timestamp_atom];
+                targets.extend(self.values.iter().map(|(atom, _)| *atom));
+                conn.change_property32(PropMode::REPLACE, ev.requestor, property, AtomEnum::ATOM, &targets)?;
+                true
+            } else if ev.target == self.timestamp_atom {
+                conn.change_property32(PropMode::REPLACE, ev.requestor, property, AtomEnum::INTEGER, &[self.time])?;
+                true
+            } else {
+                match self.values.iter().find(|(atom, _)| *atom == ev.target) {
+                    Some((atom, value)) if value.len() + REQUEST_HEADER_BYTES <= conn.maximum_request_bytes() => {
+                        conn.change_property8(PropMode::REPLACE, ev.requestor, property, *atom, value)?;
+                        true
+                    }
+                    _ => false,
+                }
+            };
+            let notify = SelectionNotifyEvent {
+                response_type: SELECTION_NOTIFY_EVENT,
+                sequence: 0,
+                time: ev.time,
+                requestor: ev.requestor,
+                selection: ev.selection,
+                target: ev.target,
+                property: if stored { property } else { x11rb::NONE },
+            };
+            conn.send_event(false, ev.requestor, EventMask::NO_EVENT, notify)?;
+            conn.flush()
+        }
+    }
+}
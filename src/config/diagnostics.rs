@@ -0,0 +1,210 @@
+//! Error messages pointing at the offending spot in a config file:
+//! `path:line:column: message`, with a "did you mean" hint for misspelled
+//! keys and values.
+
+use std::path::Path;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::layers::{ConfigSource, ConfigSources};
+
+/// Minimum similarity for a "did you mean" suggestion.
+const SUGGESTION_THRESHOLD: f64 = 0.8;
+
+static UNKNOWN_NAME_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"unknown (?:field|variant) `([^`]*)`, (?:expected|there are no) (.*)$").unwrap()
+});
+static BACKTICKED_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"`([^`]+)`").unwrap());
+static MARK_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r" at line \d+ column \d+").unwrap());
+
+/// Format a YAML parse or deserialization error for the file at `path`.
+pub(super) fn yaml_error(path: &Path, err: &serde_yaml::Error) -> String {
+    let message = MARK_RE.replace(&err.to_string(), "").into_owned();
+    let message = with_suggestion(&message);
+    match err.location() {
+        Some(loc) => format!("{}:{}:{}: {message}", path.display(), loc.line(), loc.column()),
+        None => format!("{}: {message}", path.display()),
+    }
+}
+
+/// Replace serde's "expected one of ..." list with the closest candidate,
+/// if one is close enough to be a likely typo.
+fn with_suggestion(message: &str) -> String {
+    let Some(caps) = UNKNOWN_NAME_RE.captures(message) else {
+        return message.to_owned();
+    };
+    let unknown = &caps[1];
+    let best = BACKTICKED_RE
+        .captures_iter(&caps[2])
+        .map(|c| c.get(1).map_or("", |m| m.as_str()))
+        .map(|candidate| (strsim::jaro_winkler(unknown, candidate), candidate))
+        .max_by(|a, b| a.0.total_cmp(&b.0));
+    match best {
+        Some((score, candidate)) if score >= SUGGESTION_THRESHOLD => {
+            let start = caps.get(2).map_or(message.len(), |m| m.start());
+            // Drop the ", expected ..." tail; keep everything up to the unknown name.
+            let head = message[..start].trim_end_matches(|c: char| c != ',').trim_end_matches(',');
+            format!("{head}; did you mean `{candidate}`?")
+        }
+        _ => message.to_owned(),
+    }
+}
+
+/// Prefix each `Config::validate` error with where its key was set. Errors
+/// start with the key, top-level (`max_history must be ...`) or nested
+/// (`source_app_retention.firefox.max_age must be ...`).
+pub(super) fn locate_validation_errors(errors: Vec<String>, sources: &ConfigSources) -> Vec<String> {
+    errors
+        .into_iter()
+        .map(|error| {
+            let path = key_path(error.split_whitespace().next().unwrap_or_default());
+            match sources.values.get(path[0]) {
+                Some(ConfigSource::File(file)) => match locate_key(file, &path) {
+                    Some((line, column)) => format!("{}:{line}:{column}: {error}", file.display()),
+                    None => format!("{}: {error}", file.display()),
+                },
+                Some(source) => format!("{source}: {error}"),
+                None => error,
+            }
+        })
+        .collect()
+}
+
+/// Split a dotted key into its path. Only the first and last dots separate
+/// keys: the middle of `source_app_retention.org.gnome.Terminal.max_age` is
+/// one map key.
+fn key_path(key: &str) -> Vec<&str> {
+    match key.split_once('.') {
+        None => vec![key],
+        Some((top, rest)) => match rest.rsplit_once('.') {
+            None => vec![top, rest],
+            Some((middle, last)) => vec![top, middle, last],
+        },
+    }
+}
+
+/// Line and column (1-based) of the key at `path` in the file at `file`.
+fn locate_key(file: &Path, path: &[&str]) -> Option<(usize, usize)> {
+    let contents = std::fs::read_to_string(file).ok()?;
+    locate_key_in(&contents, path)
+}
+
+/// Walk `path` through the block mappings of `contents`. If a nested key is
+/// not found (e.g. written in flow style), the deepest key found is reported;
+/// `None` if the top-level key is missing.
+fn locate_key_in(contents: &str, path: &[&str]) -> Option<(usize, usize)> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut found = None;
+    // Lines of the current block, and the indentation of its parent key.
+    let (mut start, mut parent_indent) = (0, None);
+    for key in path {
+        let mut block_indent = None;
+        let mut hit = None;
+        for (i, line) in lines.iter().enumerate().skip(start) {
+            let content = line.trim_start();
+            if content.is_empty() || content.starts_with('#') {
+                continue;
+            }
+            let indent = line.len() - content.len();
+            if parent_indent.is_some_and(|parent| indent <= parent) {
+                break;
+            }
+            // Only keys directly under the parent, not deeper ones.
+            if *block_indent.get_or_insert(indent) != indent {
+                continue;
+            }
+            if mapping_key(content) == Some(*key) {
+                hit = Some((i, indent));
+                break;
+            }
+        }
+        let Some((line, indent)) = hit else { break };
+        found = Some((line + 1, indent + 1));
+        (start, parent_indent) = (line + 1, Some(indent));
+    }
+    found
+}
+
+/// The key of a `key: value` line, without quotes.
+fn mapping_key(content: &str) -> Option<&str> {
+    let (key, rest) = match content.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let end = content[1..].find(quote)? + 1;
+            (&content[1..end], &content[end + 1..])
+        }
+        _ => {
+            let end = content.find(':')?;
+            (content[..end].trim_end(), &content[end..])
+        }
+    };
+    rest.trim_start().starts_with(':').then_some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn error_for(yaml: &str) -> String {
+        let err = serde_yaml::from_str::<Config>(yaml).unwrap_err();
+        yaml_error(Path::new("config.yaml"), &err)
+    }
+
+    #[test]
+    fn unknown_key_suggests_closest() {
+        let err = error_for("max_history: 10\nmax_histroy: 20\n");
+        assert_eq!(err, "config.yaml:2:1: unknown field `max_histroy`; did you mean `max_history`?");
+    }
+
+    #[test]
+    fn unknown_rule_field_has_path_and_position() {
+        let err = error_for("actions:\n  - name: x\n    conditions:\n      sorce_app: foo\n    actions: {}\n");
+        assert!(err.starts_with("config.yaml:4:7: "), "{err}");
+        assert!(err.contains("did you mean `source_app`?"), "{err}");
+    }
+
+    #[test]
+    fn unknown_variant_suggests_closest() {
+        let err = error_for("sync_mode: to-clipbaord\n");
+        assert!(err.contains("did you mean `to-clipboard`?"), "{err}");
+    }
+
+    #[test]
+    fn unrelated_key_lists_expected() {
+        let err = error_for("colour: blue\n");
+        assert!(err.contains("unknown field `colour`, expected one of"), "{err}");
+        assert!(!err.contains("did you mean"), "{err}");
+    }
+
+    #[test]
+    fn syntax_error_has_position() {
+        let err = error_for("max_history: [10\n");
+        assert!(err.starts_with("config.yaml:"), "{err}");
+        assert!(!err.contains(" at line "), "{err}");
+    }
+
+    #[test]
+    fn locates_top_level_keys_only() {
+        let yaml = "profiles:\n  work:\n    max_history: 0\nmax_history : 0\n";
+        assert_eq!(locate_key_in(yaml, &["max_history"]), Some((4, 1)));
+        assert_eq!(locate_key_in(yaml, &["max_age"]), None);
+    }
+
+    #[test]
+    fn locates_nested_keys_with_their_column() {
+        let yaml = "source_app_retention:\n  firefox:\n    max_age: 1d\n  \"org.gnome.Terminal\":\n    # keep\n    max_age: 0s\n";
+        let path = key_path("source_app_retention.org.gnome.Terminal.max_age");
+        assert_eq!(path, ["source_app_retention", "org.gnome.Terminal", "max_age"]);
+        assert_eq!(locate_key_in(yaml, &path), Some((6, 5)));
+        assert_eq!(locate_key_in(yaml, &key_path("source_app_retention.firefox.max_age")), Some((3, 5)));
+    }
+
+    #[test]
+    fn nested_unknown_key_points_at_its_deepest_parent() {
+        let yaml = "source_app_retention:\n  firefox:\n    max_age: 1d\n  chromium: {max_age: 0s}\n";
+        // `max_age` under firefox must not be mistaken for chromium's.
+        assert_eq!(locate_key_in(yaml, &key_path("source_app_retention.chromium.max_age")), Some((4, 3)));
+        assert_eq!(locate_key_in(yaml, &key_path("source_app_retention.slack.max_age")), Some((1, 1)));
+    }
+}
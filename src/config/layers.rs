@@ -0,0 +1,743 @@
+//! Layered config loading.
+//!
+//! Merge order (later wins): files named by `include:` (before the file that
+//! includes them), the main config file, then `conf.d/*.yaml` next to it in
+//! name order. Top-level scalars are overridden; `actions` lists are appended
+//! in file order. The profile chosen by `clio profile switch` comes next,
+//! then `CLIO_<KEY>` environment variables and `--set key=value` overrides,
+//! which replace values outright. Finally the active profile's settings are
+//! applied over the file values (but not over environment or `--set` ones).
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use log::{debug, warn};
+use serde_yaml::{Mapping, Value};
+
+use super::diagnostics;
+use super::types::{Config, DEFAULT_PROFILE};
+use crate::errors::{AppError, Result};
+
+/// Top-level key listing files (or globs) to merge before the current file.
+const INCLUDE_KEY: &str = "include";
+/// The only list key that is appended instead of overridden.
+const ACTIONS_KEY: &str = "actions";
+/// Drop-in directory next to the main config file.
+const CONF_D_DIR: &str = "conf.d";
+/// Guards against runaway include chains.
+const MAX_INCLUDE_DEPTH: usize = 8;
+/// Legacy key names merged under their current name, so overriding one with
+/// the other does not produce a duplicate field.
+const KEY_ALIASES: &[(&str, &str)] = &[("watch_interval_ms", "watch_interval")];
+const PROFILE_KEY: &str = "profile";
+const PROFILES_KEY: &str = "profiles";
+/// Keys a profile may set, in the order they are applied.
+const PROFILE_FIELDS: &[&str] = &["db_path", "max_history", "max_age", ACTIONS_KEY];
+/// Prefix of environment variables overriding config keys (`CLIO_MAX_HISTORY`).
+const ENV_PREFIX: &str = "CLIO_";
+
+/// Where a config value came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    File(PathBuf),
+    /// A `CLIO_<KEY>` environment variable (holds the variable name).
+    Env(String),
+    /// A `--set key=value` (or `--profile`) command-line flag.
+    Set,
+    /// The named profile.
+    Profile(String),
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Env(var) => write!(f, "env {var}"),
+            Self::Set => f.write_str("--set"),
+            Self::Profile(name) => write!(f, "profile {name}"),
+        }
+    }
+}
+
+/// Where each effective config value came from.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSources {
+    /// Files merged, in merge order.
+    pub files: Vec<PathBuf>,
+    /// Top-level key -> source that set it last. Keys not listed use defaults.
+    pub values: HashMap<String, ConfigSource>,
+    /// Source of each action rule, parallel to `Config::actions`.
+    pub actions: Vec<ConfigSource>,
+}
+
+/// A `key=value` config override, as given to `--set`. The value is parsed as
+/// YAML, so `--set max_age=` clears an optional setting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigOverride {
+    pub key: String,
+    pub value: String,
+}
+
+impl ConfigOverride {
+    /// Name of the environment variable that overrides this key.
+    pub fn env_var(&self) -> String {
+        format!("{ENV_PREFIX}{}", self.key.to_uppercase())
+    }
+}
+
+impl FromStr for ConfigOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected KEY=VALUE, got {s:?}"))?;
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(format!("missing key in {s:?}"));
+        }
+        Ok(Self { key: key.to_owned(), value: value.to_owned() })
+    }
+}
+
+/// Load `main` (if it exists) with its includes and `conf.d` drop-ins, then
+/// apply the switched-to profile (`active`: state file and name), `CLIO_<KEY>`
+/// variables from `env` and the `sets` overrides. The result is not validated.
+pub(super) fn load_layered(
+    main: &Path,
+    active: Option<(&Path, &str)>,
+    env: impl IntoIterator<Item = (String, String)>,
+    sets: &[ConfigOverride],
+) -> Result<(Config, ConfigSources)> {
+    let mut merger = Merger::default();
+    if main.exists() {
+        merger.load_file(main, 0)?;
+    } else {
+        debug!("config file not found at {}, using defaults", main.display());
+    }
+    for dropin in conf_d_files(main)? {
+        merger.load_file(&dropin, 0)?;
+    }
+    if let Some((path, name)) = active {
+        if name == DEFAULT_PROFILE || merger.has_profile(name) {
+            merger.apply_override(PROFILE_KEY, name, ConfigSource::File(path.to_path_buf()))?;
+        } else {
+            warn!("ignoring active profile '{name}' from {}: no such profile", path.display());
+        }
+    }
+    for (var, value) in env_overrides(env) {
+        let key = var[ENV_PREFIX.len()..].to_lowercase();
+        merger.apply_override(&key, &value, ConfigSource::Env(var))?;
+    }
+    for set in sets {
+        if !is_known_key(&set.key) {
+            return Err(AppError::Config(format!("--set: unknown config key '{}'", set.key)));
+        }
+        merger.apply_override(&set.key, &set.value, ConfigSource::Set)?;
+    }
+    merger.finish()
+}
+
+/// `CLIO_<KEY>` variables naming a config key, sorted by name. Other
+/// variables with the prefix are ignored.
+fn env_overrides(env: impl IntoIterator<Item = (String, String)>) -> Vec<(String, String)> {
+    let mut vars: Vec<_> = env
+        .into_iter()
+        .filter(|(var, _)| {
+            var.strip_prefix(ENV_PREFIX)
+                .is_some_and(|key| key == key.to_uppercase() && is_known_key(&key.to_lowercase()))
+        })
+        .collect();
+    vars.sort();
+    vars
+}
+
+/// True if `key` (or a legacy alias) is a top-level config key.
+fn is_known_key(key: &str) -> bool {
+    let key = canonical_key(key);
+    key != INCLUDE_KEY
+        && matches!(serde_yaml::to_value(Config::default()), Ok(Value::Mapping(m)) if m.contains_key(key))
+}
+
+fn canonical_key(name: &str) -> &str {
+    KEY_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, canonical)| canonical)
+}
+
+/// `*.yaml` / `*.yml` files in the `conf.d` directory next to `main`, sorted by name.
+fn conf_d_files(main: &Path) -> Result<Vec<PathBuf>> {
+    let dir = conf_d_dir(main);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.is_file() && is_yaml(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The `conf.d` drop-in directory for the config file at `main`.
+pub fn conf_d_dir(main: &Path) -> PathBuf {
+    main.parent().unwrap_or_else(|| Path::new(".")).join(CONF_D_DIR)
+}
+
+/// True for `.yaml` and `.yml` files.
+pub fn is_yaml(path: &Path) -> bool {
+    matches!(path.extension().and_then(|e| e.to_str()), Some("yaml" | "yml"))
+}
+
+#[derive(Default)]
+struct Merger {
+    merged: Mapping,
+    actions: Vec<Value>,
+    sources: ConfigSources,
+    /// Files on the current include chain, for cycle detection.
+    chain: Vec<PathBuf>,
+}
+
+impl Merger {
+    fn load_file(&mut self, path: &Path, depth: usize) -> Result<()> {
+        let canonical = path.canonicalize()?;
+        if self.chain.contains(&canonical) {
+            return Err(AppError::Config(format!("{}: include cycle", path.display())));
+        }
+        if depth > MAX_INCLUDE_DEPTH {
+            return Err(AppError::Config(format!(
+                "{}: includes nested deeper than {MAX_INCLUDE_DEPTH} levels",
+                path.display()
+            )));
+        }
+
+        debug!("loading config from {}", path.display());
+        let mut map = read_mapping(path)?;
+        let includes = map.remove(INCLUDE_KEY);
+        let base = path.parent().unwrap_or_else(|| Path::new("."));
+
+        self.chain.push(canonical);
+        for include in include_paths(includes, base, path)? {
+            self.load_file(&include, depth + 1)?;
+        }
+        self.chain.pop();
+
+        self.merge(map, path);
+        Ok(())
+    }
+
+    fn merge(&mut self, map: Mapping, path: &Path) {
+        self.sources.files.push(path.to_path_buf());
+        let source = ConfigSource::File(path.to_path_buf());
+        for (key, value) in map {
+            let Some(name) = key.as_str() else { continue };
+            let name = canonical_key(name).to_owned();
+
+            if name == ACTIONS_KEY {
+                if let Value::Sequence(rules) = value {
+                    self.sources.actions.extend(std::iter::repeat_n(source.clone(), rules.len()));
+                    self.actions.extend(rules);
+                }
+                continue;
+            }
+            self.set(name, value, source.clone());
+        }
+    }
+
+    /// Apply one override on top of everything merged so far. Unlike files,
+    /// an `actions` override replaces the rule list.
+    fn apply_override(&mut self, key: &str, raw: &str, source: ConfigSource) -> Result<()> {
+        let name = canonical_key(key).to_owned();
+        // Anything that is not valid YAML (e.g. a path with `: `) is taken as a string.
+        let value: Value = serde_yaml::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_owned()));
+
+        let mut check = Mapping::new();
+        check.insert(Value::String(name.clone()), value.clone());
+        serde_yaml::from_value::<Config>(Value::Mapping(check))
+            .map_err(|e| AppError::Config(format!("{source}: {e}")))?;
+
+        debug!("config override from {source}: {name}");
+        if name == ACTIONS_KEY {
+            let rules = match value {
+                Value::Sequence(rules) => rules,
+                _ => Vec::new(),
+            };
+            self.sources.actions = vec![source; rules.len()];
+            self.actions = rules;
+        } else {
+            self.set(name, value, source);
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, name: String, value: Value, source: ConfigSource) {
+        // An alias and its current name may both be present from different layers.
+        for (alias, canonical) in KEY_ALIASES {
+            if *canonical == name {
+                self.merged.remove(*alias);
+            }
+        }
+        self.merged.insert(Value::String(name.clone()), value);
+        self.sources.values.insert(name, source);
+    }
+
+    fn has_profile(&self, name: &str) -> bool {
+        self.merged
+            .get(PROFILES_KEY)
+            .and_then(Value::as_mapping)
+            .is_some_and(|profiles| profiles.contains_key(name))
+    }
+
+    /// Apply the selected profile's settings over file values. `default`
+    /// selects no profile.
+    fn apply_profile(&mut self) -> Result<()> {
+        let name = match self.merged.get(PROFILE_KEY) {
+            Some(Value::String(name)) => name.clone(),
+            _ => return Ok(()),
+        };
+        if name == DEFAULT_PROFILE {
+            self.merged.insert(Value::String(PROFILE_KEY.to_owned()), Value::Null);
+            return Ok(());
+        }
+
+        let profiles = self.merged.get(PROFILES_KEY).and_then(Value::as_mapping);
+        let Some(profile) = profiles.and_then(|p| p.get(name.as_str())).cloned() else {
+            let available: Vec<_> = profiles
+                .into_iter()
+                .flat_map(|p| p.keys().filter_map(Value::as_str))
+                .collect();
+            let available = if available.is_empty() { "none defined".to_owned() } else { available.join(", ") };
+            let source = &self.sources.values[PROFILE_KEY];
+            return Err(AppError::Config(format!(
+                "{source}: unknown profile '{name}' (available: {available})"
+            )));
+        };
+
+        debug!("applying profile {name}");
+        let source = ConfigSource::Profile(name);
+        for field in PROFILE_FIELDS {
+            let explicit = matches!(
+                self.sources.values.get(*field),
+                Some(ConfigSource::Env(_) | ConfigSource::Set)
+            );
+            if explicit {
+                continue;
+            }
+            // A profile without db_path still gets its own database (Null
+            // makes resolve_db_path pick the per-profile default).
+            let value = match (profile.get(*field), *field) {
+                (Some(value), _) => value.clone(),
+                (None, "db_path") => Value::Null,
+                (None, _) => continue,
+            };
+            if *field == ACTIONS_KEY {
+                let rules = match value {
+                    Value::Sequence(rules) => rules,
+                    _ => Vec::new(),
+                };
+                self.sources.actions = vec![source.clone(); rules.len()];
+                self.actions = rules;
+            } else {
+                self.set((*field).to_owned(), value, source.clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(Config, ConfigSources)> {
+        self.apply_profile()?;
+        if !self.actions.is_empty() {
+            self.merged.insert(Value::String(ACTIONS_KEY.to_owned()), Value::Sequence(self.actions));
+        }
+        let config: Config = serde_yaml::from_value(Value::Mapping(self.merged))
+            .map_err(|e| AppError::Config(format!("merged config: {e}")))?;
+        Ok((config, self.sources))
+    }
+}
+
+/// Parse one file as a top-level mapping. Each file is also checked against
+/// the `Config` schema on its own, so errors point at the file, line and
+/// column they are in.
+fn read_mapping(path: &Path) -> Result<Mapping> {
+    let contents = std::fs::read_to_string(path)?;
+    let value: Value = serde_yaml::from_str(&contents)
+        .map_err(|e| AppError::Config(diagnostics::yaml_error(path, &e)))?;
+    let mut map = match value {
+        Value::Mapping(m) => m,
+        Value::Null => Mapping::new(),
+        _ => {
+            return Err(AppError::Config(format!(
+                "{}: expected a mapping of config keys",
+                path.display()
+            )))
+        }
+    };
+    if remove_shadowed_aliases(&mut map) {
+        // The text has both names, so check the normalized mapping instead
+        // (serde would report a duplicate field); errors lose their position.
+        serde_yaml::from_value::<Config>(Value::Mapping(map.clone()))
+            .map_err(|e| AppError::Config(format!("{}: {e}", path.display())))?;
+    } else {
+        serde_yaml::from_str::<Config>(&contents)
+            .map_err(|e| AppError::Config(diagnostics::yaml_error(path, &e)))?;
+    }
+    if !matches!(map.get(ACTIONS_KEY), None | Some(Value::Sequence(_))) {
+        map.remove(ACTIONS_KEY);
+    }
+    Ok(map)
+}
+
+/// Drop legacy keys whose current name is also in `map`, which wins as it
+/// does across layers. Returns true if any were dropped.
+fn remove_shadowed_aliases(map: &mut Mapping) -> bool {
+    let mut removed = false;
+    for (alias, canonical) in KEY_ALIASES {
+        if map.contains_key(*canonical) && map.remove(*alias).is_some() {
+            debug!("{alias} is ignored: {canonical} is set in the same file");
+            removed = true;
+        }
+    }
+    removed
+}
+
+/// Resolve `include:` (a string or list of strings) relative to `base`.
+/// Globs expand to their matches in name order; a plain path must exist.
+fn include_paths(value: Option<Value>, base: &Path, file: &Path) -> Result<Vec<PathBuf>> {
+    let patterns = match value {
+        None | Some(Value::Null) => return Ok(Vec::new()),
+        Some(Value::String(s)) => vec![s],
+        Some(Value::Sequence(items)) => items
+            .into_iter()
+            .map(|v| match v {
+                Value::String(s) => Ok(s),
+                _ => Err(AppError::Config(format!("{}: include entries must be strings", file.display()))),
+            })
+            .collect::<Result<_>>()?,
+        Some(_) => {
+            return Err(AppError::Config(format!(
+                "{}: include must be a path or a list of paths",
+                file.display()
+            )))
+        }
+    };
+
+    let mut paths = Vec::new();
+    for pattern in patterns {
+        let resolved = resolve_include(&pattern, base);
+        if pattern.contains(['*', '?', '[']) {
+            let pattern_str = resolved.to_string_lossy();
+            let matches = glob::glob(&pattern_str)
+                .map_err(|e| AppError::Config(format!("{}: include {pattern:?}: {e}", file.display())))?;
+            let mut found: Vec<PathBuf> = matches.filter_map(|m| m.ok()).filter(|p| p.is_file()).collect();
+            found.sort();
+            if found.is_empty() {
+                debug!("include {pattern:?} in {} matched no files", file.display());
+            }
+            paths.extend(found);
+        } else if resolved.is_file() {
+            paths.push(resolved);
+        } else {
+            return Err(AppError::Config(format!(
+                "{}: included file not found: {}",
+                file.display(),
+                resolved.display()
+            )));
+        }
+    }
+    Ok(paths)
+}
+
+/// Expand a leading `~/` and make relative paths relative to the including file.
+fn resolve_include(pattern: &str, base: &Path) -> PathBuf {
+    if let Some(rest) = pattern.strip_prefix("~/") {
+        if let Ok(home) = std::env::var("HOME") {
+            return Path::new(&home).join(rest);
+        }
+    }
+    base.join(pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, contents: &str) -> PathBuf {
+        let path = dir.join(name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn load(main: &Path) -> Result<(Config, ConfigSources)> {
+        load_layered(main, None, Vec::new(), &[])
+    }
+
+    fn file(path: &Path) -> ConfigSource {
+        ConfigSource::File(path.to_path_buf())
+    }
+
+    const RULE_A: &str = "  - name: a\n    conditions: {}\n    actions:\n      ttl: 5s\n";
+    const RULE_B: &str = "  - name: b\n    conditions: {}\n    actions:\n      ttl: 5s\n";
+    const RULE_C: &str = "  - name: c\n    conditions: {}\n    actions:\n      ttl: 5s\n";
+
+    #[test]
+    fn includes_merge_before_the_including_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let shared = write(dir.path(), "shared/team.yaml", &format!("max_history: 10\nwindow_width: 900\nactions:\n{RULE_A}"));
+        let main = write(
+            dir.path(),
+            "config.yaml",
+            &format!("include: shared/*.yaml\nmax_history: 20\nactions:\n{RULE_B}"),
+        );
+
+        let (config, sources) = load(&main).unwrap();
+        assert_eq!(config.max_history, 20);
+        assert_eq!(config.window_width, 900);
+        let names: Vec<_> = config.actions.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["a", "b"]);
+
+        assert_eq!(sources.values["max_history"], file(&main));
+        assert_eq!(sources.values["window_width"], file(&shared));
+        assert_eq!(sources.actions, vec![file(&shared), file(&main)]);
+        assert_eq!(sources.files, vec![shared, main]);
+    }
+
+    #[test]
+    fn conf_d_overrides_main_and_appends_actions() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = write(dir.path(), "config.yaml", &format!("max_history: 20\nactions:\n{RULE_A}"));
+        write(dir.path(), "conf.d/20-late.yaml", &format!("max_history: 40\nactions:\n{RULE_C}"));
+        write(dir.path(), "conf.d/10-early.yaml", &format!("max_history: 30\nactions:\n{RULE_B}"));
+        write(dir.path(), "conf.d/notes.txt", "max_history: 99\n");
+
+        let (config, sources) = load(&main).unwrap();
+        assert_eq!(config.max_history, 40);
+        let names: Vec<_> = config.actions.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert_eq!(sources.values["max_history"], file(&dir.path().join("conf.d/20-late.yaml")));
+    }
+
+    #[test]
+    fn conf_d_applies_without_main_file() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "conf.d/local.yaml", "max_history: 30\n");
+
+        let (config, _) = load(&dir.path().join("config.yaml")).unwrap();
+        assert_eq!(config.max_history, 30);
+    }
+
+    #[test]
+    fn missing_literal_include_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = write(dir.path(), "config.yaml", "include: [missing.yaml]\n");
+        let err = load(&main).unwrap_err().to_string();
+        assert!(err.contains("missing.yaml"), "{err}");
+    }
+
+    #[test]
+    fn include_cycle_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "a.yaml", "include: b.yaml\n");
+        write(dir.path(), "b.yaml", "include: a.yaml\n");
+        let main = write(dir.path(), "config.yaml", "include: a.yaml\n");
+        let err = load(&main).unwrap_err().to_string();
+        assert!(err.contains("include cycle"), "{err}");
+    }
+
+    #[test]
+    fn type_errors_name_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = write(dir.path(), "config.yaml", "max_history: 20\n");
+        write(dir.path(), "conf.d/bad.yaml", "max_history: lots\n");
+        let err = load(&main).unwrap_err().to_string();
+        assert!(err.contains("bad.yaml"), "{err}");
+    }
+
+    #[test]
+    fn legacy_alias_is_overridden_by_current_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = write(dir.path(), "config.yaml", "watch_interval_ms: 200\n");
+        write(dir.path(), "conf.d/fast.yaml", "watch_interval: 100ms\n");
+        let (config, sources) = load(&main).unwrap();
+        assert_eq!(config.watch_interval, std::time::Duration::from_millis(100));
+        assert_eq!(sources.values["watch_interval"], file(&dir.path().join("conf.d/fast.yaml")));
+    }
+
+    #[test]
+    fn legacy_alias_and_current_name_in_one_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = write(dir.path(), "config.yaml", "watch_interval_ms: 200\nwatch_interval: 100ms\n");
+        let (config, sources) = load(&main).unwrap();
+        assert_eq!(config.watch_interval, std::time::Duration::from_millis(100));
+        assert_eq!(sources.values["watch_interval"], file(&main));
+
+        write(dir.path(), "config.yaml", "watch_interval_ms: 200\nwatch_interval: soon\n");
+        let err = load(&main).unwrap_err().to_string();
+        assert!(err.contains("config.yaml"), "{err}");
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter().map(|(k, v)| ((*k).to_owned(), (*v).to_owned())).collect()
+    }
+
+    fn set(s: &str) -> ConfigOverride {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_override() {
+        assert_eq!(set("max_history=10"), ConfigOverride { key: "max_history".into(), value: "10".into() });
+        assert_eq!(set("db_path=/tmp/a=b.db").value, "/tmp/a=b.db");
+        assert!("max_history".parse::<ConfigOverride>().is_err());
+        assert!("=10".parse::<ConfigOverride>().is_err());
+        assert_eq!(set("sync_mode=disabled").env_var(), "CLIO_SYNC_MODE");
+    }
+
+    #[test]
+    fn env_then_set_override_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = write(dir.path(), "config.yaml", "max_history: 20\nsync_mode: both\nmax_age: 1h\n");
+        let vars = env(&[
+            ("CLIO_MAX_HISTORY", "30"),
+            ("CLIO_SYNC_MODE", "disabled"),
+            ("CLIO_UNRELATED", "x"),
+            ("HOME", "/home/me"),
+        ]);
+
+        let (config, sources) =
+            load_layered(&main, None, vars, &[set("max_history=40"), set("max_age=")]).unwrap();
+        assert_eq!(config.max_history, 40);
+        assert_eq!(config.sync_mode, crate::config::SyncMode::Disabled);
+        assert_eq!(config.max_age, None);
+        assert_eq!(sources.values["max_history"], ConfigSource::Set);
+        assert_eq!(sources.values["sync_mode"], ConfigSource::Env("CLIO_SYNC_MODE".into()));
+    }
+
+    #[test]
+    fn overrides_replace_actions() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = write(dir.path(), "config.yaml", &format!("actions:\n{RULE_A}{RULE_B}"));
+        let (config, sources) = load_layered(&main, None, Vec::new(), &[set("actions=[]")]).unwrap();
+        assert!(config.actions.is_empty());
+        assert!(sources.actions.is_empty());
+    }
+
+    #[test]
+    fn invalid_overrides_name_their_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("config.yaml");
+
+        let err = load_layered(&main, None, env(&[("CLIO_MAX_HISTORY", "lots")]), &[]).unwrap_err().to_string();
+        assert!(err.contains("CLIO_MAX_HISTORY"), "{err}");
+
+        let err = load_layered(&main, None, Vec::new(), &[set("max_histroy=5")]).unwrap_err().to_string();
+        assert!(err.contains("unknown config key 'max_histroy'"), "{err}");
+
+        let err = load_layered(&main, None, Vec::new(), &[set("include=x.yaml")]).unwrap_err().to_string();
+        assert!(err.contains("unknown config key"), "{err}");
+    }
+
+    #[test]
+    fn legacy_alias_override() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = write(dir.path(), "config.yaml", "watch_interval: 1s\n");
+        let (config, _) = load_layered(&main, None, env(&[("CLIO_WATCH_INTERVAL_MS", "250")]), &[]).unwrap();
+        assert_eq!(config.watch_interval, std::time::Duration::from_millis(250));
+    }
+
+    const PROFILES: &str = r#"
+db_path: /tmp/main.db
+max_history: 20
+actions:
+  - name: base
+    conditions: {}
+    actions:
+      ttl: 5s
+profiles:
+  work:
+    db_path: /tmp/work.db
+    max_history: 2000
+    actions:
+      - name: w
+        conditions: {}
+        actions:
+          ttl: 5s
+  personal:
+    max_age: 7d
+"#;
+
+    #[test]
+    fn profile_overrides_file_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = write(dir.path(), "config.yaml", PROFILES);
+
+        let (config, _) = load(&main).unwrap();
+        assert_eq!(config.max_history, 20);
+        assert_eq!(config.profiles.len(), 2);
+
+        let (config, sources) = load_layered(&main, None, Vec::new(), &[set("profile=work")]).unwrap();
+        assert_eq!(config.profile.as_deref(), Some("work"));
+        assert_eq!(config.max_history, 2000);
+        assert_eq!(config.db_path.as_deref(), Some("/tmp/work.db"));
+        let names: Vec<_> = config.actions.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["w"]);
+        assert_eq!(sources.values["max_history"], ConfigSource::Profile("work".into()));
+    }
+
+    #[test]
+    fn profile_without_db_path_gets_its_own_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = write(dir.path(), "config.yaml", PROFILES);
+        let (config, _) = load_layered(&main, None, Vec::new(), &[set("profile=personal")]).unwrap();
+        assert_eq!(config.db_path, None);
+        assert_eq!(config.max_history, 20);
+        assert_eq!(config.max_age, Some(std::time::Duration::from_secs(7 * 86400)));
+        assert_eq!(config.actions.len(), 1);
+    }
+
+    #[test]
+    fn explicit_overrides_beat_the_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = write(dir.path(), "config.yaml", PROFILES);
+        let vars = env(&[("CLIO_MAX_HISTORY", "7")]);
+        let (config, _) = load_layered(&main, None, vars, &[set("profile=work"), set("db_path=/tmp/x.db")]).unwrap();
+        assert_eq!(config.max_history, 7);
+        assert_eq!(config.db_path.as_deref(), Some("/tmp/x.db"));
+    }
+
+    #[test]
+    fn switched_profile_is_below_explicit_selection() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = write(dir.path(), "config.yaml", PROFILES);
+        let state = dir.path().join("active_profile");
+
+        let (config, sources) = load_layered(&main, Some((&state, "work")), Vec::new(), &[]).unwrap();
+        assert_eq!(config.profile.as_deref(), Some("work"));
+        assert_eq!(sources.values["profile"], file(&state));
+
+        let (config, _) =
+            load_layered(&main, Some((&state, "work")), Vec::new(), &[set("profile=default")]).unwrap();
+        assert_eq!(config.profile, None);
+        assert_eq!(config.db_path.as_deref(), Some("/tmp/main.db"));
+
+        // A stale state file naming a removed profile is ignored.
+        let (config, _) = load_layered(&main, Some((&state, "gone")), Vec::new(), &[]).unwrap();
+        assert_eq!(config.profile, None);
+    }
+
+    #[test]
+    fn unknown_profile_lists_available() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = write(dir.path(), "config.yaml", PROFILES);
+        let err = load_layered(&main, None, Vec::new(), &[set("profile=play")]).unwrap_err().to_string();
+        assert!(err.contains("unknown profile 'play' (available: work, personal)"), "{err}");
+    }
+}
@@ -1,13 +1,16 @@
+mod diagnostics;
+mod layers;
 mod types;
 
-pub use types::{CompiledRule, Config, SyncMode};
+pub use layers::{conf_d_dir, is_yaml, ConfigOverride, ConfigSource, ConfigSources};
+pub(crate) use types::render_value;
+pub use types::{CompiledRule, Config, HistorySort, ImageEncoding, Profile, SensitiveHintPolicy, SourceAppRetention, SyncMode, DEFAULT_PROFILE};
 #[cfg(test)]
 pub use types::{ActionRule, RuleActions, RuleConditions};
 
 use std::path::{Path, PathBuf};
 
 use directories::ProjectDirs;
-use log::debug;
 
 use crate::errors::{AppError, Result};
 
@@ -16,24 +19,35 @@ pub fn default_config_path() -> PathBuf {
 }
 
 pub fn load_config(override_path: Option<&Path>) -> Result<Config> {
+    load_config_with_overrides(override_path, &[])
+}
+
+/// Load the config with `CLIO_<KEY>` environment variables and `sets`
+/// (from `--set key=value`) applied on top.
+pub fn load_config_with_overrides(override_path: Option<&Path>, sets: &[ConfigOverride]) -> Result<Config> {
+    load_config_with_sources(override_path, sets).map(|(config, _)| config)
+}
+
+/// Like [`load_config_with_overrides`], also reporting which file, variable
+/// or flag each effective value came from.
+pub fn load_config_with_sources(
+    override_path: Option<&Path>,
+    sets: &[ConfigOverride],
+) -> Result<(Config, ConfigSources)> {
     let path = match override_path {
         Some(p) => p.to_path_buf(),
         None => default_config_path(),
     };
 
-    if !path.exists() {
-        debug!("config file not found at {}, using defaults", path.display());
-        return Ok(Config::default());
-    }
-
-    debug!("loading config from {}", path.display());
-    let contents = std::fs::read_to_string(&path)?;
-    let config: Config = serde_yaml::from_str(&contents)
-        .map_err(|e| AppError::Config(format!("{}: {}", path.display(), e)))?;
+    let state_path = active_profile_path();
+    let active = read_active_profile(&state_path);
+    let active = active.as_deref().map(|name| (state_path.as_path(), name));
+    let (config, sources) = layers::load_layered(&path, active, std::env::vars(), sets)?;
     config.validate().map_err(|errs| {
+        let errs = diagnostics::locate_validation_errors(errs, &sources);
         AppError::Config(format!("config validation failed:\n  {}", errs.join("\n  ")))
     })?;
-    Ok(config)
+    Ok((config, sources))
 }
 
 /// Resolve a project directory from XDG, with fallback and auto-creation.
@@ -58,20 +72,70 @@ pub fn config_dir() -> PathBuf {
     resolve_dir(ProjectDirs::config_dir, &[".config", "clio"])
 }
 
-/// Resolve the database path: use config override if set, otherwise default location.
+/// Resolve the database path: use config override if set, otherwise default
+/// location (`clio-<profile>.db` when a profile is active).
 pub fn resolve_db_path(config: &Config) -> PathBuf {
-    config
-        .db_path
-        .as_ref()
-        .map(PathBuf::from)
-        .unwrap_or_else(|| data_dir().join("clio.db"))
+    config.db_path.as_ref().map(PathBuf::from).unwrap_or_else(|| {
+        let name = match &config.profile {
+            Some(profile) => format!("clio-{profile}.db"),
+            None => "clio.db".to_owned(),
+        };
+        data_dir().join(name)
+    })
+}
+
+/// File recording the profile chosen by `clio profile switch`.
+pub fn active_profile_path() -> PathBuf {
+    data_dir().join("active_profile")
+}
+
+/// Profile name stored at `path`, if any.
+pub fn read_active_profile(path: &Path) -> Option<String> {
+    let name = std::fs::read_to_string(path).ok()?;
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_owned())
+}
+
+/// Record `name` as the active profile; `default` removes the record.
+pub fn write_active_profile(path: &Path, name: &str) -> Result<()> {
+    if name == DEFAULT_PROFILE {
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => return Ok(()),
+        }
+    }
+    std::fs::write(path, format!("{name}\n"))?;
+    Ok(())
+}
+
+/// Make `name` the active profile for commands that do not pick one.
+/// A running `clio watch` only moves to it once asked to reload.
+pub fn activate_profile(config: &Config, name: &str) -> Result<()> {
+    if name != DEFAULT_PROFILE && !config.profiles.contains_key(name) {
+        let mut available = vec![DEFAULT_PROFILE];
+        available.extend(config.profiles.keys().map(String::as_str));
+        return Err(AppError::Config(format!(
+            "unknown profile '{name}' (available: {})",
+            available.join(", ")
+        )));
+    }
+    write_active_profile(&active_profile_path(), name)
 }
 
 pub fn data_dir() -> PathBuf {
     resolve_dir(ProjectDirs::data_dir, &[".local", "share", "clio"])
 }
 
+/// Per-session runtime directory (`$XDG_RUNTIME_DIR/clio`), falling back to
+/// `data_dir()` when no runtime directory is available.
+pub fn runtime_dir() -> PathBuf {
+    ProjectDirs::from("", "", "clio")
+        .and_then(|dirs| dirs.runtime_dir().map(Path::to_path_buf))
+        .unwrap_or_else(data_dir)
+}
+
 #[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
 mod tests {
     use super::*;
 
@@ -313,6 +377,67 @@ mod tests {
         assert!(errors.iter().any(|e| e.contains("image_preview_max_px")));
     }
 
+    #[test]
+    fn test_image_encoding_default_and_yaml() {
+        let config: Config = serde_yaml::from_str(&Config::default_yaml()).unwrap();
+        assert_eq!(config.image_encoding, ImageEncoding::Original);
+        assert_eq!(config.image_quality, 85);
+
+        let config: Config = serde_yaml::from_str("image_encoding: jpeg\nimage_quality: 70\n").unwrap();
+        assert_eq!(config.image_encoding, ImageEncoding::Jpeg);
+        assert_eq!(config.image_quality, 70);
+    }
+
+    #[test]
+    fn test_sort_default_and_yaml() {
+        let config: Config = serde_yaml::from_str(&Config::default_yaml()).unwrap();
+        assert_eq!(config.sort, HistorySort::Chronological);
+
+        let config: Config = serde_yaml::from_str("sort: frecency\n").unwrap();
+        assert_eq!(config.sort, HistorySort::Frecency);
+        assert!(serde_yaml::from_str::<Config>("sort: popular\n").is_err());
+    }
+
+    #[test]
+    fn test_validate_image_quality_out_of_range() {
+        let config = Config {
+            image_quality: 0,
+            ..Config::default()
+        };
+        let errors = config.validate().unwrap_err();
+        assert!(errors.iter().any(|e| e.contains("image_quality")));
+    }
+
+    #[test]
+    fn test_image_dedupe_distance() {
+        let config: Config = serde_yaml::from_str(&Config::default_yaml()).unwrap();
+        assert_eq!(config.image_dedupe_distance, None);
+
+        let config: Config = serde_yaml::from_str("image_dedupe_distance: 40\n").unwrap();
+        let errors = config.validate().unwrap_err();
+        assert!(errors.iter().any(|e| e.contains("image_dedupe_distance")));
+    }
+
+    #[test]
+    fn test_time_formats_default_and_yaml() {
+        let config: Config = serde_yaml::from_str(&Config::default_yaml()).unwrap();
+        assert_eq!(config.date_format, "%Y-%m-%d");
+        assert_eq!(config.datetime_format, Config::default().datetime_format);
+
+        let config: Config = serde_yaml::from_str("date_format: \"%d.%m.%Y\"\n").unwrap();
+        assert_eq!(config.date_format, "%d.%m.%Y");
+    }
+
+    #[test]
+    fn test_validate_invalid_date_format() {
+        let config = Config {
+            datetime_format: "%H:%Q".to_owned(),
+            ..Config::default()
+        };
+        let errors = config.validate().unwrap_err();
+        assert!(errors.iter().any(|e| e.contains("datetime_format")));
+    }
+
     #[test]
     fn test_default_yaml_has_image_preview_max_px() {
         let yaml = Config::default_yaml();
@@ -426,6 +551,29 @@ actions:
         assert_eq!(compiled[0].name, "Good rule");
     }
 
+    #[test]
+    fn test_tag_rules() {
+        let yaml = r#"
+actions:
+  - name: "kubectl"
+    conditions:
+      content_regex: "^kubectl "
+    actions:
+      tags: ["k8s"]
+  - name: "Bad tag"
+    conditions:
+      content_regex: "^SELECT "
+    actions:
+      tags: ["two words"]
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let errors = config.validate().unwrap_err();
+        assert!(errors.iter().any(|e| e.contains("rule 'Bad tag'")), "{errors:?}");
+        let compiled = config.compile_rules();
+        assert_eq!(compiled.len(), 1);
+        assert_eq!(compiled[0].tags, ["k8s"]);
+    }
+
     #[test]
     fn test_default_yaml_with_actions_parses() {
         let yaml = Config::default_yaml();
@@ -454,6 +602,163 @@ actions:
         assert!(errors.iter().any(|e| e.contains("prune_interval")));
     }
 
+    #[test]
+    fn test_sensitive_hint_defaults() {
+        let config = Config::default();
+        assert_eq!(config.sensitive_hint_policy, SensitiveHintPolicy::Skip);
+        assert_eq!(config.sensitive_hint_ttl, std::time::Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_sensitive_hint_deserialization() {
+        let yaml = "sensitive_hint_policy: mask\nsensitive_hint_ttl: 10s\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.sensitive_hint_policy, SensitiveHintPolicy::Mask);
+        assert_eq!(config.sensitive_hint_ttl, std::time::Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_default_yaml_has_sensitive_hint_policy() {
+        let config: Config = serde_yaml::from_str(&Config::default_yaml()).unwrap();
+        assert_eq!(config.sensitive_hint_policy, SensitiveHintPolicy::Skip);
+        assert_eq!(config.sensitive_hint_ttl, std::time::Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_validate_sensitive_hint_ttl_zero() {
+        let config = Config {
+            sensitive_hint_ttl: std::time::Duration::ZERO,
+            ..Config::default()
+        };
+        let errors = config.validate().unwrap_err();
+        assert!(errors.iter().any(|e| e.contains("sensitive_hint_ttl")));
+    }
+
+    #[test]
+    fn test_diff_lists_changed_settings() {
+        let old = Config::default();
+        assert!(old.diff(&Config::default()).is_empty());
+
+        let new = Config {
+            max_history: 1000,
+            sync_mode: SyncMode::Disabled,
+            max_age: Some(std::time::Duration::from_secs(3600)),
+            ..Config::default()
+        };
+        let changes = old.diff(&new);
+        assert!(changes.contains(&"max_history: 500 -> 1000".to_owned()), "{changes:?}");
+        assert!(changes.contains(&"sync_mode: both -> disabled".to_owned()), "{changes:?}");
+        assert!(changes.contains(&"max_age: none -> 1h".to_owned()), "{changes:?}");
+        assert_eq!(changes.len(), 3);
+    }
+
+    #[test]
+    fn test_diff_action_rules_by_name() {
+        let rule = |name: &str, ttl: &str| -> ActionRule {
+            serde_yaml::from_str(&format!(
+                "name: {name}\nconditions: {{}}\nactions:\n  ttl: {ttl}\n"
+            ))
+            .unwrap()
+        };
+        let old = Config { actions: vec![rule("a", "5s"), rule("b", "5s")], ..Config::default() };
+        let new = Config { actions: vec![rule("a", "10s"), rule("c", "5s")], ..Config::default() };
+        let changes = old.diff(&new);
+        assert_eq!(
+            changes,
+            vec![
+                "action rule 'a' changed".to_owned(),
+                "action rule 'c' added".to_owned(),
+                "action rule 'b' removed".to_owned(),
+            ]
+        );
+
+        let reordered = Config { actions: vec![rule("b", "5s"), rule("a", "5s")], ..Config::default() };
+        assert_eq!(old.diff(&reordered), vec!["action rules reordered".to_owned()]);
+    }
+
+    #[test]
+    fn test_primary_max_age() {
+        let config: Config = serde_yaml::from_str("primary_max_age: 1d\n").unwrap();
+        assert_eq!(config.primary_max_age, Some(std::time::Duration::from_secs(86400)));
+        assert!(Config::default().primary_max_age.is_none());
+
+        let config: Config = serde_yaml::from_str("primary_max_age: 0s\n").unwrap();
+        let errors = config.validate().unwrap_err();
+        assert!(errors.iter().any(|e| e.contains("primary_max_age")));
+    }
+
+    #[test]
+    fn test_compress_text_over_kb() {
+        let config = Config::default();
+        assert_eq!(config.compress_text_over_bytes(), Some(64 * 1024));
+
+        let config: Config = serde_yaml::from_str("compress_text_over_kb: null\n").unwrap();
+        assert_eq!(config.compress_text_over_bytes(), None);
+
+        let config: Config = serde_yaml::from_str("compress_text_over_kb: 0\n").unwrap();
+        let errors = config.validate().unwrap_err();
+        assert!(errors.iter().any(|e| e.contains("compress_text_over_kb")));
+    }
+
+    #[test]
+    fn test_blob_store_over_kb() {
+        assert_eq!(Config::default().blob_store_over_bytes(), None);
+
+        let config: Config = serde_yaml::from_str("blob_store_over_kb: 512\n").unwrap();
+        assert_eq!(config.blob_store_over_bytes(), Some(512 * 1024));
+
+        let config: Config = serde_yaml::from_str("blob_store_over_kb: 0\n").unwrap();
+        let errors = config.validate().unwrap_err();
+        assert!(errors.iter().any(|e| e.contains("blob_store_over_kb")));
+    }
+
+    #[test]
+    fn test_retention_limits() {
+        let yaml = r#"
+max_images: 20
+image_max_age: 7d
+max_db_size_mb: 100
+source_app_retention:
+  KeePassXC:
+    max_age: 1m
+  kitty:
+    max_entries: 50
+"#;
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.max_images, Some(20));
+        assert_eq!(config.image_max_age, Some(std::time::Duration::from_secs(7 * 86400)));
+        assert_eq!(config.max_db_size_mb, Some(100));
+        assert_eq!(config.source_app_retention["KeePassXC"].max_age, Some(std::time::Duration::from_secs(60)));
+        assert_eq!(config.source_app_retention["kitty"].max_entries, Some(50));
+        assert!(config.validate().is_ok());
+
+        let yaml = "max_images: 0\nimage_max_age: 0s\nmax_db_size_mb: 0\nsource_app_retention:\n  kitty:\n    max_entries: 0\n";
+        let errors = Config::validate(&serde_yaml::from_str(yaml).unwrap()).unwrap_err();
+        for key in ["max_images", "image_max_age", "max_db_size_mb", "source_app_retention.kitty.max_entries"] {
+            assert!(errors.iter().any(|e| e.starts_with(key)), "{key}: {errors:?}");
+        }
+    }
+
+    #[test]
+    fn test_primary_debounce_defaults() {
+        let config = Config::default();
+        assert_eq!(config.primary_debounce, std::time::Duration::from_secs(1));
+        assert_eq!(config.primary_merge_window, std::time::Duration::from_secs(5));
+
+        let config: Config = serde_yaml::from_str(&Config::default_yaml()).unwrap();
+        assert_eq!(config.primary_debounce, std::time::Duration::from_secs(1));
+        assert_eq!(config.primary_merge_window, std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_primary_debounce_deserialization() {
+        let yaml = "primary_debounce: 0s\nprimary_merge_window: 2s\n";
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.primary_debounce.is_zero());
+        assert_eq!(config.primary_merge_window, std::time::Duration::from_secs(2));
+        assert!(config.validate().is_ok());
+    }
+
     #[test]
     fn test_watch_interval_new_format() {
         let yaml = "watch_interval: 250ms\n";
@@ -475,4 +780,83 @@ actions:
         let errors = config.validate().unwrap_err();
         assert!(errors.iter().any(|e| e.contains("watch_interval")));
     }
+
+    #[test]
+    fn test_validate_profiles() {
+        let mut config = Config::default();
+        config.profiles.insert("work".into(), Profile { max_history: Some(0), ..Profile::default() });
+        config.profiles.insert("my profile".into(), Profile::default());
+        config.profiles.insert(DEFAULT_PROFILE.into(), Profile::default());
+        let errors = config.validate().unwrap_err();
+        assert!(errors.contains(&"profiles.work.max_history must be greater than 0".to_owned()), "{errors:?}");
+        assert_eq!(errors.iter().filter(|e| e.starts_with("profile name")).count(), 2, "{errors:?}");
+    }
+
+    #[test]
+    fn test_activate_unknown_profile() {
+        let err = activate_profile(&Config::default(), "play").unwrap_err().to_string();
+        assert!(err.contains("unknown profile 'play' (available: default)"), "{err}");
+    }
+
+    #[test]
+    fn test_active_profile_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("active_profile");
+        assert_eq!(read_active_profile(&path), None);
+        write_active_profile(&path, "work").unwrap();
+        assert_eq!(read_active_profile(&path).as_deref(), Some("work"));
+        write_active_profile(&path, DEFAULT_PROFILE).unwrap();
+        assert!(!path.exists());
+        write_active_profile(&path, DEFAULT_PROFILE).unwrap();
+    }
+
+    #[test]
+    fn test_profile_db_path_default() {
+        let config = Config { profile: Some("work".into()), ..Config::default() };
+        assert!(resolve_db_path(&config).ends_with("clio-work.db"));
+    }
+
+    #[test]
+    fn test_json_schema_covers_rules() {
+        let schema = Config::json_schema();
+        assert_eq!(schema["additionalProperties"], false);
+        assert_eq!(schema["properties"]["max_history"]["type"], "integer");
+        assert_eq!(schema["properties"]["watch_interval"]["type"], "string");
+        let conditions = &schema["$defs"]["RuleConditions"];
+        assert!(conditions["properties"]["content_regex"].is_object());
+        assert_eq!(conditions["additionalProperties"], false);
+        assert!(schema["$defs"]["RuleActions"]["properties"]["ttl"].is_object());
+    }
+
+    #[test]
+    fn test_load_rejects_unknown_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        std::fs::write(&path, "max_history: 100\nwindow_widht: 800\n").unwrap();
+
+        let err = load_config(Some(&path)).unwrap_err().to_string();
+        assert!(err.contains("config.yaml:2:1: unknown field `window_widht`"), "{err}");
+        assert!(err.contains("did you mean `window_width`?"), "{err}");
+    }
+
+    #[test]
+    fn test_validation_errors_point_at_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        std::fs::write(&path, "window_width: 600\nmax_history: 0\n").unwrap();
+
+        let err = load_config(Some(&path)).unwrap_err().to_string();
+        assert!(err.contains("config.yaml:2:1: max_history must be greater than 0"), "{err}");
+    }
+
+    #[test]
+    fn test_validation_errors_point_at_nested_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        std::fs::write(&path, "max_history: 0\nprofiles:\n  work:\n    max_age: 1d\n    max_history: 0\n").unwrap();
+
+        let err = load_config(Some(&path)).unwrap_err().to_string();
+        assert!(err.contains("config.yaml:1:1: max_history must be greater than 0"), "{err}");
+        assert!(err.contains("config.yaml:5:5: profiles.work.max_history must be greater than 0"), "{err}");
+    }
 }
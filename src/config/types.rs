@@ -1,11 +1,16 @@
+use std::collections::BTreeMap;
 use std::fmt;
 use std::time::Duration;
 
 use log::warn;
 use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+use crate::time_fmt;
+
+/// Synchronization between PRIMARY (mouse selection) and CLIPBOARD (Ctrl+C/V).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum SyncMode {
     ToClipboard,
@@ -14,6 +19,7 @@ pub enum SyncMode {
     Disabled,
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for SyncMode {
     fn default() -> Self {
         Self::Both
@@ -31,30 +37,145 @@ impl fmt::Display for SyncMode {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Order of entries in `clio list`, search and the history window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum HistorySort {
+    /// Most recently copied first.
+    #[default]
+    Chronological,
+    /// Often and recently selected entries first.
+    Frecency,
+}
+
+impl fmt::Display for HistorySort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Chronological => write!(f, "chronological"),
+            Self::Frecency => write!(f, "frecency"),
+        }
+    }
+}
+
+/// What to do with copies the source app marked as sensitive
+/// (`x-kde-passwordManagerHint`, `application/x-nspasteboard-concealed-type`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum SensitiveHintPolicy {
+    /// Do not store the entry at all.
+    #[default]
+    Skip,
+    /// Store the entry masked, with `sensitive_hint_ttl` as its TTL.
+    Mask,
+    /// Treat it like any other copy.
+    Ignore,
+}
+
+impl fmt::Display for SensitiveHintPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Skip => write!(f, "skip"),
+            Self::Mask => write!(f, "mask"),
+            Self::Ignore => write!(f, "ignore"),
+        }
+    }
+}
+
+/// How `clio watch` encodes captured images for storage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImageEncoding {
+    /// Keep the PNG, WebP or JPEG bytes the source offered; PNG if it offered raw pixels only.
+    #[default]
+    Original,
+    /// Always re-encode as PNG.
+    Png,
+    /// Lossless WebP, usually smaller than PNG for screenshots.
+    Webp,
+    /// Lossy JPEG at `image_quality`; transparency is dropped.
+    Jpeg,
+}
+
+impl fmt::Display for ImageEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Original => write!(f, "original"),
+            Self::Png => write!(f, "png"),
+            Self::Webp => write!(f, "webp"),
+            Self::Jpeg => write!(f, "jpeg"),
+        }
+    }
+}
+
+/// When a rule applies; all given conditions must match.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct RuleConditions {
+    /// Exact match on the application that owns the clipboard (X11 only).
     pub source_app: Option<String>,
+    /// Regex matched against the text content.
     pub content_regex: Option<String>,
+    /// Regex matched against the source window title.
     pub source_title_regex: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// What a matching rule does.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct RuleActions {
+    /// Expire the entry after this duration (e.g. 30s, 5m).
     #[serde(with = "humantime_serde::option", default)]
+    #[schemars(with = "Option<String>")]
     pub ttl: Option<Duration>,
+    /// Command that receives the text on stdin; its stdout replaces the text.
     pub command: Option<Vec<String>>,
+    /// Kill `command` after this duration (default 5s).
     #[serde(with = "humantime_serde::option", default)]
+    #[schemars(with = "Option<String>")]
     pub command_timeout: Option<Duration>,
+    /// Text shown in the history UI instead of the real content.
     pub mask_with: Option<String>,
+    /// Tags added to the entry.
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct ActionRule {
     pub name: String,
     pub conditions: RuleConditions,
     pub actions: RuleActions,
 }
 
+/// Name that selects the top-level settings, i.e. no profile.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Settings a named profile overrides. Unset fields keep the top-level value,
+/// except `db_path`, which defaults to a per-profile database.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub db_path: Option<String>,
+    pub max_history: Option<usize>,
+    #[serde(with = "humantime_serde::option", default)]
+    #[schemars(with = "Option<String>")]
+    pub max_age: Option<Duration>,
+    pub actions: Option<Vec<ActionRule>>,
+}
+
+/// Retention overrides for entries copied from one application.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SourceAppRetention {
+    /// Delete this app's entries older than this, instead of `max_age`.
+    #[serde(with = "humantime_serde::option", default)]
+    #[schemars(with = "Option<String>")]
+    pub max_age: Option<Duration>,
+    /// Keep at most this many entries from this app.
+    pub max_entries: Option<usize>,
+}
+
 /// Validated version of ActionRule with compiled regex.
 #[derive(Debug, Clone)]
 pub struct CompiledRule {
@@ -66,6 +187,7 @@ pub struct CompiledRule {
     pub command: Option<Vec<String>>,
     pub command_timeout: Duration,
     pub mask_with: Option<String>,
+    pub tags: Vec<String>,
 }
 
 const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
@@ -74,6 +196,12 @@ const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_millis(500);
 
 const DEFAULT_PRUNE_INTERVAL: Duration = Duration::from_secs(3);
 
+const DEFAULT_SENSITIVE_HINT_TTL: Duration = Duration::from_secs(30);
+
+const DEFAULT_PRIMARY_DEBOUNCE: Duration = Duration::from_secs(1);
+
+const DEFAULT_PRIMARY_MERGE_WINDOW: Duration = Duration::from_secs(5);
+
 fn default_watch_interval() -> Duration {
     DEFAULT_WATCH_INTERVAL
 }
@@ -82,6 +210,18 @@ fn default_prune_interval() -> Duration {
     DEFAULT_PRUNE_INTERVAL
 }
 
+fn default_sensitive_hint_ttl() -> Duration {
+    DEFAULT_SENSITIVE_HINT_TTL
+}
+
+fn default_primary_debounce() -> Duration {
+    DEFAULT_PRIMARY_DEBOUNCE
+}
+
+fn default_primary_merge_window() -> Duration {
+    DEFAULT_PRIMARY_MERGE_WINDOW
+}
+
 /// Deserialize watch_interval: accepts both humantime strings ("500ms") and legacy u64 millis (500).
 fn deserialize_watch_interval<'de, D>(deserializer: D) -> Result<Duration, D::Error>
 where
@@ -145,6 +285,9 @@ impl ActionRule {
                 return Err(format!("rule '{}': command must not be empty", self.name));
             }
         }
+        for tag in &self.actions.tags {
+            crate::db::tags::check_name(tag).map_err(|e| format!("rule '{}': {e}", self.name))?;
+        }
 
         Ok(CompiledRule {
             name: self.name.clone(),
@@ -155,53 +298,147 @@ impl ActionRule {
             command: self.actions.command.clone(),
             command_timeout: self.actions.command_timeout.unwrap_or(DEFAULT_COMMAND_TIMEOUT),
             mask_with: self.actions.mask_with.clone(),
+            tags: self.actions.tags.clone(),
         })
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(default)]
+/// Files merged before the one naming them: a path or a list of paths (globs allowed).
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Includes {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Clio configuration (`~/.config/clio/config.yaml`).
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
+    /// Merged by the loader; always empty in the effective config.
+    #[serde(skip_serializing)]
+    pub include: Option<Includes>,
+    /// Maximum number of clipboard entries to retain.
     pub max_history: usize,
+    /// Clipboard polling interval (e.g. 500ms, 1s).
     #[serde(
         default = "default_watch_interval",
         alias = "watch_interval_ms",
         deserialize_with = "deserialize_watch_interval",
         serialize_with = "humantime_serde::serialize"
     )]
+    #[schemars(with = "String")]
     pub watch_interval: Duration,
+    /// Custom SQLite database path.
     pub db_path: Option<String>,
+    /// Skip entries larger than this many KB.
     pub max_entry_size_kb: u64,
+    /// Store text larger than this many KB zstd-compressed; unset stores all text verbatim.
+    pub compress_text_over_kb: Option<u64>,
+    /// Keep content larger than this many KB in the on-disk blob store instead of the database.
+    pub blob_store_over_kb: Option<u64>,
+    /// History window width in pixels.
     pub window_width: i32,
+    /// History window height in pixels.
     pub window_height: i32,
     pub sync_mode: SyncMode,
+    /// Maximum characters shown in a history entry preview.
     pub preview_text_chars: usize,
+    /// Entries loaded per page in the history window.
     pub history_page_size: usize,
+    pub sort: HistorySort,
+    /// Maximum thumbnail dimension in pixels (longest side).
     pub image_preview_max_px: i32,
+    pub image_encoding: ImageEncoding,
+    /// JPEG quality (1-100) when `image_encoding` is `jpeg`.
+    pub image_quality: u8,
+    /// A new image replaces older ones whose perceptual hashes differ in at most
+    /// this many of 64 bits; unset only merges identical images.
+    pub image_dedupe_distance: Option<u32>,
+    /// strftime format of dates older than a week in `clio list` and the history window (local time).
+    pub date_format: String,
+    /// strftime format of full timestamps, e.g. in history window tooltips (local time).
+    pub datetime_format: String,
+    /// Delete entries older than this (e.g. 30d); unset keeps them forever.
     #[serde(with = "humantime_serde::option", default)]
+    #[schemars(with = "Option<String>")]
     pub max_age: Option<Duration>,
+    /// Shorter retention for entries captured only from PRIMARY.
+    #[serde(with = "humantime_serde::option", default)]
+    #[schemars(with = "Option<String>")]
+    pub primary_max_age: Option<Duration>,
+    /// Keep at most this many image entries.
+    pub max_images: Option<usize>,
+    /// Delete image entries older than this (e.g. 7d).
+    #[serde(with = "humantime_serde::option", default)]
+    #[schemars(with = "Option<String>")]
+    pub image_max_age: Option<Duration>,
+    /// Delete the oldest entries once the database holds more than this many MB.
+    pub max_db_size_mb: Option<u64>,
+    /// Per-application retention, keyed by source app name.
+    pub source_app_retention: BTreeMap<String, SourceAppRetention>,
+    /// How often `clio watch` prunes expired entries.
     #[serde(with = "humantime_serde", default = "default_prune_interval")]
+    #[schemars(with = "String")]
     pub prune_interval: Duration,
+    pub sensitive_hint_policy: SensitiveHintPolicy,
+    /// TTL for sensitive copies when `sensitive_hint_policy` is `mask`.
+    #[serde(with = "humantime_serde", default = "default_sensitive_hint_ttl")]
+    #[schemars(with = "String")]
+    pub sensitive_hint_ttl: Duration,
+    /// Store a PRIMARY selection only after it stayed unchanged this long.
+    #[serde(with = "humantime_serde", default = "default_primary_debounce")]
+    #[schemars(with = "String")]
+    pub primary_debounce: Duration,
+    /// A PRIMARY selection extending the previous one within this window replaces it.
+    #[serde(with = "humantime_serde", default = "default_primary_merge_window")]
+    #[schemars(with = "String")]
+    pub primary_merge_window: Duration,
+    /// Action rules applied to matching clipboard entries.
     #[serde(default)]
     pub actions: Vec<ActionRule>,
+    /// Active profile; its settings are already applied to the fields above.
+    pub profile: Option<String>,
+    /// Named profiles overriding db_path, max_history, max_age and actions.
+    pub profiles: BTreeMap<String, Profile>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            include: None,
             max_history: 500,
             watch_interval: DEFAULT_WATCH_INTERVAL,
             db_path: None,
             max_entry_size_kb: 51200,
+            compress_text_over_kb: Some(64),
+            blob_store_over_kb: None,
             window_width: 600,
             window_height: 400,
             sync_mode: SyncMode::default(),
             preview_text_chars: 4096,
             history_page_size: 50,
+            sort: HistorySort::default(),
             image_preview_max_px: 640,
+            image_encoding: ImageEncoding::default(),
+            image_quality: 85,
+            image_dedupe_distance: None,
+            date_format: time_fmt::DEFAULT_DATE_FORMAT.to_owned(),
+            datetime_format: time_fmt::DEFAULT_DATETIME_FORMAT.to_owned(),
             max_age: None,
+            primary_max_age: None,
+            max_images: None,
+            image_max_age: None,
+            max_db_size_mb: None,
+            source_app_retention: BTreeMap::new(),
             prune_interval: DEFAULT_PRUNE_INTERVAL,
+            sensitive_hint_policy: SensitiveHintPolicy::default(),
+            sensitive_hint_ttl: DEFAULT_SENSITIVE_HINT_TTL,
+            primary_debounce: DEFAULT_PRIMARY_DEBOUNCE,
+            primary_merge_window: DEFAULT_PRIMARY_MERGE_WINDOW,
             actions: Vec::new(),
+            profile: None,
+            profiles: BTreeMap::new(),
         }
     }
 }
@@ -213,6 +450,18 @@ impl Config {
         self.max_entry_size_kb * 1024
     }
 
+    /// Compression threshold in bytes (converts from KB config value).
+    #[must_use]
+    pub fn compress_text_over_bytes(&self) -> Option<usize> {
+        self.compress_text_over_kb.map(|kb| (kb * 1024) as usize)
+    }
+
+    /// Blob store threshold in bytes (converts from KB config value).
+    #[must_use]
+    pub fn blob_store_over_bytes(&self) -> Option<usize> {
+        self.blob_store_over_kb.map(|kb| (kb * 1024) as usize)
+    }
+
     /// Generate a default configuration file with explanatory comments.
     #[must_use]
     pub fn default_yaml() -> String {
@@ -231,6 +480,15 @@ watch_interval: 500ms
 # Maximum clipboard entry size in kilobytes (default 50 MB).
 max_entry_size_kb: 51200
 
+# Text larger than this many kilobytes is stored zstd-compressed (default 64).
+# Previews and search work as before. Set to null to store all text verbatim.
+compress_text_over_kb: 64
+
+# Keep entries larger than this many kilobytes (after compression) as files in
+# the blob store (blobs/ next to the database) instead of inside it, keeping
+# the database, its WAL and backups small. Omit to store everything inline.
+# blob_store_over_kb: 1024
+
 # GTK history window dimensions.
 window_width: 600
 window_height: 400
@@ -245,16 +503,77 @@ preview_text_chars: 4096
 # Number of entries loaded per page in the history window (default 50).
 history_page_size: 50
 
+# Order of `clio list`, search and the history window:
+#   chronological (default) - most recently copied first
+#   frecency                - entries selected often and recently first
+#                             (`clio select`, history window)
+sort: chronological
+
 # Maximum image preview size in pixels (longest side, default 640).
 image_preview_max_px: 640
 
+# How captured images are stored:
+#   original (default) - keep the PNG, WebP or JPEG the source app offered,
+#                        PNG if it offered raw pixels only
+#   png                - always re-encode as PNG
+#   webp               - lossless WebP, usually smaller than PNG for screenshots
+#   jpeg               - lossy JPEG at image_quality (1-100), smallest for photos
+image_encoding: original
+image_quality: 85
+
+# Treat images as near-duplicates when their perceptual hashes differ in at
+# most this many of 64 bits (e.g. successive screenshots of the same window):
+# a new image then replaces the older ones. Omit to merge identical images
+# only. `clio dedupe --images` cleans up existing history.
+# image_dedupe_distance: 5
+
+# How absolute times are shown, in the local timezone (strftime syntax).
+# Entries from the last week show a relative age ("5m ago") instead.
+date_format: "%Y-%m-%d"
+datetime_format: "%Y-%m-%d %H:%M:%S"
+
 # Delete entries older than this duration (e.g. 30d, 12h, 90m).
 # Omit or leave empty to keep entries forever.
 # max_age: 30d
 
+# Shorter retention for entries captured only from PRIMARY (mouse selections).
+# Entries also copied with Ctrl+C or `clio copy` follow max_age.
+# primary_max_age: 1d
+
+# Keep at most this many images, and drop images older than image_max_age.
+# max_images: 50
+# image_max_age: 7d
+
+# Delete the oldest entries once the database grows past this size (MB).
+# max_db_size_mb: 200
+
+# Per-application retention: max_age replaces the global max_age for the
+# app's entries, max_entries caps how many of them are kept.
+# source_app_retention:
+#   KeePassXC:
+#     max_age: 1m
+#   firefox:
+#     max_entries: 100
+
 # How often to prune expired entries during `clio watch` (e.g. 3s, 1m).
 prune_interval: 3s
 
+# Copies marked sensitive by the source app (KeePassXC, KDE apps, 1Password).
+# Values: skip (default, never stored), mask (stored masked with a short TTL), ignore
+# Detection requires X11.
+sensitive_hint_policy: skip
+
+# TTL applied to sensitive copies when sensitive_hint_policy is mask.
+sensitive_hint_ttl: 30s
+
+# Only store a PRIMARY (mouse) selection once it stayed unchanged this long,
+# so drag-selecting does not flood history. 0s stores every change.
+primary_debounce: 1s
+
+# A PRIMARY selection that only extends or shrinks the previous one captured
+# within this window replaces it instead of adding a new entry. 0s disables.
+primary_merge_window: 5s
+
 # Action rules: conditions → actions applied to matching clipboard entries.
 # actions:
 #   - name: "Expire passwords quickly"
@@ -313,10 +632,33 @@ prune_interval: 3s
 #     actions:
 #       ttl: "30s"
 #       mask_with: "••••••"
+#
+#   - name: "Collect kubectl commands"
+#     conditions:
+#       content_regex: "^kubectl "
+#     actions:
+#       tags: ["k8s"]
+
+# Named profiles with their own history and rules. Each may override db_path
+# (default: clio-<name>.db in the data directory), max_history, max_age and
+# actions. Select one with `profile:`, `--profile` or `clio profile switch`.
+# profiles:
+#   work:
+#     max_history: 2000
+#     max_age: 30d
+#   personal:
+#     max_age: 7d
+# profile: work
 "#
         .to_owned()
     }
 
+    /// JSON Schema of the config file, for editor completion and validation.
+    #[must_use]
+    pub fn json_schema() -> serde_json::Value {
+        serde_json::to_value(schemars::schema_for!(Config)).unwrap_or_default()
+    }
+
     /// Validate configuration values.
     /// Returns `Ok(())` if valid, or `Err` with a list of error messages.
     pub fn validate(&self) -> Result<(), Vec<String>> {
@@ -331,6 +673,12 @@ prune_interval: 3s
         if self.max_entry_size_kb == 0 {
             errors.push("max_entry_size_kb must be greater than 0".to_owned());
         }
+        if self.compress_text_over_kb == Some(0) {
+            errors.push("compress_text_over_kb must be greater than 0".to_owned());
+        }
+        if self.blob_store_over_kb == Some(0) {
+            errors.push("blob_store_over_kb must be greater than 0".to_owned());
+        }
         if self.window_width <= 0 {
             errors.push("window_width must be greater than 0".to_owned());
         }
@@ -346,9 +694,44 @@ prune_interval: 3s
         if self.image_preview_max_px <= 0 {
             errors.push("image_preview_max_px must be greater than 0".to_owned());
         }
+        if self.image_dedupe_distance.is_some_and(|d| d > 32) {
+            errors.push("image_dedupe_distance must be at most 32".to_owned());
+        }
+        if !(1..=100).contains(&self.image_quality) {
+            errors.push("image_quality must be between 1 and 100".to_owned());
+        }
+        if !time_fmt::is_valid_format(&self.date_format) {
+            errors.push(format!("date_format '{}' is not a valid strftime format", self.date_format));
+        }
+        if !time_fmt::is_valid_format(&self.datetime_format) {
+            errors.push(format!("datetime_format '{}' is not a valid strftime format", self.datetime_format));
+        }
+        if self.primary_max_age.is_some_and(|age| age.is_zero()) {
+            errors.push("primary_max_age must be greater than 0".to_owned());
+        }
+        if self.max_images == Some(0) {
+            errors.push("max_images must be greater than 0".to_owned());
+        }
+        if self.image_max_age.is_some_and(|age| age.is_zero()) {
+            errors.push("image_max_age must be greater than 0".to_owned());
+        }
+        if self.max_db_size_mb == Some(0) {
+            errors.push("max_db_size_mb must be greater than 0".to_owned());
+        }
+        for (app, retention) in &self.source_app_retention {
+            if retention.max_age.is_some_and(|age| age.is_zero()) {
+                errors.push(format!("source_app_retention.{app}.max_age must be greater than 0"));
+            }
+            if retention.max_entries == Some(0) {
+                errors.push(format!("source_app_retention.{app}.max_entries must be greater than 0"));
+            }
+        }
         if self.prune_interval.is_zero() {
             errors.push("prune_interval must be greater than 0".to_owned());
         }
+        if self.sensitive_hint_ttl.is_zero() {
+            errors.push("sensitive_hint_ttl must be greater than 0".to_owned());
+        }
 
         for rule in &self.actions {
             if let Err(e) = rule.compile() {
@@ -356,6 +739,22 @@ prune_interval: 3s
             }
         }
 
+        for (name, profile) in &self.profiles {
+            if !is_valid_profile_name(name) {
+                errors.push(format!(
+                    "profile name '{name}' must be letters, digits, '-' or '_' and not '{DEFAULT_PROFILE}'"
+                ));
+            }
+            if profile.max_history == Some(0) {
+                errors.push(format!("profiles.{name}.max_history must be greater than 0"));
+            }
+            for rule in profile.actions.iter().flatten() {
+                if let Err(e) = rule.compile() {
+                    errors.push(format!("profiles.{name}: {e}"));
+                }
+            }
+        }
+
         if errors.is_empty() {
             Ok(())
         } else {
@@ -370,9 +769,13 @@ prune_interval: 3s
         for rule in &self.actions {
             match rule.compile() {
                 Ok(r) => {
-                    if r.ttl.is_none() && r.command.is_none() && r.mask_with.is_none() {
+                    if r.ttl.is_none()
+                        && r.command.is_none()
+                        && r.mask_with.is_none()
+                        && r.tags.is_empty()
+                    {
                         warn!(
-                            "skipping rule '{}': no actions (no ttl, command, or mask_with)",
+                            "skipping rule '{}': no actions (no ttl, command, mask_with, or tags)",
                             r.name
                         );
                         continue;
@@ -384,4 +787,70 @@ prune_interval: 3s
         }
         compiled
     }
+
+    /// Human-readable list of settings that differ from `self` in `new`,
+    /// e.g. `max_history: 500 -> 1000`. Action rules are compared by name.
+    pub fn diff(&self, new: &Config) -> Vec<String> {
+        let (Ok(old_value), Ok(new_value)) = (serde_yaml::to_value(self), serde_yaml::to_value(new)) else {
+            return vec!["configuration changed".to_owned()];
+        };
+        let (Some(old_map), Some(new_map)) = (old_value.as_mapping(), new_value.as_mapping()) else {
+            return vec!["configuration changed".to_owned()];
+        };
+
+        let mut changes = Vec::new();
+        for (key, new_field) in new_map {
+            let Some(name) = key.as_str() else { continue };
+            let old_field = old_map.get(key).unwrap_or(&serde_yaml::Value::Null);
+            if name == "actions" || old_field == new_field {
+                continue;
+            }
+            changes.push(format!("{name}: {} -> {}", render_value(old_field), render_value(new_field)));
+        }
+        changes.extend(diff_rules(&self.actions, &new.actions));
+        changes
+    }
+}
+
+/// Profile names end up in database file names, so keep them simple.
+fn is_valid_profile_name(name: &str) -> bool {
+    name != DEFAULT_PROFILE
+        && !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+}
+
+/// Compact one-line rendering of a config value for change logs.
+pub(crate) fn render_value(value: &serde_yaml::Value) -> String {
+    match value {
+        serde_yaml::Value::Null => "none".to_owned(),
+        serde_yaml::Value::String(s) => s.clone(),
+        other => serde_yaml::to_string(other)
+            .map(|s| s.trim().replace('\n', " "))
+            .unwrap_or_default(),
+    }
+}
+
+fn diff_rules(old: &[ActionRule], new: &[ActionRule]) -> Vec<String> {
+    let as_value = |rule: &ActionRule| serde_yaml::to_value(rule).ok();
+    let mut changes = Vec::new();
+    for rule in new {
+        match old.iter().find(|r| r.name == rule.name) {
+            None => changes.push(format!("action rule '{}' added", rule.name)),
+            Some(prev) if as_value(prev) != as_value(rule) => {
+                changes.push(format!("action rule '{}' changed", rule.name));
+            }
+            Some(_) => {}
+        }
+    }
+    for rule in old {
+        if !new.iter().any(|r| r.name == rule.name) {
+            changes.push(format!("action rule '{}' removed", rule.name));
+        }
+    }
+    let old_order: Vec<_> = old.iter().map(|r| &r.name).filter(|n| new.iter().any(|r| &r.name == *n)).collect();
+    let new_order: Vec<_> = new.iter().map(|r| &r.name).filter(|n| old.iter().any(|r| &r.name == *n)).collect();
+    if changes.is_empty() && old_order != new_order {
+        changes.push("action rules reordered".to_owned());
+    }
+    changes
 }
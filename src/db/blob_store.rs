@@ -0,0 +1,485 @@
+//! Content-addressed store for large entries, outside SQLite.
+//!
+//! Content above `blob_store_over_kb` is written to `<blob dir>/<blake3 hex>`,
+//! named by the entry's `content_hash`, and its row gets `storage = 'file'`
+//! with `blob_content` left NULL (text keeps its preview prefix). Queries read
+//! the file back through the `clio_blob(content_hash)` SQL function, so
+//! decompression, previews and search work unchanged.
+//!
+//! Files are removed once the deletion of their entry is committed; `check`
+//! finds files no entry references and entries whose file is gone. A missing
+//! file reads as NULL, so listing and search keep working without it, while
+//! reading the entry's content fails rather than return part of it.
+
+use std::path::{Path, PathBuf};
+
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::errors::{AppError, Result};
+use crate::models::entry::ContentHash;
+
+/// Value of the `storage` column for entries kept in the blob store.
+pub(crate) const FILE: &str = "file";
+
+/// Files whose entries were deleted in the current transaction.
+const CREATE_RELEASED: &str = "CREATE TEMP TABLE IF NOT EXISTS released_blobs (content_hash BLOB PRIMARY KEY)";
+
+/// Blob directory for the database at `db_path`: `blobs/` next to the
+/// default `clio.db`, `<name>.blobs/` next to any other database.
+pub fn blob_dir(db_path: &Path) -> PathBuf {
+    let parent = db_path.parent().unwrap_or_else(|| Path::new("."));
+    match db_path.file_stem().and_then(|s| s.to_str()) {
+        Some("clio") | None => parent.join("blobs"),
+        Some(stem) => parent.join(format!("{stem}.blobs")),
+    }
+}
+
+/// Blob directory of the database `conn` is open on; `None` in memory.
+pub(crate) fn dir_for(conn: &Connection) -> Option<PathBuf> {
+    conn.path().filter(|p| !p.is_empty()).map(|p| blob_dir(Path::new(p)))
+}
+
+fn file_name(hash: &ContentHash) -> String {
+    blake3::Hash::from_bytes(*hash).to_hex().to_string()
+}
+
+/// Store `bytes` under `hash`, compressed as `compression` says. An existing
+/// file is kept only if an entry already reads it the same way; a leftover
+/// file (e.g. raw text where the new entry is compressed) is replaced.
+pub(crate) fn write(conn: &Connection, hash: &ContentHash, bytes: &[u8], compression: Option<&str>) -> Result<()> {
+    let dir = dir_for(conn).ok_or_else(|| AppError::DataIntegrity("blob store needs an on-disk database".to_owned()))?;
+    let path = dir.join(file_name(hash));
+    let in_use: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM clipboard_entries
+                       WHERE content_hash = ?1 AND storage IS NOT NULL AND compression IS ?2)",
+        params![hash as &[u8], compression],
+        |row| row.get(0),
+    )?;
+    if in_use && path.exists() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(&dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+    // Write under a temporary name so a crash never leaves a truncated blob.
+    let tmp = dir.join(format!(".{}.tmp", file_name(hash)));
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// Bytes of the stored file of `hash`, if it exists.
+pub(crate) fn read(conn: &Connection, hash: &ContentHash) -> Option<Vec<u8>> {
+    std::fs::read(dir_for(conn)?.join(file_name(hash))).ok()
+}
+
+/// Size of the stored file of `hash`, if it exists.
+pub(crate) fn file_size(conn: &Connection, hash: &ContentHash) -> Option<u64> {
+    let path = dir_for(conn)?.join(file_name(hash));
+    std::fs::metadata(path).ok().map(|metadata| metadata.len())
+}
+
+/// Remove the files of `hashes` that no entry references any more. Inside a
+/// transaction they are only queued: the caller runs `remove_released` after
+/// the commit, and a rollback forgets them along with the deletions.
+pub(crate) fn release(conn: &Connection, hashes: &[ContentHash]) -> Result<()> {
+    if dir_for(conn).is_none() {
+        return Ok(());
+    }
+    conn.execute_batch(CREATE_RELEASED)?;
+    let mut queue = conn.prepare_cached("INSERT OR IGNORE INTO temp.released_blobs (content_hash) VALUES (?1)")?;
+    for hash in hashes {
+        queue.execute(params![hash as &[u8]])?;
+    }
+    if conn.is_autocommit() {
+        remove_released(conn)?;
+    }
+    Ok(())
+}
+
+/// Remove the files queued by `release` that no entry references any more.
+pub(crate) fn remove_released(conn: &Connection) -> Result<()> {
+    let Some(dir) = dir_for(conn) else { return Ok(()) };
+    conn.execute_batch(CREATE_RELEASED)?;
+    let mut stmt = conn.prepare_cached(
+        "SELECT content_hash FROM temp.released_blobs r WHERE NOT EXISTS
+           (SELECT 1 FROM clipboard_entries e WHERE e.content_hash = r.content_hash AND e.storage IS NOT NULL)",
+    )?;
+    let hashes = stmt
+        .query_map([], |row| row.get::<_, Vec<u8>>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    conn.execute("DELETE FROM temp.released_blobs", [])?;
+    for hash in hashes.into_iter().filter_map(|hash| ContentHash::try_from(hash).ok()) {
+        match std::fs::remove_file(dir.join(file_name(&hash))) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                log::warn!("failed to remove blob {}: {e}", file_name(&hash));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Copy the blob files in `from` that `to` lacks; unfinished writes are skipped.
+pub(crate) fn copy_files(from: &Path, to: &Path) -> Result<()> {
+    let entries = match std::fs::read_dir(from) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    std::fs::create_dir_all(to)?;
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') || !entry.file_type()?.is_file() {
+            continue;
+        }
+        let target = to.join(&name);
+        if !target.exists() {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Copy the files the entries of `to` reference from `from` into the blob
+/// directory of `to`'s database. Files missing in `from` are skipped with a
+/// warning; `check` on the copy reports their entries.
+pub(crate) fn copy_referenced(from: &Path, to: &Connection) -> Result<()> {
+    let Some(dir) = dir_for(to) else { return Ok(()) };
+    let mut stmt = to.prepare("SELECT DISTINCT content_hash FROM clipboard_entries WHERE storage IS NOT NULL")?;
+    let hashes = stmt
+        .query_map([], |row| row.get::<_, Vec<u8>>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    if hashes.is_empty() {
+        return Ok(());
+    }
+    std::fs::create_dir_all(&dir)?;
+    for hash in hashes.into_iter().filter_map(|hash| ContentHash::try_from(hash).ok()) {
+        let name = file_name(&hash);
+        let target = dir.join(&name);
+        if target.exists() {
+            continue;
+        }
+        match std::fs::copy(from.join(&name), target) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => log::warn!("blob {name} is missing, not copied"),
+            result => {
+                result?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Hashes of the stored-as-file entries among `ids`.
+pub(crate) fn hashes_in(conn: &Connection, ids: &[i64]) -> Result<Vec<ContentHash>> {
+    let mut stmt =
+        conn.prepare_cached("SELECT content_hash FROM clipboard_entries WHERE storage IS NOT NULL AND id = ?1")?;
+    let mut hashes = Vec::new();
+    for id in ids {
+        let hash: Option<Vec<u8>> = stmt.query_row(params![id], |row| row.get(0)).optional()?;
+        if let Some(Ok(hash)) = hash.map(ContentHash::try_from) {
+            hashes.push(hash);
+        }
+    }
+    Ok(hashes)
+}
+
+/// Register `clio_blob(content_hash)`, which reads a stored file, on `conn`.
+pub(crate) fn register_functions(conn: &Connection) -> Result<()> {
+    let dir = dir_for(conn);
+    conn.create_scalar_function("clio_blob", 1, FunctionFlags::SQLITE_UTF8, move |ctx| {
+        let hash: ContentHash = ctx
+            .get::<Vec<u8>>(0)?
+            .try_into()
+            .map_err(|_| rusqlite::Error::UserFunctionError("clio_blob: expected a 32-byte hash".into()))?;
+        let dir = dir
+            .as_ref()
+            .ok_or_else(|| rusqlite::Error::UserFunctionError("clio_blob: no blob store for this database".into()))?;
+        let path = dir.join(file_name(&hash));
+        match std::fs::read(&path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            // One lost file must not break every query; `clio db check` reports it.
+            Err(e) => {
+                log::warn!("cannot read blob {}: {e}", path.display());
+                Ok(None)
+            }
+        }
+    })?;
+    Ok(())
+}
+
+/// Result of `check`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BlobCheck {
+    /// Files in the blob store.
+    pub files: usize,
+    /// IDs of entries whose file is missing.
+    pub missing: Vec<i64>,
+    /// Files no entry references.
+    pub orphaned: Vec<PathBuf>,
+}
+
+impl BlobCheck {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.orphaned.is_empty()
+    }
+}
+
+/// Compare the blob store of `conn`'s database with the entries referencing it.
+pub fn check(conn: &Connection) -> Result<BlobCheck> {
+    let Some(dir) = dir_for(conn) else { return Ok(BlobCheck::default()) };
+    let mut result = BlobCheck::default();
+
+    let mut stmt = conn.prepare("SELECT id, content_hash FROM clipboard_entries WHERE storage IS NOT NULL ORDER BY id")?;
+    let referenced = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let mut names = std::collections::HashSet::new();
+    for (id, hash) in referenced {
+        let name = ContentHash::try_from(hash).map(|h| file_name(&h)).unwrap_or_default();
+        if name.is_empty() || !dir.join(&name).is_file() {
+            result.missing.push(id);
+        }
+        names.insert(name);
+    }
+
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(result),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        // Dot-files are writes in progress, renamed into place once complete.
+        if name.starts_with('.') || !path.is_file() {
+            continue;
+        }
+        result.files += 1;
+        if !names.contains(name) {
+            result.orphaned.push(path);
+        }
+    }
+    result.orphaned.sort();
+    Ok(result)
+}
+
+/// Delete orphaned files and the entries whose file is missing.
+pub fn repair(conn: &Connection, check: &BlobCheck) -> Result<()> {
+    for path in &check.orphaned {
+        std::fs::remove_file(path)?;
+    }
+    let mut delete = conn.prepare("DELETE FROM clipboard_entries WHERE id = ?1")?;
+    for id in &check.missing {
+        delete.execute(params![id])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HistorySort;
+    use crate::db::init_db;
+    use crate::db::repository::{self, EntryFilter, StorageOptions};
+    use crate::db::retention::{self, RetentionPolicy};
+    use crate::models::entry::{ClipboardEntry, CopyOrigin};
+
+    const EXTERNAL: StorageOptions = StorageOptions {
+        compress_text_over: None,
+        blob_store_over: Some(100),
+        thumbnail_max_px: None,
+        image_dedupe_distance: None,
+    };
+
+    fn save(conn: &Connection, entry: &ClipboardEntry) -> i64 {
+        repository::save_entry(conn, entry, &RetentionPolicy::max_history(100), &EXTERNAL, CopyOrigin::Watch).unwrap().id
+    }
+
+    fn stored_file(conn: &Connection, entry: &ClipboardEntry) -> PathBuf {
+        dir_for(conn).unwrap().join(file_name(entry.content_hash()))
+    }
+
+    #[test]
+    fn large_entries_live_in_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = init_db(&dir.path().join("clio.db")).unwrap();
+        let text = format!("{}needle", "a".repeat(10_000));
+        let big = ClipboardEntry::from_text(text.clone(), None);
+        let small = ClipboardEntry::from_text("small".to_owned(), None);
+        let big_id = save(&conn, &big);
+        save(&conn, &small);
+
+        let path = stored_file(&conn, &big);
+        assert_eq!(path.parent().unwrap(), dir.path().join("blobs"));
+        assert_eq!(std::fs::read(&path).unwrap(), text.as_bytes());
+        assert!(!stored_file(&conn, &small).exists());
+        let inline: Option<Vec<u8>> = conn
+            .query_row("SELECT blob_content FROM clipboard_entries WHERE id = ?1", params![big_id], |row| row.get(0))
+            .unwrap();
+        assert!(inline.is_none());
+
+        let entry = repository::get_entry_content(&conn, big_id).unwrap().unwrap();
+        assert_eq!(entry.content().text(), Some(text.as_str()));
+        let (sort, all) = (HistorySort::Chronological, EntryFilter::default());
+        assert_eq!(repository::search_entries_preview(&conn, "needle", 10, None, 10, &all, sort).unwrap().entries.len(), 1);
+        let preview = repository::list_entries_preview(&conn, 10, None, 10, &all, sort).unwrap().entries;
+        assert_eq!(preview[1].content().text(), Some("aaaaaaaaaa"));
+
+        repository::delete_entry(&conn, big_id).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn compressed_text_and_images_are_stored_too() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = init_db(&dir.path().join("clio-work.db")).unwrap();
+        let options = StorageOptions { compress_text_over: Some(100), blob_store_over: Some(100), ..StorageOptions::default() };
+        let text: String = (0..2000).map(|i| format!("log line {i}\n")).collect();
+        let entry = ClipboardEntry::from_text(text.clone(), None);
+        let id = repository::save_entry(&conn, &entry, &RetentionPolicy::max_history(100), &options, CopyOrigin::Watch).unwrap().id;
+
+        let stored = std::fs::read(stored_file(&conn, &entry)).unwrap();
+        assert!(stored.len() < text.len());
+        let loaded = repository::get_entry_content(&conn, id).unwrap().unwrap();
+        assert_eq!(loaded.content().text(), Some(text.as_str()));
+        let (sort, all) = (HistorySort::Chronological, EntryFilter::default());
+        assert_eq!(repository::search_entries_preview(&conn, "line 1999", 10, None, 10, &all, sort).unwrap().entries.len(), 1);
+
+        let rgba: Vec<u8> = (0..64 * 64 * 4).map(|i| (i * 7 % 251) as u8).collect();
+        let image = ClipboardEntry::from_image(64, 64, rgba, None).unwrap();
+        let id = repository::save_entry(&conn, &image, &RetentionPolicy::max_history(100), &options, CopyOrigin::Watch).unwrap().id;
+        assert!(stored_file(&conn, &image).exists());
+        let loaded = repository::get_entry_content(&conn, id).unwrap().unwrap();
+        assert_eq!(loaded.content().blob(), image.content().blob());
+        let preview = repository::list_entries_preview(&conn, 10, None, 10, &all, sort).unwrap().entries;
+        assert_eq!(preview[0].content().blob(), image.content().blob());
+    }
+
+    #[test]
+    fn pruning_releases_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = init_db(&dir.path().join("clio.db")).unwrap();
+        let old = ClipboardEntry::from_text("old".repeat(100), None);
+        save(&conn, &old);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        save(&conn, &ClipboardEntry::from_text("new".repeat(100), None));
+
+        let path = stored_file(&conn, &old);
+        assert!(path.exists());
+        retention::enforce(&conn, &RetentionPolicy::max_history(1)).unwrap();
+        assert!(!path.exists());
+        assert!(check(&conn).unwrap().is_ok());
+    }
+
+    #[test]
+    fn missing_files_fail_reads_but_not_listings() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = init_db(&dir.path().join("clio.db")).unwrap();
+        let text = ClipboardEntry::from_text(format!("{}needle", "a".repeat(10_000)), None);
+        let rgba: Vec<u8> = (0..64 * 64 * 4).map(|i| (i * 7 % 251) as u8).collect();
+        let image = ClipboardEntry::from_image(64, 64, rgba, None).unwrap();
+        let text_id = save(&conn, &text);
+        let image_id = save(&conn, &image);
+        std::fs::remove_file(stored_file(&conn, &text)).unwrap();
+        std::fs::remove_file(stored_file(&conn, &image)).unwrap();
+
+        let (sort, all) = (HistorySort::Chronological, EntryFilter::default());
+        assert_eq!(repository::list_entries_preview(&conn, 10, None, 10, &all, sort).unwrap().entries.len(), 2);
+        assert!(repository::search_entries_preview(&conn, "needle", 10, None, 10, &all, sort).unwrap().entries.is_empty());
+        assert!(repository::get_entry_content(&conn, text_id).is_err());
+        assert!(repository::get_entry_content(&conn, image_id).is_err());
+        // Copying the same text again still finds the entry.
+        assert_eq!(save(&conn, &text), text_id);
+    }
+
+    #[test]
+    fn unfinished_writes_are_not_orphans() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = init_db(&dir.path().join("clio.db")).unwrap();
+        let entry = ClipboardEntry::from_text("kept".repeat(100), None);
+        save(&conn, &entry);
+        let tmp = dir_for(&conn).unwrap().join(format!(".{}.tmp", "0".repeat(64)));
+        std::fs::write(&tmp, "partial").unwrap();
+
+        let result = check(&conn).unwrap();
+        assert_eq!(result, BlobCheck { files: 1, missing: Vec::new(), orphaned: Vec::new() });
+        repair(&conn, &result).unwrap();
+        assert!(tmp.exists());
+    }
+
+    #[test]
+    fn files_are_removed_after_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = init_db(&dir.path().join("clio.db")).unwrap();
+        let entry = ClipboardEntry::from_text("kept".repeat(100), None);
+        let id = save(&conn, &entry);
+        let path = stored_file(&conn, &entry);
+
+        let tx = conn.unchecked_transaction().unwrap();
+        repository::delete_entry(&tx, id).unwrap();
+        assert!(path.exists());
+        drop(tx);
+        assert!(path.exists());
+        assert!(check(&conn).unwrap().is_ok());
+
+        let tx = conn.unchecked_transaction().unwrap();
+        repository::delete_entry(&tx, id).unwrap();
+        tx.commit().unwrap();
+        assert!(path.exists());
+        remove_released(&conn).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn leftover_files_in_another_format_are_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = init_db(&dir.path().join("clio.db")).unwrap();
+        let text: String = (0..2000).map(|i| format!("log line {i}\n")).collect();
+        let entry = ClipboardEntry::from_text(text.clone(), None);
+        let id = save(&conn, &entry);
+        // Drop the row without releasing its file: the raw text stays behind.
+        conn.execute("DELETE FROM clipboard_entries WHERE id = ?1", params![id]).unwrap();
+
+        let options = StorageOptions { compress_text_over: Some(100), ..EXTERNAL };
+        let id = repository::save_entry(&conn, &entry, &RetentionPolicy::max_history(100), &options, CopyOrigin::Watch)
+            .unwrap()
+            .id;
+        assert!(std::fs::metadata(stored_file(&conn, &entry)).unwrap().len() < text.len() as u64);
+        let loaded = repository::get_entry_content(&conn, id).unwrap().unwrap();
+        assert_eq!(loaded.content().text(), Some(text.as_str()));
+    }
+
+    #[test]
+    fn check_finds_missing_and_orphaned_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = init_db(&dir.path().join("clio.db")).unwrap();
+        let kept = ClipboardEntry::from_text("kept".repeat(100), None);
+        let lost = ClipboardEntry::from_text("lost".repeat(100), None);
+        save(&conn, &kept);
+        let lost_id = save(&conn, &lost);
+        std::fs::remove_file(stored_file(&conn, &lost)).unwrap();
+        let orphan = dir_for(&conn).unwrap().join(file_name(&[7; 32]));
+        std::fs::write(&orphan, b"stray").unwrap();
+
+        let result = check(&conn).unwrap();
+        assert_eq!(result, BlobCheck { files: 2, missing: vec![lost_id], orphaned: vec![orphan.clone()] });
+
+        repair(&conn, &result).unwrap();
+        assert!(!orphan.exists());
+        assert!(repository::get_entry_content(&conn, lost_id).unwrap().is_none());
+        assert!(stored_file(&conn, &kept).exists());
+        assert!(check(&conn).unwrap().is_ok());
+    }
+
+    #[test]
+    fn blob_dir_per_database() {
+        assert_eq!(blob_dir(Path::new("/data/clio/clio.db")), Path::new("/data/clio/blobs"));
+        assert_eq!(blob_dir(Path::new("/data/clio/clio-work.db")), Path::new("/data/clio/clio-work.blobs"));
+    }
+}
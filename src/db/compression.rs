@@ -0,0 +1,93 @@
+//! Transparent zstd compression of large text entries.
+//!
+//! A compressed entry keeps the first `PREFIX_CHARS` characters in
+//! `text_content`, so previews can `substr` it as usual, and the whole text
+//! zstd-compressed in `blob_content`, marked by `compression = 'zstd'`.
+//! Searches decompress on the fly through the `clio_decompress` SQL function.
+
+use rusqlite::functions::FunctionFlags;
+use rusqlite::Connection;
+
+use crate::errors::{AppError, Result};
+
+/// Value of the `compression` column for zstd-compressed text.
+pub(crate) const ZSTD: &str = "zstd";
+
+/// Plain-text characters kept in `text_content` of a compressed entry.
+pub(crate) const PREFIX_CHARS: usize = 8192;
+
+/// Fast level; logs and JSON still shrink several times.
+const ZSTD_LEVEL: i32 = 3;
+
+/// Compressed form of `text`, or `None` if compression would not save space.
+pub(crate) fn compress(text: &str) -> Result<Option<Vec<u8>>> {
+    let compressed = zstd::encode_all(text.as_bytes(), ZSTD_LEVEL)?;
+    Ok((compressed.len() < text.len()).then_some(compressed))
+}
+
+pub(crate) fn decompress(blob: &[u8]) -> Result<String> {
+    let bytes = zstd::decode_all(blob)?;
+    String::from_utf8(bytes).map_err(|e| AppError::DataIntegrity(format!("compressed text is not UTF-8: {e}")))
+}
+
+/// The first `PREFIX_CHARS` characters of `text`.
+pub(crate) fn prefix(text: &str) -> &str {
+    match text.char_indices().nth(PREFIX_CHARS) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/// Register `clio_decompress(blob)`, used to search compressed text, on `conn`.
+pub(crate) fn register_functions(conn: &Connection) -> Result<()> {
+    conn.create_scalar_function(
+        "clio_decompress",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let Some(blob) = ctx.get_raw(0).as_blob_or_null()? else {
+                return Ok(None);
+            };
+            decompress(blob)
+                .map(Some)
+                .map_err(|e| rusqlite::Error::UserFunctionError(e.into()))
+        },
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let text = "line of a log file\n".repeat(1000);
+        let compressed = compress(&text).unwrap().unwrap();
+        assert!(compressed.len() < text.len() / 10);
+        assert_eq!(decompress(&compressed).unwrap(), text);
+    }
+
+    #[test]
+    fn incompressible_text_is_kept() {
+        assert!(compress("ab").unwrap().is_none());
+    }
+
+    #[test]
+    fn prefix_counts_characters() {
+        let text = "é".repeat(PREFIX_CHARS + 10);
+        assert_eq!(prefix(&text).chars().count(), PREFIX_CHARS);
+        assert_eq!(prefix("short"), "short");
+    }
+
+    #[test]
+    fn sql_function_decompresses() {
+        let conn = Connection::open_in_memory().unwrap();
+        register_functions(&conn).unwrap();
+        let blob = compress(&"x".repeat(100)).unwrap().unwrap();
+        let text: String = conn.query_row("SELECT clio_decompress(?1)", [blob], |row| row.get(0)).unwrap();
+        assert_eq!(text, "x".repeat(100));
+        let null: Option<String> = conn.query_row("SELECT clio_decompress(NULL)", [], |row| row.get(0)).unwrap();
+        assert!(null.is_none());
+    }
+}
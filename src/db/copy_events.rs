@@ -0,0 +1,200 @@
+//! Timeline of copies: one `copy_events` row per time an entry was put on
+//! the clipboard. `clipboard_entries` stays the deduplicated view whose
+//! `created_at` is the latest copy; the events keep the first one, how often
+//! an entry was used, and from where.
+
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::errors::Result;
+use crate::models::entry::{ClipboardEntry, CopyOrigin, Selection, Timestamp};
+
+/// `clio watch` sees every write by `clio copy`, `clio select` and the
+/// history window as a clipboard change. A watch event this soon after one of
+/// those for the same entry is that echo and is not recorded.
+const ECHO_WINDOW: Duration = Duration::from_secs(5);
+
+/// Record that `entry`, stored as `entry_id`, was copied now.
+pub(crate) fn record(conn: &Connection, entry_id: i64, origin: CopyOrigin, entry: &ClipboardEntry) -> Result<()> {
+    if origin == CopyOrigin::Watch && is_echo(conn, entry_id)? {
+        return Ok(());
+    }
+    conn.prepare_cached(
+        "INSERT INTO copy_events (entry_id, created_at, selection, source_app, source_title, origin)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?
+    .execute(params![
+        entry_id,
+        Timestamp::now(),
+        entry.selection(),
+        entry.source_app(),
+        entry.source_title(),
+        origin,
+    ])?;
+    Ok(())
+}
+
+/// Record that the stored entry `entry_id` was put back on the clipboard,
+/// e.g. by `clio select`. Selection and source are the entry's own. Also
+/// counts the use on the entry, which frecency ordering ranks by.
+pub fn record_use(conn: &Connection, entry_id: i64, origin: CopyOrigin) -> Result<()> {
+    let now = Timestamp::now();
+    conn.execute(
+        "INSERT INTO copy_events (entry_id, created_at, selection, source_app, source_title, origin)
+         SELECT id, ?2, selection, source_app, source_title, ?3 FROM clipboard_entries WHERE id = ?1",
+        params![entry_id, now, origin],
+    )?;
+    conn.execute(
+        "UPDATE clipboard_entries SET use_count = use_count + 1, last_used_at = ?2 WHERE id = ?1",
+        params![entry_id, now],
+    )?;
+    Ok(())
+}
+
+/// One copy of an entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyEvent {
+    pub entry_id: i64,
+    pub created_at: Timestamp,
+    pub selection: Option<Selection>,
+    pub source_app: Option<String>,
+    pub source_title: Option<String>,
+    /// `None` for copies recorded before events existed.
+    pub origin: Option<CopyOrigin>,
+}
+
+const EVENT_COLUMNS: &str = "entry_id, created_at, selection, source_app, source_title, origin";
+
+fn row_to_event(row: &rusqlite::Row<'_>) -> rusqlite::Result<CopyEvent> {
+    Ok(CopyEvent {
+        entry_id: row.get(0)?,
+        created_at: row.get(1)?,
+        selection: row.get(2)?,
+        source_app: row.get(3)?,
+        source_title: row.get(4)?,
+        origin: row.get(5)?,
+    })
+}
+
+/// Copies of `entry_id`, oldest first.
+pub fn for_entry(conn: &Connection, entry_id: i64) -> Result<Vec<CopyEvent>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT {EVENT_COLUMNS} FROM copy_events WHERE entry_id = ?1 ORDER BY created_at, id"
+    ))?;
+    let events = stmt
+        .query_map(params![entry_id], row_to_event)?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(events)
+}
+
+/// The latest copy at or before `at` that was not a PRIMARY selection, i.e.
+/// what Ctrl+V would have pasted then. Copies of entries deleted since are
+/// gone with them.
+pub fn on_clipboard_at(conn: &Connection, at: Timestamp) -> Result<Option<CopyEvent>> {
+    let event = conn
+        .prepare_cached(&format!(
+            "SELECT {EVENT_COLUMNS} FROM copy_events
+             WHERE created_at <= ?1 AND (selection IS NULL OR selection != 'primary')
+             ORDER BY created_at DESC, id DESC LIMIT 1"
+        ))?
+        .query_row(params![at], row_to_event)
+        .optional()?;
+    Ok(event)
+}
+
+fn is_echo(conn: &Connection, entry_id: i64) -> Result<bool> {
+    let since = Timestamp::before(ECHO_WINDOW);
+    let echo = conn
+        .prepare_cached(
+            "SELECT EXISTS(SELECT 1 FROM copy_events
+             WHERE entry_id = ?1 AND created_at >= ?2 AND origin IN ('copy', 'select', 'history'))",
+        )?
+        .query_row(params![entry_id, since], |row| row.get(0))?;
+    Ok(echo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db_in_memory;
+    use crate::db::repository::{delete_entry, save_entry, StorageOptions};
+    use crate::db::retention::RetentionPolicy;
+
+    fn save(conn: &Connection, text: &str, selection: Selection, origin: CopyOrigin) -> i64 {
+        let mut entry = ClipboardEntry::from_text(text.to_owned(), Some("kitty".to_owned()));
+        entry.set_selection(Some(selection));
+        save_entry(conn, &entry, &RetentionPolicy::max_history(100), &StorageOptions::default(), origin)
+            .unwrap()
+            .id
+    }
+
+    fn set_time(conn: &Connection, ms: i64) {
+        conn.execute("UPDATE copy_events SET created_at = ?1 WHERE id = (SELECT MAX(id) FROM copy_events)", [ms])
+            .unwrap();
+    }
+
+    #[test]
+    fn every_copy_is_recorded() {
+        let conn = init_db_in_memory().unwrap();
+        let id = save(&conn, "hello", Selection::Clipboard, CopyOrigin::Watch);
+        save(&conn, "hello", Selection::Primary, CopyOrigin::Watch);
+        record_use(&conn, id, CopyOrigin::History).unwrap();
+
+        let events = for_entry(&conn, id).unwrap();
+        let summary: Vec<_> = events.iter().map(|e| (e.selection, e.origin)).collect();
+        assert_eq!(
+            summary,
+            [
+                (Some(Selection::Clipboard), Some(CopyOrigin::Watch)),
+                (Some(Selection::Primary), Some(CopyOrigin::Watch)),
+                // The entry's merged selection.
+                (Some(Selection::Both), Some(CopyOrigin::History)),
+            ]
+        );
+        assert_eq!(events[0].source_app.as_deref(), Some("kitty"));
+        assert_eq!(for_entry(&conn, id).unwrap().len(), 3);
+
+        let (selected, last_used): (i64, Option<Timestamp>) = conn
+            .query_row("SELECT use_count, last_used_at FROM clipboard_entries WHERE id = ?1", [id], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(selected, 1);
+        assert_eq!(last_used, Some(events[2].created_at));
+    }
+
+    #[test]
+    fn watch_echo_of_own_write_is_skipped() {
+        let conn = init_db_in_memory().unwrap();
+        let id = save(&conn, "hello", Selection::Cli, CopyOrigin::Copy);
+        save(&conn, "hello", Selection::Clipboard, CopyOrigin::Watch);
+        assert_eq!(for_entry(&conn, id).unwrap().len(), 1);
+
+        // Long after the write, a copy seen by the watcher counts again.
+        set_time(&conn, 0);
+        save(&conn, "hello", Selection::Clipboard, CopyOrigin::Watch);
+        assert_eq!(for_entry(&conn, id).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn clipboard_at_ignores_primary() {
+        let conn = init_db_in_memory().unwrap();
+        // An hour ago: saving prunes events older than the retention period.
+        let base = Timestamp::before(Duration::from_secs(3600)).as_millis();
+        let first = save(&conn, "first", Selection::Clipboard, CopyOrigin::Watch);
+        set_time(&conn, base + 1_000);
+        save(&conn, "selected", Selection::Primary, CopyOrigin::Watch);
+        set_time(&conn, base + 2_000);
+        let second = save(&conn, "second", Selection::Clipboard, CopyOrigin::Watch);
+        set_time(&conn, base + 3_000);
+
+        let at = |ms| on_clipboard_at(&conn, Timestamp::from_millis(base + ms)).unwrap().map(|e| e.entry_id);
+        assert_eq!(at(500), None);
+        assert_eq!(at(2_500), Some(first));
+        assert_eq!(at(3_000), Some(second));
+
+        delete_entry(&conn, second).unwrap();
+        assert_eq!(at(3_000), Some(first));
+    }
+}
@@ -0,0 +1,472 @@
+//! Database maintenance behind `clio db`: vacuum, integrity checks,
+//! online backup and restore, and size statistics.
+
+use std::path::Path;
+
+use rusqlite::{params, Connection, DatabaseName, OpenFlags, OptionalExtension};
+
+use super::{blob_store, migrations, near_duplicates, repository};
+use crate::errors::{AppError, Result};
+use crate::models::entry::{ContentHash, EntryContent};
+use crate::models::images;
+
+/// Database size before and after `vacuum`, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VacuumResult {
+    pub before: u64,
+    pub after: u64,
+}
+
+/// Rebuild the database file, returning the space freed by deleted entries
+/// to the filesystem, and truncate the WAL.
+pub fn vacuum(conn: &Connection) -> Result<VacuumResult> {
+    let before = db_bytes(conn)?;
+    conn.execute_batch("VACUUM;")?;
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    Ok(VacuumResult { before, after: db_bytes(conn)? })
+}
+
+/// Problems reported by SQLite's `PRAGMA integrity_check`; empty when healthy.
+pub fn integrity_check(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let messages = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(messages.into_iter().filter(|m| m != "ok").collect())
+}
+
+/// Entries whose content no longer matches their `content_hash`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct HashCheck {
+    pub checked: usize,
+    /// IDs of entries whose content hashes differently.
+    pub mismatched: Vec<i64>,
+    /// IDs of entries whose content could not be read, with the reason.
+    pub unreadable: Vec<(i64, String)>,
+}
+
+/// Recompute the hash of every entry's content and compare it to the stored one.
+pub fn verify_hashes(conn: &Connection) -> Result<HashCheck> {
+    let mut stmt = conn.prepare("SELECT id FROM clipboard_entries ORDER BY id")?;
+    let ids = stmt
+        .query_map([], |row| row.get::<_, i64>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut result = HashCheck::default();
+    for id in ids {
+        match repository::get_entry_content(conn, id) {
+            Ok(Some(entry)) => {
+                result.checked += 1;
+                if !hash_matches(entry.content(), entry.content_hash()) {
+                    result.mismatched.push(id);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => result.unreadable.push((id, e.to_string())),
+        }
+    }
+    Ok(result)
+}
+
+/// Lossy images (JPEG, lossy WebP) no longer decode to the pixels they were
+/// hashed from; for them only decoding is checked.
+fn hash_matches(content: &EntryContent, hash: &ContentHash) -> bool {
+    match content {
+        EntryContent::Image(bytes) => match images::decoded_hash(bytes) {
+            Ok(pixels) => pixels == *hash || !images::is_lossless(bytes),
+            Err(_) => false,
+        },
+        other => other.compute_hash() == *hash,
+    }
+}
+
+/// Give lossless images stored before hashes covered pixels (hashed by their
+/// PNG bytes) the pixel hash new images get, moving blob store files to the
+/// new name. An image whose pixels match another entry's is merged into the
+/// newer of the two. Returns the number of rehashed images.
+pub(crate) fn rehash_images(conn: &Connection) -> Result<usize> {
+    let mut stmt = conn.prepare(
+        "SELECT id, created_at, content_hash, blob_content, storage FROM clipboard_entries
+         WHERE content_type = 'image' ORDER BY created_at, id",
+    )?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, Vec<u8>>(2)?,
+                row.get::<_, Option<Vec<u8>>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+            ))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut rehashed = 0;
+    for (id, created_at, old, inline, storage) in rows {
+        let Ok(old) = ContentHash::try_from(old) else { continue };
+        let bytes = match (inline, &storage) {
+            (Some(bytes), None) => bytes,
+            (_, Some(_)) => match blob_store::read(conn, &old) {
+                Some(bytes) => bytes,
+                None => continue,
+            },
+            (None, None) => continue,
+        };
+        if !images::is_lossless(&bytes) {
+            continue;
+        }
+        let Ok(new) = images::decoded_hash(&bytes) else { continue };
+        if new == old {
+            continue;
+        }
+        let same_pixels: Option<(i64, i64)> = conn
+            .query_row(
+                "SELECT id, created_at FROM clipboard_entries WHERE content_hash = ?1",
+                [new.as_slice()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        match same_pixels {
+            Some((other, other_created)) if (other_created, other) > (created_at, id) => {
+                near_duplicates::merge_into(conn, id, other)?;
+                continue;
+            }
+            Some((other, _)) => near_duplicates::merge_into(conn, other, id)?,
+            None => {}
+        }
+        conn.execute(
+            "UPDATE clipboard_entries SET content_hash = ?2 WHERE id = ?1",
+            params![id, new.as_slice()],
+        )?;
+        if storage.is_some() {
+            blob_store::write(conn, &new, &bytes, None)?;
+            blob_store::release(conn, &[old])?;
+        }
+        rehashed += 1;
+    }
+    Ok(rehashed)
+}
+
+/// Copy the database to `dest` with SQLite's online backup API, which is
+/// safe while `clio watch` writes to it. The blob store files the copy
+/// references are then copied to the blob directory next to `dest`.
+pub fn backup(conn: &Connection, dest: &Path) -> Result<()> {
+    if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    conn.backup(DatabaseName::Main, dest, None)?;
+    if let Some(dir) = blob_store::dir_for(conn) {
+        let copy = Connection::open_with_flags(dest, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        blob_store::copy_referenced(&dir, &copy)?;
+    }
+    Ok(())
+}
+
+/// Replace the database with the backup at `src`, then migrate it to the
+/// current schema. Blob files of the backup are copied in; files only the
+/// replaced history used are removed.
+pub fn restore(conn: &mut Connection, src: &Path) -> Result<()> {
+    let source = Connection::open_with_flags(src, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| AppError::DataIntegrity(format!("cannot open backup {}: {e}", src.display())))?;
+    let has_entries: bool = source
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'clipboard_entries')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| AppError::DataIntegrity(format!("{} is not a database: {e}", src.display())))?;
+    if !has_entries {
+        return Err(AppError::DataIntegrity(format!("{} is not a clio database", src.display())));
+    }
+    let (backup_version, current_version) = (schema_version(&source)?, schema_version(conn)?);
+    if backup_version > current_version {
+        return Err(AppError::DataIntegrity(format!(
+            "backup schema version {backup_version} is newer than this clio supports ({current_version})"
+        )));
+    }
+    drop(source);
+
+    if let Some(dir) = blob_store::dir_for(conn) {
+        blob_store::copy_files(&blob_store::blob_dir(src), &dir)?;
+    }
+    conn.restore(DatabaseName::Main, src, None::<fn(rusqlite::backup::Progress)>)?;
+    migrations::run_migrations(conn)?;
+
+    let blobs = blob_store::check(conn)?;
+    for path in &blobs.orphaned {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Entry count and stored bytes of one content type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeStats {
+    pub content_type: String,
+    pub entries: u64,
+    /// Bytes stored inside the database.
+    pub bytes: u64,
+    pub compressed: u64,
+    pub in_blob_store: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbStats {
+    pub schema_version: i64,
+    /// Database size (all pages), in bytes.
+    pub db_bytes: u64,
+    /// Unused pages that `vacuum` would return, in bytes.
+    pub free_bytes: u64,
+    pub types: Vec<TypeStats>,
+    pub blob_files: u64,
+    pub blob_bytes: u64,
+}
+
+pub fn stats(conn: &Connection) -> Result<DbStats> {
+    let mut stmt = conn.prepare(
+        "SELECT content_type, COUNT(*),
+                COALESCE(SUM(COALESCE(octet_length(text_content), 0) + COALESCE(length(blob_content), 0)
+                             + COALESCE(length(thumbnail), 0)), 0),
+                COUNT(compression), COUNT(storage)
+         FROM clipboard_entries GROUP BY content_type ORDER BY content_type",
+    )?;
+    let types = stmt
+        .query_map([], |row| {
+            Ok(TypeStats {
+                content_type: row.get(0)?,
+                entries: row.get::<_, i64>(1)? as u64,
+                bytes: row.get::<_, i64>(2)? as u64,
+                compressed: row.get::<_, i64>(3)? as u64,
+                in_blob_store: row.get::<_, i64>(4)? as u64,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let (mut blob_files, mut blob_bytes) = (0, 0);
+    if let Some(dir) = blob_store::dir_for(conn) {
+        for entry in std::fs::read_dir(dir).into_iter().flatten() {
+            let metadata = entry?.metadata()?;
+            if metadata.is_file() {
+                blob_files += 1;
+                blob_bytes += metadata.len();
+            }
+        }
+    }
+
+    Ok(DbStats {
+        schema_version: schema_version(conn)?,
+        db_bytes: db_bytes(conn)?,
+        free_bytes: pragma(conn, "freelist_count")? * pragma(conn, "page_size")?,
+        types,
+        blob_files,
+        blob_bytes,
+    })
+}
+
+/// Migration level, as recorded by `rusqlite_migration` in `user_version`.
+fn schema_version(conn: &Connection) -> Result<i64> {
+    Ok(conn.pragma_query_value(None, "user_version", |row| row.get(0))?)
+}
+
+fn db_bytes(conn: &Connection) -> Result<u64> {
+    Ok(pragma(conn, "page_count")? * pragma(conn, "page_size")?)
+}
+
+fn pragma(conn: &Connection, name: &str) -> Result<u64> {
+    Ok(conn.pragma_query_value(None, name, |row| row.get::<_, i64>(0))? as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db;
+    use crate::db::repository::{save_entry, StorageOptions};
+    use crate::db::retention::RetentionPolicy;
+    use crate::models::entry::{ClipboardEntry, CopyOrigin};
+
+    fn save(conn: &Connection, text: &str, storage: &StorageOptions) -> i64 {
+        let entry = ClipboardEntry::from_text(text.to_owned(), None);
+        save_entry(conn, &entry, &RetentionPolicy::max_history(100), storage, CopyOrigin::Watch).unwrap().id
+    }
+
+    #[test]
+    fn vacuum_returns_freed_space() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = init_db(&dir.path().join("clio.db")).unwrap();
+        let id = save(&conn, &"x".repeat(200_000), &StorageOptions::default());
+        repository::delete_entry(&conn, id).unwrap();
+
+        let result = vacuum(&conn).unwrap();
+        assert!(result.after < result.before, "{result:?}");
+    }
+
+    #[test]
+    fn check_finds_hash_mismatch() {
+        let conn = crate::db::init_db_in_memory().unwrap();
+        let good = save(&conn, "good", &StorageOptions::default());
+        let bad = save(&conn, "bad", &StorageOptions::default());
+        conn.execute("UPDATE clipboard_entries SET text_content = 'tampered' WHERE id = ?1", [bad]).unwrap();
+
+        assert!(integrity_check(&conn).unwrap().is_empty());
+        let result = verify_hashes(&conn).unwrap();
+        assert_eq!(result.checked, 2);
+        assert_eq!(result.mismatched, vec![bad]);
+        assert!(!result.mismatched.contains(&good));
+    }
+
+    #[test]
+    fn check_hashes_images_by_pixels() {
+        use crate::config::ImageEncoding;
+        use crate::models::images::ImageStorage;
+
+        let conn = crate::db::init_db_in_memory().unwrap();
+        let insert = |seed: u8, encoding| {
+            let rgba = (0..64u8).map(|i| i.wrapping_mul(seed)).collect();
+            let storage = ImageStorage { encoding, quality: 50 };
+            let entry = ClipboardEntry::from_image_with(4, 4, rgba, None, &storage, None).unwrap();
+            repository::insert_entry(&conn, &entry).unwrap()
+        };
+        insert(3, ImageEncoding::Webp);
+        insert(5, ImageEncoding::Jpeg);
+        // Hashed by its PNG bytes, as before hashes covered pixels.
+        let legacy = insert(7, ImageEncoding::Png);
+        let legacy_hash = crate::models::entry::compute_hash(&stored_blob(&conn, legacy));
+        conn.execute("UPDATE clipboard_entries SET content_hash = ?2 WHERE id = ?1", rusqlite::params![legacy, legacy_hash])
+            .unwrap();
+        assert_eq!(verify_hashes(&conn).unwrap().mismatched, vec![legacy]);
+        assert_eq!(rehash_images(&conn).unwrap(), 1);
+        let tampered = insert(9, ImageEncoding::Png);
+        let mut png = stored_blob(&conn, tampered);
+        png.truncate(png.len() / 2);
+        conn.execute("UPDATE clipboard_entries SET blob_content = ?2 WHERE id = ?1", rusqlite::params![tampered, png])
+            .unwrap();
+
+        let result = verify_hashes(&conn).unwrap();
+        assert_eq!(result.checked, 4);
+        assert_eq!(result.mismatched, vec![tampered]);
+    }
+
+    #[test]
+    fn rehash_merges_legacy_image_into_newer_copy() {
+        use crate::config::ImageEncoding;
+        use crate::models::entry::compute_hash;
+        use crate::models::images::{self, ImageStorage};
+
+        let dir = tempfile::tempdir().unwrap();
+        let conn = init_db(&dir.path().join("clio.db")).unwrap();
+        let external = StorageOptions { blob_store_over: Some(0), ..StorageOptions::default() };
+        let policy = RetentionPolicy::max_history(100);
+        let png = |seed: u8| {
+            let rgba = (0..64u8).map(|i| i.wrapping_mul(seed)).collect();
+            let storage = ImageStorage { encoding: ImageEncoding::Png, quality: 50 };
+            ClipboardEntry::from_image_with(4, 4, rgba, None, &storage, None).unwrap()
+        };
+        let legacy_entry = png(3);
+        let EntryContent::Image(bytes) = legacy_entry.content() else { unreachable!() };
+        let bytes = bytes.clone();
+        let legacy = save_entry(&conn, &legacy_entry, &policy, &external, CopyOrigin::Watch).unwrap().id;
+        let legacy_hash = compute_hash(&bytes);
+        conn.execute(
+            "UPDATE clipboard_entries SET content_hash = ?2, created_at = 1000 WHERE id = ?1",
+            rusqlite::params![legacy, legacy_hash],
+        )
+        .unwrap();
+        blob_store::write(&conn, &legacy_hash, &bytes, None).unwrap();
+        let newer = save_entry(&conn, &png(3), &policy, &StorageOptions::default(), CopyOrigin::Watch).unwrap().id;
+        let other = save_entry(&conn, &png(5), &policy, &StorageOptions::default(), CopyOrigin::Watch).unwrap().id;
+        let other_hash = compute_hash(&stored_blob(&conn, other));
+        conn.execute("UPDATE clipboard_entries SET content_hash = ?2 WHERE id = ?1", rusqlite::params![other, other_hash])
+            .unwrap();
+
+        assert_eq!(rehash_images(&conn).unwrap(), 1);
+        blob_store::remove_released(&conn).unwrap();
+
+        assert!(!repository::entry_exists(&conn, legacy).unwrap());
+        assert_eq!(crate::db::copy_events::for_entry(&conn, newer).unwrap().len(), 2);
+        let rehashed = repository::get_entry_content(&conn, other).unwrap().unwrap();
+        assert_eq!(*rehashed.content_hash(), images::decoded_hash(&stored_blob(&conn, other)).unwrap());
+        assert!(blob_store::read(&conn, &legacy_hash).is_none());
+        assert!(verify_hashes(&conn).unwrap().mismatched.is_empty());
+    }
+
+    fn stored_blob(conn: &Connection, id: i64) -> Vec<u8> {
+        conn.query_row("SELECT blob_content FROM clipboard_entries WHERE id = ?1", [id], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn backup_and_restore_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let external = StorageOptions { blob_store_over: Some(100), ..StorageOptions::default() };
+        let conn = init_db(&dir.path().join("clio.db")).unwrap();
+        save(&conn, "kept", &StorageOptions::default());
+        let big = "big".repeat(100);
+        save(&conn, &big, &external);
+
+        let backup_path = dir.path().join("backup").join("clio-backup.db");
+        backup(&conn, &backup_path).unwrap();
+        assert_eq!(std::fs::read_dir(blob_store::blob_dir(&backup_path)).unwrap().count(), 1);
+
+        // Diverge from the backup, then restore it.
+        conn.execute("DELETE FROM clipboard_entries", []).unwrap();
+        save(&conn, &"later".repeat(100), &external);
+        let mut conn = conn;
+        restore(&mut conn, &backup_path).unwrap();
+
+        let texts: Vec<String> = conn
+            .prepare("SELECT text_content FROM clipboard_entries WHERE storage IS NULL")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(texts, ["kept"]);
+        assert_eq!(verify_hashes(&conn).unwrap().checked, 2);
+        // The blob of the dropped "later" entry is gone, the backup's is back.
+        let blobs = blob_store::check(&conn).unwrap();
+        assert_eq!((blobs.files, blobs.is_ok()), (1, true));
+    }
+
+    #[test]
+    fn backup_copies_the_blobs_it_references() {
+        let dir = tempfile::tempdir().unwrap();
+        let external = StorageOptions { blob_store_over: Some(100), ..StorageOptions::default() };
+        let conn = init_db(&dir.path().join("clio.db")).unwrap();
+        save(&conn, &"big".repeat(100), &external);
+        let stray = blob_store::dir_for(&conn).unwrap().join("stray");
+        std::fs::write(&stray, b"not referenced").unwrap();
+
+        let backup_path = dir.path().join("clio-backup.db");
+        backup(&conn, &backup_path).unwrap();
+
+        let copy = Connection::open(&backup_path).unwrap();
+        let blobs = blob_store::check(&copy).unwrap();
+        assert_eq!((blobs.files, blobs.is_ok()), (1, true));
+    }
+
+    #[test]
+    fn restore_rejects_non_clio_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let other = dir.path().join("other.db");
+        Connection::open(&other).unwrap().execute_batch("CREATE TABLE t (x);").unwrap();
+        let mut conn = init_db(&dir.path().join("clio.db")).unwrap();
+
+        let err = restore(&mut conn, &other).unwrap_err().to_string();
+        assert!(err.contains("not a clio database"), "{err}");
+    }
+
+    #[test]
+    fn stats_by_type() {
+        let conn = crate::db::init_db_in_memory().unwrap();
+        save(&conn, "hello", &StorageOptions::default());
+        save(&conn, &"log\n".repeat(1000), &StorageOptions { compress_text_over: Some(10), ..StorageOptions::default() });
+
+        let stats = stats(&conn).unwrap();
+        assert!(stats.schema_version > 0);
+        assert_eq!(stats.types.len(), 1);
+        let text = &stats.types[0];
+        assert_eq!((text.content_type.as_str(), text.entries, text.compressed), ("text", 2, 1));
+        // "hello" plus the plain prefix and the zstd blob of the log.
+        assert!(text.bytes > 4000 + 5, "{text:?}");
+    }
+}
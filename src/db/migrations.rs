@@ -1,10 +1,18 @@
 use rusqlite::Connection;
-use rusqlite_migration::{Migrations, M};
+use rusqlite_migration::{HookError, Migrations, M};
 
+use super::{blob_store, maintenance};
 use crate::errors::Result;
 
 pub fn run_migrations(conn: &mut Connection) -> Result<()> {
-    let migrations = Migrations::new(vec![
+    migrations().to_latest(conn)?;
+    // Blob files a migration moved to a new name.
+    blob_store::remove_released(conn)?;
+    Ok(())
+}
+
+fn migrations() -> Migrations<'static> {
+    Migrations::new(vec![
         M::up(
             "CREATE TABLE IF NOT EXISTS clipboard_entries (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -32,7 +40,292 @@ pub fn run_migrations(conn: &mut Connection) -> Result<()> {
         ),
         M::up("ALTER TABLE clipboard_entries ADD COLUMN source_title TEXT;"),
         M::up("ALTER TABLE clipboard_entries ADD COLUMN mask_text TEXT;"),
-    ]);
-    migrations.to_latest(conn)?;
-    Ok(())
+        // SQLite cannot alter a CHECK constraint in place: rebuild the table to allow 'files'.
+        M::up(
+            "CREATE TABLE clipboard_entries_new (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                content_type TEXT NOT NULL CHECK(content_type IN ('text', 'image', 'files', 'unknown')),
+                text_content TEXT,
+                blob_content BLOB,
+                content_hash BLOB NOT NULL,
+                source_app TEXT,
+                created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f', 'now')),
+                metadata TEXT DEFAULT '{}',
+                expires_at TEXT,
+                source_title TEXT,
+                mask_text TEXT
+            );
+
+            INSERT INTO clipboard_entries_new
+                (id, content_type, text_content, blob_content, content_hash, source_app,
+                 created_at, metadata, expires_at, source_title, mask_text)
+            SELECT id, content_type, text_content, blob_content, content_hash, source_app,
+                   created_at, metadata, expires_at, source_title, mask_text
+            FROM clipboard_entries;
+
+            DROP TABLE clipboard_entries;
+            ALTER TABLE clipboard_entries_new RENAME TO clipboard_entries;
+
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_entries_hash
+                ON clipboard_entries(content_hash);
+
+            CREATE INDEX IF NOT EXISTS idx_entries_created
+                ON clipboard_entries(created_at DESC);
+
+            CREATE INDEX IF NOT EXISTS idx_entries_expires
+                ON clipboard_entries(expires_at)
+                WHERE expires_at IS NOT NULL;",
+        ),
+        // Which selection an entry came from; NULL for entries recorded before this migration.
+        M::up(
+            "ALTER TABLE clipboard_entries ADD COLUMN selection TEXT
+                CHECK(selection IN ('clipboard', 'primary', 'both', 'cli'));",
+        ),
+        // Large text stored compressed in blob_content; text_content keeps a plain prefix.
+        M::up(
+            "ALTER TABLE clipboard_entries ADD COLUMN compression TEXT
+                CHECK(compression IN ('zstd'));",
+        ),
+        // Large content kept in the on-disk blob store instead of blob_content.
+        M::up(
+            "ALTER TABLE clipboard_entries ADD COLUMN storage TEXT
+                CHECK(storage IN ('file'));",
+        ),
+        // Timestamps as INTEGER milliseconds since the Unix epoch instead of
+        // ISO 8601 text; rebuilds the table and its indexes.
+        M::up(
+            "CREATE TABLE clipboard_entries_new (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                content_type TEXT NOT NULL CHECK(content_type IN ('text', 'image', 'files', 'unknown')),
+                text_content TEXT,
+                blob_content BLOB,
+                content_hash BLOB NOT NULL,
+                source_app TEXT,
+                created_at INTEGER NOT NULL DEFAULT (CAST(unixepoch('subsec') * 1000 AS INTEGER)),
+                metadata TEXT DEFAULT '{}',
+                expires_at INTEGER,
+                source_title TEXT,
+                mask_text TEXT,
+                selection TEXT CHECK(selection IN ('clipboard', 'primary', 'both', 'cli')),
+                compression TEXT CHECK(compression IN ('zstd')),
+                storage TEXT CHECK(storage IN ('file'))
+            );
+
+            INSERT INTO clipboard_entries_new
+                (id, content_type, text_content, blob_content, content_hash, source_app,
+                 created_at, metadata, expires_at, source_title, mask_text, selection,
+                 compression, storage)
+            SELECT id, content_type, text_content, blob_content, content_hash, source_app,
+                   COALESCE(CAST(ROUND(unixepoch(created_at, 'subsec') * 1000) AS INTEGER),
+                            CAST(unixepoch('subsec') * 1000 AS INTEGER)),
+                   metadata,
+                   CAST(ROUND(unixepoch(expires_at, 'subsec') * 1000) AS INTEGER),
+                   source_title, mask_text, selection, compression, storage
+            FROM clipboard_entries;
+
+            DROP TABLE clipboard_entries;
+            ALTER TABLE clipboard_entries_new RENAME TO clipboard_entries;
+
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_entries_hash
+                ON clipboard_entries(content_hash);
+
+            CREATE INDEX IF NOT EXISTS idx_entries_created
+                ON clipboard_entries(created_at DESC);
+
+            CREATE INDEX IF NOT EXISTS idx_entries_expires
+                ON clipboard_entries(expires_at)
+                WHERE expires_at IS NOT NULL;",
+        ),
+        // Downscaled PNG of large images, returned by preview queries.
+        M::up("ALTER TABLE clipboard_entries ADD COLUMN thumbnail BLOB;"),
+        // 64-bit dHash of images for near-duplicate detection.
+        M::up("ALTER TABLE clipboard_entries ADD COLUMN perceptual_hash INTEGER;"),
+        // One row per time an entry was copied; entries keep the latest copy in
+        // created_at. Existing entries get one event of unknown origin.
+        M::up(
+            "CREATE TABLE copy_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                entry_id INTEGER NOT NULL,
+                created_at INTEGER NOT NULL DEFAULT (CAST(unixepoch('subsec') * 1000 AS INTEGER)),
+                selection TEXT CHECK(selection IN ('clipboard', 'primary', 'both', 'cli')),
+                source_app TEXT,
+                source_title TEXT,
+                origin TEXT CHECK(origin IN ('watch', 'copy', 'select', 'history'))
+            );
+
+            CREATE INDEX idx_copy_events_entry ON copy_events(entry_id, created_at);
+            CREATE INDEX idx_copy_events_created ON copy_events(created_at);
+
+            CREATE TRIGGER copy_events_delete AFTER DELETE ON clipboard_entries
+            BEGIN
+                DELETE FROM copy_events WHERE entry_id = OLD.id;
+            END;
+
+            INSERT INTO copy_events (entry_id, created_at, selection, source_app, source_title)
+            SELECT id, created_at, selection, source_app, source_title FROM clipboard_entries;",
+        ),
+        // How often and when an entry was last selected from history, for
+        // frecency ordering; counted from the events recorded so far.
+        M::up(
+            "ALTER TABLE clipboard_entries ADD COLUMN use_count INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE clipboard_entries ADD COLUMN last_used_at INTEGER;
+
+            UPDATE clipboard_entries SET
+                use_count = (SELECT COUNT(*) FROM copy_events
+                             WHERE entry_id = clipboard_entries.id AND origin IN ('select', 'history')),
+                last_used_at = (SELECT MAX(created_at) FROM copy_events
+                                WHERE entry_id = clipboard_entries.id AND origin IN ('select', 'history'));",
+        ),
+        // One row per action rule match in `clio watch`, for `clio stats`.
+        // Kept when the entry is deleted: TTL rules mostly match entries that
+        // expire soon after.
+        M::up(
+            "CREATE TABLE rule_matches (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                rule TEXT NOT NULL,
+                created_at INTEGER NOT NULL DEFAULT (CAST(unixepoch('subsec') * 1000 AS INTEGER))
+            );
+
+            CREATE INDEX idx_rule_matches_created ON rule_matches(created_at);",
+        ),
+        // User tags; an entry's tags go with it when it is deleted.
+        M::up(
+            "CREATE TABLE tags (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE
+            );
+
+            CREATE TABLE entry_tags (
+                entry_id INTEGER NOT NULL,
+                tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+                PRIMARY KEY (entry_id, tag_id)
+            ) WITHOUT ROWID;
+
+            CREATE INDEX idx_entry_tags_tag ON entry_tags(tag_id);
+
+            CREATE TRIGGER entry_tags_delete AFTER DELETE ON clipboard_entries
+            BEGIN
+                DELETE FROM entry_tags WHERE entry_id = OLD.id;
+            END;",
+        ),
+        // Named snippets, kept apart from the history: retention never prunes them.
+        M::up(
+            "CREATE TABLE snippets (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                body TEXT NOT NULL,
+                updated_at INTEGER NOT NULL
+            );",
+        ),
+        // User-set title and note of an entry; NULL when none was given.
+        M::up(
+            "ALTER TABLE clipboard_entries ADD COLUMN title TEXT;
+             ALTER TABLE clipboard_entries ADD COLUMN note TEXT;",
+        ),
+        // Pinned entries are never pruned by retention.
+        M::up("ALTER TABLE clipboard_entries ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;"),
+        // Images from before pixel hashing are hashed by their PNG bytes, so a
+        // new copy of the same image would not deduplicate against them.
+        M::up_with_hook("", |tx| {
+            maintenance::rehash_images(tx).map(drop).map_err(|e| HookError::Hook(e.to_string()))
+        }),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_become_epoch_millis() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrations().to_version(&mut conn, 8).unwrap();
+        conn.execute(
+            "INSERT INTO clipboard_entries (content_type, text_content, content_hash, created_at, expires_at)
+             VALUES ('text', 'a', x'01', '2024-05-01T12:30:00.250', '2024-05-02T00:00:00.000'),
+                    ('text', 'b', x'02', '1970-01-01T00:00:01.000', NULL)",
+            [],
+        )
+        .unwrap();
+
+        run_migrations(&mut conn).unwrap();
+
+        let rows: Vec<(i64, Option<i64>)> = conn
+            .prepare("SELECT created_at, expires_at FROM clipboard_entries ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(rows, [(1_714_566_600_250, Some(1_714_608_000_000)), (1_000, None)]);
+
+        let indexes: i64 = conn
+            .query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name LIKE 'idx_entries_%'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(indexes, 3);
+    }
+
+    #[test]
+    fn default_created_at_is_now() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn).unwrap();
+        conn.execute("INSERT INTO clipboard_entries (content_type, content_hash) VALUES ('text', x'01')", [])
+            .unwrap();
+        let created: i64 = conn.query_row("SELECT created_at FROM clipboard_entries", [], |row| row.get(0)).unwrap();
+        let now = chrono::Utc::now().timestamp_millis();
+        assert!((now - created).abs() < 5_000, "{created} vs {now}");
+    }
+
+    #[test]
+    fn existing_entries_get_one_copy_event() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrations().to_version(&mut conn, 11).unwrap();
+        conn.execute(
+            "INSERT INTO clipboard_entries (content_type, text_content, content_hash, created_at, source_app)
+             VALUES ('text', 'a', x'01', 1000, 'kitty'), ('text', 'b', x'02', 2000, NULL)",
+            [],
+        )
+        .unwrap();
+
+        run_migrations(&mut conn).unwrap();
+
+        let events: Vec<(i64, i64, Option<String>, Option<String>)> = conn
+            .prepare("SELECT entry_id, created_at, source_app, origin FROM copy_events ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(events, [(1, 1000, Some("kitty".to_owned()), None), (2, 2000, None, None)]);
+
+        conn.execute("DELETE FROM clipboard_entries WHERE id = 1", []).unwrap();
+        let left: i64 = conn.query_row("SELECT COUNT(*) FROM copy_events", [], |row| row.get(0)).unwrap();
+        assert_eq!(left, 1);
+    }
+
+    #[test]
+    fn use_counts_come_from_selections() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrations().to_version(&mut conn, 12).unwrap();
+        conn.execute_batch(
+            "INSERT INTO clipboard_entries (content_type, text_content, content_hash, created_at)
+             VALUES ('text', 'a', x'01', 1000), ('text', 'b', x'02', 2000);
+             INSERT INTO copy_events (entry_id, created_at, origin)
+             VALUES (1, 3000, 'select'), (1, 4000, 'history'), (1, 5000, 'watch'), (2, 6000, 'copy');",
+        )
+        .unwrap();
+
+        run_migrations(&mut conn).unwrap();
+
+        let uses: Vec<(i64, Option<i64>)> = conn
+            .prepare("SELECT use_count, last_used_at FROM clipboard_entries ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(uses, [(2, Some(4000)), (0, None)]);
+    }
 }
@@ -1,5 +1,15 @@
+pub mod blob_store;
+pub(crate) mod compression;
+pub mod copy_events;
+pub mod maintenance;
 pub mod migrations;
+pub mod near_duplicates;
 pub mod repository;
+pub mod retention;
+pub mod snippets;
+pub mod tags;
+pub mod thumbnails;
+pub mod usage;
 
 use std::path::Path;
 
@@ -31,6 +41,7 @@ pub fn init_db(path: &Path) -> Result<Connection> {
     conn.pragma_update(None, "busy_timeout", BUSY_TIMEOUT_CLI_MS)?;
     conn.pragma_update(None, "foreign_keys", "ON")?;
     migrations::run_migrations(&mut conn)?;
+    register_functions(&conn)?;
     Ok(conn)
 }
 
@@ -42,13 +53,21 @@ pub fn init_db_ui(path: &Path) -> Result<Connection> {
     conn.pragma_update(None, "synchronous", "NORMAL")?;
     conn.pragma_update(None, "busy_timeout", BUSY_TIMEOUT_UI_MS)?;
     conn.pragma_update(None, "mmap_size", MMAP_SIZE_UI)?;
+    register_functions(&conn)?;
     Ok(conn)
 }
 
+/// SQL functions the repository queries rely on.
+fn register_functions(conn: &Connection) -> Result<()> {
+    compression::register_functions(conn)?;
+    blob_store::register_functions(conn)
+}
+
 #[cfg(test)]
 pub fn init_db_in_memory() -> Result<Connection> {
     let mut conn = Connection::open_in_memory()?;
     conn.pragma_update(None, "foreign_keys", "ON")?;
     migrations::run_migrations(&mut conn)?;
+    register_functions(&conn)?;
     Ok(conn)
 }
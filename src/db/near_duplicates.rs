@@ -0,0 +1,301 @@
+//! Near-duplicate images, found by comparing perceptual hashes.
+//!
+//! Exact duplicates share a `content_hash` and are merged on save. Images
+//! that look the same but differ in a few pixels (successive screenshots of
+//! one window, an image re-encoded by another app) only have close
+//! `perceptual_hash` values: with `image_dedupe_distance` set, a new image
+//! replaces older ones within that distance, and `clio dedupe --images`
+//! cleans up existing history. The kept image takes over what the user added
+//! to the ones it replaces: copies, use counts, tags, title, note and pin.
+
+use std::collections::HashMap;
+
+use rusqlite::{params, Connection};
+
+use super::repository::{self, IMAGE_SQL};
+use crate::errors::Result;
+use crate::models::images;
+
+/// `clio dedupe --images` distance when neither `--distance` nor
+/// `image_dedupe_distance` is set.
+pub const DEFAULT_DISTANCE: u32 = 5;
+
+/// Perceptual hash of an image as stored in the `perceptual_hash` column,
+/// or `None` if the image cannot be decoded.
+pub(crate) fn column_value(image: &[u8]) -> Option<i64> {
+    images::perceptual_hash(image)
+        .inspect_err(|e| log::warn!("cannot compute perceptual hash: {e}"))
+        .ok()
+        // Stored bit for bit; SQLite integers are signed.
+        .map(|hash| hash as i64)
+}
+
+/// Image entries other than `id` whose perceptual hash is within `distance`
+/// of `id`'s. Empty if `id` is not an image or has no perceptual hash.
+pub fn similar_to(conn: &Connection, id: i64, distance: u32) -> Result<Vec<i64>> {
+    let hash: Option<i64> = conn
+        .query_row("SELECT perceptual_hash FROM clipboard_entries WHERE id = ?1", params![id], |row| row.get(0))?;
+    let Some(hash) = hash else {
+        return Ok(Vec::new());
+    };
+    Ok(hashed_images(conn)?
+        .into_iter()
+        .filter(|&(other, other_hash)| other != id && images::hash_distance(hash as u64, other_hash as u64) <= distance)
+        .map(|(other, _)| other)
+        .collect())
+}
+
+/// Delete the images `similar_to` the newly saved entry `id`, which takes
+/// their place. Returns how many were deleted.
+pub fn replace_similar(conn: &Connection, id: i64, distance: u32) -> Result<usize> {
+    let similar = similar_to(conn, id, distance)?;
+    for &older in &similar {
+        log::debug!("entry {id} replaces near-duplicate image {older}");
+        merge_into(conn, older, id)?;
+    }
+    Ok(similar.len())
+}
+
+/// Delete entry `older` in favour of `kept`, which takes over its copies,
+/// use count, tags and pin, and its title and note unless it has its own.
+pub fn merge_into(conn: &Connection, older: i64, kept: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE clipboard_entries AS e SET
+            use_count = e.use_count + old.use_count,
+            last_used_at = NULLIF(MAX(COALESCE(e.last_used_at, 0), COALESCE(old.last_used_at, 0)), 0),
+            title = COALESCE(e.title, old.title),
+            note = COALESCE(e.note, old.note),
+            pinned = e.pinned OR old.pinned,
+            expires_at = CASE WHEN old.pinned THEN NULL ELSE e.expires_at END
+         FROM (SELECT use_count, last_used_at, title, note, pinned FROM clipboard_entries WHERE id = ?1) AS old
+         WHERE e.id = ?2",
+        params![older, kept],
+    )?;
+    conn.execute("UPDATE copy_events SET entry_id = ?2 WHERE entry_id = ?1", params![older, kept])?;
+    conn.execute(
+        "INSERT OR IGNORE INTO entry_tags (entry_id, tag_id) SELECT ?2, tag_id FROM entry_tags WHERE entry_id = ?1",
+        params![older, kept],
+    )?;
+    repository::delete_entry(conn, older)
+}
+
+/// An image that looks like a newer one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NearDuplicate {
+    pub id: i64,
+    /// The newer image it duplicates, which is kept.
+    pub kept: i64,
+    pub distance: u32,
+}
+
+/// Group images by perceptual hash and return every image within `distance`
+/// of a newer one; the newest image of each group is kept. Images without a
+/// stored hash use theirs from `missing` (see `missing_hashes`), if any.
+pub fn find_all(conn: &Connection, distance: u32, missing: &HashMap<i64, i64>) -> Result<Vec<NearDuplicate>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, perceptual_hash FROM clipboard_entries WHERE content_type = 'image'
+         ORDER BY created_at DESC, id DESC",
+    )?;
+    let images = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<i64>>(1)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut kept: Vec<(i64, u64)> = Vec::new();
+    let mut duplicates = Vec::new();
+    for (id, hash) in images {
+        let Some(hash) = hash.or_else(|| missing.get(&id).copied()) else {
+            continue;
+        };
+        let hash = hash as u64;
+        let closest = kept
+            .iter()
+            .map(|&(kept_id, kept_hash)| (kept_id, images::hash_distance(hash, kept_hash)))
+            .filter(|&(_, d)| d <= distance)
+            .min_by_key(|&(_, d)| d);
+        match closest {
+            Some((kept_id, d)) => duplicates.push(NearDuplicate { id, kept: kept_id, distance: d }),
+            None => kept.push((id, hash)),
+        }
+    }
+    Ok(duplicates)
+}
+
+/// Compute the perceptual hash of images stored before it existed, without
+/// storing it (see `store_hashes`). Also returns the IDs of images that could
+/// not be decoded.
+pub fn missing_hashes(conn: &Connection) -> Result<(HashMap<i64, i64>, Vec<i64>)> {
+    let ids = conn
+        .prepare("SELECT id FROM clipboard_entries WHERE content_type = 'image' AND perceptual_hash IS NULL ORDER BY id")?
+        .query_map([], |row| row.get::<_, i64>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut image = conn.prepare(&format!("SELECT {IMAGE_SQL} FROM clipboard_entries WHERE id = ?1"))?;
+    let mut hashes = HashMap::new();
+    let mut failed = Vec::new();
+    for id in ids {
+        let bytes: Vec<u8> = image.query_row(params![id], |row| row.get(0))?;
+        match column_value(&bytes) {
+            Some(hash) => {
+                hashes.insert(id, hash);
+            }
+            None => failed.push(id),
+        }
+    }
+    Ok((hashes, failed))
+}
+
+/// Store perceptual hashes computed by `missing_hashes`.
+pub fn store_hashes(conn: &Connection, hashes: &HashMap<i64, i64>) -> Result<()> {
+    let mut stmt = conn.prepare("UPDATE clipboard_entries SET perceptual_hash = ?2 WHERE id = ?1")?;
+    for (id, hash) in hashes {
+        stmt.execute(params![id, hash])?;
+    }
+    Ok(())
+}
+
+/// Images with a perceptual hash, newest first.
+fn hashed_images(conn: &Connection) -> Result<Vec<(i64, i64)>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, perceptual_hash FROM clipboard_entries
+         WHERE content_type = 'image' AND perceptual_hash IS NOT NULL
+         ORDER BY created_at DESC, id DESC",
+    )?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::repository::{insert_entry, save_entry, Annotation, StorageOptions};
+    use crate::db::{copy_events, init_db_in_memory, tags};
+    use crate::db::retention::RetentionPolicy;
+    use crate::models::entry::{ClipboardEntry, CopyOrigin};
+
+    /// 64x64 grayscale pattern; `phase` shifts it sideways.
+    fn image(phase: f32, mark: bool) -> ClipboardEntry {
+        let rgba = (0..64 * 64)
+            .flat_map(|i| {
+                let (x, y) = ((i % 64) as f32, (i / 64) as f32);
+                let v = (128.0 + 100.0 * ((x + phase) / 5.0).sin() * (y / 9.0).cos()) as u8;
+                let v = if mark && x < 4.0 && y < 4.0 { 255 } else { v };
+                [v, v, v, 255]
+            })
+            .collect();
+        ClipboardEntry::from_image(64, 64, rgba, None).unwrap()
+    }
+
+    /// A screenshot, optionally with a small change in one corner.
+    fn screenshot(mark: bool) -> ClipboardEntry {
+        image(0.0, mark)
+    }
+
+    fn different_image() -> ClipboardEntry {
+        image(15.0, false)
+    }
+
+    fn ids(conn: &Connection) -> Vec<i64> {
+        conn.prepare("SELECT id FROM clipboard_entries ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn insert_stores_perceptual_hash() {
+        let conn = init_db_in_memory().unwrap();
+        insert_entry(&conn, &screenshot(false)).unwrap();
+        insert_entry(&conn, &ClipboardEntry::from_text("text".to_owned(), None)).unwrap();
+        let hashed: i64 =
+            conn.query_row("SELECT COUNT(perceptual_hash) FROM clipboard_entries", [], |row| row.get(0)).unwrap();
+        assert_eq!(hashed, 1);
+    }
+
+    #[test]
+    fn save_replaces_near_duplicates() {
+        let conn = init_db_in_memory().unwrap();
+        let policy = RetentionPolicy::max_history(10);
+        let options = StorageOptions { image_dedupe_distance: Some(DEFAULT_DISTANCE), ..StorageOptions::default() };
+        save_entry(&conn, &screenshot(false), &policy, &options, CopyOrigin::Watch).unwrap();
+        let other = save_entry(&conn, &different_image(), &policy, &options, CopyOrigin::Watch).unwrap().id;
+        let newest = save_entry(&conn, &screenshot(true), &policy, &options, CopyOrigin::Watch).unwrap();
+
+        assert!(newest.inserted);
+        assert_eq!(ids(&conn), [other, newest.id]);
+    }
+
+    #[test]
+    fn replacement_keeps_tags_annotations_and_uses() {
+        let conn = init_db_in_memory().unwrap();
+        let policy = RetentionPolicy::max_history(10);
+        let options = StorageOptions { image_dedupe_distance: Some(DEFAULT_DISTANCE), ..StorageOptions::default() };
+        let older = save_entry(&conn, &screenshot(false), &policy, &options, CopyOrigin::Watch).unwrap().id;
+        tags::add(&conn, older, "design").unwrap();
+        let annotation = Annotation { title: Some("Login page".to_owned()), note: Some("before the redesign".to_owned()) };
+        repository::set_annotation(&conn, older, &annotation).unwrap();
+        repository::set_pinned(&conn, older, true).unwrap();
+        copy_events::record_use(&conn, older, CopyOrigin::Select).unwrap();
+
+        let newest = save_entry(&conn, &screenshot(true), &policy, &options, CopyOrigin::Watch).unwrap().id;
+        assert_eq!(ids(&conn), [newest]);
+        assert_eq!(tags::for_entry(&conn, newest).unwrap(), ["design"]);
+        assert_eq!(repository::get_annotation(&conn, newest).unwrap(), Some(annotation));
+        let (use_count, pinned, copies): (i64, bool, i64) = conn
+            .query_row(
+                "SELECT use_count, pinned, (SELECT COUNT(*) FROM copy_events WHERE entry_id = ?1)
+                 FROM clipboard_entries WHERE id = ?1",
+                [newest],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!((use_count, pinned, copies), (1, true, 3));
+    }
+
+    #[test]
+    fn save_keeps_near_duplicates_by_default() {
+        let conn = init_db_in_memory().unwrap();
+        let policy = RetentionPolicy::max_history(10);
+        save_entry(&conn, &screenshot(false), &policy, &StorageOptions::default(), CopyOrigin::Watch).unwrap();
+        save_entry(&conn, &screenshot(true), &policy, &StorageOptions::default(), CopyOrigin::Watch).unwrap();
+        assert_eq!(ids(&conn).len(), 2);
+    }
+
+    #[test]
+    fn find_all_keeps_newest_of_each_group() {
+        let conn = init_db_in_memory().unwrap();
+        let first = insert_entry(&conn, &screenshot(false)).unwrap();
+        let other = insert_entry(&conn, &different_image()).unwrap();
+        let second = insert_entry(&conn, &screenshot(true)).unwrap();
+        let newest = insert_entry(&conn, &image(0.5, false)).unwrap();
+
+        let duplicates = find_all(&conn, DEFAULT_DISTANCE, &HashMap::new()).unwrap();
+        let found: Vec<_> = duplicates.iter().map(|d| (d.id, d.kept)).collect();
+        assert_eq!(found, [(second, newest), (first, newest)]);
+        assert!(!found.iter().any(|&(id, _)| id == other));
+    }
+
+    #[test]
+    fn old_images_are_hashed_in_memory_until_stored() {
+        let conn = init_db_in_memory().unwrap();
+        let id = insert_entry(&conn, &screenshot(false)).unwrap();
+        insert_entry(&conn, &screenshot(true)).unwrap();
+        conn.execute("UPDATE clipboard_entries SET perceptual_hash = NULL", []).unwrap();
+        conn.execute(
+            "INSERT INTO clipboard_entries (content_type, blob_content, content_hash) VALUES ('image', x'00', x'01')",
+            [],
+        )
+        .unwrap();
+
+        let (hashes, failed) = missing_hashes(&conn).unwrap();
+        assert_eq!(failed, [conn.last_insert_rowid()]);
+        assert_eq!(find_all(&conn, DEFAULT_DISTANCE, &hashes).unwrap().len(), 1);
+        assert!(similar_to(&conn, id, DEFAULT_DISTANCE).unwrap().is_empty());
+
+        store_hashes(&conn, &hashes).unwrap();
+        assert_eq!(similar_to(&conn, id, DEFAULT_DISTANCE).unwrap().len(), 1);
+    }
+}
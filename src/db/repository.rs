@@ -1,47 +1,195 @@
-use std::time::Duration;
-
-use chrono::{Duration as ChronoDuration, Utc};
-use rusqlite::{params, Connection};
-
+use rusqlite::{params, Connection, OptionalExtension, ToSql};
+
+use super::blob_store;
+use super::compression;
+use super::copy_events;
+use super::near_duplicates;
+use super::retention::{self, RetentionPolicy};
+use super::tags;
+use super::thumbnails;
+use crate::config::{Config, HistorySort};
 use crate::errors::{AppError, Result};
-use crate::models::entry::{ClipboardEntry, ContentHash, ContentType, EntryContent, Timestamp, TIMESTAMP_FORMAT};
+use crate::models::entry::{
+    ClipboardEntry, ContentHash, ContentType, CopyOrigin, EntryContent, FileList, Selection, Timestamp,
+};
 
 /// Column list for clipboard_entries SELECT queries.
 /// Positional indices in `row_to_entry` must match this order.
-const ENTRY_COLUMNS: &str = "id, content_type, text_content, blob_content, content_hash, source_app, source_title, created_at, metadata, expires_at, mask_text";
+/// Blobs kept in the blob store are read back through `clio_blob`.
+const ENTRY_COLUMNS: &str = "id, content_type, text_content, \
+     CASE WHEN storage IS NULL THEN blob_content ELSE clio_blob(content_hash) END, \
+     content_hash, source_app, source_title, created_at, metadata, expires_at, mask_text, selection, compression, storage, title, note, pinned";
+
+/// SQL expression for the full text of a row, decompressing or reading the
+/// blob store when `text_content` only holds a prefix.
+const FULL_TEXT_SQL: &str = "CASE \
+     WHEN compression IS NULL AND storage IS NULL THEN text_content \
+     WHEN compression IS NULL THEN CAST(clio_blob(content_hash) AS TEXT) \
+     WHEN storage IS NULL THEN clio_decompress(blob_content) \
+     ELSE clio_decompress(clio_blob(content_hash)) END";
+
+/// Condition matching the LIKE pattern `:pattern` against the full text,
+/// title or note of a row.
+fn search_condition() -> String {
+    format!(
+        "({FULL_TEXT_SQL} LIKE :pattern ESCAPE '\\' OR title LIKE :pattern ESCAPE '\\' \
+         OR note LIKE :pattern ESCAPE '\\')"
+    )
+}
+
+/// SQL expression for the bytes of an image row, inline or in the blob store.
+pub(crate) const IMAGE_SQL: &str = "CASE WHEN storage IS NULL THEN blob_content ELSE clio_blob(content_hash) END";
 
 /// Default JSON metadata for entries without explicit metadata.
 const DEFAULT_METADATA: &str = "{}";
 
 /// Build the SELECT column list for preview queries that truncate text_content.
-/// `text_param` is the SQL parameter placeholder for the preview length (e.g. "?3").
+/// `text_param` is the SQL parameter placeholder for the preview length (e.g. ":preview").
+/// Text that is compressed or in the blob store is previewed from its plain
+/// prefix, without reading the blob; images from their thumbnail, if any.
+/// An image whose blob store file is gone previews as empty bytes.
 fn preview_columns(text_param: &str) -> String {
     format!(
         "id, content_type, CASE WHEN content_type = 'text' THEN substr(text_content, 1, {text_param}) ELSE text_content END, \
-         blob_content, content_hash, source_app, source_title, created_at, metadata, expires_at, mask_text"
+         CASE WHEN content_type = 'text' THEN NULL WHEN thumbnail IS NOT NULL THEN thumbnail \
+              WHEN storage IS NULL THEN blob_content ELSE COALESCE(clio_blob(content_hash), X'') END, \
+         content_hash, source_app, source_title, created_at, metadata, expires_at, mask_text, selection, NULL, NULL, \
+         title, note, pinned"
     )
 }
 
+/// How new entries are stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StorageOptions {
+    /// Compress text longer than this many bytes.
+    pub compress_text_over: Option<usize>,
+    /// Keep content longer than this many bytes (after compression) in the blob store.
+    pub blob_store_over: Option<usize>,
+    /// Store a thumbnail of images larger than this many pixels (longest side).
+    pub thumbnail_max_px: Option<u32>,
+    /// A new image replaces older ones whose perceptual hash differs in at most this many bits.
+    pub image_dedupe_distance: Option<u32>,
+}
+
+impl StorageOptions {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            compress_text_over: config.compress_text_over_bytes(),
+            blob_store_over: config.blob_store_over_bytes(),
+            thumbnail_max_px: u32::try_from(config.image_preview_max_px).ok(),
+            image_dedupe_distance: config.image_dedupe_distance,
+        }
+    }
+}
+
+/// Which entries a listing includes; the default includes all.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntryFilter {
+    /// Only entries from these selections (see `Selection::matching`).
+    pub selections: Vec<Selection>,
+    /// Only entries with at least one of these tags.
+    pub tags: Vec<String>,
+}
+
+impl EntryFilter {
+    /// SQL condition for the filter; uses `:tags` when filtering by tags.
+    fn condition(&self) -> String {
+        let selections = selection_condition(&self.selections);
+        if self.tags.is_empty() {
+            selections
+        } else {
+            format!("{selections} AND {}", tags::FILTER_SQL)
+        }
+    }
+}
+
+/// SQL condition restricting rows to the given selection filters
+/// (see `Selection::matching`). An empty filter matches every row.
+fn selection_condition(selections: &[Selection]) -> String {
+    if selections.is_empty() {
+        return "1".to_owned();
+    }
+    let mut values: Vec<&str> = selections
+        .iter()
+        .flat_map(|s| s.matching())
+        .map(Selection::as_str)
+        .collect();
+    values.sort_unstable();
+    values.dedup();
+    // Values come from a closed enum, so inlining them is safe.
+    let list = values.iter().map(|v| format!("'{v}'")).collect::<Vec<_>>().join(", ");
+    format!("selection IN ({list})")
+}
+
 /// Escape special LIKE characters (`%`, `_`, `\`) for safe use in SQL LIKE patterns.
 fn escape_like(query: &str) -> String {
     query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
 }
 
 pub fn insert_entry(conn: &Connection, entry: &ClipboardEntry) -> Result<i64> {
+    insert_entry_with(conn, entry, &StorageOptions::default())
+}
+
+/// Insert `entry`, compressing large text and moving large content to the
+/// blob store as `options` ask. Text stored either way keeps a plain prefix
+/// in `text_content` for previews.
+fn insert_entry_with(conn: &Connection, entry: &ClipboardEntry, options: &StorageOptions) -> Result<i64> {
+    let compressed = match (entry.content(), options.compress_text_over) {
+        (EntryContent::Text(text), Some(min)) if text.len() > min => compression::compress(text)?,
+        _ => None,
+    };
+    let payload: Option<&[u8]> = match entry.content() {
+        EntryContent::Text(text) => Some(compressed.as_deref().unwrap_or(text.as_bytes())),
+        EntryContent::Image(bytes) => Some(bytes),
+        EntryContent::Files(_) => None,
+    };
+    let external = match (payload, options.blob_store_over) {
+        (Some(bytes), Some(min)) if bytes.len() > min => {
+            blob_store::write(conn, entry.content_hash(), bytes, compressed.is_some().then_some(compression::ZSTD))?;
+            true
+        }
+        _ => false,
+    };
+
+    let text_column = match entry.content() {
+        EntryContent::Text(text) if compressed.is_some() || external => Some(compression::prefix(text).to_owned()),
+        content => content.text_column(),
+    };
+    let blob = match (&compressed, external) {
+        (_, true) => None,
+        (Some(compressed), false) => Some(compressed.as_slice()),
+        (None, false) => entry.content().blob(),
+    };
+    let compression = compressed.is_some().then_some(compression::ZSTD);
+    let storage = external.then_some(blob_store::FILE);
+    let thumbnail = match (entry.content(), options.thumbnail_max_px) {
+        (EntryContent::Image(bytes), Some(max_px)) => thumbnails::create(bytes, max_px)
+            .inspect_err(|e| log::warn!("cannot create thumbnail: {e}"))
+            .ok()
+            .flatten(),
+        _ => None,
+    };
+    let perceptual_hash = entry.content().blob().and_then(near_duplicates::column_value);
+
     let mut stmt = conn.prepare_cached(
-        "INSERT INTO clipboard_entries (content_type, text_content, blob_content, content_hash, source_app, source_title, metadata, expires_at, mask_text)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        "INSERT INTO clipboard_entries (content_type, text_content, blob_content, content_hash, source_app, source_title, metadata, expires_at, mask_text, selection, compression, storage, thumbnail, perceptual_hash)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
     )?;
     stmt.execute(params![
         entry.content().content_type().as_str(),
-        entry.content().text(),
-        entry.content().blob(),
+        text_column,
+        blob,
         entry.content_hash() as &[u8],
         entry.source_app(),
         entry.source_title(),
         entry.metadata().unwrap_or(DEFAULT_METADATA),
         entry.expires_at(),
         entry.mask_text(),
+        entry.selection(),
+        compression,
+        storage,
+        thumbnail,
+        perceptual_hash,
     ])?;
     Ok(conn.last_insert_rowid())
 }
@@ -56,6 +204,22 @@ pub fn find_by_hash(conn: &Connection, hash: &ContentHash) -> Result<Option<Clip
     }
 }
 
+/// ID and selection of the entry with `hash`, without reading its content.
+fn find_duplicate(conn: &Connection, hash: &ContentHash) -> Result<Option<(i64, Option<Selection>)>> {
+    let found = conn
+        .prepare_cached("SELECT id, selection FROM clipboard_entries WHERE content_hash = ?1")?
+        .query_row(params![hash as &[u8]], |row| Ok((row.get(0)?, row.get(1)?)))
+        .optional()?;
+    Ok(found)
+}
+
+pub fn hash_exists(conn: &Connection, hash: &ContentHash) -> Result<bool> {
+    let exists = conn
+        .prepare_cached("SELECT EXISTS(SELECT 1 FROM clipboard_entries WHERE content_hash = ?1)")?
+        .query_row(params![hash.as_slice()], |row| row.get(0))?;
+    Ok(exists)
+}
+
 pub fn find_expires_at(conn: &Connection, hash: &ContentHash) -> Result<Option<Option<Timestamp>>> {
     let mut stmt = conn.prepare_cached(
         "SELECT expires_at FROM clipboard_entries WHERE content_hash = ?1"
@@ -73,18 +237,21 @@ pub fn update_timestamp_and_expiry(
     expires_at: Option<&Timestamp>,
 ) -> Result<()> {
     conn.execute(
-        "UPDATE clipboard_entries SET created_at = strftime('%Y-%m-%dT%H:%M:%f', 'now'), expires_at = ?2 WHERE id = ?1",
-        params![id, expires_at],
+        "UPDATE clipboard_entries SET created_at = ?3, expires_at = CASE WHEN pinned THEN NULL ELSE ?2 END
+         WHERE id = ?1",
+        params![id, expires_at, Timestamp::now()],
     )?;
     Ok(())
 }
 
-/// Update entry on dedup: refresh timestamp, COALESCE all optional fields.
+/// Update entry on dedup: move `created_at` to the latest copy (earlier ones
+/// are in `copy_events`), COALESCE all optional fields.
 /// `None` means "keep existing value" (SQL COALESCE returns the first non-NULL).
 /// NOTE: This means existing values cannot be cleared to NULL via dedup.
 /// For example, an entry with `expires_at` set by `clio copy --ttl` will keep
 /// that TTL even when re-copied without `--ttl`. This is intentional: the watch
 /// daemon should not silently remove user-configured TTL on re-detection.
+/// A pinned entry keeps no TTL, whatever the new copy brings.
 fn update_on_dedup(
     conn: &Connection,
     id: i64,
@@ -92,16 +259,18 @@ fn update_on_dedup(
     source_app: Option<&str>,
     source_title: Option<&str>,
     mask_text: Option<&str>,
+    selection: Option<Selection>,
 ) -> Result<()> {
     conn.execute(
         "UPDATE clipboard_entries
-         SET created_at = strftime('%Y-%m-%dT%H:%M:%f', 'now'),
-             expires_at = COALESCE(?2, expires_at),
+         SET created_at = ?7,
+             expires_at = CASE WHEN pinned THEN NULL ELSE COALESCE(?2, expires_at) END,
              source_app = COALESCE(?3, source_app),
              source_title = COALESCE(?4, source_title),
-             mask_text = COALESCE(?5, mask_text)
+             mask_text = COALESCE(?5, mask_text),
+             selection = COALESCE(?6, selection)
          WHERE id = ?1",
-        params![id, expires_at, source_app, source_title, mask_text],
+        params![id, expires_at, source_app, source_title, mask_text, selection, Timestamp::now()],
     )?;
     Ok(())
 }
@@ -114,66 +283,153 @@ pub fn list_entries(conn: &Connection, limit: usize) -> Result<Vec<ClipboardEntr
     collect_entries(rows)
 }
 
-#[cfg(test)]
-pub fn list_entries_page(
+/// Chronological order of history listings, newest first.
+const HISTORY_ORDER: &str = "ORDER BY created_at DESC, id DESC";
+
+/// Frecency of a row at `:now`: one plus the times it was selected, divided
+/// by one plus the days since it was last copied or selected. Scaled by 1000
+/// to an integer so that cursors compare it exactly.
+const FRECENCY_SQL: &str = "(use_count + 1) * 86400000000 \
+     / (86400000 + MAX(:now - MAX(created_at, COALESCE(last_used_at, 0)), 0))";
+
+/// Position in a history listing to continue after. Opaque: obtained from
+/// `Page::next` and passed back to fetch the following page of the same
+/// listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    created_at: Timestamp,
+    id: i64,
+    /// In frecency listings, the entry's score and the time all scores of
+    /// the listing are computed at, so later pages rank consistently.
+    frecency: Option<(i64, Timestamp)>,
+}
+
+impl Cursor {
+    fn after(entry: &ClipboardEntry, frecency: Option<(i64, Timestamp)>) -> Option<Self> {
+        Some(Self { created_at: entry.created_at?, id: entry.id?, frecency })
+    }
+}
+
+/// One page of a history listing.
+#[derive(Debug, Default)]
+pub struct Page {
+    pub entries: Vec<ClipboardEntry>,
+    /// Where the next page starts; `None` once the listing is exhausted.
+    pub next: Option<Cursor>,
+}
+
+/// Page query selecting `columns` of the rows matching `filter` in `sort`
+/// order, after `after`. Frecency queries add the score as a last column.
+/// The keyset condition keeps pages from skipping or repeating entries:
+/// rows inserted or bumped meanwhile sort before the cursor.
+fn page_sql(columns: &str, filter: &str, sort: HistorySort, after: Option<&Cursor>) -> String {
+    match sort {
+        HistorySort::Chronological => {
+            let after = match after {
+                Some(_) => "(created_at, id) < (:after_created, :after_id)",
+                None => "1",
+            };
+            format!("SELECT {columns} FROM clipboard_entries WHERE {filter} AND {after} {HISTORY_ORDER} LIMIT :limit")
+        }
+        HistorySort::Frecency => {
+            let after = match after {
+                Some(_) => format!("({FRECENCY_SQL}, created_at, id) < (:after_score, :after_created, :after_id)"),
+                None => "1".to_owned(),
+            };
+            format!(
+                "SELECT {columns}, {FRECENCY_SQL} FROM clipboard_entries WHERE {filter} AND {after} \
+                 ORDER BY {FRECENCY_SQL} DESC, created_at DESC, id DESC LIMIT :limit"
+            )
+        }
+    }
+}
+
+/// Run a `page_sql` query; `params` are those used by its columns and filter.
+fn query_page(
     conn: &Connection,
+    sql: &str,
+    params: &[(&str, &dyn ToSql)],
     limit: usize,
-    offset: usize,
-) -> Result<Vec<ClipboardEntry>> {
-    let sql = format!("SELECT {ENTRY_COLUMNS} FROM clipboard_entries ORDER BY created_at DESC LIMIT ?1 OFFSET ?2");
-    let mut stmt = conn.prepare_cached(&sql)?;
-    let rows = stmt.query_map(params![limit as i64, offset as i64], row_to_entry)?;
-    collect_entries(rows)
+    sort: HistorySort,
+    after: Option<&Cursor>,
+) -> Result<Page> {
+    let limit_param = limit as i64;
+    let now = after.and_then(|cursor| cursor.frecency).map_or_else(Timestamp::now, |(_, now)| now);
+    let mut all: Vec<(&str, &dyn ToSql)> = params.to_vec();
+    all.push((":limit", &limit_param));
+    if sort == HistorySort::Frecency {
+        all.push((":now", &now));
+    }
+    if let Some(cursor) = after {
+        all.push((":after_created", &cursor.created_at));
+        all.push((":after_id", &cursor.id));
+        if let Some((score, _)) = &cursor.frecency {
+            all.push((":after_score", score));
+        }
+    }
+    let mut stmt = conn.prepare_cached(sql)?;
+    let rows = stmt.query_map(all.as_slice(), |row| {
+        let score = match sort {
+            HistorySort::Chronological => None,
+            HistorySort::Frecency => Some(row.get::<_, i64>(col::FRECENCY)?),
+        };
+        Ok((row_to_entry(row)?, score))
+    })?;
+    let rows = rows.collect::<std::result::Result<Vec<_>, _>>()?;
+    let next = match rows.last() {
+        Some((entry, score)) if rows.len() == limit => Cursor::after(entry, score.map(|score| (score, now))),
+        _ => None,
+    };
+    Ok(Page { entries: rows.into_iter().map(|(entry, _)| entry).collect(), next })
+}
+
+#[cfg(test)]
+pub fn list_entries_page(conn: &Connection, limit: usize, after: Option<&Cursor>) -> Result<Page> {
+    let sql = page_sql(ENTRY_COLUMNS, "1", HistorySort::Chronological, after);
+    query_page(conn, &sql, &[], limit, HistorySort::Chronological, after)
 }
 
-/// Like `list_entries_page`, but truncates `text_content` to `preview_chars`
-/// characters in SQL to avoid transferring large blobs for UI preview.
+/// Like `list_entries_page`, but in `sort` order and with `text_content`
+/// truncated to `preview_chars` characters in SQL to avoid transferring
+/// large blobs for UI preview. Only entries matching `filter` are returned.
 ///
 /// **Note:** returned entries contain truncated text but the original `content_hash`
 /// (computed from the full text). Do not use the hash for content comparison.
 pub fn list_entries_preview(
     conn: &Connection,
     limit: usize,
-    offset: usize,
+    after: Option<&Cursor>,
     preview_chars: usize,
-) -> Result<Vec<ClipboardEntry>> {
-    let sql = format!(
-        "SELECT {} FROM clipboard_entries ORDER BY created_at DESC LIMIT ?1 OFFSET ?2",
-        preview_columns("?3")
-    );
-    let mut stmt = conn.prepare_cached(&sql)?;
-    let rows = stmt.query_map(
-        params![limit as i64, offset as i64, preview_chars as i64],
-        row_to_entry,
-    )?;
-    let mut entries = collect_entries(rows)?;
+    filter: &EntryFilter,
+    sort: HistorySort,
+) -> Result<Page> {
+    let sql = page_sql(&preview_columns(":preview"), &filter.condition(), sort, after);
+    let preview = preview_chars as i64;
+    let tags = tags::filter_param(&filter.tags);
+    let mut params: Vec<(&str, &dyn ToSql)> = vec![(":preview", &preview)];
+    if !filter.tags.is_empty() {
+        params.push((":tags", &tags));
+    }
+    let mut page = query_page(conn, &sql, &params, limit, sort, after)?;
     // Clear hash — preview content is truncated, hash would be misleading.
-    for entry in &mut entries {
+    for entry in &mut page.entries {
         entry.content_hash = [0; 32];
     }
-    Ok(entries)
+    Ok(page)
 }
 
 #[cfg(test)]
-pub fn search_entries_page(
-    conn: &Connection,
-    query: &str,
-    limit: usize,
-    offset: usize,
-) -> Result<Vec<ClipboardEntry>> {
+pub fn search_entries_page(conn: &Connection, query: &str, limit: usize, after: Option<&Cursor>) -> Result<Page> {
     let pattern = format!("%{}%", escape_like(query));
-    let sql = format!(
-        "SELECT {ENTRY_COLUMNS} FROM clipboard_entries WHERE text_content LIKE ?1 ESCAPE '\\' ORDER BY created_at DESC LIMIT ?2 OFFSET ?3"
-    );
-    let mut stmt = conn.prepare_cached(&sql)?;
-    let rows = stmt.query_map(params![pattern, limit as i64, offset as i64], row_to_entry)?;
-    collect_entries(rows)
+    let sql = page_sql(ENTRY_COLUMNS, &search_condition(), HistorySort::Chronological, after);
+    query_page(conn, &sql, &[(":pattern", &pattern)], limit, HistorySort::Chronological, after)
 }
 
-/// Like `search_entries_page`, but truncates `text_content` to `preview_chars`
-/// characters in SQL to avoid transferring large blobs for UI preview.
-/// LIKE still matches against the full `text_content`, only the returned column
-/// is truncated.
+/// Like `search_entries_page`, but in `sort` order and with `text_content`
+/// truncated to `preview_chars` characters in SQL to avoid transferring
+/// large blobs for UI preview.
+/// LIKE still matches against the full text (decompressed if needed), title
+/// and note; only the returned column is truncated. Only entries matching `filter` are returned.
 ///
 /// **Note:** returned entries contain truncated text but the original `content_hash`
 /// (computed from the full text). Do not use the hash for content comparison.
@@ -181,30 +437,78 @@ pub fn search_entries_preview(
     conn: &Connection,
     query: &str,
     limit: usize,
-    offset: usize,
+    after: Option<&Cursor>,
     preview_chars: usize,
-) -> Result<Vec<ClipboardEntry>> {
+    filter: &EntryFilter,
+    sort: HistorySort,
+) -> Result<Page> {
     let pattern = format!("%{}%", escape_like(query));
-    let sql = format!(
-        "SELECT {} FROM clipboard_entries WHERE text_content LIKE ?1 ESCAPE '\\' ORDER BY created_at DESC LIMIT ?2 OFFSET ?3",
-        preview_columns("?4")
-    );
-    let mut stmt = conn.prepare_cached(&sql)?;
-    let rows = stmt.query_map(
-        params![pattern, limit as i64, offset as i64, preview_chars as i64],
-        row_to_entry,
-    )?;
-    let mut entries = collect_entries(rows)?;
+    let condition = format!("{} AND {}", search_condition(), filter.condition());
+    let sql = page_sql(&preview_columns(":preview"), &condition, sort, after);
+    let preview = preview_chars as i64;
+    let tags = tags::filter_param(&filter.tags);
+    let mut params: Vec<(&str, &dyn ToSql)> = vec![(":pattern", &pattern), (":preview", &preview)];
+    if !filter.tags.is_empty() {
+        params.push((":tags", &tags));
+    }
+    let mut page = query_page(conn, &sql, &params, limit, sort, after)?;
     // Clear hash — preview content is truncated, hash would be misleading.
-    for entry in &mut entries {
+    for entry in &mut page.entries {
         entry.content_hash = [0; 32];
     }
-    Ok(entries)
+    Ok(page)
+}
+
+pub fn entry_exists(conn: &Connection, id: i64) -> Result<bool> {
+    let exists = conn
+        .prepare_cached("SELECT EXISTS(SELECT 1 FROM clipboard_entries WHERE id = ?1)")?
+        .query_row(params![id], |row| row.get(0))?;
+    Ok(exists)
+}
+
+/// User-given title and note of an entry.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Annotation {
+    pub title: Option<String>,
+    pub note: Option<String>,
+}
+
+/// Annotation of entry `id`, or `None` if there is no such entry.
+pub fn get_annotation(conn: &Connection, id: i64) -> Result<Option<Annotation>> {
+    let annotation = conn
+        .query_row("SELECT title, note FROM clipboard_entries WHERE id = ?1", params![id], |row| {
+            Ok(Annotation { title: row.get(0)?, note: row.get(1)? })
+        })
+        .optional()?;
+    Ok(annotation)
+}
+
+/// Replace the annotation of entry `id`; blank values are stored as NULL.
+/// Returns false if there is no such entry.
+pub fn set_annotation(conn: &Connection, id: i64, annotation: &Annotation) -> Result<bool> {
+    let non_blank = |value: &Option<String>| value.as_deref().filter(|v| !v.trim().is_empty()).map(str::to_owned);
+    let updated = conn.execute(
+        "UPDATE clipboard_entries SET title = ?2, note = ?3 WHERE id = ?1",
+        params![id, non_blank(&annotation.title), non_blank(&annotation.note)],
+    )?;
+    Ok(updated > 0)
+}
+
+/// Pin or unpin entry `id`. Pinned entries are kept by retention, so
+/// pinning also drops the entry's TTL. Returns false if there is no such entry.
+pub fn set_pinned(conn: &Connection, id: i64, pinned: bool) -> Result<bool> {
+    let updated = conn.execute(
+        "UPDATE clipboard_entries SET pinned = ?2, expires_at = CASE WHEN ?2 THEN NULL ELSE expires_at END
+         WHERE id = ?1",
+        params![id, pinned],
+    )?;
+    Ok(updated > 0)
 }
 
 pub fn delete_entry(conn: &Connection, id: i64) -> Result<()> {
+    let stored = blob_store::hashes_in(conn, &[id])?;
     conn.execute("DELETE FROM clipboard_entries WHERE id = ?1", params![id])?;
-    Ok(())
+    blob_store::release(conn, &stored)
 }
 
 pub fn get_entry_content(conn: &Connection, id: i64) -> Result<Option<ClipboardEntry>> {
@@ -217,50 +521,6 @@ pub fn get_entry_content(conn: &Connection, id: i64) -> Result<Option<ClipboardE
     }
 }
 
-pub fn prune_oldest(conn: &Connection, max_count: usize) -> Result<u64> {
-    let count: i64 = conn.query_row("SELECT COUNT(*) FROM clipboard_entries", [], |row| {
-        row.get(0)
-    })?;
-    if count <= max_count as i64 {
-        return Ok(0);
-    }
-    let to_delete = count - max_count as i64;
-    let deleted = conn.execute(
-        "DELETE FROM clipboard_entries WHERE id IN (
-            SELECT id FROM clipboard_entries ORDER BY created_at ASC LIMIT ?1
-        )",
-        params![to_delete],
-    )?;
-    Ok(deleted as u64)
-}
-
-pub fn prune_expired(conn: &Connection, max_age: Option<Duration>) -> Result<u64> {
-    let mut total_deleted: u64 = 0;
-
-    // Prune entries with per-entry TTL (expires_at)
-    let now_ts = Timestamp::now();
-    let ttl_deleted = conn.execute(
-        "DELETE FROM clipboard_entries WHERE expires_at IS NOT NULL AND expires_at < ?1",
-        params![now_ts],
-    )?;
-    total_deleted += ttl_deleted as u64;
-
-    // Prune entries by global max_age
-    if let Some(age) = max_age {
-        let chrono_age = ChronoDuration::from_std(age)
-            .map_err(|e| AppError::DataIntegrity(format!("max_age duration too large: {e}")))?;
-        let cutoff = Utc::now() - chrono_age;
-        let cutoff_ts = Timestamp::from_raw(cutoff.format(TIMESTAMP_FORMAT).to_string());
-        let deleted = conn.execute(
-            "DELETE FROM clipboard_entries WHERE created_at < ?1",
-            params![cutoff_ts],
-        )?;
-        total_deleted += deleted as u64;
-    }
-
-    Ok(total_deleted)
-}
-
 pub fn get_latest_active(conn: &Connection) -> Result<Option<ClipboardEntry>> {
     let now_ts = Timestamp::now();
     let sql = format!(
@@ -274,27 +534,68 @@ pub fn get_latest_active(conn: &Connection) -> Result<Option<ClipboardEntry>> {
     }
 }
 
-pub fn save_or_update(
+/// Result of `save_entry`: the row ID and whether a new row was inserted
+/// (`false` means an existing entry was refreshed by dedup).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SaveOutcome {
+    pub id: i64,
+    pub inserted: bool,
+}
+
+/// Save `entry` uncompressed, keeping at most `max_history` entries. Returns the row ID.
+#[cfg(test)]
+pub(crate) fn save_or_update(
     conn: &Connection,
     entry: &ClipboardEntry,
     max_history: usize,
 ) -> Result<i64> {
+    save_entry(conn, entry, &RetentionPolicy::max_history(max_history), &StorageOptions::default(), CopyOrigin::Watch)
+        .map(|outcome| outcome.id)
+}
+
+/// Insert `entry` (or refresh its duplicate), record the copy as coming from
+/// `origin`, and enforce `policy` on insert. New entries are stored as
+/// `storage` asks, replacing near-duplicate images if it sets
+/// `image_dedupe_distance`.
+pub fn save_entry(
+    conn: &Connection,
+    entry: &ClipboardEntry,
+    policy: &RetentionPolicy,
+    storage: &StorageOptions,
+    origin: CopyOrigin,
+) -> Result<SaveOutcome> {
     let tx = conn.unchecked_transaction()?;
-    let id = if let Some(existing) = find_by_hash(&tx, entry.content_hash())? {
-        let id = existing
-            .id()
-            .ok_or_else(|| AppError::DataIntegrity("entry from DB has no id".to_owned()))?;
-        update_on_dedup(&tx, id, entry.expires_at(), entry.source_app(), entry.source_title(), entry.mask_text())?;
-        id
+    let outcome = if let Some((id, existing_selection)) = find_duplicate(&tx, entry.content_hash())? {
+        let selection = match (existing_selection, entry.selection()) {
+            (Some(old), Some(new)) => Some(old.merge(new)),
+            (_, new) => new,
+        };
+        update_on_dedup(
+            &tx,
+            id,
+            entry.expires_at(),
+            entry.source_app(),
+            entry.source_title(),
+            entry.mask_text(),
+            selection,
+        )?;
+        copy_events::record(&tx, id, origin, entry)?;
+        SaveOutcome { id, inserted: false }
     } else {
-        let id = insert_entry(&tx, entry)?;
-        prune_oldest(&tx, max_history)?;
-        id
+        let id = insert_entry_with(&tx, entry, storage)?;
+        copy_events::record(&tx, id, origin, entry)?;
+        if let Some(distance) = storage.image_dedupe_distance {
+            near_duplicates::replace_similar(&tx, id, distance)?;
+        }
+        retention::enforce(&tx, policy)?;
+        SaveOutcome { id, inserted: true }
     };
     tx.commit()?;
-    Ok(id)
+    blob_store::remove_released(conn)?;
+    Ok(outcome)
 }
 
+#[cfg(test)]
 fn collect_entries(
     rows: rusqlite::MappedRows<'_, impl FnMut(&rusqlite::Row<'_>) -> rusqlite::Result<ClipboardEntry>>,
 ) -> Result<Vec<ClipboardEntry>> {
@@ -315,6 +616,14 @@ mod col {
     pub const METADATA: usize = 8;
     pub const EXPIRES_AT: usize = 9;
     pub const MASK_TEXT: usize = 10;
+    pub const SELECTION: usize = 11;
+    pub const COMPRESSION: usize = 12;
+    pub const STORAGE: usize = 13;
+    pub const TITLE: usize = 14;
+    pub const NOTE: usize = 15;
+    pub const PINNED: usize = 16;
+    /// Score appended by frecency page queries.
+    pub const FRECENCY: usize = 17;
 }
 
 fn row_to_entry(row: &rusqlite::Row<'_>) -> rusqlite::Result<ClipboardEntry> {
@@ -322,7 +631,36 @@ fn row_to_entry(row: &rusqlite::Row<'_>) -> rusqlite::Result<ClipboardEntry> {
     let text_content: Option<String> = row.get(col::TEXT_CONTENT)?;
     let blob_content: Option<Vec<u8>> = row.get(col::BLOB_CONTENT)?;
 
+    let compression: Option<String> = row.get(col::COMPRESSION)?;
+    let storage: Option<String> = row.get(col::STORAGE)?;
+
+    if storage.is_some() && blob_content.is_none() {
+        // `clio_blob` logged the missing file; a prefix or nothing would paste as if complete.
+        return Err(rusqlite::Error::FromSqlConversionFailure(
+            col::BLOB_CONTENT,
+            rusqlite::types::Type::Null,
+            "blob store file is missing (see `clio db check`)".into(),
+        ));
+    }
     let content = match ContentType::from_db_str(&ct_str) {
+        // text_content only holds a prefix; the full text is in the blob.
+        ContentType::Text if compression.is_some() || storage.is_some() => {
+            let blob = blob_content.ok_or_else(|| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    col::BLOB_CONTENT,
+                    rusqlite::types::Type::Null,
+                    "text stored as blob but blob_content is NULL".into(),
+                )
+            })?;
+            let text = match compression {
+                Some(_) => compression::decompress(&blob),
+                None => String::from_utf8(blob)
+                    .map_err(|e| AppError::DataIntegrity(format!("stored text is not UTF-8: {e}"))),
+            };
+            EntryContent::Text(text.map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(col::BLOB_CONTENT, rusqlite::types::Type::Blob, e.into())
+            })?)
+        }
         ContentType::Text => EntryContent::Text(text_content.ok_or_else(|| {
             rusqlite::Error::FromSqlConversionFailure(
                 col::TEXT_CONTENT,
@@ -337,6 +675,22 @@ fn row_to_entry(row: &rusqlite::Row<'_>) -> rusqlite::Result<ClipboardEntry> {
                 "content_type is 'image' but blob_content is NULL".into(),
             )
         })?),
+        ContentType::Files => {
+            let raw = text_content.ok_or_else(|| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    col::TEXT_CONTENT,
+                    rusqlite::types::Type::Null,
+                    "content_type is 'files' but text_content is NULL".into(),
+                )
+            })?;
+            EntryContent::Files(FileList::parse(&raw).ok_or_else(|| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    col::TEXT_CONTENT,
+                    rusqlite::types::Type::Text,
+                    format!("invalid file list: {raw:?}").into(),
+                )
+            })?)
+        }
         ContentType::Unknown => {
             log::warn!("unknown content_type in DB row, falling back to text");
             EntryContent::Text(text_content.unwrap_or_default())
@@ -362,6 +716,10 @@ fn row_to_entry(row: &rusqlite::Row<'_>) -> rusqlite::Result<ClipboardEntry> {
         metadata: row.get(col::METADATA)?,
         expires_at: row.get(col::EXPIRES_AT)?,
         mask_text: row.get(col::MASK_TEXT)?,
+        selection: row.get(col::SELECTION)?,
+        title: row.get(col::TITLE)?,
+        note: row.get(col::NOTE)?,
+        pinned: row.get(col::PINNED)?,
     })
 }
 
@@ -370,10 +728,25 @@ mod tests {
     use super::*;
     use crate::db::init_db_in_memory;
 
+    /// Unfiltered preview listing, newest first.
+    fn preview(conn: &Connection, preview_chars: usize) -> Vec<ClipboardEntry> {
+        let all = EntryFilter::default();
+        list_entries_preview(conn, 10, None, preview_chars, &all, HistorySort::Chronological).unwrap().entries
+    }
+
+    fn search_preview(conn: &Connection, query: &str, preview_chars: usize) -> Vec<ClipboardEntry> {
+        let all = EntryFilter::default();
+        search_entries_preview(conn, query, 10, None, preview_chars, &all, HistorySort::Chronological).unwrap().entries
+    }
+
     fn setup() -> Connection {
         init_db_in_memory().unwrap()
     }
 
+    fn compress_over_1k() -> StorageOptions {
+        StorageOptions { compress_text_over: Some(1024), ..StorageOptions::default() }
+    }
+
     #[test]
     fn test_insert_and_find_by_hash() {
         let conn = setup();
@@ -402,6 +775,135 @@ mod tests {
         assert_eq!(entries.len(), 1);
     }
 
+    #[test]
+    fn test_save_entry_reports_insert_and_dedup() {
+        let conn = setup();
+        let entry = ClipboardEntry::from_text("hello".to_string(), None);
+        let first = save_entry(&conn, &entry, &RetentionPolicy::max_history(500), &StorageOptions::default(), CopyOrigin::Watch).unwrap();
+        assert!(first.inserted);
+
+        let second = save_entry(&conn, &entry, &RetentionPolicy::max_history(500), &StorageOptions::default(), CopyOrigin::Watch).unwrap();
+        assert_eq!(second.id, first.id);
+        assert!(!second.inserted);
+    }
+
+    fn insert_with_selection(conn: &Connection, text: &str, selection: Option<Selection>) {
+        let mut entry = ClipboardEntry::from_text(text.to_string(), None);
+        entry.set_selection(selection);
+        insert_entry(conn, &entry).unwrap();
+    }
+
+    #[test]
+    fn test_selection_roundtrip_and_merge_on_dedup() {
+        let conn = setup();
+        let mut entry = ClipboardEntry::from_text("hello".to_string(), None);
+        entry.set_selection(Some(Selection::Primary));
+        let id = save_or_update(&conn, &entry, 500).unwrap();
+        let found = get_entry_content(&conn, id).unwrap().unwrap();
+        assert_eq!(found.selection(), Some(Selection::Primary));
+
+        entry.set_selection(Some(Selection::Clipboard));
+        save_or_update(&conn, &entry, 500).unwrap();
+        let found = get_entry_content(&conn, id).unwrap().unwrap();
+        assert_eq!(found.selection(), Some(Selection::Both));
+
+        // Unknown selection keeps the stored value.
+        entry.set_selection(None);
+        save_or_update(&conn, &entry, 500).unwrap();
+        let found = get_entry_content(&conn, id).unwrap().unwrap();
+        assert_eq!(found.selection(), Some(Selection::Both));
+    }
+
+    #[test]
+    fn test_preview_selection_filter() {
+        let conn = setup();
+        insert_with_selection(&conn, "from clipboard", Some(Selection::Clipboard));
+        insert_with_selection(&conn, "from primary", Some(Selection::Primary));
+        insert_with_selection(&conn, "from both", Some(Selection::Both));
+        insert_with_selection(&conn, "from cli", Some(Selection::Cli));
+        insert_with_selection(&conn, "from legacy", None);
+
+        // Rows share a timestamp, so compare sorted.
+        let texts = |entries: Vec<ClipboardEntry>| -> Vec<String> {
+            let mut texts: Vec<_> = entries.into_iter().map(|e| e.content.text().unwrap().to_owned()).collect();
+            texts.sort();
+            texts
+        };
+
+        let sort = HistorySort::Chronological;
+        let only = |selections: &[Selection]| EntryFilter { selections: selections.to_vec(), ..EntryFilter::default() };
+        assert_eq!(preview(&conn, 50).len(), 5);
+        assert_eq!(
+            texts(list_entries_preview(&conn, 10, None, 50, &only(&[Selection::Primary]), sort).unwrap().entries),
+            vec!["from both", "from primary"]
+        );
+        assert_eq!(
+            texts(list_entries_preview(&conn, 10, None, 50, &only(&[Selection::Cli, Selection::Both]), sort).unwrap().entries),
+            vec!["from both", "from cli"]
+        );
+        assert_eq!(
+            texts(search_entries_preview(&conn, "from", 10, None, 50, &only(&[Selection::Clipboard]), sort).unwrap().entries),
+            vec!["from both", "from clipboard"]
+        );
+    }
+
+    #[test]
+    fn test_preview_tag_filter() {
+        let conn = setup();
+        let sql = insert_entry(&conn, &ClipboardEntry::from_text("SELECT 1".to_string(), None)).unwrap();
+        let pods = insert_entry(&conn, &ClipboardEntry::from_text("kubectl get pods".to_string(), None)).unwrap();
+        insert_entry(&conn, &ClipboardEntry::from_text("untagged".to_string(), None)).unwrap();
+        crate::db::tags::add(&conn, sql, "sql").unwrap();
+        crate::db::tags::add(&conn, pods, "k8s").unwrap();
+
+        let sort = HistorySort::Chronological;
+        let tagged = |tags: &[&str]| EntryFilter { tags: tags.iter().map(|t| t.to_string()).collect(), ..EntryFilter::default() };
+        let ids = |page: Page| -> Vec<i64> { page.entries.iter().filter_map(ClipboardEntry::id).collect() };
+        assert_eq!(ids(list_entries_preview(&conn, 10, None, 50, &tagged(&["sql"]), sort).unwrap()), [sql]);
+        assert_eq!(ids(list_entries_preview(&conn, 10, None, 50, &tagged(&["sql", "k8s"]), sort).unwrap()).len(), 2);
+        assert!(ids(list_entries_preview(&conn, 10, None, 50, &tagged(&["none"]), sort).unwrap()).is_empty());
+        assert_eq!(ids(search_entries_preview(&conn, "get", 10, None, 50, &tagged(&["k8s"]), sort).unwrap()), [pods]);
+        assert!(ids(search_entries_preview(&conn, "get", 10, None, 50, &tagged(&["sql"]), sort).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_annotation_is_listed_and_searched() {
+        let conn = setup();
+        let id = insert_entry(&conn, &ClipboardEntry::from_text("SELECT * FROM orders".to_string(), None)).unwrap();
+        assert_eq!(get_annotation(&conn, id).unwrap(), Some(Annotation::default()));
+        assert!(!set_annotation(&conn, id + 1, &Annotation::default()).unwrap());
+
+        let annotation = Annotation { title: Some("Open orders".to_string()), note: Some("for the weekly report".to_string()) };
+        assert!(set_annotation(&conn, id, &annotation).unwrap());
+        assert_eq!(get_annotation(&conn, id).unwrap(), Some(annotation));
+        let listed = &preview(&conn, 50)[0];
+        assert_eq!((listed.title(), listed.note()), (Some("Open orders"), Some("for the weekly report")));
+
+        assert_eq!(search_preview(&conn, "open ord", 50).len(), 1);
+        assert_eq!(search_preview(&conn, "weekly", 50).len(), 1);
+        assert_eq!(search_preview(&conn, "monthly", 50).len(), 0);
+
+        let cleared = Annotation { title: Some(" ".to_string()), note: None };
+        set_annotation(&conn, id, &cleared).unwrap();
+        assert_eq!(get_annotation(&conn, id).unwrap(), Some(Annotation::default()));
+    }
+
+    #[test]
+    fn test_annotation_survives_dedup() {
+        let conn = setup();
+        let save = |entry: &ClipboardEntry| {
+            save_entry(&conn, entry, &RetentionPolicy::max_history(10), &StorageOptions::default(), CopyOrigin::Watch)
+                .unwrap()
+        };
+        let id = save(&ClipboardEntry::from_text("SELECT * FROM orders".to_string(), None)).id;
+        let annotation = Annotation { title: Some("Open orders".to_string()), note: Some("weekly".to_string()) };
+        set_annotation(&conn, id, &annotation).unwrap();
+
+        let again = save(&ClipboardEntry::from_text("SELECT * FROM orders".to_string(), Some("psql".to_string())));
+        assert_eq!(again, SaveOutcome { id, inserted: false });
+        assert_eq!(get_annotation(&conn, id).unwrap(), Some(annotation));
+    }
+
     #[test]
     fn test_list_entries_ordered() {
         let conn = setup();
@@ -436,22 +938,6 @@ mod tests {
         assert!(found.is_none());
     }
 
-    #[test]
-    fn test_prune_oldest() {
-        let conn = setup();
-        for i in 0..10 {
-            let entry = ClipboardEntry::from_text(format!("entry {i}"), None);
-            insert_entry(&conn, &entry).unwrap();
-            std::thread::sleep(std::time::Duration::from_millis(5));
-        }
-
-        let pruned = prune_oldest(&conn, 5).unwrap();
-        assert_eq!(pruned, 5);
-
-        let entries = list_entries(&conn, 100).unwrap();
-        assert_eq!(entries.len(), 5);
-    }
-
     #[test]
     fn test_save_or_update_with_pruning() {
         let conn = setup();
@@ -488,20 +974,83 @@ mod tests {
             std::thread::sleep(std::time::Duration::from_millis(5));
         }
 
-        let page1 = list_entries_page(&conn, 3, 0).unwrap();
-        assert_eq!(page1.len(), 3);
+        let page1 = list_entries_page(&conn, 3, None).unwrap();
+        assert_eq!(page1.entries.len(), 3);
+        assert_eq!(page1.entries[0].content.text(), Some("entry 9"));
 
-        let page2 = list_entries_page(&conn, 3, 3).unwrap();
-        assert_eq!(page2.len(), 3);
+        let page2 = list_entries_page(&conn, 3, page1.next.as_ref()).unwrap();
+        assert_eq!(page2.entries.len(), 3);
+        assert_eq!(page2.entries[0].content.text(), Some("entry 6"));
 
-        let ids1: Vec<_> = page1.iter().map(|e| e.id).collect();
-        let ids2: Vec<_> = page2.iter().map(|e| e.id).collect();
+        let ids1: Vec<_> = page1.entries.iter().map(|e| e.id).collect();
+        let ids2: Vec<_> = page2.entries.iter().map(|e| e.id).collect();
         for id in &ids1 {
             assert!(!ids2.contains(id));
         }
 
-        let empty = list_entries_page(&conn, 3, 100).unwrap();
-        assert!(empty.is_empty());
+        let rest = list_entries_page(&conn, 100, page2.next.as_ref()).unwrap();
+        assert_eq!(rest.entries.len(), 4);
+        assert!(rest.next.is_none());
+    }
+
+    #[test]
+    fn test_page_cursor_survives_concurrent_inserts() {
+        let conn = setup();
+        // Equal timestamps: the id breaks ties.
+        for i in 0..6 {
+            conn.execute(
+                "INSERT INTO clipboard_entries (content_type, text_content, content_hash, created_at) VALUES ('text', ?1, ?2, 1000)",
+                params![format!("entry {i}"), &crate::models::entry::compute_hash(format!("entry {i}").as_bytes())[..]],
+            )
+            .unwrap();
+        }
+        let page1 = list_entries_page(&conn, 3, None).unwrap();
+
+        // New entries arrive, and an old one is bumped to the top.
+        insert_entry(&conn, &ClipboardEntry::from_text("new".to_string(), None)).unwrap();
+        save_or_update(&conn, &ClipboardEntry::from_text("entry 0".to_string(), None), 500).unwrap();
+
+        let page2 = list_entries_page(&conn, 3, page1.next.as_ref()).unwrap();
+        let texts: Vec<_> = page1.entries.iter().chain(&page2.entries).filter_map(|e| e.content.text()).collect();
+        assert_eq!(texts, ["entry 5", "entry 4", "entry 3", "entry 2", "entry 1"]);
+    }
+
+    #[test]
+    fn test_frecency_ranks_selected_entries_first() {
+        let conn = setup();
+        let day = 86_400_000;
+        let now = Timestamp::now().as_millis();
+        let insert = |text: &str, created_at: i64, use_count: i64, last_used_at: Option<i64>| {
+            let hash = crate::models::entry::compute_hash(text.as_bytes());
+            conn.execute(
+                "INSERT INTO clipboard_entries (content_type, text_content, content_hash, created_at, use_count, last_used_at)
+                 VALUES ('text', ?1, ?2, ?3, ?4, ?5)",
+                params![text, &hash[..], created_at, use_count, last_used_at],
+            )
+            .unwrap();
+        };
+        insert("snippet", now - 30 * day, 20, Some(now - day / 2));
+        insert("stale snippet", now - 90 * day, 5, Some(now - 60 * day));
+        insert("one-off", now - 1000, 0, None);
+        insert("older one-off", now - 2000, 0, None);
+
+        let all = EntryFilter::default();
+        let texts = |page: &Page| page.entries.iter().filter_map(|e| e.content.text().map(str::to_owned)).collect::<Vec<_>>();
+        let chronological = list_entries_preview(&conn, 10, None, 50, &all, HistorySort::Chronological).unwrap();
+        assert_eq!(texts(&chronological), ["one-off", "older one-off", "snippet", "stale snippet"]);
+        let frecency = list_entries_preview(&conn, 10, None, 50, &all, HistorySort::Frecency).unwrap();
+        assert_eq!(texts(&frecency), ["snippet", "one-off", "older one-off", "stale snippet"]);
+
+        // Pages continue in the same order, even after an entry is bumped.
+        let page1 = list_entries_preview(&conn, 2, None, 50, &all, HistorySort::Frecency).unwrap();
+        save_or_update(&conn, &ClipboardEntry::from_text("stale snippet".to_string(), None), 500).unwrap();
+        let page2 = list_entries_preview(&conn, 2, page1.next.as_ref(), 50, &all, HistorySort::Frecency).unwrap();
+        assert_eq!(texts(&page1), ["snippet", "one-off"]);
+        assert_eq!(texts(&page2), ["older one-off"]);
+
+        let found = search_entries_preview(&conn, "snippet", 10, None, 50, &all, HistorySort::Frecency).unwrap();
+        // Re-copied, the stale snippet ranks by its recent copy.
+        assert_eq!(texts(&found), ["snippet", "stale snippet"]);
     }
 
     #[test]
@@ -516,16 +1065,17 @@ mod tests {
             insert_entry(&conn, &entry).unwrap();
         }
 
-        let results = search_entries_page(&conn, "apple", 10, 0).unwrap();
-        assert_eq!(results.len(), 5);
+        let results = search_entries_page(&conn, "apple", 10, None).unwrap();
+        assert_eq!(results.entries.len(), 5);
 
-        let page1 = search_entries_page(&conn, "apple", 2, 0).unwrap();
-        assert_eq!(page1.len(), 2);
-        let page2 = search_entries_page(&conn, "apple", 2, 2).unwrap();
-        assert_eq!(page2.len(), 2);
+        let page1 = search_entries_page(&conn, "apple", 2, None).unwrap();
+        assert_eq!(page1.entries.len(), 2);
+        let page2 = search_entries_page(&conn, "apple", 2, page1.next.as_ref()).unwrap();
+        assert_eq!(page2.entries.len(), 2);
+        assert!(page2.entries.iter().all(|e| !page1.entries.iter().any(|f| f.id == e.id)));
 
-        let none = search_entries_page(&conn, "cherry", 10, 0).unwrap();
-        assert!(none.is_empty());
+        let none = search_entries_page(&conn, "cherry", 10, None).unwrap();
+        assert!(none.entries.is_empty());
     }
 
     #[test]
@@ -535,7 +1085,7 @@ mod tests {
         let entry = ClipboardEntry::from_text(long_text, None);
         insert_entry(&conn, &entry).unwrap();
 
-        let entries = list_entries_preview(&conn, 10, 0, 50).unwrap();
+        let entries = preview(&conn, 50);
         assert_eq!(entries.len(), 1);
         let text = entries[0].content.text().unwrap();
         assert_eq!(text.len(), 50);
@@ -547,7 +1097,7 @@ mod tests {
         let entry = ClipboardEntry::from_text("short".to_string(), None);
         insert_entry(&conn, &entry).unwrap();
 
-        let entries = list_entries_preview(&conn, 10, 0, 50).unwrap();
+        let entries = preview(&conn, 50);
         assert_eq!(entries[0].content.text(), Some("short"));
     }
 
@@ -558,7 +1108,7 @@ mod tests {
         let entry = ClipboardEntry::from_text(long_text, None);
         insert_entry(&conn, &entry).unwrap();
 
-        let entries = search_entries_preview(&conn, "needle", 10, 0, 20).unwrap();
+        let entries = search_preview(&conn, "needle", 20);
         assert_eq!(entries.len(), 1);
         let text = entries[0].content.text().unwrap();
         assert_eq!(text.len(), 20);
@@ -573,101 +1123,22 @@ mod tests {
         insert_entry(&conn, &entry).unwrap();
 
         // Search should still find it (LIKE matches full text)
-        let entries = search_entries_preview(&conn, "keyword_here", 10, 0, 50).unwrap();
+        let entries = search_preview(&conn, "keyword_here", 50);
         assert_eq!(entries.len(), 1);
         // But returned text is truncated to 50 chars
         assert_eq!(entries[0].content.text().unwrap().len(), 50);
     }
 
-    #[test]
-    fn test_prune_expired_no_op_when_none() {
-        let conn = setup();
-        let entry = ClipboardEntry::from_text("hello".to_string(), None);
-        insert_entry(&conn, &entry).unwrap();
-
-        let deleted = prune_expired(&conn, None).unwrap();
-        assert_eq!(deleted, 0);
-
-        let entries = list_entries(&conn, 100).unwrap();
-        assert_eq!(entries.len(), 1);
-    }
-
-    #[test]
-    fn test_prune_expired_deletes_old_entries() {
-        let conn = setup();
-
-        conn.execute(
-            "INSERT INTO clipboard_entries (content_type, text_content, content_hash, created_at)
-             VALUES ('text', 'old', X'0000000000000000000000000000000000000000000000000000000000000000', strftime('%Y-%m-%dT%H:%M:%f', 'now', '-2 hours'))",
-            [],
-        )
-        .unwrap();
-
-        let fresh = ClipboardEntry::from_text("fresh".to_string(), None);
-        insert_entry(&conn, &fresh).unwrap();
-
-        let entries = list_entries(&conn, 100).unwrap();
-        assert_eq!(entries.len(), 2);
-
-        let deleted = prune_expired(&conn, Some(Duration::from_secs(3600))).unwrap();
-        assert_eq!(deleted, 1);
-
-        let entries = list_entries(&conn, 100).unwrap();
-        assert_eq!(entries.len(), 1);
-        assert_eq!(entries[0].content.text(), Some("fresh"));
-    }
-
-    #[test]
-    fn test_prune_expired_deletes_by_expires_at() {
-        let conn = setup();
-
-        // Entry with expired per-entry TTL
-        let mut entry = ClipboardEntry::from_text("expiring".to_string(), None);
-        entry.set_expires_at(Some(Timestamp::from_raw("2000-01-01T00:00:00.000".to_string())));
-        insert_entry(&conn, &entry).unwrap();
-
-        // Entry without TTL
-        let fresh = ClipboardEntry::from_text("fresh".to_string(), None);
-        insert_entry(&conn, &fresh).unwrap();
-
-        let entries = list_entries(&conn, 100).unwrap();
-        assert_eq!(entries.len(), 2);
-
-        // Even without global max_age, per-entry TTL should be pruned
-        let deleted = prune_expired(&conn, None).unwrap();
-        assert_eq!(deleted, 1);
-
-        let entries = list_entries(&conn, 100).unwrap();
-        assert_eq!(entries.len(), 1);
-        assert_eq!(entries[0].content.text(), Some("fresh"));
-    }
-
-    #[test]
-    fn test_prune_expired_keeps_future_expires_at() {
-        let conn = setup();
-
-        // Entry with future TTL
-        let mut entry = ClipboardEntry::from_text("future".to_string(), None);
-        entry.set_expires_at(Some(Timestamp::from_raw("2099-01-01T00:00:00.000".to_string())));
-        insert_entry(&conn, &entry).unwrap();
-
-        let deleted = prune_expired(&conn, None).unwrap();
-        assert_eq!(deleted, 0);
-
-        let entries = list_entries(&conn, 100).unwrap();
-        assert_eq!(entries.len(), 1);
-    }
-
     #[test]
     fn test_save_or_update_preserves_expires_at() {
         let conn = setup();
 
         let mut entry = ClipboardEntry::from_text("ttl-entry".to_string(), None);
-        entry.set_expires_at(Some(Timestamp::from_raw("2099-01-01T00:00:00.000".to_string())));
+        entry.set_expires_at(Some(Timestamp::from_millis(4_070_908_800_000)));
         let id = save_or_update(&conn, &entry, 500).unwrap();
 
         let found = get_entry_content(&conn, id).unwrap().unwrap();
-        assert_eq!(found.expires_at.as_ref().map(|t| t.as_str()), Some("2099-01-01T00:00:00.000"));
+        assert_eq!(found.expires_at, Some(Timestamp::from_millis(4_070_908_800_000)));
     }
 
     #[test]
@@ -683,12 +1154,12 @@ mod tests {
 
         // Same content, now with TTL
         let mut entry2 = ClipboardEntry::from_text("hello".to_string(), None);
-        entry2.set_expires_at(Some(Timestamp::from_raw("2099-01-01T00:00:00.000".to_string())));
+        entry2.set_expires_at(Some(Timestamp::from_millis(4_070_908_800_000)));
         let id2 = save_or_update(&conn, &entry2, 500).unwrap();
 
         assert_eq!(id1, id2);
         let found2 = get_entry_content(&conn, id2).unwrap().unwrap();
-        assert_eq!(found2.expires_at.as_ref().map(|t| t.as_str()), Some("2099-01-01T00:00:00.000"));
+        assert_eq!(found2.expires_at, Some(Timestamp::from_millis(4_070_908_800_000)));
     }
 
     #[test]
@@ -697,11 +1168,11 @@ mod tests {
 
         // First save with TTL
         let mut entry1 = ClipboardEntry::from_text("hello".to_string(), None);
-        entry1.set_expires_at(Some(Timestamp::from_raw("2099-01-01T00:00:00.000".to_string())));
+        entry1.set_expires_at(Some(Timestamp::from_millis(4_070_908_800_000)));
         let id1 = save_or_update(&conn, &entry1, 500).unwrap();
 
         let found1 = get_entry_content(&conn, id1).unwrap().unwrap();
-        assert_eq!(found1.expires_at.as_ref().map(|t| t.as_str()), Some("2099-01-01T00:00:00.000"));
+        assert_eq!(found1.expires_at, Some(Timestamp::from_millis(4_070_908_800_000)));
 
         // Same content without TTL (simulates daemon dedup)
         let entry2 = ClipboardEntry::from_text("hello".to_string(), None);
@@ -711,7 +1182,21 @@ mod tests {
         assert_eq!(id1, id2);
         // Existing TTL must be preserved
         let found2 = get_entry_content(&conn, id2).unwrap().unwrap();
-        assert_eq!(found2.expires_at.as_ref().map(|t| t.as_str()), Some("2099-01-01T00:00:00.000"));
+        assert_eq!(found2.expires_at, Some(Timestamp::from_millis(4_070_908_800_000)));
+    }
+
+    #[test]
+    fn test_recopying_pinned_entry_with_ttl_keeps_it_pinned() {
+        let conn = setup();
+        let id = save_or_update(&conn, &ClipboardEntry::from_text("hello".to_string(), None), 500).unwrap();
+        assert!(set_pinned(&conn, id, true).unwrap());
+
+        let mut again = ClipboardEntry::from_text("hello".to_string(), None);
+        again.set_expires_at(Some(Timestamp::from_millis(4_070_908_800_000)));
+        assert_eq!(save_or_update(&conn, &again, 500).unwrap(), id);
+
+        let found = get_entry_content(&conn, id).unwrap().unwrap();
+        assert!(found.expires_at.is_none());
     }
 
     #[test]
@@ -731,7 +1216,7 @@ mod tests {
 
         // Insert expired entry
         let mut expired = ClipboardEntry::from_text("expired".to_string(), None);
-        expired.set_expires_at(Some(Timestamp::from_raw("2000-01-01T00:00:00.000".to_string())));
+        expired.set_expires_at(Some(Timestamp::from_millis(946_684_800_000)));
         insert_entry(&conn, &expired).unwrap();
 
         // Insert active entry (older by created_at but not expired)
@@ -748,7 +1233,7 @@ mod tests {
         let conn = setup();
 
         let mut expired = ClipboardEntry::from_text("expired".to_string(), None);
-        expired.set_expires_at(Some(Timestamp::from_raw("2000-01-01T00:00:00.000".to_string())));
+        expired.set_expires_at(Some(Timestamp::from_millis(946_684_800_000)));
         insert_entry(&conn, &expired).unwrap();
 
         let result = get_latest_active(&conn).unwrap();
@@ -871,16 +1356,79 @@ mod tests {
         assert_eq!(found2.source_title.as_deref(), Some("GitHub - Mozilla Firefox"));
     }
 
+    #[test]
+    fn test_files_entry_roundtrip() {
+        use crate::models::entry::FileOperation;
+
+        let conn = setup();
+        let files = FileList::parse("cut\nfile:///tmp/a.txt\nfile:///tmp/b%20c.txt").unwrap();
+        let entry = ClipboardEntry::from_files(files.clone(), None);
+        let id = save_or_update(&conn, &entry, 500).unwrap();
+
+        let found = get_entry_content(&conn, id).unwrap().unwrap();
+        let stored = found.content.files().unwrap();
+        assert_eq!(stored, &files);
+        assert_eq!(stored.operation, FileOperation::Cut);
+
+        // File URIs are searchable like text
+        let results = search_preview(&conn, "b%20c", 50);
+        assert_eq!(results.len(), 1);
+    }
+
     #[test]
     fn test_sqlite_timestamp_roundtrip() {
-        // Verify that SQLite strftime-generated timestamps parse correctly in Rust.
+        // The DB default for created_at agrees with Timestamp::now().
         let conn = setup();
+        let before = Timestamp::now();
         let entry = ClipboardEntry::from_text("ts-test".to_string(), None);
         insert_entry(&conn, &entry).unwrap();
 
         let found = find_by_hash(&conn, &entry.content_hash).unwrap().unwrap();
-        let ts = found.created_at.as_ref().expect("created_at should be set by DB");
-        // This would panic if SQLite format doesn't match TIMESTAMP_FORMAT
-        let _naive = ts.to_naive();
+        let ts = found.created_at.expect("created_at should be set by DB");
+        assert!(ts >= before && ts <= Timestamp::now(), "{ts} not between {before} and now");
+    }
+
+    #[test]
+    fn test_large_text_is_stored_compressed() {
+        let conn = setup();
+        let text = format!("{}needle at the end", "log line\n".repeat(10_000));
+        let entry = ClipboardEntry::from_text(text.clone(), None);
+        let outcome = save_entry(&conn, &entry, &RetentionPolicy::max_history(10), &compress_over_1k(), CopyOrigin::Watch).unwrap();
+
+        let (prefix_len, blob_len, compression): (i64, i64, Option<String>) = conn
+            .query_row(
+                "SELECT length(text_content), length(blob_content), compression FROM clipboard_entries WHERE id = ?1",
+                params![outcome.id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(prefix_len, compression::PREFIX_CHARS as i64);
+        assert!((blob_len as usize) < text.len() / 10);
+        assert_eq!(compression.as_deref(), Some(compression::ZSTD));
+
+        // Full content, dedup and previews behave as for plain text.
+        let full = get_entry_content(&conn, outcome.id).unwrap().unwrap();
+        assert_eq!(full.content.text(), Some(text.as_str()));
+        assert_eq!(full.content_hash, entry.content_hash);
+        assert!(!save_entry(&conn, &entry, &RetentionPolicy::max_history(10), &compress_over_1k(), CopyOrigin::Watch).unwrap().inserted);
+
+        let listed = preview(&conn, 20);
+        assert_eq!(listed[0].content.text(), Some(&text[..20]));
+
+        // Search looks past the stored prefix.
+        let found = search_preview(&conn, "needle at the end", 20);
+        assert_eq!(found.len(), 1);
+        assert!(search_preview(&conn, "not there", 20).is_empty());
+    }
+
+    #[test]
+    fn test_small_text_is_not_compressed() {
+        let conn = setup();
+        let entry = ClipboardEntry::from_text("short".to_string(), None);
+        let outcome = save_entry(&conn, &entry, &RetentionPolicy::max_history(10), &compress_over_1k(), CopyOrigin::Watch).unwrap();
+        let compression: Option<String> = conn
+            .query_row("SELECT compression FROM clipboard_entries WHERE id = ?1", params![outcome.id], |row| row.get(0))
+            .unwrap();
+        assert!(compression.is_none());
     }
 }
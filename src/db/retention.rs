@@ -0,0 +1,643 @@
+//! Retention engine: decides which entries to delete under the configured
+//! count, age, size and per-app limits, and deletes them (or only reports
+//! what it would delete).
+//!
+//! Rules run in a fixed order and each entry is attributed to the first rule
+//! that selects it, so count limits only see entries no earlier rule removed.
+//! Pinned entries are never selected and do not count towards any limit.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+use rusqlite::{params, Connection, ToSql};
+
+use super::blob_store;
+use crate::config::{Config, SourceAppRetention};
+use crate::errors::Result;
+use crate::models::entry::{ContentType, Selection, Timestamp};
+
+/// Approximate on-disk size of an entry's content, in bytes.
+const ENTRY_SIZE: &str =
+    "COALESCE(octet_length(text_content), 0) + COALESCE(length(blob_content), 0) + COALESCE(length(thumbnail), 0)";
+
+/// How long rule matches and copy events are kept without a `max_age`.
+const EVENTS_MAX_AGE: Duration = Duration::from_secs(90 * 24 * 3600);
+
+/// Entries that are not yet selected for deletion.
+const NOT_SELECTED: &str = "id NOT IN (SELECT id FROM temp.retention_candidates)";
+
+/// Retention limits, usually built from the config with `from_config`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub max_history: usize,
+    pub max_age: Option<Duration>,
+    pub primary_max_age: Option<Duration>,
+    pub max_images: Option<usize>,
+    pub image_max_age: Option<Duration>,
+    pub max_db_size_bytes: Option<u64>,
+    pub source_apps: BTreeMap<String, SourceAppRetention>,
+}
+
+impl RetentionPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_history: config.max_history,
+            max_age: config.max_age,
+            primary_max_age: config.primary_max_age,
+            max_images: config.max_images,
+            image_max_age: config.image_max_age,
+            max_db_size_bytes: config.max_db_size_mb.map(|mb| mb * 1024 * 1024),
+            source_apps: config.source_app_retention.clone(),
+        }
+    }
+
+    /// Policy that only caps the number of entries.
+    pub fn max_history(max_history: usize) -> Self {
+        Self { max_history, ..Self::default() }
+    }
+
+    /// Whether any limit depends on entry age, so entries expire over time
+    /// even when nothing new is saved.
+    pub fn has_age_limits(&self) -> bool {
+        self.max_age.is_some()
+            || self.primary_max_age.is_some()
+            || self.image_max_age.is_some()
+            || self.source_apps.values().any(|app| app.max_age.is_some())
+    }
+}
+
+/// Rule that selected an entry for deletion, in evaluation order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PruneReason {
+    Expired,
+    MaxAge,
+    PrimaryMaxAge,
+    ImageMaxAge,
+    SourceAppMaxAge,
+    SourceAppMaxEntries,
+    MaxImages,
+    MaxHistory,
+    MaxDbSize,
+}
+
+impl PruneReason {
+    pub const ALL: [Self; 9] = [
+        Self::Expired,
+        Self::MaxAge,
+        Self::PrimaryMaxAge,
+        Self::ImageMaxAge,
+        Self::SourceAppMaxAge,
+        Self::SourceAppMaxEntries,
+        Self::MaxImages,
+        Self::MaxHistory,
+        Self::MaxDbSize,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Expired => "expired",
+            Self::MaxAge => "max_age",
+            Self::PrimaryMaxAge => "primary_max_age",
+            Self::ImageMaxAge => "image_max_age",
+            Self::SourceAppMaxAge => "source_app_retention.max_age",
+            Self::SourceAppMaxEntries => "source_app_retention.max_entries",
+            Self::MaxImages => "max_images",
+            Self::MaxHistory => "max_history",
+            Self::MaxDbSize => "max_db_size_mb",
+        }
+    }
+}
+
+impl fmt::Display for PruneReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Entries and content bytes removed by one rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PruneCount {
+    pub reason: PruneReason,
+    pub entries: u64,
+    pub bytes: u64,
+}
+
+/// What a retention pass deleted, or would delete on a dry run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PruneReport {
+    /// Per-rule counts, only for rules that selected something.
+    pub counts: Vec<PruneCount>,
+    /// Database pages in use before the pass, in bytes.
+    pub db_size_bytes: u64,
+}
+
+impl PruneReport {
+    pub fn total_entries(&self) -> u64 {
+        self.counts.iter().map(|c| c.entries).sum()
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.counts.iter().map(|c| c.bytes).sum()
+    }
+}
+
+/// Delete every entry the policy no longer allows, and rule matches and
+/// copy events older than `max_age` (`EVENTS_MAX_AGE` without one).
+pub fn enforce(conn: &Connection, policy: &RetentionPolicy) -> Result<PruneReport> {
+    let report = run(conn, policy, true)?;
+    let cutoff = Timestamp::before(policy.max_age.unwrap_or(EVENTS_MAX_AGE));
+    conn.execute("DELETE FROM rule_matches WHERE created_at < ?1", params![cutoff])?;
+    conn.execute("DELETE FROM copy_events WHERE created_at < ?1", params![cutoff])?;
+    Ok(report)
+}
+
+/// Report what `enforce` would delete, without deleting anything.
+pub fn plan(conn: &Connection, policy: &RetentionPolicy) -> Result<PruneReport> {
+    run(conn, policy, false)
+}
+
+fn run(conn: &Connection, policy: &RetentionPolicy, delete: bool) -> Result<PruneReport> {
+    conn.execute_batch(
+        "CREATE TEMP TABLE IF NOT EXISTS retention_candidates (
+            id INTEGER PRIMARY KEY,
+            reason TEXT NOT NULL,
+            size INTEGER NOT NULL
+        );
+        DELETE FROM temp.retention_candidates;",
+    )?;
+    let db_size_bytes = used_db_bytes(conn)?;
+
+    select_candidates(conn, policy, db_size_bytes)?;
+    let report = PruneReport { counts: count_candidates(conn)?, db_size_bytes };
+
+    if delete && report.total_entries() > 0 {
+        let ids = conn
+            .prepare("SELECT id FROM temp.retention_candidates")?
+            .query_map([], |row| row.get::<_, i64>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let stored = blob_store::hashes_in(conn, &ids)?;
+        conn.execute(
+            "DELETE FROM clipboard_entries WHERE id IN (SELECT id FROM temp.retention_candidates)",
+            [],
+        )?;
+        blob_store::release(conn, &stored)?;
+    }
+    conn.execute("DELETE FROM temp.retention_candidates", [])?;
+    Ok(report)
+}
+
+fn select_candidates(conn: &Connection, policy: &RetentionPolicy, db_size_bytes: u64) -> Result<()> {
+    conn.execute(
+        &insert_sql("expires_at IS NOT NULL AND expires_at < ?2"),
+        params![PruneReason::Expired.as_str(), Timestamp::now()],
+    )?;
+
+    if let Some(age) = policy.max_age {
+        // Apps with their own max_age are handled by that rule instead.
+        let own_age: Vec<&str> = policy
+            .source_apps
+            .iter()
+            .filter(|(_, app)| app.max_age.is_some())
+            .map(|(name, _)| name.as_str())
+            .collect();
+        conn.execute(
+            &insert_sql(
+                "created_at < ?2 AND (source_app IS NULL OR source_app NOT IN (SELECT value FROM json_each(?3)))",
+            ),
+            params![
+                PruneReason::MaxAge.as_str(),
+                Timestamp::before(age),
+                serde_json::Value::from(own_age).to_string()
+            ],
+        )?;
+    }
+    if let Some(age) = policy.primary_max_age {
+        conn.execute(
+            &insert_sql("selection = ?3 AND created_at < ?2"),
+            params![PruneReason::PrimaryMaxAge.as_str(), Timestamp::before(age), Selection::Primary],
+        )?;
+    }
+    if let Some(age) = policy.image_max_age {
+        conn.execute(
+            &insert_sql("content_type = ?3 AND created_at < ?2"),
+            params![
+                PruneReason::ImageMaxAge.as_str(),
+                Timestamp::before(age),
+                ContentType::Image.as_str()
+            ],
+        )?;
+    }
+    for (app, retention) in &policy.source_apps {
+        if let Some(age) = retention.max_age {
+            conn.execute(
+                &insert_sql("source_app = ?3 AND created_at < ?2"),
+                params![PruneReason::SourceAppMaxAge.as_str(), Timestamp::before(age), app],
+            )?;
+        }
+    }
+
+    for (app, retention) in &policy.source_apps {
+        if let Some(max) = retention.max_entries {
+            keep_newest(conn, PruneReason::SourceAppMaxEntries, Some(("source_app = ?3", app)), max)?;
+        }
+    }
+    if let Some(max) = policy.max_images {
+        let image = ContentType::Image.as_str();
+        keep_newest(conn, PruneReason::MaxImages, Some(("content_type = ?3", &image)), max)?;
+    }
+    keep_newest(conn, PruneReason::MaxHistory, None, policy.max_history)?;
+
+    if let Some(limit) = policy.max_db_size_bytes {
+        shrink_to(conn, db_size_bytes, limit)?;
+    }
+    Ok(())
+}
+
+/// `INSERT` selecting unpinned entries matching `condition` for reason `?1`.
+fn insert_sql(condition: &str) -> String {
+    format!(
+        "INSERT OR IGNORE INTO temp.retention_candidates (id, reason, size)
+         SELECT id, ?1, {ENTRY_SIZE} FROM clipboard_entries WHERE NOT pinned AND {condition}"
+    )
+}
+
+/// Select all but the newest `max` remaining entries, optionally only those
+/// matching a condition on `?3` bound to the given value.
+fn keep_newest(
+    conn: &Connection,
+    reason: PruneReason,
+    filter: Option<(&str, &dyn ToSql)>,
+    max: usize,
+) -> Result<()> {
+    let condition = filter.map_or("1", |(condition, _)| condition);
+    let sql = insert_sql(&format!(
+        "{NOT_SELECTED} AND {condition} ORDER BY created_at DESC, id DESC LIMIT -1 OFFSET ?2"
+    ));
+    let reason = reason.as_str();
+    let max = max as i64;
+    let mut values: Vec<&dyn ToSql> = vec![&reason, &max];
+    values.extend(filter.map(|(_, value)| value));
+    conn.execute(&sql, values.as_slice())?;
+    Ok(())
+}
+
+/// Select the oldest remaining entries until the content they free brings
+/// the database under `limit`. The newest entry is always kept.
+fn shrink_to(conn: &Connection, db_size_bytes: u64, limit: u64) -> Result<()> {
+    let selected: i64 =
+        conn.query_row("SELECT COALESCE(SUM(size), 0) FROM temp.retention_candidates", [], |row| row.get(0))?;
+    let mut excess = db_size_bytes.saturating_sub(selected as u64).saturating_sub(limit);
+    if excess == 0 {
+        return Ok(());
+    }
+
+    let sql = format!(
+        "SELECT id, {ENTRY_SIZE} FROM clipboard_entries
+         WHERE {NOT_SELECTED} AND NOT pinned
+           AND id != (SELECT id FROM clipboard_entries ORDER BY created_at DESC, id DESC LIMIT 1)
+         ORDER BY created_at ASC, id ASC"
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt.query([])?;
+    let mut victims = Vec::new();
+    while excess > 0 {
+        let Some(row) = rows.next()? else { break };
+        let (id, size): (i64, i64) = (row.get(0)?, row.get(1)?);
+        victims.push((id, size));
+        excess = excess.saturating_sub(size as u64);
+    }
+    drop(rows);
+
+    let mut insert = conn.prepare("INSERT INTO temp.retention_candidates (id, reason, size) VALUES (?1, ?2, ?3)")?;
+    for (id, size) in victims {
+        insert.execute(params![id, PruneReason::MaxDbSize.as_str(), size])?;
+    }
+    Ok(())
+}
+
+/// Bytes of database pages in use (excluding the freelist).
+fn used_db_bytes(conn: &Connection) -> Result<u64> {
+    let pragma = |name: &str| -> Result<i64> { Ok(conn.query_row(&format!("PRAGMA {name}"), [], |row| row.get(0))?) };
+    let used_pages = pragma("page_count")? - pragma("freelist_count")?;
+    Ok((used_pages.max(0) * pragma("page_size")?) as u64)
+}
+
+fn count_candidates(conn: &Connection) -> Result<Vec<PruneCount>> {
+    let mut stmt = conn.prepare("SELECT reason, COUNT(*), SUM(size) FROM temp.retention_candidates GROUP BY reason")?;
+    let by_reason = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(PruneReason::ALL
+        .into_iter()
+        .filter_map(|reason| {
+            by_reason.iter().find(|(name, ..)| name == reason.as_str()).map(|&(_, entries, bytes)| PruneCount {
+                reason,
+                entries: entries as u64,
+                bytes: bytes as u64,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db_in_memory;
+    use crate::db::repository::insert_entry;
+    use crate::models::entry::{compute_hash, ClipboardEntry};
+
+    fn setup() -> Connection {
+        init_db_in_memory().unwrap()
+    }
+
+    /// Insert an entry created `age_sql` ago (an SQLite modifier like '-2 hours').
+    /// Images store `text` as their blob.
+    fn insert_aged(conn: &Connection, text: &str, content_type: &str, source_app: Option<&str>, age_sql: &str) {
+        conn.execute(
+            "INSERT INTO clipboard_entries (content_type, text_content, blob_content, content_hash, source_app, created_at)
+             VALUES (?1, CASE WHEN ?1 = 'image' THEN NULL ELSE ?2 END, CASE WHEN ?1 = 'image' THEN CAST(?2 AS BLOB) END,
+                     ?3, ?4, CAST(unixepoch('now', ?5, 'subsec') * 1000 AS INTEGER))",
+            params![content_type, text, &compute_hash(text.as_bytes())[..], source_app, age_sql],
+        )
+        .unwrap();
+    }
+
+    /// Text (or image blob as text) of each entry, newest first.
+    fn texts(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT COALESCE(text_content, CAST(blob_content AS TEXT)) FROM clipboard_entries
+                 ORDER BY created_at DESC, id DESC",
+            )
+            .unwrap();
+        stmt.query_map([], |row| row.get(0)).unwrap().collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    fn count(report: &PruneReport, reason: PruneReason) -> u64 {
+        report.counts.iter().find(|c| c.reason == reason).map_or(0, |c| c.entries)
+    }
+
+    #[test]
+    fn max_history_keeps_newest() {
+        let conn = setup();
+        for i in 0..10 {
+            insert_aged(&conn, &format!("entry {i}"), "text", None, &format!("-{} seconds", 10 - i));
+        }
+
+        let report = enforce(&conn, &RetentionPolicy::max_history(5)).unwrap();
+        assert_eq!(count(&report, PruneReason::MaxHistory), 5);
+        assert_eq!(texts(&conn), ["entry 9", "entry 8", "entry 7", "entry 6", "entry 5"]);
+    }
+
+    #[test]
+    fn no_op_when_within_limits() {
+        let conn = setup();
+        insert_entry(&conn, &ClipboardEntry::from_text("hello".to_string(), None)).unwrap();
+
+        let report = enforce(&conn, &RetentionPolicy::max_history(10)).unwrap();
+        assert_eq!(report.total_entries(), 0);
+        assert_eq!(texts(&conn).len(), 1);
+    }
+
+    #[test]
+    fn max_age_deletes_old_entries() {
+        let conn = setup();
+        insert_aged(&conn, "old", "text", None, "-2 hours");
+        insert_entry(&conn, &ClipboardEntry::from_text("fresh".to_string(), None)).unwrap();
+
+        let policy = RetentionPolicy { max_age: Some(Duration::from_secs(3600)), ..RetentionPolicy::max_history(100) };
+        let report = enforce(&conn, &policy).unwrap();
+        assert_eq!(count(&report, PruneReason::MaxAge), 1);
+        assert_eq!(texts(&conn), ["fresh"]);
+    }
+
+    #[test]
+    fn primary_max_age_only_hits_primary_entries() {
+        let conn = setup();
+        for (text, selection) in [("old primary", "primary"), ("old both", "both"), ("old clipboard", "clipboard")] {
+            conn.execute(
+                "INSERT INTO clipboard_entries (content_type, text_content, content_hash, selection, created_at)
+                 VALUES ('text', ?1, ?2, ?3, CAST(unixepoch('now', '-2 hours') * 1000 AS INTEGER))",
+                params![text, &compute_hash(text.as_bytes())[..], selection],
+            )
+            .unwrap();
+        }
+        let mut fresh = ClipboardEntry::from_text("fresh primary".to_string(), None);
+        fresh.set_selection(Some(Selection::Primary));
+        insert_entry(&conn, &fresh).unwrap();
+
+        let policy =
+            RetentionPolicy { primary_max_age: Some(Duration::from_secs(3600)), ..RetentionPolicy::max_history(100) };
+        let report = enforce(&conn, &policy).unwrap();
+        assert_eq!(count(&report, PruneReason::PrimaryMaxAge), 1);
+        let texts = texts(&conn);
+        assert_eq!(texts.len(), 3);
+        assert!(!texts.contains(&"old primary".to_owned()));
+    }
+
+    #[test]
+    fn expired_ttl_is_deleted_and_future_ttl_kept() {
+        let conn = setup();
+        let mut expired = ClipboardEntry::from_text("expiring".to_string(), None);
+        expired.set_expires_at(Some(Timestamp::from_millis(946_684_800_000)));
+        insert_entry(&conn, &expired).unwrap();
+        let mut future = ClipboardEntry::from_text("future".to_string(), None);
+        future.set_expires_at(Some(Timestamp::from_millis(4_070_908_800_000)));
+        insert_entry(&conn, &future).unwrap();
+        insert_entry(&conn, &ClipboardEntry::from_text("fresh".to_string(), None)).unwrap();
+
+        // Per-entry TTLs apply even without any age limit.
+        let report = enforce(&conn, &RetentionPolicy::max_history(100)).unwrap();
+        assert_eq!(count(&report, PruneReason::Expired), 1);
+        let texts = texts(&conn);
+        assert_eq!(texts.len(), 2);
+        assert!(!texts.contains(&"expiring".to_owned()));
+    }
+
+    #[test]
+    fn image_limits_leave_text_alone() {
+        let conn = setup();
+        for i in 0..4 {
+            insert_aged(&conn, &format!("image {i}"), "image", None, &format!("-{} hours", 4 - i));
+            insert_aged(&conn, &format!("text {i}"), "text", None, "-10 hours");
+        }
+
+        let policy = RetentionPolicy {
+            max_images: Some(2),
+            image_max_age: Some(Duration::from_secs(3 * 3600 + 1800)),
+            ..RetentionPolicy::max_history(100)
+        };
+        let report = enforce(&conn, &policy).unwrap();
+        assert_eq!(count(&report, PruneReason::ImageMaxAge), 1);
+        assert_eq!(count(&report, PruneReason::MaxImages), 1);
+        let texts = texts(&conn);
+        assert_eq!(texts.iter().filter(|t| t.starts_with("text")).count(), 4);
+        assert_eq!(texts.iter().filter(|t| t.starts_with("image")).collect::<Vec<_>>(), ["image 3", "image 2"]);
+    }
+
+    #[test]
+    fn source_app_max_age_overrides_global() {
+        let conn = setup();
+        insert_aged(&conn, "keepass old", "text", Some("KeePassXC"), "-2 minutes");
+        insert_aged(&conn, "browser", "text", Some("firefox"), "-2 hours");
+        insert_aged(&conn, "notes", "text", Some("obsidian"), "-2 hours");
+
+        let mut source_apps = BTreeMap::new();
+        source_apps.insert(
+            "KeePassXC".to_owned(),
+            SourceAppRetention { max_age: Some(Duration::from_secs(60)), max_entries: None },
+        );
+        source_apps.insert(
+            "obsidian".to_owned(),
+            SourceAppRetention { max_age: Some(Duration::from_secs(30 * 86400)), max_entries: None },
+        );
+        let policy = RetentionPolicy {
+            max_age: Some(Duration::from_secs(3600)),
+            source_apps,
+            ..RetentionPolicy::max_history(100)
+        };
+        let report = enforce(&conn, &policy).unwrap();
+        assert_eq!(count(&report, PruneReason::SourceAppMaxAge), 1);
+        assert_eq!(count(&report, PruneReason::MaxAge), 1);
+        assert_eq!(texts(&conn), ["notes"]);
+    }
+
+    #[test]
+    fn source_app_max_entries_caps_one_app() {
+        let conn = setup();
+        for i in 0..3 {
+            insert_aged(&conn, &format!("term {i}"), "text", Some("kitty"), &format!("-{} seconds", 10 - i));
+        }
+        insert_aged(&conn, "other", "text", Some("firefox"), "-60 seconds");
+
+        let mut source_apps = BTreeMap::new();
+        source_apps.insert("kitty".to_owned(), SourceAppRetention { max_age: None, max_entries: Some(1) });
+        let policy = RetentionPolicy { source_apps, ..RetentionPolicy::max_history(100) };
+        let report = enforce(&conn, &policy).unwrap();
+        assert_eq!(count(&report, PruneReason::SourceAppMaxEntries), 2);
+        assert_eq!(texts(&conn), ["term 2", "other"]);
+    }
+
+    #[test]
+    fn max_db_size_drops_oldest_but_keeps_newest() {
+        let conn = setup();
+        let big = "x".repeat(64 * 1024);
+        for i in 0..4 {
+            insert_aged(&conn, &format!("{i}{big}"), "text", None, &format!("-{} seconds", 10 - i));
+        }
+
+        let used = used_db_bytes(&conn).unwrap();
+        assert!(used > 4 * 64 * 1024);
+        // Freeing 100 KB takes the two oldest 64 KB entries.
+        let limit = used - 100 * 1024;
+        let policy = RetentionPolicy { max_db_size_bytes: Some(limit), ..RetentionPolicy::max_history(100) };
+        let report = enforce(&conn, &policy).unwrap();
+        assert_eq!(report.db_size_bytes, used);
+        assert_eq!(count(&report, PruneReason::MaxDbSize), 2);
+        let left: Vec<_> = texts(&conn).iter().map(|t| t[..1].to_owned()).collect();
+        assert_eq!(left, ["3", "2"]);
+
+        // Even a limit below the newest entry never empties the history.
+        let policy = RetentionPolicy { max_db_size_bytes: Some(1), ..RetentionPolicy::max_history(100) };
+        enforce(&conn, &policy).unwrap();
+        assert_eq!(texts(&conn).len(), 1);
+    }
+
+    #[test]
+    fn entries_count_under_first_matching_rule() {
+        let conn = setup();
+        insert_aged(&conn, "old image", "image", None, "-2 hours");
+        insert_aged(&conn, "new image", "image", None, "-1 seconds");
+
+        let policy = RetentionPolicy {
+            max_age: Some(Duration::from_secs(3600)),
+            max_images: Some(1),
+            ..RetentionPolicy::max_history(100)
+        };
+        let report = enforce(&conn, &policy).unwrap();
+        assert_eq!(count(&report, PruneReason::MaxAge), 1);
+        assert_eq!(count(&report, PruneReason::MaxImages), 0);
+        assert_eq!(texts(&conn), ["new image"]);
+    }
+
+    #[test]
+    fn pinned_entries_are_kept() {
+        let conn = setup();
+        insert_aged(&conn, "pinned", "text", None, "-2 hours");
+        insert_aged(&conn, "old", "text", None, "-90 minutes");
+        for i in 0..3 {
+            insert_aged(&conn, &format!("entry {i}"), "text", None, &format!("-{} seconds", 10 - i));
+        }
+        let pinned_id: i64 =
+            conn.query_row("SELECT id FROM clipboard_entries WHERE text_content = 'pinned'", [], |row| row.get(0)).unwrap();
+        assert!(crate::db::repository::set_pinned(&conn, pinned_id, true).unwrap());
+
+        let policy = RetentionPolicy { max_age: Some(Duration::from_secs(3600)), ..RetentionPolicy::max_history(2) };
+        enforce(&conn, &policy).unwrap();
+        // The pinned entry is too old and does not take one of the two slots.
+        assert_eq!(texts(&conn), ["entry 2", "entry 1", "pinned"]);
+
+        let policy = RetentionPolicy { max_db_size_bytes: Some(1), ..RetentionPolicy::max_history(100) };
+        enforce(&conn, &policy).unwrap();
+        assert_eq!(texts(&conn), ["entry 2", "pinned"]);
+    }
+
+    #[test]
+    fn old_rule_matches_and_copy_events_are_pruned() {
+        let conn = setup();
+        insert_aged(&conn, "live", "text", None, "+0 seconds");
+        for age in ["-2 hours", "-100 days", "+0 seconds"] {
+            conn.execute(
+                "INSERT INTO rule_matches (rule, created_at) VALUES ('passwords', CAST(unixepoch('now', ?1) * 1000 AS INTEGER))",
+                [age],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO copy_events (entry_id, created_at, origin)
+                 SELECT id, CAST(unixepoch('now', ?1) * 1000 AS INTEGER), 'watch' FROM clipboard_entries",
+                [age],
+            )
+            .unwrap();
+        }
+        let count = |table: &str| {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| row.get::<_, i64>(0)).unwrap()
+        };
+
+        enforce(&conn, &RetentionPolicy::max_history(100)).unwrap();
+        assert_eq!((count("rule_matches"), count("copy_events")), (2, 2));
+        let policy = RetentionPolicy { max_age: Some(Duration::from_secs(3600)), ..RetentionPolicy::max_history(100) };
+        plan(&conn, &policy).unwrap();
+        assert_eq!((count("rule_matches"), count("copy_events")), (2, 2));
+        enforce(&conn, &policy).unwrap();
+        assert_eq!((count("rule_matches"), count("copy_events")), (1, 1));
+        assert_eq!(texts(&conn), ["live"]);
+    }
+
+    #[test]
+    fn plan_reports_without_deleting() {
+        let conn = setup();
+        for i in 0..3 {
+            insert_aged(&conn, &format!("entry {i}"), "text", None, &format!("-{} seconds", 10 - i));
+        }
+
+        let policy = RetentionPolicy::max_history(1);
+        let report = plan(&conn, &policy).unwrap();
+        assert_eq!(report.total_entries(), 2);
+        assert_eq!(report.total_bytes(), 14);
+        assert_eq!(texts(&conn).len(), 3);
+
+        assert_eq!(enforce(&conn, &policy).unwrap(), report);
+        assert_eq!(texts(&conn), ["entry 2"]);
+    }
+
+    #[test]
+    fn has_age_limits() {
+        assert!(!RetentionPolicy::max_history(10).has_age_limits());
+        let mut policy = RetentionPolicy::max_history(10);
+        policy
+            .source_apps
+            .insert("x".to_owned(), SourceAppRetention { max_age: Some(Duration::from_secs(1)), max_entries: None });
+        assert!(policy.has_age_limits());
+    }
+}
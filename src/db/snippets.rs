@@ -0,0 +1,101 @@
+//! Named snippets, kept in their own table: retention and `clio dedupe`
+//! only touch history entries, so snippets stay until `clio snippet rm`.
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::errors::Result;
+use crate::models::entry::Timestamp;
+use crate::models::snippet::Snippet;
+
+/// Check that `name` can be used as a snippet name: non-empty, without
+/// whitespace (it is typed on the command line and listed for dmenu).
+pub fn check_name(name: &str) -> std::result::Result<(), String> {
+    if name.is_empty() {
+        return Err("snippet name must not be empty".to_owned());
+    }
+    if name.contains(char::is_whitespace) {
+        return Err(format!("snippet name '{name}' must not contain whitespace"));
+    }
+    Ok(())
+}
+
+/// Save snippet `name`, replacing the body of an existing one.
+/// Returns true if the snippet is new.
+pub fn save(conn: &Connection, name: &str, body: &str) -> Result<bool> {
+    let existed = get(conn, name)?.is_some();
+    conn.execute(
+        "INSERT INTO snippets (name, body, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(name) DO UPDATE SET body = excluded.body, updated_at = excluded.updated_at",
+        params![name, body, Timestamp::now()],
+    )?;
+    Ok(!existed)
+}
+
+pub fn get(conn: &Connection, name: &str) -> Result<Option<Snippet>> {
+    let snippet = conn
+        .query_row("SELECT name, body, updated_at FROM snippets WHERE name = ?1", params![name], from_row)
+        .optional()?;
+    Ok(snippet)
+}
+
+/// All snippets, by name.
+pub fn all(conn: &Connection) -> Result<Vec<Snippet>> {
+    let mut stmt = conn.prepare_cached("SELECT name, body, updated_at FROM snippets ORDER BY name")?;
+    let snippets = stmt.query_map([], from_row)?.collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(snippets)
+}
+
+/// Delete snippet `name`. Returns false if there is none.
+pub fn remove(conn: &Connection, name: &str) -> Result<bool> {
+    Ok(conn.execute("DELETE FROM snippets WHERE name = ?1", params![name])? > 0)
+}
+
+fn from_row(row: &Row<'_>) -> rusqlite::Result<Snippet> {
+    Ok(Snippet { name: row.get(0)?, body: row.get(1)?, updated_at: row.get(2)? })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db_in_memory;
+    use crate::db::repository::{save_entry, StorageOptions};
+    use crate::db::retention::{self, RetentionPolicy};
+    use crate::models::entry::{ClipboardEntry, CopyOrigin};
+
+    #[test]
+    fn save_get_and_remove() {
+        let conn = init_db_in_memory().unwrap();
+        assert!(save(&conn, "sig", "Regards").unwrap());
+        assert!(!save(&conn, "sig", "Best regards").unwrap());
+        assert!(save(&conn, "logs", "kubectl logs").unwrap());
+
+        assert_eq!(get(&conn, "sig").unwrap().unwrap().body, "Best regards");
+        let names: Vec<_> = all(&conn).unwrap().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, ["logs", "sig"]);
+
+        assert!(remove(&conn, "sig").unwrap());
+        assert!(!remove(&conn, "sig").unwrap());
+        assert!(get(&conn, "sig").unwrap().is_none());
+    }
+
+    #[test]
+    fn snippets_survive_pruning() {
+        let conn = init_db_in_memory().unwrap();
+        save(&conn, "sig", "Regards").unwrap();
+        let policy = RetentionPolicy::max_history(1);
+        for text in ["a", "b", "c"] {
+            let entry = ClipboardEntry::from_text(text.to_owned(), None);
+            save_entry(&conn, &entry, &policy, &StorageOptions::default(), CopyOrigin::Watch).unwrap();
+        }
+        retention::enforce(&conn, &policy).unwrap();
+
+        assert_eq!(all(&conn).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn names_are_checked() {
+        assert!(check_name("kubectl-logs").is_ok());
+        assert!(check_name("").is_err());
+        assert!(check_name("two words").is_err());
+    }
+}
@@ -0,0 +1,123 @@
+//! User tags grouping entries into collections such as "sql" or "k8s".
+//! Tags are added by `clio tag` or by the `tags` action of a rule, and
+//! filter `clio list`, search and the history window.
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::errors::Result;
+
+/// Condition restricting `clipboard_entries` rows to those with any of the
+/// tags in the `:tags` parameter, a JSON array (see `filter_param`).
+pub(crate) const FILTER_SQL: &str = "id IN (SELECT entry_id FROM entry_tags JOIN tags ON tags.id = tag_id \
+     WHERE tags.name IN (SELECT value FROM json_each(:tags)))";
+
+/// `:tags` value of `FILTER_SQL` for `tags`.
+pub(crate) fn filter_param(tags: &[String]) -> String {
+    serde_json::Value::from(tags).to_string()
+}
+
+/// Check that `name` can be used as a tag: non-empty, without whitespace or
+/// commas (tag lists on the command line are comma-separated).
+pub fn check_name(name: &str) -> std::result::Result<(), String> {
+    if name.is_empty() {
+        return Err("tag must not be empty".to_owned());
+    }
+    if name.contains(|c: char| c.is_whitespace() || c == ',') {
+        return Err(format!("tag '{name}' must not contain whitespace or commas"));
+    }
+    Ok(())
+}
+
+/// Tag entry `entry_id` with `name`. Returns false if it already had the tag.
+pub fn add(conn: &Connection, entry_id: i64, name: &str) -> Result<bool> {
+    conn.prepare_cached("INSERT OR IGNORE INTO tags (name) VALUES (?1)")?.execute(params![name])?;
+    let added = conn
+        .prepare_cached("INSERT OR IGNORE INTO entry_tags (entry_id, tag_id) SELECT ?1, id FROM tags WHERE name = ?2")?
+        .execute(params![entry_id, name])?;
+    Ok(added > 0)
+}
+
+/// Remove tag `name` from entry `entry_id`. Returns false if it did not have it.
+pub fn remove(conn: &Connection, entry_id: i64, name: &str) -> Result<bool> {
+    let Some(tag_id) = conn
+        .query_row("SELECT id FROM tags WHERE name = ?1", params![name], |row| row.get::<_, i64>(0))
+        .optional()?
+    else {
+        return Ok(false);
+    };
+    let removed =
+        conn.execute("DELETE FROM entry_tags WHERE entry_id = ?1 AND tag_id = ?2", params![entry_id, tag_id])?;
+    Ok(removed > 0)
+}
+
+/// Tags of entry `entry_id`, by name.
+pub fn for_entry(conn: &Connection, entry_id: i64) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT name FROM tags JOIN entry_tags ON tags.id = tag_id WHERE entry_id = ?1 ORDER BY name",
+    )?;
+    let names = stmt
+        .query_map(params![entry_id], |row| row.get(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(names)
+}
+
+/// Tags in use with their number of entries, by name.
+pub fn all(conn: &Connection) -> Result<Vec<(String, u64)>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT name, COUNT(*) FROM tags JOIN entry_tags ON tags.id = tag_id GROUP BY tags.id ORDER BY name",
+    )?;
+    let tags = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as u64)))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::init_db_in_memory;
+    use crate::db::repository::{delete_entry, insert_entry};
+    use crate::models::entry::ClipboardEntry;
+
+    fn insert(conn: &Connection, text: &str) -> i64 {
+        insert_entry(conn, &ClipboardEntry::from_text(text.to_owned(), None)).unwrap()
+    }
+
+    #[test]
+    fn add_and_remove() {
+        let conn = init_db_in_memory().unwrap();
+        let id = insert(&conn, "SELECT 1");
+
+        assert!(add(&conn, id, "sql").unwrap());
+        assert!(!add(&conn, id, "sql").unwrap());
+        assert!(add(&conn, id, "customer-x").unwrap());
+        assert_eq!(for_entry(&conn, id).unwrap(), ["customer-x", "sql"]);
+
+        assert!(remove(&conn, id, "sql").unwrap());
+        assert!(!remove(&conn, id, "sql").unwrap());
+        assert!(!remove(&conn, id, "unknown").unwrap());
+        assert_eq!(for_entry(&conn, id).unwrap(), ["customer-x"]);
+    }
+
+    #[test]
+    fn deleted_entries_lose_their_tags() {
+        let conn = init_db_in_memory().unwrap();
+        let first = insert(&conn, "kubectl get pods");
+        let second = insert(&conn, "kubectl logs");
+        add(&conn, first, "k8s").unwrap();
+        add(&conn, second, "k8s").unwrap();
+        add(&conn, second, "logs").unwrap();
+        assert_eq!(all(&conn).unwrap(), [("k8s".to_owned(), 2), ("logs".to_owned(), 1)]);
+
+        delete_entry(&conn, second).unwrap();
+        assert_eq!(all(&conn).unwrap(), [("k8s".to_owned(), 1)]);
+    }
+
+    #[test]
+    fn names_are_checked() {
+        assert!(check_name("k8s").is_ok());
+        assert!(check_name("").is_err());
+        assert!(check_name("two words").is_err());
+        assert!(check_name("a,b").is_err());
+    }
+}
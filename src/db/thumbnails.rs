@@ -0,0 +1,154 @@
+//! Downscaled copies of image entries for the history window.
+//!
+//! Images larger than `image_preview_max_px` get a PNG thumbnail in the
+//! `thumbnail` column when they are stored, and preview queries return it
+//! instead of the full image. Smaller images, and images stored before
+//! thumbnails existed, are previewed from the image itself; `backfill`
+//! (`clio db thumbnails`) fills in the missing ones.
+
+use std::io::Cursor;
+
+use image::imageops::FilterType;
+use image::{ImageFormat, ImageReader};
+use rusqlite::{params, Connection};
+
+use super::repository::IMAGE_SQL;
+use crate::errors::Result;
+
+/// PNG thumbnail of `image` fitting in `max_px` × `max_px`, or `None` if the
+/// image already fits and serves as its own preview.
+pub(crate) fn create(image: &[u8], max_px: u32) -> Result<Option<Vec<u8>>> {
+    let reader = ImageReader::new(Cursor::new(image)).with_guessed_format()?;
+    let (width, height) = reader.into_dimensions()?;
+    if width <= max_px && height <= max_px {
+        return Ok(None);
+    }
+    let decoded = image::load_from_memory(image)?;
+    let thumbnail = decoded.resize(max_px, max_px, FilterType::Triangle);
+    let mut buf = Cursor::new(Vec::new());
+    thumbnail.write_to(&mut buf, ImageFormat::Png)?;
+    Ok(Some(buf.into_inner()))
+}
+
+/// Result of `backfill`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BackfillReport {
+    /// Images checked (those without a thumbnail).
+    pub checked: usize,
+    /// Thumbnails written.
+    pub created: usize,
+    /// IDs of images that could not be read or decoded.
+    pub failed: Vec<i64>,
+}
+
+/// Create missing thumbnails for images larger than `max_px`. With `rebuild`,
+/// existing thumbnails are dropped first, e.g. after `image_preview_max_px`
+/// changed.
+pub fn backfill(conn: &Connection, max_px: u32, rebuild: bool) -> Result<BackfillReport> {
+    if rebuild {
+        conn.execute("UPDATE clipboard_entries SET thumbnail = NULL WHERE thumbnail IS NOT NULL", [])?;
+    }
+    let ids = conn
+        .prepare("SELECT id FROM clipboard_entries WHERE content_type = 'image' AND thumbnail IS NULL ORDER BY id")?
+        .query_map([], |row| row.get::<_, i64>(0))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    let mut report = BackfillReport { checked: ids.len(), ..BackfillReport::default() };
+    let mut image = conn.prepare(&format!("SELECT {IMAGE_SQL} FROM clipboard_entries WHERE id = ?1"))?;
+    for id in ids {
+        let thumbnail = image
+            .query_row(params![id], |row| row.get::<_, Vec<u8>>(0))
+            .map_err(Into::into)
+            .and_then(|bytes| create(&bytes, max_px));
+        match thumbnail {
+            Ok(Some(thumbnail)) => {
+                conn.execute("UPDATE clipboard_entries SET thumbnail = ?2 WHERE id = ?1", params![id, thumbnail])?;
+                report.created += 1;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                log::warn!("cannot create thumbnail for entry {id}: {e}");
+                report.failed.push(id);
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::HistorySort;
+    use crate::db::init_db_in_memory;
+    use crate::db::repository::{insert_entry, list_entries_preview, save_entry, EntryFilter, StorageOptions};
+    use crate::db::retention::RetentionPolicy;
+    use crate::models::entry::{ClipboardEntry, CopyOrigin, EntryContent};
+
+    fn image_entry(width: u32, height: u32) -> ClipboardEntry {
+        ClipboardEntry::from_image(width, height, vec![200; (width * height * 4) as usize], None).unwrap()
+    }
+
+    fn dimensions(png: &[u8]) -> (u32, u32) {
+        image::load_from_memory(png).map(|img| (img.width(), img.height())).unwrap()
+    }
+
+    #[test]
+    fn create_keeps_aspect_ratio() {
+        let EntryContent::Image(png) = image_entry(400, 100).content else { unreachable!() };
+        let thumbnail = create(&png, 64).unwrap().unwrap();
+        assert_eq!(dimensions(&thumbnail), (64, 16));
+        assert!(create(&png, 400).unwrap().is_none());
+    }
+
+    #[test]
+    fn backfill_creates_missing_thumbnails() {
+        let conn = init_db_in_memory().unwrap();
+        insert_entry(&conn, &image_entry(300, 150)).unwrap();
+        insert_entry(&conn, &image_entry(20, 20)).unwrap();
+        insert_entry(&conn, &ClipboardEntry::from_text("text".to_owned(), None)).unwrap();
+
+        let report = backfill(&conn, 100, false).unwrap();
+        assert_eq!(report, BackfillReport { checked: 2, created: 1, failed: vec![] });
+        assert_eq!(backfill(&conn, 100, false).unwrap().created, 0);
+
+        // The preview returns the thumbnail for the large image, the image itself for the small one.
+        let sizes: Vec<_> = list_entries_preview(&conn, 10, None, 10, &EntryFilter::default(), HistorySort::default())
+            .unwrap()
+            .entries
+            .iter()
+            .filter_map(|e| match &e.content {
+                EntryContent::Image(bytes) => Some(dimensions(bytes)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(sizes, [(20, 20), (100, 50)]);
+
+        let report = backfill(&conn, 50, true).unwrap();
+        assert_eq!((report.checked, report.created), (2, 1));
+    }
+
+    #[test]
+    fn large_images_get_thumbnail_when_saved() {
+        let conn = init_db_in_memory().unwrap();
+        let options = StorageOptions { thumbnail_max_px: Some(100), ..StorageOptions::default() };
+        save_entry(&conn, &image_entry(300, 150), &RetentionPolicy::max_history(10), &options, CopyOrigin::Watch).unwrap();
+        save_entry(&conn, &image_entry(20, 20), &RetentionPolicy::max_history(10), &options, CopyOrigin::Watch).unwrap();
+
+        let with_thumbnail: i64 =
+            conn.query_row("SELECT COUNT(thumbnail) FROM clipboard_entries", [], |row| row.get(0)).unwrap();
+        assert_eq!(with_thumbnail, 1);
+        assert_eq!(backfill(&conn, 100, false).unwrap().created, 0);
+    }
+
+    #[test]
+    fn backfill_reports_undecodable_images() {
+        let conn = init_db_in_memory().unwrap();
+        conn.execute(
+            "INSERT INTO clipboard_entries (content_type, blob_content, content_hash) VALUES ('image', x'00', x'01')",
+            [],
+        )
+        .unwrap();
+        let report = backfill(&conn, 100, false).unwrap();
+        assert_eq!(report.failed.len(), 1);
+    }
+}
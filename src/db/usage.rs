@@ -0,0 +1,235 @@
+//! Usage report behind `clio stats`: what the history holds and how it is
+//! used, from the entries, the `copy_events` timeline and the rule matches
+//! `clio watch` records.
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use super::blob_store;
+use crate::errors::Result;
+use crate::models::entry::{ContentHash, Timestamp};
+
+/// Record that the action rules `rules` matched a clipboard change.
+pub fn record_rule_matches(conn: &Connection, rules: &[String]) -> Result<()> {
+    let mut stmt = conn.prepare_cached("INSERT INTO rule_matches (rule, created_at) VALUES (?1, ?2)")?;
+    let now = Timestamp::now();
+    for rule in rules {
+        stmt.execute(params![rule, now])?;
+    }
+    Ok(())
+}
+
+/// Number of entries, copies or matches sharing `name`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Count {
+    pub name: String,
+    pub count: u64,
+}
+
+/// The entry taking the most space.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LargestEntry {
+    pub id: i64,
+    pub content_type: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Usage {
+    /// Entries last copied in the period.
+    pub entries: u64,
+    pub by_type: Vec<Count>,
+    /// Most entries first; entries without a source app count as "unknown".
+    pub by_source_app: Vec<Count>,
+    /// Stored bytes of the entries, compressed or in the blob store as they are.
+    pub total_bytes: u64,
+    pub largest: Option<LargestEntry>,
+    /// Entries with a TTL that has not run out yet.
+    pub expiring: u64,
+    pub masked: u64,
+    /// Copies in the period, including re-copies of the same entry.
+    pub copies: u64,
+    /// Copies by `clio select` or the history window.
+    pub uses: u64,
+    /// Action rule matches, most frequent first.
+    pub rule_matches: Vec<Count>,
+    /// Copies by hour of the day (local time), midnight first.
+    pub copies_by_hour: [u64; 24],
+}
+
+impl Usage {
+    /// Hours with the most copies, busiest first, at most `n`.
+    pub fn busiest_hours(&self, n: usize) -> Vec<(u32, u64)> {
+        let mut hours: Vec<(u32, u64)> =
+            (0..24).zip(self.copies_by_hour).filter(|&(_, copies)| copies > 0).collect();
+        hours.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hours.truncate(n);
+        hours
+    }
+}
+
+/// Stored size of a row: inline text and blob, without the thumbnail.
+const INLINE_BYTES_SQL: &str = "COALESCE(octet_length(text_content), 0) + COALESCE(length(blob_content), 0)";
+
+/// Usage of entries copied at or after `since` (all time when `None`).
+pub fn usage(conn: &Connection, since: Option<Timestamp>) -> Result<Usage> {
+    let since = since.unwrap_or(Timestamp::from_millis(0));
+    let (entries, expiring, masked) = conn.query_row(
+        "SELECT COUNT(*), COUNT(CASE WHEN expires_at > ?2 THEN 1 END), COUNT(mask_text)
+         FROM clipboard_entries WHERE created_at >= ?1",
+        params![since, Timestamp::now()],
+        |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64, row.get::<_, i64>(2)? as u64)),
+    )?;
+    let (total_bytes, largest) = sizes(conn, since)?;
+    let (copies, uses) = conn.query_row(
+        "SELECT COUNT(*), COUNT(CASE WHEN origin IN ('select', 'history') THEN 1 END)
+         FROM copy_events WHERE created_at >= ?1",
+        params![since],
+        |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64)),
+    )?;
+
+    let mut copies_by_hour = [0; 24];
+    let mut stmt = conn.prepare(
+        "SELECT CAST(strftime('%H', created_at / 1000, 'unixepoch', 'localtime') AS INTEGER), COUNT(*)
+         FROM copy_events WHERE created_at >= ?1 GROUP BY 1",
+    )?;
+    for row in stmt.query_map(params![since], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))? {
+        let (hour, count) = row?;
+        if let Some(slot) = usize::try_from(hour).ok().and_then(|hour| copies_by_hour.get_mut(hour)) {
+            *slot = count as u64;
+        }
+    }
+
+    Ok(Usage {
+        entries,
+        by_type: counts(
+            conn,
+            "SELECT content_type, COUNT(*) FROM clipboard_entries WHERE created_at >= ?1
+             GROUP BY 1 ORDER BY 2 DESC, 1",
+            since,
+        )?,
+        by_source_app: counts(
+            conn,
+            "SELECT COALESCE(source_app, 'unknown'), COUNT(*) FROM clipboard_entries WHERE created_at >= ?1
+             GROUP BY 1 ORDER BY 2 DESC, 1",
+            since,
+        )?,
+        total_bytes,
+        largest,
+        expiring,
+        masked,
+        copies,
+        uses,
+        rule_matches: counts(
+            conn,
+            "SELECT rule, COUNT(*) FROM rule_matches WHERE created_at >= ?1 GROUP BY 1 ORDER BY 2 DESC, 1",
+            since,
+        )?,
+        copies_by_hour,
+    })
+}
+
+/// Run a `name, count` query taking `since` as `?1`.
+fn counts(conn: &Connection, sql: &str, since: Timestamp) -> Result<Vec<Count>> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt
+        .query_map(params![since], |row| Ok(Count { name: row.get(0)?, count: row.get::<_, i64>(1)? as u64 }))?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+/// Total stored bytes and the largest entry. Entries in the blob store are
+/// measured by their file.
+fn sizes(conn: &Connection, since: Timestamp) -> Result<(u64, Option<LargestEntry>)> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, content_type, {INLINE_BYTES_SQL}, CASE WHEN storage IS NOT NULL THEN content_hash END
+         FROM clipboard_entries WHERE created_at >= ?1"
+    ))?;
+    let rows = stmt.query_map(params![since], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?, row.get::<_, Option<Vec<u8>>>(3)?))
+    })?;
+    let mut total = 0;
+    let mut largest: Option<LargestEntry> = None;
+    for row in rows {
+        let (id, content_type, inline, stored_hash) = row?;
+        let file = stored_hash
+            .and_then(|hash| ContentHash::try_from(hash).ok())
+            .and_then(|hash| blob_store::file_size(conn, &hash))
+            .unwrap_or(0);
+        let bytes = inline as u64 + file;
+        total += bytes;
+        if largest.as_ref().is_none_or(|l| bytes > l.bytes) {
+            largest = Some(LargestEntry { id, content_type, bytes });
+        }
+    }
+    Ok((total, largest))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Timelike;
+
+    use super::*;
+    use crate::db::init_db_in_memory;
+    use crate::db::repository::{save_entry, StorageOptions};
+    use crate::db::retention::RetentionPolicy;
+    use crate::models::entry::{ClipboardEntry, CopyOrigin};
+
+    fn save(conn: &Connection, entry: &ClipboardEntry) -> i64 {
+        save_entry(conn, entry, &RetentionPolicy::max_history(100), &StorageOptions::default(), CopyOrigin::Watch)
+            .unwrap()
+            .id
+    }
+
+    fn text(text: &str, source_app: Option<&str>) -> ClipboardEntry {
+        ClipboardEntry::from_text(text.to_owned(), source_app.map(str::to_owned))
+    }
+
+    #[test]
+    fn counts_entries_sizes_and_copies() {
+        let conn = init_db_in_memory().unwrap();
+        save(&conn, &text("a", Some("kitty")));
+        let big = save(&conn, &text(&"x".repeat(1000), Some("firefox")));
+        let mut secret = text("secret", Some("kitty"));
+        secret.set_mask_text(Some("***".to_owned()));
+        secret.set_expires_at(Some(Timestamp::after(std::time::Duration::from_secs(60))));
+        save(&conn, &secret);
+        save(&conn, &ClipboardEntry::from_image(2, 2, vec![0; 16], None).unwrap());
+        crate::db::copy_events::record_use(&conn, big, CopyOrigin::Select).unwrap();
+
+        let usage = usage(&conn, None).unwrap();
+        assert_eq!(usage.entries, 4);
+        let count = |name: &str, count| Count { name: name.to_owned(), count };
+        assert_eq!(usage.by_type, [count("text", 3), count("image", 1)]);
+        assert_eq!(usage.by_source_app, [count("kitty", 2), count("firefox", 1), count("unknown", 1)]);
+        assert_eq!(usage.largest, Some(LargestEntry { id: big, content_type: "text".to_owned(), bytes: 1000 }));
+        assert!(usage.total_bytes > 1000 + 1 + 6, "{usage:?}");
+        assert_eq!((usage.expiring, usage.masked), (1, 1));
+        assert_eq!((usage.copies, usage.uses), (5, 1));
+
+        let hour = Timestamp::now().to_local().hour();
+        assert_eq!(usage.busiest_hours(3), [(hour, 5)]);
+    }
+
+    #[test]
+    fn since_limits_the_period() {
+        let conn = init_db_in_memory().unwrap();
+        let old = save(&conn, &text("old", None));
+        save(&conn, &text("new", None));
+        conn.execute("UPDATE clipboard_entries SET created_at = 1000 WHERE id = ?1", [old]).unwrap();
+        conn.execute("UPDATE copy_events SET created_at = 1000 WHERE entry_id = ?1", [old]).unwrap();
+
+        let usage = usage(&conn, Some(Timestamp::from_millis(2000))).unwrap();
+        assert_eq!((usage.entries, usage.copies), (1, 1));
+    }
+
+    #[test]
+    fn rule_matches_are_counted() {
+        let conn = init_db_in_memory().unwrap();
+        record_rule_matches(&conn, &["passwords".to_owned(), "urls".to_owned()]).unwrap();
+        record_rule_matches(&conn, &["passwords".to_owned()]).unwrap();
+
+        let names: Vec<_> = usage(&conn, None).unwrap().rule_matches.into_iter().map(|c| (c.name, c.count)).collect();
+        assert_eq!(names, [("passwords".to_owned(), 2), ("urls".to_owned(), 1)]);
+    }
+}
@@ -14,6 +14,9 @@ pub enum AppError {
     #[error("config error: {0}")]
     Config(String),
 
+    #[error("snippet error: {0}")]
+    Snippet(String),
+
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -0,0 +1,135 @@
+//! Finding and signalling the running `clio watch`.
+//!
+//! Each watcher holds an `flock` on `<db_stem>.watch.lock` in the runtime
+//! directory (see `cli::watch`). The file holds the watcher's PID and, when it
+//! was started with `--profile` or `CLIO_PROFILE`, the profile it is pinned to.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::errors::Result;
+
+/// A running `clio watch`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watcher {
+    pub pid: u32,
+    /// Profile selected by `--profile` or `CLIO_PROFILE`; reloads keep it,
+    /// whatever `clio profile switch` chose.
+    pub pinned_profile: Option<String>,
+}
+
+impl Watcher {
+    /// Lock file contents: the PID, then the pinned profile if any.
+    pub(crate) fn lock_file_contents(&self) -> String {
+        match &self.pinned_profile {
+            Some(profile) => format!("{}\n{profile}\n", self.pid),
+            None => format!("{}\n", self.pid),
+        }
+    }
+
+    pub(crate) fn parse(contents: &str) -> Option<Self> {
+        let mut lines = contents.lines();
+        let pid = lines.next()?.trim().parse().ok()?;
+        let pinned_profile = lines.next().map(str::trim).filter(|name| !name.is_empty()).map(str::to_owned);
+        Some(Self { pid, pinned_profile })
+    }
+}
+
+/// Lock file of the watcher recording into `db_path`: `<db_stem>.watch.lock`
+/// in the runtime directory.
+pub(crate) fn lock_path(db_path: &Path) -> PathBuf {
+    let stem = db_path.file_stem().map_or_else(|| "clio".into(), |stem| stem.to_string_lossy());
+    config::runtime_dir().join(format!("{stem}.watch.lock"))
+}
+
+/// The `clio watch` recording into `db_path`, if any.
+pub fn running_watcher(db_path: &Path) -> Option<Watcher> {
+    watcher_at(&lock_path(db_path))
+}
+
+/// The watcher holding the lock at `path`, or `None` if no watcher runs.
+#[cfg(unix)]
+pub(crate) fn watcher_at(path: &Path) -> Option<Watcher> {
+    let mut file = File::open(path).ok()?;
+    // Probing with a shared lock: it only fails while a watcher holds the exclusive one.
+    if try_flock(&file, libc::LOCK_SH).ok()? {
+        return None;
+    }
+    let mut contents = String::new();
+    file.read_to_string(&mut contents).ok()?;
+    Watcher::parse(&contents)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn watcher_at(_path: &Path) -> Option<Watcher> {
+    None
+}
+
+/// Ask the `clio watch` recording into `db_path` to reload its configuration
+/// (SIGHUP). Returns the watcher, or `None` if none is running.
+pub fn request_reload(db_path: &Path) -> Result<Option<Watcher>> {
+    let Some(watcher) = running_watcher(db_path) else {
+        return Ok(None);
+    };
+    send_sighup(watcher.pid)?;
+    Ok(Some(watcher))
+}
+
+#[cfg(unix)]
+fn send_sighup(pid: u32) -> Result<()> {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("invalid watcher PID {pid}")).into());
+    };
+    // SAFETY: plain syscall with no memory arguments.
+    if unsafe { libc::kill(pid, libc::SIGHUP) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send_sighup(_pid: u32) -> Result<()> {
+    Ok(())
+}
+
+/// Non-blocking `flock`. `Ok(false)` means another process holds a conflicting lock.
+#[cfg(unix)]
+pub(crate) fn try_flock(file: &File, operation: libc::c_int) -> std::io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: valid open file descriptor owned by `file`.
+    let ret = unsafe { libc::flock(file.as_raw_fd(), operation | libc::LOCK_NB) };
+    if ret == 0 {
+        return Ok(true);
+    }
+    let err = std::io::Error::last_os_error();
+    if err.raw_os_error() == Some(libc::EWOULDBLOCK) {
+        Ok(false)
+    } else {
+        Err(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_file_contents_roundtrip() {
+        for pinned_profile in [None, Some("work".to_owned())] {
+            let watcher = Watcher { pid: 42, pinned_profile };
+            assert_eq!(Watcher::parse(&watcher.lock_file_contents()), Some(watcher));
+        }
+        assert_eq!(Watcher::parse(""), None);
+    }
+
+    #[test]
+    fn each_database_has_its_own_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let work = lock_path(&dir.path().join("work.db"));
+        assert!(work.ends_with("work.watch.lock"), "{}", work.display());
+        assert_ne!(work, lock_path(&dir.path().join("clio.db")));
+    }
+}
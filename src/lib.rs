@@ -4,6 +4,7 @@ pub mod clipboard;
 pub mod config;
 pub mod db;
 pub(crate) mod errors;
+pub mod ipc;
 pub mod models;
 pub(crate) mod platform;
 pub mod time_fmt;
@@ -4,6 +4,7 @@ use log::debug;
 
 use clio::cli::{Cli, Commands};
 use clio::{clipboard, config, db};
+use clio::db::repository::EntryFilter;
 
 fn main() -> anyhow::Result<()> {
     // Fast path: _serve-clipboard needs no config/db — handle before parsing anything heavy.
@@ -16,7 +17,8 @@ fn main() -> anyhow::Result<()> {
     debug!("clio starting");
 
     let cli = Cli::parse();
-    let config = config::load_config(cli.config.as_deref()).context("failed to load config")?;
+    let overrides = cli.config_overrides();
+    let config = config::load_config_with_overrides(cli.config.as_deref(), &overrides).context("failed to load config")?;
     debug!("config loaded, max_history={}, watch_interval={}ms", config.max_history, config.watch_interval.as_millis());
 
     let db_path = config::resolve_db_path(&config);
@@ -29,27 +31,77 @@ fn main() -> anyhow::Result<()> {
         }
         Commands::Watch => {
             let conn = db::init_db(&db_path).context("failed to initialize database")?;
-            clio::cli::watch::run(&conn, &config)
+            let config_path = cli.config.clone().unwrap_or_else(config::default_config_path);
+            clio::cli::watch::run(conn, &config, &config_path, &overrides)
         }
-        Commands::History => clio::cli::history::run(cli.config.as_deref(), db_path),
+        Commands::History => clio::cli::history::run(cli.config.as_deref(), &overrides, db_path),
         Commands::List {
             ref format,
             preview_length,
             limit,
+            ref selection,
+            ref tag,
+            sort,
         } => {
             let conn = db::init_db(&db_path).context("failed to initialize database")?;
-            clio::cli::list::run(&conn, format, preview_length, limit)
+            let filter = EntryFilter { selections: selection.iter().map(|&s| s.into()).collect(), tags: tag.clone() };
+            let sort = sort.unwrap_or(config.sort);
+            clio::cli::list::run(&conn, format, preview_length, limit, &filter, sort, &config.date_format)
         }
         Commands::Select { ref source } => {
             let conn = db::init_db(&db_path).context("failed to initialize database")?;
             clio::cli::select::run(&conn, source)
         }
+        Commands::Tag { id, ref action } => {
+            let conn = db::init_db(&db_path).context("failed to initialize database")?;
+            clio::cli::tag::run(&conn, id, action.as_ref())
+        }
+        Commands::Annotate { id, ref title, ref note } => {
+            let conn = db::init_db(&db_path).context("failed to initialize database")?;
+            clio::cli::annotate::run(&conn, id, title.clone(), note.clone())
+        }
+        Commands::Pin { id } => {
+            let conn = db::init_db(&db_path).context("failed to initialize database")?;
+            clio::cli::pin::run(&conn, id, true)
+        }
+        Commands::Unpin { id } => {
+            let conn = db::init_db(&db_path).context("failed to initialize database")?;
+            clio::cli::pin::run(&conn, id, false)
+        }
+        Commands::Timeline { id, at } => {
+            let conn = db::init_db(&db_path).context("failed to initialize database")?;
+            clio::cli::timeline::run(&conn, id, at, &config.datetime_format)
+        }
+        Commands::Snippet { ref command } => {
+            let conn = db::init_db(&db_path).context("failed to initialize database")?;
+            clio::cli::snippet::run(&conn, &config, command)
+        }
+        Commands::Prune { dry_run } => {
+            let conn = db::init_db(&db_path).context("failed to initialize database")?;
+            clio::cli::prune::run(&conn, &config, dry_run)
+        }
+        Commands::Dedupe { images: _, distance, dry_run } => {
+            let conn = db::init_db(&db_path).context("failed to initialize database")?;
+            clio::cli::dedupe::run(&conn, &config, distance, dry_run)
+        }
+        Commands::Stats { since, format } => {
+            let conn = db::init_db(&db_path).context("failed to initialize database")?;
+            clio::cli::stats::run(&conn, since, format)
+        }
+        Commands::Db { ref command } => {
+            let mut conn = db::init_db(&db_path).context("failed to initialize database")?;
+            clio::cli::db::run(&mut conn, &config, command)
+        }
+        Commands::Profile { ref command } => clio::cli::profile::run(command, &config),
+        Commands::Service { ref command } => {
+            clio::cli::service::run(command, cli.config.as_deref(), &config)
+        }
         Commands::Config { ref command } => {
             let config_path = cli
                 .config
                 .clone()
                 .unwrap_or_else(config::default_config_path);
-            clio::cli::config::run(&config_path, command)
+            clio::cli::config::run(&config_path, &overrides, command)
         }
         // Handled by early return above; unreachable via normal flow.
         Commands::ServeClipboard => clipboard::serve::run().map_err(Into::into),
@@ -1,83 +1,75 @@
 use std::fmt;
-use std::io::Cursor;
+use std::path::PathBuf;
 use std::time::Duration;
 
-use chrono::{NaiveDateTime, Utc};
-use image::{ImageFormat, RgbaImage};
+use chrono::{DateTime, Local, Utc};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
 
-use crate::errors::{AppError, Result};
+use crate::errors::Result;
+use crate::models::images::{self, ImageStorage};
 
-/// ISO 8601 timestamp format matching SQLite's strftime('%Y-%m-%dT%H:%M:%f').
-/// Use this constant for all chrono format calls to ensure consistency.
-pub const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.3f";
-
-/// ISO 8601 timestamp. Guaranteed to be in `TIMESTAMP_FORMAT`.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Timestamp(String);
+/// Instant in milliseconds since the Unix epoch, as stored in the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(i64);
 
 impl Timestamp {
-    /// Create from a pre-validated string (e.g. from DB or strftime).
-    /// Does NOT re-parse — caller guarantees format correctness.
-    pub(crate) fn from_raw(s: String) -> Self {
-        Self(s)
+    pub const fn from_millis(ms: i64) -> Self {
+        Self(ms)
     }
 
-    /// Parse and validate a timestamp string.
-    pub fn parse(s: &str) -> std::result::Result<Self, chrono::ParseError> {
-        NaiveDateTime::parse_from_str(s, TIMESTAMP_FORMAT)?;
-        Ok(Self(s.to_owned()))
+    pub const fn as_millis(self) -> i64 {
+        self.0
     }
 
-    /// Current UTC time.
+    /// Current time.
     pub fn now() -> Self {
-        Self(Utc::now().format(TIMESTAMP_FORMAT).to_string())
+        Self(Utc::now().timestamp_millis())
     }
 
-    /// Current UTC time + duration (for TTL).
+    /// Current time + duration (for TTL).
     pub fn after(ttl: Duration) -> Self {
-        let chrono_d = match chrono::Duration::from_std(ttl) {
-            Ok(d) => d,
-            Err(_) => {
-                log::warn!("TTL duration {ttl:?} too large, using max duration");
-                chrono::Duration::MAX
-            }
-        };
-        let expires = Utc::now() + chrono_d;
-        Self(expires.format(TIMESTAMP_FORMAT).to_string())
+        let ms = i64::try_from(ttl.as_millis()).unwrap_or_else(|_| {
+            log::warn!("TTL duration {ttl:?} too large, using max duration");
+            i64::MAX
+        });
+        Self(Self::now().0.saturating_add(ms))
     }
 
-    /// Parse into `NaiveDateTime` for calculations.
-    /// Infallible — we validated at construction.
-    pub fn to_naive(&self) -> NaiveDateTime {
-        NaiveDateTime::parse_from_str(&self.0, TIMESTAMP_FORMAT)
-            .expect("Timestamp invariant broken: invalid format")
+    /// Current time - duration (for age cutoffs).
+    pub fn before(age: Duration) -> Self {
+        let ms = i64::try_from(age.as_millis()).unwrap_or(i64::MAX);
+        Self(Self::now().0.saturating_sub(ms))
     }
 
-    pub fn as_str(&self) -> &str {
-        &self.0
+    /// Time left until this instant; zero once it has passed.
+    pub fn remaining(self) -> Duration {
+        Duration::from_millis(u64::try_from(self.0.saturating_sub(Self::now().0)).unwrap_or(0))
     }
-}
 
-impl AsRef<str> for Timestamp {
-    fn as_ref(&self) -> &str {
-        &self.0
+    pub fn to_utc(self) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(self.0).unwrap_or(if self.0 < 0 {
+            DateTime::<Utc>::MIN_UTC
+        } else {
+            DateTime::<Utc>::MAX_UTC
+        })
+    }
+
+    /// The same instant in the local timezone, for display.
+    pub fn to_local(self) -> DateTime<Local> {
+        self.to_utc().with_timezone(&Local)
     }
 }
 
+/// RFC 3339 in UTC with milliseconds, e.g. `2024-05-01T12:30:00.000Z`.
 impl fmt::Display for Timestamp {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(&self.0)
+        write!(f, "{}", self.to_utc().format("%Y-%m-%dT%H:%M:%S%.3fZ"))
     }
 }
 
 impl rusqlite::types::FromSql for Timestamp {
     fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
-        let s = String::column_result(value)?;
-        Timestamp::parse(&s).map_err(|e| {
-            rusqlite::types::FromSqlError::Other(
-                format!("invalid timestamp '{s}': {e}").into(),
-            )
-        })
+        i64::column_result(value).map(Self)
     }
 }
 
@@ -94,6 +86,7 @@ pub type ContentHash = [u8; 32];
 pub(crate) enum ContentType {
     Text,
     Image,
+    Files,
     Unknown,
 }
 
@@ -102,6 +95,7 @@ impl ContentType {
         match self {
             Self::Text => "text",
             Self::Image => "image",
+            Self::Files => "files",
             Self::Unknown => "unknown",
         }
     }
@@ -110,6 +104,7 @@ impl ContentType {
         match s {
             "text" => Self::Text,
             "image" => Self::Image,
+            "files" => Self::Files,
             other => {
                 log::warn!("unknown content_type in database: {other:?}, treating as text");
                 Self::Unknown
@@ -118,11 +113,231 @@ impl ContentType {
     }
 }
 
+/// Which selection an entry was captured from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    /// CLIPBOARD (Ctrl+C / Ctrl+V).
+    Clipboard,
+    /// PRIMARY (mouse selection / middle-click paste).
+    Primary,
+    /// Seen in both CLIPBOARD and PRIMARY.
+    Both,
+    /// Stored via `clio copy`.
+    Cli,
+}
+
+impl Selection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Clipboard => "clipboard",
+            Self::Primary => "primary",
+            Self::Both => "both",
+            Self::Cli => "cli",
+        }
+    }
+
+    pub fn from_db_str(s: &str) -> Option<Self> {
+        match s {
+            "clipboard" => Some(Self::Clipboard),
+            "primary" => Some(Self::Primary),
+            "both" => Some(Self::Both),
+            "cli" => Some(Self::Cli),
+            _ => None,
+        }
+    }
+
+    /// Combine the stored selection with the one an entry was seen in again.
+    /// `Cli` is sticky: re-detecting a `clio copy` by the watcher keeps it.
+    pub fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Self::Cli, _) | (_, Self::Cli) => Self::Cli,
+            _ => Self::Both,
+        }
+    }
+
+    /// Stored values a filter on `self` matches: `both` entries match
+    /// both the `clipboard` and the `primary` filter.
+    pub fn matching(self) -> &'static [Selection] {
+        match self {
+            Self::Clipboard => &[Self::Clipboard, Self::Both],
+            Self::Primary => &[Self::Primary, Self::Both],
+            Self::Both => &[Self::Both],
+            Self::Cli => &[Self::Cli],
+        }
+    }
+}
+
+impl rusqlite::types::FromSql for Selection {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        let s = value.as_str()?;
+        Self::from_db_str(s).ok_or_else(|| {
+            rusqlite::types::FromSqlError::Other(format!("invalid selection: {s:?}").into())
+        })
+    }
+}
+
+impl rusqlite::types::ToSql for Selection {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        Ok(self.as_str().into())
+    }
+}
+
+/// What put an entry on the clipboard, recorded per copy event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CopyOrigin {
+    /// Copied in another app and captured by `clio watch`.
+    Watch,
+    /// `clio copy`.
+    Copy,
+    /// `clio select`.
+    Select,
+    /// Chosen in the history window.
+    History,
+}
+
+impl CopyOrigin {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Watch => "watch",
+            Self::Copy => "copy",
+            Self::Select => "select",
+            Self::History => "history",
+        }
+    }
+
+    pub fn from_db_str(s: &str) -> Option<Self> {
+        match s {
+            "watch" => Some(Self::Watch),
+            "copy" => Some(Self::Copy),
+            "select" => Some(Self::Select),
+            "history" => Some(Self::History),
+            _ => None,
+        }
+    }
+}
+
+impl rusqlite::types::FromSql for CopyOrigin {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        let s = value.as_str()?;
+        Self::from_db_str(s).ok_or_else(|| {
+            rusqlite::types::FromSqlError::Other(format!("invalid copy origin: {s:?}").into())
+        })
+    }
+}
+
+impl rusqlite::types::ToSql for CopyOrigin {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        Ok(self.as_str().into())
+    }
+}
+
+/// Characters percent-encoded in `file://` URIs (matches what file managers emit).
+const URI_PATH_ESCAPE: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// Whether the files were copied or cut in the file manager.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileOperation {
+    Copy,
+    Cut,
+}
+
+impl FileOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Copy => "copy",
+            Self::Cut => "cut",
+        }
+    }
+}
+
+/// Files copied from a file manager: a `text/uri-list` plus the copy/cut operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileList {
+    pub operation: FileOperation,
+    pub uris: Vec<String>,
+}
+
+impl FileList {
+    /// Build from local paths, percent-encoding them into `file://` URIs.
+    pub fn from_paths(paths: &[PathBuf], operation: FileOperation) -> Self {
+        let uris = paths
+            .iter()
+            .map(|p| format!("file://{}", utf8_percent_encode(&p.to_string_lossy(), URI_PATH_ESCAPE)))
+            .collect();
+        Self { operation, uris }
+    }
+
+    /// Parse the `x-special/gnome-copied-files` format: an operation line
+    /// (`copy` or `cut`) followed by one URI per line.
+    /// Returns `None` if the operation is unknown or there are no URIs.
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut lines = s.lines().map(str::trim).filter(|l| !l.is_empty());
+        let operation = match lines.next()? {
+            "copy" => FileOperation::Copy,
+            "cut" => FileOperation::Cut,
+            _ => return None,
+        };
+        let uris: Vec<String> = lines.map(str::to_owned).collect();
+        if uris.is_empty() {
+            return None;
+        }
+        Some(Self { operation, uris })
+    }
+
+    /// Serialize in `x-special/gnome-copied-files` format (the DB storage format).
+    pub fn to_gnome_format(&self) -> String {
+        let mut out = self.operation.as_str().to_owned();
+        for uri in &self.uris {
+            out.push('\n');
+            out.push_str(uri);
+        }
+        out
+    }
+
+    /// Local paths for `file://` URIs; other schemes are skipped.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.uris
+            .iter()
+            .filter_map(|uri| uri.strip_prefix("file://"))
+            .map(|p| PathBuf::from(percent_decode_str(p).decode_utf8_lossy().as_ref()))
+            .collect()
+    }
+
+    /// Display names (last path component) of all files.
+    pub fn file_names(&self) -> Vec<String> {
+        self.paths()
+            .iter()
+            .map(|p| {
+                p.file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| p.display().to_string())
+            })
+            .collect()
+    }
+}
+
 /// Content payload of a clipboard entry.
 #[derive(Debug, Clone)]
 pub enum EntryContent {
     Text(String),
-    Image(Vec<u8>), // PNG bytes
+    Image(Vec<u8>), // PNG, WebP or JPEG bytes
+    Files(FileList),
 }
 
 impl EntryContent {
@@ -130,6 +345,7 @@ impl EntryContent {
         match self {
             Self::Text(_) => ContentType::Text,
             Self::Image(_) => ContentType::Image,
+            Self::Files(_) => ContentType::Files,
         }
     }
 
@@ -152,10 +368,37 @@ impl EntryContent {
         }
     }
 
+    pub fn files(&self) -> Option<&FileList> {
+        match self {
+            Self::Files(f) => Some(f),
+            _ => None,
+        }
+    }
+
+    /// Value stored in the `text_content` column: the text itself, or the
+    /// serialized file list for file entries.
+    pub(crate) fn text_column(&self) -> Option<String> {
+        match self {
+            Self::Text(s) => Some(s.clone()),
+            Self::Image(_) => None,
+            Self::Files(f) => Some(f.to_gnome_format()),
+        }
+    }
+
     pub fn size_bytes(&self) -> usize {
         match self {
             Self::Text(s) => s.len(),
             Self::Image(b) => b.len(),
+            Self::Files(f) => f.uris.iter().map(|u| u.len() + 1).sum(),
+        }
+    }
+
+    pub(crate) fn compute_hash(&self) -> ContentHash {
+        match self {
+            Self::Text(t) => compute_hash(t.as_bytes()),
+            // Undecodable bytes get a hash no image can have.
+            Self::Image(b) => images::decoded_hash(b).unwrap_or_else(|_| compute_hash(b)),
+            Self::Files(f) => compute_hash(f.to_gnome_format().as_bytes()),
         }
     }
 }
@@ -171,6 +414,12 @@ pub struct ClipboardEntry {
     pub(crate) metadata: Option<String>,
     pub(crate) expires_at: Option<Timestamp>,
     pub(crate) mask_text: Option<String>,
+    pub(crate) selection: Option<Selection>,
+    /// User-given title, shown instead of the content.
+    pub(crate) title: Option<String>,
+    pub(crate) note: Option<String>,
+    /// Kept by retention until unpinned.
+    pub(crate) pinned: bool,
 }
 
 impl ClipboardEntry {
@@ -186,20 +435,41 @@ impl ClipboardEntry {
             metadata: None,
             expires_at: None,
             mask_text: None,
+            selection: None,
+            title: None,
+            note: None,
+            pinned: false,
         }
     }
 
+    /// Image entry stored as PNG.
     pub fn from_image(
         width: u32,
         height: u32,
         rgba_bytes: Vec<u8>,
         source_app: Option<String>,
     ) -> Result<Self> {
-        let png_bytes = encode_rgba_to_png(width, height, rgba_bytes)?;
-        let hash = compute_hash(&png_bytes);
+        Self::from_image_with(width, height, rgba_bytes, None, &ImageStorage::default(), source_app)
+    }
+
+    /// Image entry encoded per `storage`; `encoded` is the image as the source
+    /// offered it. The hash covers the pixels, not the stored bytes: those
+    /// given for lossless encodings, and for lossy ones the decoded pixels
+    /// that restoring the entry puts on the clipboard.
+    pub fn from_image_with(
+        width: u32,
+        height: u32,
+        rgba_bytes: Vec<u8>,
+        encoded: Option<Vec<u8>>,
+        storage: &ImageStorage,
+        source_app: Option<String>,
+    ) -> Result<Self> {
+        let pixels = images::pixel_hash(width, height, &rgba_bytes);
+        let bytes = storage.encode(width, height, rgba_bytes, encoded)?;
+        let hash = if images::is_lossless(&bytes) { pixels } else { images::decoded_hash(&bytes)? };
         Ok(Self {
             id: None,
-            content: EntryContent::Image(png_bytes),
+            content: EntryContent::Image(bytes),
             content_hash: hash,
             source_app,
             source_title: None,
@@ -207,9 +477,33 @@ impl ClipboardEntry {
             metadata: None,
             expires_at: None,
             mask_text: None,
+            selection: None,
+            title: None,
+            note: None,
+            pinned: false,
         })
     }
 
+    pub fn from_files(files: FileList, source_app: Option<String>) -> Self {
+        let content = EntryContent::Files(files);
+        let hash = content.compute_hash();
+        Self {
+            id: None,
+            content,
+            content_hash: hash,
+            source_app,
+            source_title: None,
+            created_at: None,
+            metadata: None,
+            expires_at: None,
+            mask_text: None,
+            selection: None,
+            title: None,
+            note: None,
+            pinned: false,
+        }
+    }
+
     /// Read-only access to content.
     pub fn content(&self) -> &EntryContent { &self.content }
 
@@ -230,17 +524,19 @@ impl ClipboardEntry {
     pub fn metadata(&self) -> Option<&str> { self.metadata.as_deref() }
     pub fn expires_at(&self) -> Option<&Timestamp> { self.expires_at.as_ref() }
     pub fn mask_text(&self) -> Option<&str> { self.mask_text.as_deref() }
+    pub fn selection(&self) -> Option<Selection> { self.selection }
+    pub fn title(&self) -> Option<&str> { self.title.as_deref() }
+    pub fn note(&self) -> Option<&str> { self.note.as_deref() }
+    pub fn pinned(&self) -> bool { self.pinned }
 
     pub fn set_source_title(&mut self, title: Option<String>) { self.source_title = title; }
     pub fn set_expires_at(&mut self, ts: Option<Timestamp>) { self.expires_at = ts; }
     pub fn set_mask_text(&mut self, mask: Option<String>) { self.mask_text = mask; }
+    pub fn set_selection(&mut self, selection: Option<Selection>) { self.selection = selection; }
 
     /// Replace content and recompute hash atomically, preserving the invariant.
     pub fn set_content(&mut self, content: EntryContent) {
-        let hash = match &content {
-            EntryContent::Text(t) => compute_hash(t.as_bytes()),
-            EntryContent::Image(b) => compute_hash(b),
-        };
+        let hash = content.compute_hash();
         self.content = content;
         self.content_hash = hash;
     }
@@ -250,23 +546,23 @@ pub fn compute_hash(data: &[u8]) -> ContentHash {
     *blake3::hash(data).as_bytes()
 }
 
-pub fn encode_rgba_to_png(width: u32, height: u32, rgba_bytes: Vec<u8>) -> Result<Vec<u8>> {
-    let img = RgbaImage::from_raw(width, height, rgba_bytes).ok_or_else(|| {
-        AppError::Image(image::ImageError::Parameter(
-            image::error::ParameterError::from_kind(
-                image::error::ParameterErrorKind::DimensionMismatch,
-            ),
-        ))
-    })?;
-    let mut buf = Cursor::new(Vec::new());
-    img.write_to(&mut buf, ImageFormat::Png)?;
-    Ok(buf.into_inner())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_timestamp_display_is_utc() {
+        assert_eq!(Timestamp::from_millis(1_714_566_600_250).to_string(), "2024-05-01T12:30:00.250Z");
+    }
+
+    #[test]
+    fn test_timestamp_remaining() {
+        assert_eq!(Timestamp::from_millis(0).remaining(), Duration::ZERO);
+        let left = Timestamp::after(Duration::from_secs(60)).remaining();
+        assert!(left > Duration::from_secs(55) && left <= Duration::from_secs(60), "{left:?}");
+        assert!(Timestamp::after(Duration::MAX) > Timestamp::now());
+    }
+
     #[test]
     fn test_compute_hash_deterministic() {
         let h1 = compute_hash(b"hello");
@@ -282,6 +578,23 @@ mod tests {
         assert_ne!(h1, h2);
     }
 
+    #[test]
+    fn test_selection_merge() {
+        assert_eq!(Selection::Primary.merge(Selection::Primary), Selection::Primary);
+        assert_eq!(Selection::Primary.merge(Selection::Clipboard), Selection::Both);
+        assert_eq!(Selection::Both.merge(Selection::Primary), Selection::Both);
+        assert_eq!(Selection::Cli.merge(Selection::Clipboard), Selection::Cli);
+        assert_eq!(Selection::Primary.merge(Selection::Cli), Selection::Cli);
+    }
+
+    #[test]
+    fn test_selection_db_roundtrip() {
+        for sel in [Selection::Clipboard, Selection::Primary, Selection::Both, Selection::Cli] {
+            assert_eq!(Selection::from_db_str(sel.as_str()), Some(sel));
+        }
+        assert_eq!(Selection::from_db_str("other"), None);
+    }
+
     #[test]
     fn test_from_text() {
         let entry = ClipboardEntry::from_text("test".to_string(), None);
@@ -301,14 +614,64 @@ mod tests {
         assert_eq!(&blob[1..4], b"PNG");
     }
 
+    #[test]
+    fn test_image_hash_ignores_encoding() {
+        let rgba = vec![255u8; 4 * 2 * 2];
+        let png = ClipboardEntry::from_image(2, 2, rgba.clone(), None).unwrap();
+        let storage = ImageStorage { encoding: crate::config::ImageEncoding::Webp, quality: 85 };
+        let webp = ClipboardEntry::from_image_with(2, 2, rgba.clone(), None, &storage, None).unwrap();
+        assert_ne!(png.content.blob(), webp.content.blob());
+        assert_eq!(png.content_hash, webp.content_hash);
+        assert_eq!(png.content_hash, images::pixel_hash(2, 2, &rgba));
+        assert_eq!(webp.content.compute_hash(), webp.content_hash);
+    }
+
     #[test]
     fn test_content_type_roundtrip() {
         assert_eq!(ContentType::from_db_str("text"), ContentType::Text);
         assert_eq!(ContentType::from_db_str("image"), ContentType::Image);
+        assert_eq!(ContentType::from_db_str("files"), ContentType::Files);
         assert_eq!(ContentType::from_db_str("unknown"), ContentType::Unknown);
         assert_eq!(ContentType::from_db_str("other"), ContentType::Unknown);
     }
 
+    #[test]
+    fn test_file_list_gnome_format_roundtrip() {
+        let files = FileList::from_paths(
+            &[PathBuf::from("/home/user/My Report.pdf"), PathBuf::from("/tmp/a.txt")],
+            FileOperation::Cut,
+        );
+        let serialized = files.to_gnome_format();
+        assert_eq!(
+            serialized,
+            "cut\nfile:///home/user/My%20Report.pdf\nfile:///tmp/a.txt"
+        );
+        assert_eq!(FileList::parse(&serialized), Some(files));
+    }
+
+    #[test]
+    fn test_file_list_parse_rejects_invalid() {
+        assert!(FileList::parse("").is_none());
+        assert!(FileList::parse("copy\n").is_none());
+        assert!(FileList::parse("move\nfile:///tmp/a").is_none());
+    }
+
+    #[test]
+    fn test_file_list_paths_and_names() {
+        let files = FileList::parse("copy\nfile:///home/user/My%20Report.pdf\nhttps://example.com/x").unwrap();
+        assert_eq!(files.paths(), vec![PathBuf::from("/home/user/My Report.pdf")]);
+        assert_eq!(files.file_names(), vec!["My Report.pdf".to_string()]);
+    }
+
+    #[test]
+    fn test_from_files() {
+        let files = FileList::from_paths(&[PathBuf::from("/tmp/a.txt")], FileOperation::Copy);
+        let entry = ClipboardEntry::from_files(files, None);
+        assert_eq!(entry.content.content_type(), ContentType::Files);
+        assert!(entry.content.text().is_none());
+        assert_eq!(entry.content.text_column().as_deref(), Some("copy\nfile:///tmp/a.txt"));
+    }
+
     #[test]
     fn test_content_size_bytes() {
         let entry = ClipboardEntry::from_text("hello".to_string(), None);
@@ -0,0 +1,221 @@
+//! Storage encodings of image entries, and the pixel hash that identifies an
+//! image whatever it is encoded as.
+
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ExtendedColorType, ImageFormat, RgbaImage};
+
+use crate::config::{Config, ImageEncoding};
+use crate::errors::{AppError, Result};
+use crate::models::entry::ContentHash;
+
+/// Selection targets carrying an encoded image, in order of preference.
+/// Compressed first: `original` keeps these bytes, and a PNG offered next to a
+/// JPEG or WebP is usually converted on the fly and several times larger.
+pub(crate) const IMAGE_TARGETS: &[(&str, ImageFormat)] = &[
+    ("image/jpeg", ImageFormat::Jpeg),
+    ("image/webp", ImageFormat::WebP),
+    ("image/png", ImageFormat::Png),
+];
+
+/// How captured images are encoded before they are stored.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageStorage {
+    pub encoding: ImageEncoding,
+    /// JPEG quality, 1-100.
+    pub quality: u8,
+}
+
+impl Default for ImageStorage {
+    fn default() -> Self {
+        Self { encoding: ImageEncoding::default(), quality: 85 }
+    }
+}
+
+impl ImageStorage {
+    pub fn from_config(config: &Config) -> Self {
+        Self { encoding: config.image_encoding, quality: config.image_quality }
+    }
+
+    /// Bytes to store for a captured image. `encoded` is the image as the
+    /// source offered it (see `IMAGE_TARGETS`), kept as-is by `original`.
+    pub fn encode(&self, width: u32, height: u32, rgba: Vec<u8>, encoded: Option<Vec<u8>>) -> Result<Vec<u8>> {
+        let img = rgba_image(width, height, rgba)?;
+        let mut buf = Cursor::new(Vec::new());
+        match (self.encoding, encoded) {
+            (ImageEncoding::Original, Some(bytes)) => return Ok(bytes),
+            (ImageEncoding::Original | ImageEncoding::Png, None) | (ImageEncoding::Png, Some(_)) => {
+                img.write_to(&mut buf, ImageFormat::Png)?;
+            }
+            (ImageEncoding::Webp, _) => {
+                WebPEncoder::new_lossless(&mut buf).encode(img.as_raw(), width, height, ExtendedColorType::Rgba8)?;
+            }
+            (ImageEncoding::Jpeg, _) => {
+                let rgb = DynamicImage::ImageRgba8(img).to_rgb8();
+                JpegEncoder::new_with_quality(&mut buf, self.quality).encode_image(&rgb)?;
+            }
+        }
+        Ok(buf.into_inner())
+    }
+}
+
+fn rgba_image(width: u32, height: u32, rgba: Vec<u8>) -> Result<RgbaImage> {
+    RgbaImage::from_raw(width, height, rgba).ok_or_else(|| {
+        AppError::Image(image::ImageError::Parameter(
+            image::error::ParameterError::from_kind(
+                image::error::ParameterErrorKind::DimensionMismatch,
+            ),
+        ))
+    })
+}
+
+/// Hash of an image's dimensions and RGBA pixels. The watch loop hashes the
+/// pixels it reads and entries store the same hash, so an image deduplicates
+/// no matter how it was encoded.
+pub fn pixel_hash(width: u32, height: u32, rgba: &[u8]) -> ContentHash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&width.to_le_bytes());
+    hasher.update(&height.to_le_bytes());
+    hasher.update(rgba);
+    *hasher.finalize().as_bytes()
+}
+
+/// Decode a PNG, WebP or JPEG image to RGBA.
+pub fn decode(bytes: &[u8]) -> Result<RgbaImage> {
+    Ok(image::load_from_memory(bytes)?.to_rgba8())
+}
+
+/// `pixel_hash` of an encoded image.
+pub fn decoded_hash(bytes: &[u8]) -> Result<ContentHash> {
+    let img = decode(bytes)?;
+    Ok(pixel_hash(img.width(), img.height(), img.as_raw()))
+}
+
+/// Whether decoding `bytes` gives back exactly the pixels that were encoded:
+/// true for PNG and lossless WebP, false for JPEG and lossy WebP, whose
+/// pixels can no longer be checked against the stored hash.
+pub fn is_lossless(bytes: &[u8]) -> bool {
+    match image::guess_format(bytes) {
+        Ok(ImageFormat::Png) => true,
+        // The first chunk after the RIFF header: "VP8L" is lossless, "VP8 "
+        // lossy, and "VP8X" (extended) may hold either.
+        Ok(ImageFormat::WebP) => bytes.get(12..16) == Some(b"VP8L".as_slice()),
+        _ => false,
+    }
+}
+
+/// 64-bit difference hash (dHash) of an encoded image: one bit per pair of
+/// neighbouring pixels in a 9×8 grayscale downscale. Near-identical images,
+/// e.g. re-encoded or slightly changed screenshots, differ in few bits.
+pub fn perceptual_hash(bytes: &[u8]) -> Result<u64> {
+    let small = image::load_from_memory(bytes)?.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash = (hash << 1) | u64::from(small[(x, y)][0] > small[(x + 1, y)][0]);
+        }
+    }
+    Ok(hash)
+}
+
+/// Number of bits in which two perceptual hashes differ.
+pub fn hash_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Encoding name for display, e.g. "PNG".
+pub fn format_name(bytes: &[u8]) -> &'static str {
+    match image::guess_format(bytes) {
+        Ok(ImageFormat::Png) => "PNG",
+        Ok(ImageFormat::WebP) => "WebP",
+        Ok(ImageFormat::Jpeg) => "JPEG",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 16x8 gradient, so lossy encoders actually change pixels.
+    fn gradient() -> (u32, u32, Vec<u8>) {
+        let (width, height) = (16, 8);
+        let rgba = (0..width * height)
+            .flat_map(|i| [(i * 7) as u8, (i * 13) as u8, (i * 29) as u8, 255])
+            .collect();
+        (width, height, rgba)
+    }
+
+    fn storage(encoding: ImageEncoding) -> ImageStorage {
+        ImageStorage { encoding, quality: 50 }
+    }
+
+    #[test]
+    fn lossless_encodings_keep_pixel_hash() {
+        let (width, height, rgba) = gradient();
+        let hash = pixel_hash(width, height, &rgba);
+        for encoding in [ImageEncoding::Original, ImageEncoding::Png, ImageEncoding::Webp] {
+            let bytes = storage(encoding).encode(width, height, rgba.clone(), None).unwrap();
+            assert!(is_lossless(&bytes), "{encoding}");
+            assert_eq!(decoded_hash(&bytes).unwrap(), hash, "{encoding}");
+        }
+    }
+
+    #[test]
+    fn jpeg_is_lossy() {
+        let (width, height, rgba) = gradient();
+        let bytes = storage(ImageEncoding::Jpeg).encode(width, height, rgba, None).unwrap();
+        assert_eq!(format_name(&bytes), "JPEG");
+        assert!(!is_lossless(&bytes));
+        assert_eq!(decode(&bytes).unwrap().dimensions(), (16, 8));
+    }
+
+    #[test]
+    fn original_keeps_source_encoding() {
+        let (width, height, rgba) = gradient();
+        let webp = storage(ImageEncoding::Webp).encode(width, height, rgba.clone(), None).unwrap();
+
+        let kept = storage(ImageEncoding::Original).encode(width, height, rgba.clone(), Some(webp.clone())).unwrap();
+        assert_eq!(kept, webp);
+        let png = storage(ImageEncoding::Png).encode(width, height, rgba, Some(webp)).unwrap();
+        assert_eq!(format_name(&png), "PNG");
+    }
+
+    /// 64x64 grayscale pattern with structure in both directions.
+    fn waves(phase: f32) -> Vec<u8> {
+        (0..64 * 64)
+            .flat_map(|i| {
+                let (x, y) = ((i % 64) as f32, (i / 64) as f32);
+                let v = (128.0 + 100.0 * ((x + phase) / 5.0).sin() * (y / 9.0).cos()) as u8;
+                [v, v, v, 255]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn perceptual_hash_survives_encoding_and_small_edits() {
+        let png = |rgba| ImageStorage::default().encode(64, 64, rgba, None).unwrap();
+        let hash = perceptual_hash(&png(waves(0.0))).unwrap();
+
+        let jpeg = storage(ImageEncoding::Jpeg).encode(64, 64, waves(0.0), None).unwrap();
+        assert!(hash_distance(hash, perceptual_hash(&jpeg).unwrap()) <= 2);
+        let mut edited = waves(0.0);
+        edited[..4 * 64 * 2].fill(0); // top two rows
+        assert!(hash_distance(hash, perceptual_hash(&png(edited)).unwrap()) <= 4);
+        assert!(hash_distance(hash, perceptual_hash(&png(waves(15.0))).unwrap()) > 16);
+    }
+
+    #[test]
+    fn pixel_hash_covers_dimensions() {
+        let rgba = vec![0u8; 4 * 6];
+        assert_ne!(pixel_hash(2, 3, &rgba), pixel_hash(3, 2, &rgba));
+    }
+
+    #[test]
+    fn encode_rejects_dimension_mismatch() {
+        assert!(ImageStorage::default().encode(2, 2, vec![0; 4], None).is_err());
+    }
+}
@@ -1,3 +1,5 @@
 pub mod entry;
+pub mod images;
+pub mod snippet;
 
 pub use entry::{ClipboardEntry, Timestamp};
@@ -0,0 +1,197 @@
+//! Named snippets and their placeholders, expanded each time a snippet is used.
+
+use std::collections::HashMap;
+
+use crate::clipboard::{self, ClipboardContent};
+use crate::errors::{AppError, Result};
+use crate::models::entry::Timestamp;
+
+/// Reusable text stored apart from the history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub name: String,
+    pub body: String,
+    pub updated_at: Timestamp,
+}
+
+impl Snippet {
+    /// Names of the `{prompt:NAME}` placeholders, once each, in order.
+    pub fn prompts(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        for (_, placeholder) in placeholders(&self.body) {
+            if let Placeholder::Prompt(name) = placeholder {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    /// Body with every placeholder replaced by `resolve`. Text in braces that
+    /// is not a placeholder is kept as is.
+    pub fn expand<E>(
+        &self,
+        mut resolve: impl FnMut(&Placeholder<'_>) -> std::result::Result<String, E>,
+    ) -> std::result::Result<String, E> {
+        let mut out = String::with_capacity(self.body.len());
+        let mut rest = 0;
+        for (span, placeholder) in placeholders(&self.body) {
+            out.push_str(&self.body[rest..span.start]);
+            out.push_str(&resolve(&placeholder)?);
+            rest = span.end;
+        }
+        out.push_str(&self.body[rest..]);
+        Ok(out)
+    }
+
+    /// Expand the placeholders for use now. `answers` holds the values of the
+    /// `{prompt:NAME}` placeholders.
+    pub fn expand_now(&self, answers: &HashMap<String, String>) -> Result<String> {
+        self.expand_now_with(answers, |var| std::env::var(var).ok())
+    }
+
+    /// [`Snippet::expand_now`], looking up `{env:VAR}` placeholders with `env`.
+    fn expand_now_with(&self, answers: &HashMap<String, String>, env: impl Fn(&str) -> Option<String>) -> Result<String> {
+        let now = chrono::Local::now();
+        let mut clipboard_text: Option<String> = None;
+        self.expand(|placeholder| match *placeholder {
+            Placeholder::Date => Ok(now.format("%Y-%m-%d").to_string()),
+            Placeholder::Time => Ok(now.format("%H:%M").to_string()),
+            Placeholder::Clipboard => {
+                if clipboard_text.is_none() {
+                    match clipboard::read_clipboard()? {
+                        ClipboardContent::Text(text) => clipboard_text = Some(text),
+                        _ => return Err(AppError::Clipboard("{clipboard} needs text on the clipboard".to_owned())),
+                    }
+                }
+                Ok(clipboard_text.clone().unwrap_or_default())
+            }
+            Placeholder::Env(var) => {
+                env(var).ok_or_else(|| AppError::Snippet(format!("environment variable {var} is not set")))
+            }
+            Placeholder::Prompt(name) => {
+                answers.get(name).cloned().ok_or_else(|| AppError::Snippet(format!("no value for {{prompt:{name}}}")))
+            }
+        })
+    }
+}
+
+/// A `{...}` in a snippet body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placeholder<'a> {
+    /// `{date}`: today as `YYYY-MM-DD`.
+    Date,
+    /// `{time}`: the local time as `HH:MM`.
+    Time,
+    /// `{clipboard}`: the text on the clipboard.
+    Clipboard,
+    /// `{env:VAR}`: an environment variable.
+    Env(&'a str),
+    /// `{prompt:NAME}`: asked for when the snippet is used.
+    Prompt(&'a str),
+}
+
+impl<'a> Placeholder<'a> {
+    fn parse(inner: &'a str) -> Option<Self> {
+        match inner {
+            "date" => Some(Self::Date),
+            "time" => Some(Self::Time),
+            "clipboard" => Some(Self::Clipboard),
+            _ => match inner.split_once(':')? {
+                ("env", var) if !var.is_empty() => Some(Self::Env(var)),
+                ("prompt", name) if !name.is_empty() => Some(Self::Prompt(name)),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Placeholders of `body` with their byte ranges, in order.
+fn placeholders(body: &str) -> Vec<(std::ops::Range<usize>, Placeholder<'_>)> {
+    let mut found = Vec::new();
+    let mut pos = 0;
+    while let Some(open) = body[pos..].find('{').map(|i| pos + i) {
+        let inner_start = open + 1;
+        let Some(close) = body[inner_start..].find(['{', '}']).map(|i| inner_start + i) else {
+            break;
+        };
+        if body.as_bytes()[close] == b'}' {
+            if let Some(placeholder) = Placeholder::parse(&body[inner_start..close]) {
+                found.push((open..close + 1, placeholder));
+                pos = close + 1;
+                continue;
+            }
+        }
+        pos = inner_start;
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snippet(body: &str) -> Snippet {
+        Snippet { name: "test".to_owned(), body: body.to_owned(), updated_at: Timestamp::from_millis(0) }
+    }
+
+    fn expand(body: &str) -> String {
+        snippet(body)
+            .expand(|p| {
+                Ok::<_, ()>(match p {
+                    Placeholder::Date => "2024-05-01".to_owned(),
+                    Placeholder::Time => "12:30".to_owned(),
+                    Placeholder::Clipboard => "copied".to_owned(),
+                    Placeholder::Env(var) => format!("${var}"),
+                    Placeholder::Prompt(name) => format!("<{name}>"),
+                })
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn placeholders_are_expanded() {
+        assert_eq!(expand("kubectl -n {prompt:Namespace} logs {clipboard}"), "kubectl -n <Namespace> logs copied");
+        assert_eq!(expand("{date} {time} {env:USER}"), "2024-05-01 12:30 $USER");
+        assert_eq!(expand("no placeholders"), "no placeholders");
+    }
+
+    #[test]
+    fn other_braces_are_kept() {
+        assert_eq!(expand("fn main() { {date} }"), "fn main() { 2024-05-01 }");
+        assert_eq!(expand("{{date}}"), "{2024-05-01}");
+        assert_eq!(expand("{env:} {prompt:} {unknown} {date"), "{env:} {prompt:} {unknown} {date");
+        assert_eq!(expand("{\"a\": {time}}"), "{\"a\": 12:30}");
+    }
+
+    #[test]
+    fn prompts_are_listed_once() {
+        let s = snippet("{prompt:Namespace} {prompt:Pod} {prompt:Namespace} {date}");
+        assert_eq!(s.prompts(), ["Namespace", "Pod"]);
+        assert!(snippet("{date}").prompts().is_empty());
+    }
+
+    #[test]
+    fn expand_now_uses_answers_and_env() {
+        let snippet = snippet("kubectl -n {prompt:Namespace} logs {env:CLIO_TEST_POD} # {date}");
+        let answers = HashMap::from([("Namespace".to_owned(), "prod".to_owned())]);
+        let env = |var: &str| (var == "CLIO_TEST_POD").then(|| "api-0".to_owned());
+
+        let text = snippet.expand_now_with(&answers, env).unwrap();
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        assert_eq!(text, format!("kubectl -n prod logs api-0 # {today}"));
+        assert!(snippet.expand_now_with(&HashMap::new(), env).is_err());
+        let err = snippet.expand_now_with(&answers, |_| None).unwrap_err().to_string();
+        assert!(err.contains("CLIO_TEST_POD"), "{err}");
+    }
+
+    #[test]
+    fn resolve_errors_stop_expansion() {
+        let result = snippet("{date} {env:MISSING}").expand(|p| match p {
+            Placeholder::Env(var) => Err(format!("{var} is not set")),
+            _ => Ok(String::new()),
+        });
+        assert_eq!(result, Err("MISSING is not set".to_owned()));
+    }
+}
@@ -1,67 +1,112 @@
-use chrono::Utc;
+use std::fmt::Write;
+
+use chrono::format::{Item, StrftimeItems};
 
 use crate::models::entry::Timestamp;
 
-pub fn format_created_at(ts: &Timestamp) -> String {
-    let created = ts.to_naive();
-    let now = Utc::now().naive_utc();
-    let diff = now.signed_duration_since(created);
+/// Default `date_format`: absolute date of entries older than a week.
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+/// Default `datetime_format`: full local time, e.g. in tooltips.
+pub const DEFAULT_DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Relative age for entries from the last week, else the local date in `date_format`.
+pub fn format_created_at(ts: &Timestamp, date_format: &str) -> String {
+    let diff = Timestamp::now().as_millis().saturating_sub(ts.as_millis()) / 1000;
 
-    if diff.num_seconds() < 60 {
+    if diff < 60 {
         "just now".to_string()
-    } else if diff.num_hours() < 1 {
-        format!("{}m ago", diff.num_minutes())
-    } else if diff.num_hours() < 24 {
-        format!("{}h ago", diff.num_hours())
-    } else if diff.num_days() < 7 {
-        format!("{}d ago", diff.num_days())
+    } else if diff < 3600 {
+        format!("{}m ago", diff / 60)
+    } else if diff < 86400 {
+        format!("{}h ago", diff / 3600)
+    } else if diff < 7 * 86400 {
+        format!("{}d ago", diff / 86400)
     } else {
-        created.format("%Y-%m-%d").to_string()
+        format_local(ts, date_format)
+    }
+}
+
+/// `ts` in the local timezone, formatted with the strftime-style `format`.
+/// An invalid format yields the RFC 3339 UTC form instead of panicking.
+pub fn format_local(ts: &Timestamp, format: &str) -> String {
+    let mut out = String::new();
+    if write!(out, "{}", ts.to_local().format(format)).is_err() {
+        return ts.to_string();
     }
+    out
+}
+
+/// Whether `format` is a strftime format chrono understands.
+pub fn is_valid_format(format: &str) -> bool {
+    StrftimeItems::new(format).all(|item| !matches!(item, Item::Error))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::entry::{Timestamp, TIMESTAMP_FORMAT};
-    use chrono::Utc;
-
-    fn ts_ago(secs: i64) -> Timestamp {
-        let s = (Utc::now().naive_utc() - chrono::Duration::seconds(secs))
-            .format(TIMESTAMP_FORMAT)
-            .to_string();
-        Timestamp::from_raw(s)
+    use std::time::Duration;
+
+    fn ts_ago(secs: u64) -> Timestamp {
+        Timestamp::before(Duration::from_secs(secs))
+    }
+
+    fn created_at(ts: &Timestamp) -> String {
+        format_created_at(ts, DEFAULT_DATE_FORMAT)
     }
 
     #[test]
     fn test_just_now() {
-        assert_eq!(format_created_at(&ts_ago(5)), "just now");
-        assert_eq!(format_created_at(&ts_ago(59)), "just now");
+        assert_eq!(created_at(&ts_ago(5)), "just now");
+        assert_eq!(created_at(&ts_ago(59)), "just now");
     }
 
     #[test]
     fn test_minutes_ago() {
-        assert_eq!(format_created_at(&ts_ago(60)), "1m ago");
-        assert_eq!(format_created_at(&ts_ago(300)), "5m ago");
-        assert_eq!(format_created_at(&ts_ago(3599)), "59m ago");
+        assert_eq!(created_at(&ts_ago(60)), "1m ago");
+        assert_eq!(created_at(&ts_ago(300)), "5m ago");
+        assert_eq!(created_at(&ts_ago(3599)), "59m ago");
     }
 
     #[test]
     fn test_hours_ago() {
-        assert_eq!(format_created_at(&ts_ago(3600)), "1h ago");
-        assert_eq!(format_created_at(&ts_ago(7200)), "2h ago");
+        assert_eq!(created_at(&ts_ago(3600)), "1h ago");
+        assert_eq!(created_at(&ts_ago(7200)), "2h ago");
     }
 
     #[test]
     fn test_days_ago() {
-        assert_eq!(format_created_at(&ts_ago(86400)), "1d ago");
-        assert_eq!(format_created_at(&ts_ago(86400 * 6)), "6d ago");
+        assert_eq!(created_at(&ts_ago(86400)), "1d ago");
+        assert_eq!(created_at(&ts_ago(86400 * 6)), "6d ago");
     }
 
     #[test]
     fn test_old_shows_date() {
-        let result = format_created_at(&ts_ago(86400 * 30));
+        let result = created_at(&ts_ago(86400 * 30));
         assert!(result.starts_with("20"), "expected date, got: {result}");
         assert_eq!(result.len(), 10); // "YYYY-MM-DD"
     }
+
+    #[test]
+    fn test_old_uses_custom_format() {
+        let ts = ts_ago(86400 * 30);
+        assert_eq!(format_created_at(&ts, "%d.%m.%Y"), ts.to_local().format("%d.%m.%Y").to_string());
+    }
+
+    #[test]
+    fn test_format_local_uses_local_timezone() {
+        let ts = Timestamp::from_millis(1_714_566_600_250);
+        assert_eq!(
+            format_local(&ts, DEFAULT_DATETIME_FORMAT),
+            ts.to_local().format("%Y-%m-%d %H:%M:%S").to_string()
+        );
+    }
+
+    #[test]
+    fn test_invalid_format() {
+        assert!(is_valid_format(DEFAULT_DATE_FORMAT));
+        assert!(is_valid_format("%a %e %b, %H:%M"));
+        assert!(!is_valid_format("%Y-%Q"));
+        let ts = Timestamp::from_millis(0);
+        assert_eq!(format_local(&ts, "%Q"), "1970-01-01T00:00:00.000Z");
+    }
 }
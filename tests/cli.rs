@@ -28,6 +28,119 @@ fn test_config_show() {
         .success();
 }
 
+#[test]
+fn test_config_show_sources() {
+    let dir = setup_env();
+    let conf_d = dir.path().join("conf.d");
+    fs::create_dir(&conf_d).unwrap();
+    fs::write(conf_d.join("10-local.yaml"), "max_history: 42\n").unwrap();
+
+    let output = clio_cmd(&dir)
+        .args(["config", "show", "--sources"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert!(stdout.contains("max_history: 42  # "), "{stdout}");
+    assert!(stdout.contains("10-local.yaml"), "{stdout}");
+    assert!(stdout.contains("window_width: 600  # default"), "{stdout}");
+}
+
+#[test]
+fn test_config_env_and_set_overrides() {
+    let dir = setup_env();
+    let output = clio_cmd(&dir)
+        .env("CLIO_MAX_HISTORY", "42")
+        .env("CLIO_SYNC_MODE", "disabled")
+        .args(["--set", "sync_mode=to-primary", "config", "show", "--sources"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert!(stdout.contains("max_history: 42  # env CLIO_MAX_HISTORY"), "{stdout}");
+    assert!(stdout.contains("sync_mode: to-primary  # --set"), "{stdout}");
+}
+
+#[test]
+fn test_set_override_is_validated() {
+    let dir = setup_env();
+    let output = clio_cmd(&dir)
+        .args(["config", "validate", "--set", "max_history=0"])
+        .assert()
+        .failure()
+        .get_output()
+        .stderr
+        .clone();
+    let stderr = String::from_utf8(output).unwrap();
+    assert!(stderr.contains("max_history must be greater than 0"), "{stderr}");
+
+    let output = clio_cmd(&dir)
+        .args(["--set", "max_histroy=5", "list"])
+        .assert()
+        .failure()
+        .get_output()
+        .stderr
+        .clone();
+    let stderr = String::from_utf8(output).unwrap();
+    assert!(stderr.contains("unknown config key"), "{stderr}");
+}
+
+#[test]
+fn test_profile_switch() {
+    let dir = setup_env();
+    let config = format!(
+        "db_path: {}/clio.db\nprofiles:\n  work:\n    max_history: 2000\n",
+        dir.path().display()
+    );
+    fs::write(dir.path().join("config.yaml"), config).unwrap();
+    let profile_cmd = |args: &[&str]| {
+        let mut cmd = clio_cmd(&dir);
+        cmd.env("XDG_DATA_HOME", dir.path().join("data"))
+            .env("XDG_RUNTIME_DIR", dir.path().join("run"))
+            .args(args);
+        cmd
+    };
+
+    profile_cmd(&["profile", "switch", "play"]).assert().failure();
+    profile_cmd(&["profile", "switch", "work"]).assert().success();
+
+    let output = profile_cmd(&["profile", "list"]).assert().success().get_output().stdout.clone();
+    assert_eq!(String::from_utf8(output).unwrap(), "  default\n* work\n");
+
+    let output = profile_cmd(&["config", "show", "--sources"]).assert().success().get_output().stdout.clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert!(stdout.contains("max_history: 2000  # profile work"), "{stdout}");
+    assert!(stdout.contains("active_profile"), "{stdout}");
+
+    // --profile overrides the switched profile for one run.
+    let output = profile_cmd(&["--profile", "default", "profile", "list"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(String::from_utf8(output).unwrap(), "* default\n  work\n");
+}
+
+#[test]
+fn test_config_schema() {
+    let dir = setup_env();
+    let output = clio_cmd(&dir)
+        .args(["config", "schema"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert!(stdout.starts_with('{'), "{stdout}");
+    assert!(stdout.contains("\"RuleConditions\""), "{stdout}");
+}
+
 #[test]
 fn test_config_validate() {
     let dir = setup_env();
@@ -126,6 +239,136 @@ fn test_copy_from_stdin() {
     assert!(stdout.contains("hello from test"), "list should contain copied text");
 }
 
+#[test]
+fn test_list_selection_filter() {
+    let dir = setup_env();
+    clio_cmd(&dir)
+        .arg("copy")
+        .write_stdin("copied via cli")
+        .assert()
+        .success();
+
+    let list = |selection: &str| {
+        let output = clio_cmd(&dir)
+            .args(["list", "--selection", selection])
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+        String::from_utf8(output).unwrap()
+    };
+    assert!(list("cli").contains("copied via cli"));
+    assert!(list("clipboard,primary").is_empty());
+}
+
+#[test]
+fn test_list_sort() {
+    let dir = setup_env();
+    for text in ["first", "second"] {
+        clio_cmd(&dir).arg("copy").write_stdin(text).assert().success();
+    }
+
+    let list = |args: &[&str]| {
+        let output = clio_cmd(&dir).args(args).assert().success().get_output().stdout.clone();
+        String::from_utf8(output).unwrap().lines().count()
+    };
+    assert_eq!(list(&["list", "--sort", "frecency"]), 2);
+    assert_eq!(list(&["--set", "sort=frecency", "list", "--sort", "chronological"]), 2);
+    clio_cmd(&dir).args(["list", "--sort", "popular"]).assert().failure();
+}
+
+#[test]
+fn test_tags() {
+    let dir = setup_env();
+    for text in ["SELECT 1", "kubectl get pods"] {
+        clio_cmd(&dir).arg("copy").write_stdin(text).assert().success();
+    }
+
+    let run = |args: &[&str]| {
+        let output = clio_cmd(&dir).args(args).assert().success().get_output().stdout.clone();
+        String::from_utf8(output).unwrap()
+    };
+    assert_eq!(run(&["tag", "1", "add", "sql,work"]), "sql work\n");
+    assert_eq!(run(&["tag", "1", "rm", "work"]), "sql\n");
+
+    let listed = run(&["list", "--tag", "sql"]);
+    assert_eq!(listed.lines().count(), 1);
+    assert!(listed.contains("SELECT 1"), "{listed}");
+    assert_eq!(run(&["list", "--tag", "k8s"]), "");
+
+    clio_cmd(&dir).args(["tag", "99", "add", "x"]).assert().failure();
+    clio_cmd(&dir).args(["tag", "1", "add", "two words"]).assert().failure();
+}
+
+#[test]
+fn test_snippets() {
+    let dir = setup_env();
+    let run = |args: &[&str], stdin: &str| {
+        let output = clio_cmd(&dir).args(args).write_stdin(stdin).assert().success().get_output().stdout.clone();
+        String::from_utf8(output).unwrap()
+    };
+    run(&["snippet", "add", "logs"], "kubectl -n {prompt:Namespace} logs {env:POD}");
+    run(&["snippet", "add", "sig"], "Regards,\nJohn\n");
+    assert_eq!(run(&["snippet", "list"], ""), "logs\tkubectl -n {prompt:Namespace} logs {env:POD}\nsig\tRegards,…\n");
+
+    let used = clio_cmd(&dir)
+        .env("POD", "api-0")
+        .args(["snippet", "use", "logs", "--var", "Namespace=prod", "--print"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(String::from_utf8(used).unwrap(), "kubectl -n prod logs api-0");
+    // Without a terminal, prompts need --var.
+    clio_cmd(&dir).env("POD", "api-0").args(["snippet", "use", "logs", "--print"]).assert().failure();
+
+    // Snippets are not history entries.
+    assert_eq!(run(&["list"], ""), "");
+
+    run(&["snippet", "rm", "sig"], "");
+    clio_cmd(&dir).args(["snippet", "rm", "sig"]).assert().failure();
+    clio_cmd(&dir).args(["snippet", "use", "sig"]).assert().failure();
+}
+
+#[test]
+fn test_annotate() {
+    let dir = setup_env();
+    clio_cmd(&dir).arg("copy").write_stdin("SELECT * FROM orders WHERE status = 'open'").assert().success();
+
+    let run = |args: &[&str]| {
+        let output = clio_cmd(&dir).args(args).assert().success().get_output().stdout.clone();
+        String::from_utf8(output).unwrap()
+    };
+    assert_eq!(
+        run(&["annotate", "1", "--title", "Open orders", "--note", "weekly report"]),
+        "Title: Open orders\nNote: weekly report\n"
+    );
+    assert!(run(&["list"]).contains("Open orders"));
+    assert!(!run(&["list"]).contains("SELECT"));
+
+    assert_eq!(run(&["annotate", "1", "--title", ""]), "Note: weekly report\n");
+    assert!(run(&["list"]).contains("SELECT"));
+    assert_eq!(run(&["annotate", "1"]), "Note: weekly report\n");
+
+    clio_cmd(&dir).args(["annotate", "99", "--title", "x"]).assert().failure();
+}
+
+#[test]
+fn test_pin() {
+    let dir = setup_env();
+    clio_cmd(&dir).arg("copy").write_stdin("keep me").assert().success();
+    let list = || String::from_utf8(clio_cmd(&dir).arg("list").assert().success().get_output().stdout.clone()).unwrap();
+
+    clio_cmd(&dir).args(["pin", "1"]).assert().success();
+    assert!(list().contains("\u{1F4CC} keep me"));
+    clio_cmd(&dir).args(["unpin", "1"]).assert().success();
+    assert!(!list().contains('\u{1F4CC}'));
+
+    clio_cmd(&dir).args(["pin", "99"]).assert().failure();
+}
+
 #[test]
 fn test_copy_empty_stdin_fails() {
     let dir = setup_env();
@@ -144,3 +387,177 @@ fn test_select_nonexistent_id() {
         .assert()
         .failure();
 }
+
+#[test]
+fn test_service_install_autostart() {
+    let dir = setup_env();
+    let config_home = dir.path().join("xdg-config");
+    clio_cmd(&dir)
+        .env("XDG_CONFIG_HOME", &config_home)
+        .args(["service", "install", "--target", "autostart"])
+        .assert()
+        .success();
+
+    let desktop = fs::read_to_string(config_home.join("autostart/clio-watch.desktop")).unwrap();
+    assert!(desktop.contains("--config"), "exec line should keep --config");
+    assert!(desktop.contains(" watch"), "exec line should run watch");
+
+    clio_cmd(&dir)
+        .env("XDG_CONFIG_HOME", &config_home)
+        .args(["service", "uninstall"])
+        .assert()
+        .success();
+    assert!(!config_home.join("autostart/clio-watch.desktop").exists());
+}
+
+#[test]
+fn test_prune_dry_run() {
+    let dir = setup_env();
+    for text in ["one", "two", "three"] {
+        clio_cmd(&dir).arg("copy").write_stdin(text).assert().success();
+    }
+
+    let output = clio_cmd(&dir)
+        .args(["--set", "max_history=1", "prune", "--dry-run"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert!(stdout.starts_with("Would delete 2 entries"), "{stdout}");
+    assert!(stdout.contains("max_history"), "{stdout}");
+
+    let list = clio_cmd(&dir).arg("list").assert().success().get_output().stdout.clone();
+    assert_eq!(String::from_utf8(list).unwrap().lines().count(), 3);
+
+    clio_cmd(&dir).args(["--set", "max_history=1", "prune"]).assert().success();
+    let list = clio_cmd(&dir).arg("list").assert().success().get_output().stdout.clone();
+    assert_eq!(String::from_utf8(list).unwrap().lines().count(), 1);
+}
+
+#[test]
+fn test_db_check_blob_store() {
+    let dir = setup_env();
+    let text = "x".repeat(4096);
+    clio_cmd(&dir)
+        .args(["--set", "blob_store_over_kb=1", "copy"])
+        .write_stdin(text.as_str())
+        .assert()
+        .success();
+    let blobs = dir.path().join("blobs");
+    assert_eq!(fs::read_dir(&blobs).unwrap().count(), 1);
+
+    clio_cmd(&dir).args(["db", "check"]).assert().success();
+
+    fs::write(blobs.join("0".repeat(64)), "stray").unwrap();
+    let output = clio_cmd(&dir).args(["db", "check"]).assert().failure().get_output().stdout.clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert!(stdout.contains("1 orphaned"), "{stdout}");
+
+    clio_cmd(&dir).args(["db", "check", "--fix"]).assert().success();
+    assert_eq!(fs::read_dir(&blobs).unwrap().count(), 1);
+}
+
+#[test]
+fn test_db_check_fixes_missing_blob() {
+    let dir = setup_env();
+    let text = "y".repeat(10_000);
+    clio_cmd(&dir)
+        .args(["--set", "blob_store_over_kb=1", "copy"])
+        .write_stdin(text.as_str())
+        .assert()
+        .success();
+    let blobs = dir.path().join("blobs");
+    for file in fs::read_dir(&blobs).unwrap() {
+        fs::remove_file(file.unwrap().path()).unwrap();
+    }
+
+    let output = clio_cmd(&dir).args(["db", "check"]).assert().failure().get_output().stdout.clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert!(stdout.contains("1 missing"), "{stdout}");
+
+    clio_cmd(&dir).args(["db", "check", "--fix"]).assert().success();
+    clio_cmd(&dir).args(["db", "check"]).assert().success();
+    let list = clio_cmd(&dir).arg("list").assert().success().get_output().stdout.clone();
+    assert!(list.is_empty());
+}
+
+#[test]
+fn test_db_backup_restore_stats() {
+    let dir = setup_env();
+    clio_cmd(&dir).arg("copy").write_stdin("before backup").assert().success();
+    let backup = dir.path().join("backup.db");
+    clio_cmd(&dir).args(["db", "backup"]).arg(&backup).assert().success();
+    assert!(backup.is_file());
+
+    clio_cmd(&dir).arg("copy").write_stdin("after backup").assert().success();
+    clio_cmd(&dir).args(["db", "restore"]).arg(&backup).assert().success();
+    let output = clio_cmd(&dir).arg("list").assert().success().get_output().stdout.clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert!(stdout.contains("before backup"), "{stdout}");
+    assert!(!stdout.contains("after backup"), "{stdout}");
+
+    let output = clio_cmd(&dir).args(["db", "stats"]).assert().success().get_output().stdout.clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert!(stdout.contains("Schema version:"), "{stdout}");
+    assert!(stdout.contains("text"), "{stdout}");
+
+    clio_cmd(&dir).args(["db", "vacuum"]).assert().success();
+    clio_cmd(&dir).args(["db", "check"]).assert().success();
+}
+
+#[test]
+fn test_dedupe_images_dry_run() {
+    let dir = setup_env();
+    let output = clio_cmd(&dir)
+        .args(["dedupe", "--images", "--dry-run"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(output.get_output().stdout.clone()).unwrap();
+    assert!(stdout.starts_with("Would delete 0 near-duplicate images (distance <= 5)"), "{stdout}");
+
+    clio_cmd(&dir).arg("dedupe").assert().failure();
+    clio_cmd(&dir).args(["dedupe", "--images", "--distance", "40"]).assert().failure();
+}
+
+#[test]
+fn test_timeline() {
+    let dir = setup_env();
+    clio_cmd(&dir).arg("copy").write_stdin("timed").assert().success();
+    let list = clio_cmd(&dir).arg("list").assert().success().get_output().stdout.clone();
+    let id = String::from_utf8(list).unwrap().split_whitespace().last().unwrap().to_owned();
+
+    let output = clio_cmd(&dir).args(["timeline", &id]).assert().success().get_output().stdout.clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert_eq!(stdout.lines().count(), 1, "{stdout}");
+    assert!(stdout.contains(&format!("\t{id}\tcopy\tcli\t")), "{stdout}");
+
+    let output = clio_cmd(&dir).args(["timeline", "--at", "0s"]).assert().success().get_output().stdout.clone();
+    assert_eq!(output, stdout.as_bytes());
+    clio_cmd(&dir).args(["timeline", "--at", "1h"]).assert().failure();
+    clio_cmd(&dir).args(["timeline", "999"]).assert().failure();
+    clio_cmd(&dir).arg("timeline").assert().failure();
+}
+
+#[test]
+fn test_stats() {
+    let dir = setup_env();
+    clio_cmd(&dir).arg("copy").write_stdin("counted").assert().success();
+
+    let output = clio_cmd(&dir).arg("stats").assert().success().get_output().stdout.clone();
+    let stdout = String::from_utf8(output).unwrap();
+    assert!(stdout.starts_with("Entries: 1\n"), "{stdout}");
+
+    let output = clio_cmd(&dir)
+        .args(["stats", "--since", "7d", "--format", "json"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(json["entries"], 1);
+    assert_eq!(json["by_source_app"][0]["count"], 1);
+    assert_eq!(json["copies_by_hour"].as_array().map(Vec::len), Some(24));
+}